@@ -0,0 +1,516 @@
+/*!
+
+End-to-end congestion control at the servers.
+
+Routers with the key `ecn_threshold` mark the packets that enter an output queue holding at least that many phits, as the Congestion Experienced codepoint of ECN.
+When a destination server consumes a packet it returns to the origin server an acknowledgement packet of a single phit carrying the mark, with the same control field as the acknowledgements of the [transport](crate::transport). The packets of each flow are numbered to identify them in the acknowledgements. The acknowledgements travel through the network like any other packet, so they may suffer congestion and losses, and the round trip time is measured from the cycle in which the packet began to be sent.
+The origin server feeds the acknowledgements into its congestion controller, which decides when the next packet to each destination may be sent. The packets towards a destination waiting for its controller do not delay those towards other destinations.
+
+The packets dropped by the network, as by the `Lossy` transmission mechanisms, are never acknowledged. The window based controllers consider a packet lost when its acknowledgement has not arrived after `loss_timeout` cycles, releasing its space in the window.
+
+With a `server_transport` the controller is driven by the acknowledgements of the transport instead, see the [transport](crate::transport) module. Its acknowledgement packets echo the mark, a packet is considered lost when the transport retransmits it, and the retransmissions neither wait for the controller nor count again in the window.
+
+It is enabled with the `server_congestion_control` key of the simulation. The rate of each flow is sampled with each acknowledgement and reported into `congestion_statistics` and, per flow, into the `temporal_statistics`.
+
+* DCQCN is rate based. Each marked acknowledgement makes a multiplicative decrease, at most once per `period`. Each `period` without marks the flow recovers half the way towards the rate before the last decrease, which grows by `rate_increase`.
+```ignore
+server_congestion_control: DCQCN{
+	alpha_gain: 0.0625,//optional, the weight of each update of the estimation of the congestion.
+	rate_increase: 0.01,//optional, in phits per cycle.
+	minimum_rate: 0.01,//optional, in phits per cycle.
+	period: 50,//optional, the cycles between rate updates.
+}
+```
+* DCTCP is window based. Once each window of acknowledged phits the fraction of marked ones updates an estimation `alpha` of the congestion. The window is reduced by a factor `alpha/2` if there were marks and increased by `additive_increase` phits otherwise.
+```ignore
+server_congestion_control: DCTCP{
+	alpha_gain: 0.0625,//optional
+	initial_window: 64,//optional, in phits.
+	minimum_window: 16,//optional, in phits. It should be at least the maximum packet size.
+	additive_increase: 16,//optional, in phits per window.
+	loss_timeout: 1000,//optional, cycles without acknowledgement to consider a packet lost.
+}
+```
+* Swift is window based, driven by the round trip time instead of marks. The window grows by `additive_increase` phits per window while the round trip is below `target_delay` and decreases otherwise proportionally to the excess delay, at most once per round trip.
+```ignore
+server_congestion_control: Swift{
+	target_delay: 100,//optional, in cycles.
+	additive_increase: 16,//optional, in phits per window.
+	beta: 0.8,//optional, the factor of the decrease by the excess delay.
+	maximum_decrease: 0.5,//optional, the maximum fraction of window reduced at once.
+	initial_window: 64,//optional
+	minimum_window: 16,//optional
+	loss_timeout: 1000,//optional
+}
+```
+
+*/
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use quantifiable_derive::Quantifiable;//the derive macro
+
+use crate::config_parser::ConfigurationValue;
+use crate::event::Time;
+use crate::match_object_panic;
+use crate::packet::Packet;
+use crate::quantify::Quantifiable;
+
+///The acknowledgement of a packet, as received by its origin server.
+#[derive(Clone,Debug)]
+pub struct CongestionAck
+{
+	///The destination server of the acknowledged packet.
+	pub destination: usize,
+	///The size in phits of the acknowledged packet.
+	pub size: usize,
+	///Whether some router has marked the packet.
+	pub marked: bool,
+	///Cycles since the packet entered the network until the acknowledgement arrived.
+	pub round_trip: Time,
+}
+
+///A controller of the injection of a server, keeping some state for each destination.
+pub trait CongestionControl : Quantifiable + Debug
+{
+	///Whether a packet of `size` phits may be sent now towards `destination`.
+	fn can_send(&self, destination:usize, size:usize, cycle:Time) -> bool;
+	///Called when a packet begins to be sent.
+	fn notify_sent(&mut self, destination:usize, size:usize, cycle:Time);
+	///Called when an acknowledgement arrives.
+	fn notify_ack(&mut self, ack:&CongestionAck, cycle:Time);
+	///Called when a packet of `size` phits sent towards `destination` is considered lost. It will not be acknowledged.
+	///By default nothing is done, as for rate based controllers.
+	fn notify_loss(&mut self, _destination:usize, _size:usize, _cycle:Time) {}
+	///The cycles after which a packet without acknowledgement is considered lost, if the controller needs to know it.
+	fn loss_timeout(&self) -> Option<Time> { None }
+	///The current rate towards `destination` in phits per cycle. For window based controllers it is estimated as the window divided by the round trip time.
+	fn rate(&self, destination:usize) -> f64;
+}
+
+///Build a new congestion controller for a server.
+pub fn new_congestion_control(cv:&ConfigurationValue) -> Box<dyn CongestionControl>
+{
+	if let ConfigurationValue::Object(cv_name, _cv_pairs)=cv
+	{
+		match cv_name.as_ref()
+		{
+			"DCQCN" => Box::new(Dcqcn::new(cv)),
+			"DCTCP" => Box::new(Dctcp::new(cv)),
+			"Swift" => Box::new(Swift::new(cv)),
+			_ => panic!("Unknown congestion control {}",cv_name),
+		}
+	}
+	else
+	{
+		panic!("Trying to create a CongestionControl from a non-Object");
+	}
+}
+
+///The state of a DCQCN flow.
+#[derive(Debug,Quantifiable)]
+struct RateFlow
+{
+	///Current rate in phits per cycle.
+	rate: f64,
+	///The rate to recover.
+	target_rate: f64,
+	///Estimation of the congestion.
+	alpha: f64,
+	///The cycle of the last decrease, if any.
+	last_decrease: Option<Time>,
+	///The cycle of the last periodic update.
+	last_update: Time,
+	///The first cycle in which the next packet may be sent.
+	next_send: f64,
+}
+
+///DCQCN-like rate control. See the module documentation.
+#[derive(Debug,Quantifiable)]
+pub struct Dcqcn
+{
+	alpha_gain: f64,
+	rate_increase: f64,
+	minimum_rate: f64,
+	period: Time,
+	flows: BTreeMap<usize,RateFlow>,
+}
+
+impl Dcqcn
+{
+	pub fn new(cv:&ConfigurationValue) -> Dcqcn
+	{
+		let mut alpha_gain = 1.0/16.0;
+		let mut rate_increase = 0.01;
+		let mut minimum_rate = 0.01;
+		let mut period = 50;
+		match_object_panic!(cv,"DCQCN",value,
+			"alpha_gain" => alpha_gain = value.as_f64().expect("bad value for alpha_gain"),
+			"rate_increase" => rate_increase = value.as_f64().expect("bad value for rate_increase"),
+			"minimum_rate" => minimum_rate = value.as_f64().expect("bad value for minimum_rate"),
+			"period" => period = value.as_time().expect("bad value for period"),
+		);
+		if period==0
+		{
+			panic!("The period of DCQCN must be positive");
+		}
+		Dcqcn{
+			alpha_gain,
+			rate_increase,
+			minimum_rate,
+			period,
+			flows: BTreeMap::new(),
+		}
+	}
+	fn flow(&mut self, destination:usize, cycle:Time) -> &mut RateFlow
+	{
+		self.flows.entry(destination).or_insert_with(||RateFlow{
+			rate: 1.0,
+			target_rate: 1.0,
+			alpha: 1.0,
+			last_decrease: None,
+			last_update: cycle,
+			next_send: 0.0,
+		})
+	}
+	///Apply the periodic updates up to `cycle`.
+	fn recover(&mut self, destination:usize, cycle:Time)
+	{
+		let (gain,increase,period) = (self.alpha_gain,self.rate_increase,self.period);
+		let flow = self.flow(destination,cycle);
+		while flow.last_update + period <= cycle
+		{
+			flow.last_update += period;
+			if flow.last_decrease.map(|last|last + period <= flow.last_update).unwrap_or(true)
+			{
+				flow.alpha *= 1.0-gain;
+				flow.target_rate = (flow.target_rate+increase).min(1.0);
+				flow.rate = (flow.rate+flow.target_rate)/2.0;
+			}
+		}
+	}
+}
+
+impl CongestionControl for Dcqcn
+{
+	fn can_send(&self, destination:usize, _size:usize, cycle:Time) -> bool
+	{
+		self.flows.get(&destination).map(|flow|cycle as f64 >= flow.next_send).unwrap_or(true)
+	}
+	fn notify_sent(&mut self, destination:usize, size:usize, cycle:Time)
+	{
+		self.recover(destination,cycle);
+		let flow = self.flow(destination,cycle);
+		flow.next_send = flow.next_send.max(cycle as f64) + size as f64/flow.rate;
+	}
+	fn notify_ack(&mut self, ack:&CongestionAck, cycle:Time)
+	{
+		self.recover(ack.destination,cycle);
+		let (gain,minimum,period) = (self.alpha_gain,self.minimum_rate,self.period);
+		let flow = self.flow(ack.destination,cycle);
+		if ack.marked && flow.last_decrease.map(|last|last + period <= cycle).unwrap_or(true)
+		{
+			flow.target_rate = flow.rate;
+			flow.rate = (flow.rate*(1.0-flow.alpha/2.0)).max(minimum);
+			flow.alpha = (1.0-gain)*flow.alpha + gain;
+			flow.last_decrease = Some(cycle);
+		}
+	}
+	fn rate(&self, destination:usize) -> f64
+	{
+		self.flows.get(&destination).map(|flow|flow.rate).unwrap_or(1.0)
+	}
+}
+
+///The state of a flow for window based controllers.
+#[derive(Debug,Quantifiable)]
+struct WindowFlow
+{
+	///Maximum phits in flight.
+	window: f64,
+	///Phits sent and not acknowledged.
+	in_flight: usize,
+	///Estimation of the congestion, for DCTCP.
+	alpha: f64,
+	///Phits acknowledged in the current window.
+	acknowledged: usize,
+	///Marked phits acknowledged in the current window.
+	marked: usize,
+	///Smoothed round trip time.
+	round_trip: f64,
+	///The cycle of the last decrease, if any.
+	last_decrease: Option<Time>,
+}
+
+impl WindowFlow
+{
+	fn new(window:f64) -> WindowFlow
+	{
+		WindowFlow{
+			window,
+			in_flight: 0,
+			alpha: 1.0,
+			acknowledged: 0,
+			marked: 0,
+			round_trip: 0.0,
+			last_decrease: None,
+		}
+	}
+	///A packet may be sent if it fits in the window or if there is nothing in flight.
+	fn can_send(&self, size:usize) -> bool
+	{
+		self.in_flight==0 || (self.in_flight+size) as f64 <= self.window
+	}
+	fn acknowledge(&mut self, ack:&CongestionAck)
+	{
+		self.release(ack.size);
+		let sample = ack.round_trip as f64;
+		self.round_trip = if self.round_trip==0.0 { sample } else { 0.875*self.round_trip + 0.125*sample };
+	}
+	///Remove from the phits in flight those of a packet, either acknowledged or lost.
+	fn release(&mut self, size:usize)
+	{
+		self.in_flight -= size;
+	}
+	fn rate(&self) -> f64
+	{
+		if self.round_trip==0.0 { 1.0 } else { (self.window/self.round_trip).min(1.0) }
+	}
+}
+
+///DCTCP-like window control. See the module documentation.
+#[derive(Debug,Quantifiable)]
+pub struct Dctcp
+{
+	alpha_gain: f64,
+	initial_window: f64,
+	minimum_window: f64,
+	additive_increase: f64,
+	loss_timeout: Time,
+	flows: BTreeMap<usize,WindowFlow>,
+}
+
+impl Dctcp
+{
+	pub fn new(cv:&ConfigurationValue) -> Dctcp
+	{
+		let mut alpha_gain = 1.0/16.0;
+		let mut initial_window = 64.0;
+		let mut minimum_window = 16.0;
+		let mut additive_increase = 16.0;
+		let mut loss_timeout = 1000;
+		match_object_panic!(cv,"DCTCP",value,
+			"alpha_gain" => alpha_gain = value.as_f64().expect("bad value for alpha_gain"),
+			"initial_window" => initial_window = value.as_f64().expect("bad value for initial_window"),
+			"minimum_window" => minimum_window = value.as_f64().expect("bad value for minimum_window"),
+			"additive_increase" => additive_increase = value.as_f64().expect("bad value for additive_increase"),
+			"loss_timeout" => loss_timeout = value.as_time().expect("bad value for loss_timeout"),
+		);
+		Dctcp{
+			alpha_gain,
+			initial_window,
+			minimum_window,
+			additive_increase,
+			loss_timeout,
+			flows: BTreeMap::new(),
+		}
+	}
+}
+
+impl CongestionControl for Dctcp
+{
+	fn can_send(&self, destination:usize, size:usize, _cycle:Time) -> bool
+	{
+		self.flows.get(&destination).map(|flow|flow.can_send(size)).unwrap_or(true)
+	}
+	fn notify_sent(&mut self, destination:usize, size:usize, _cycle:Time)
+	{
+		let initial_window = self.initial_window;
+		self.flows.entry(destination).or_insert_with(||WindowFlow::new(initial_window)).in_flight += size;
+	}
+	fn notify_ack(&mut self, ack:&CongestionAck, _cycle:Time)
+	{
+		let flow = self.flows.get_mut(&ack.destination).expect("acknowledgement of a flow never sent");
+		flow.acknowledge(ack);
+		flow.acknowledged += ack.size;
+		if ack.marked
+		{
+			flow.marked += ack.size;
+		}
+		if flow.acknowledged as f64 >= flow.window
+		{
+			let fraction = flow.marked as f64 / flow.acknowledged as f64;
+			flow.alpha = (1.0-self.alpha_gain)*flow.alpha + self.alpha_gain*fraction;
+			if flow.marked>0
+			{
+				flow.window = (flow.window*(1.0-flow.alpha/2.0)).max(self.minimum_window);
+			}
+			else
+			{
+				flow.window += self.additive_increase;
+			}
+			flow.acknowledged = 0;
+			flow.marked = 0;
+		}
+	}
+	fn notify_loss(&mut self, destination:usize, size:usize, _cycle:Time)
+	{
+		self.flows.get_mut(&destination).expect("loss of a flow never sent").release(size);
+	}
+	fn loss_timeout(&self) -> Option<Time>
+	{
+		Some(self.loss_timeout)
+	}
+	fn rate(&self, destination:usize) -> f64
+	{
+		self.flows.get(&destination).map(|flow|flow.rate()).unwrap_or(1.0)
+	}
+}
+
+///Swift-like delay based window control. See the module documentation.
+#[derive(Debug,Quantifiable)]
+pub struct Swift
+{
+	target_delay: f64,
+	additive_increase: f64,
+	beta: f64,
+	maximum_decrease: f64,
+	initial_window: f64,
+	minimum_window: f64,
+	loss_timeout: Time,
+	flows: BTreeMap<usize,WindowFlow>,
+}
+
+impl Swift
+{
+	pub fn new(cv:&ConfigurationValue) -> Swift
+	{
+		let mut target_delay = 100.0;
+		let mut additive_increase = 16.0;
+		let mut beta = 0.8;
+		let mut maximum_decrease = 0.5;
+		let mut initial_window = 64.0;
+		let mut minimum_window = 16.0;
+		let mut loss_timeout = 1000;
+		match_object_panic!(cv,"Swift",value,
+			"target_delay" => target_delay = value.as_f64().expect("bad value for target_delay"),
+			"additive_increase" => additive_increase = value.as_f64().expect("bad value for additive_increase"),
+			"beta" => beta = value.as_f64().expect("bad value for beta"),
+			"maximum_decrease" => maximum_decrease = value.as_f64().expect("bad value for maximum_decrease"),
+			"initial_window" => initial_window = value.as_f64().expect("bad value for initial_window"),
+			"minimum_window" => minimum_window = value.as_f64().expect("bad value for minimum_window"),
+			"loss_timeout" => loss_timeout = value.as_time().expect("bad value for loss_timeout"),
+		);
+		Swift{
+			target_delay,
+			additive_increase,
+			beta,
+			maximum_decrease,
+			initial_window,
+			minimum_window,
+			loss_timeout,
+			flows: BTreeMap::new(),
+		}
+	}
+}
+
+impl CongestionControl for Swift
+{
+	fn can_send(&self, destination:usize, size:usize, _cycle:Time) -> bool
+	{
+		self.flows.get(&destination).map(|flow|flow.can_send(size)).unwrap_or(true)
+	}
+	fn notify_sent(&mut self, destination:usize, size:usize, _cycle:Time)
+	{
+		let initial_window = self.initial_window;
+		self.flows.entry(destination).or_insert_with(||WindowFlow::new(initial_window)).in_flight += size;
+	}
+	fn notify_ack(&mut self, ack:&CongestionAck, cycle:Time)
+	{
+		let flow = self.flows.get_mut(&ack.destination).expect("acknowledgement of a flow never sent");
+		flow.acknowledge(ack);
+		let delay = ack.round_trip as f64;
+		if delay < self.target_delay
+		{
+			flow.window += self.additive_increase * ack.size as f64 / flow.window;
+		}
+		else if flow.last_decrease.map(|last|(cycle-last) as f64 >= flow.round_trip).unwrap_or(true)
+		{
+			let factor = (1.0 - self.beta*(delay-self.target_delay)/delay).max(1.0-self.maximum_decrease);
+			flow.window = (flow.window*factor).max(self.minimum_window);
+			flow.last_decrease = Some(cycle);
+		}
+	}
+	fn notify_loss(&mut self, destination:usize, size:usize, _cycle:Time)
+	{
+		self.flows.get_mut(&destination).expect("loss of a flow never sent").release(size);
+	}
+	fn loss_timeout(&self) -> Option<Time>
+	{
+		Some(self.loss_timeout)
+	}
+	fn rate(&self, destination:usize) -> f64
+	{
+		self.flows.get(&destination).map(|flow|flow.rate()).unwrap_or(1.0)
+	}
+}
+
+///The packets sent by a server whose acknowledgement has not arrived yet, to measure their round trip and detect their loss.
+#[derive(Debug,Quantifiable)]
+pub struct UnacknowledgedPackets
+{
+	///Cycles after its transmission to consider a packet lost, if the controller needs it.
+	loss_timeout: Option<Time>,
+	///The size of each packet and the cycle in which it was sent, indexed by its destination and sequence number.
+	packets: BTreeMap<(usize,usize),(usize,Time)>,
+	///The packets to be considered lost at each cycle if still not acknowledged.
+	deadlines: BTreeMap<Time,Vec<(usize,usize)>>,
+}
+
+impl UnacknowledgedPackets
+{
+	pub fn new(loss_timeout:Option<Time>) -> UnacknowledgedPackets
+	{
+		UnacknowledgedPackets{
+			loss_timeout,
+			packets: BTreeMap::new(),
+			deadlines: BTreeMap::new(),
+		}
+	}
+	///Start waiting the acknowledgement of a packet sent at `cycle`.
+	pub fn track_sent(&mut self, packet:&Packet, cycle:Time)
+	{
+		let key = (packet.message.destination,packet.sequence);
+		self.packets.insert(key,(packet.size,cycle));
+		if let Some(loss_timeout) = self.loss_timeout
+		{
+			self.deadlines.entry(cycle+loss_timeout).or_default().push(key);
+		}
+	}
+	///Stop waiting for a packet, returning its size and the cycle in which it was sent. Returns None if it was already considered lost.
+	pub fn acknowledge(&mut self, destination:usize, sequence:usize) -> Option<(usize,Time)>
+	{
+		self.packets.remove(&(destination,sequence))
+	}
+	///Remove the packets whose deadline is `cycle`, returning the destination and size of each of them.
+	pub fn expired(&mut self, cycle:Time) -> Vec<(usize,usize)>
+	{
+		let keys = self.deadlines.remove(&cycle).unwrap_or_default();
+		keys.into_iter().filter_map(|key|self.packets.remove(&key).map(|(size,_sent)|(key.0,size))).collect()
+	}
+}
+
+///Summarize the rates sampled for each flow `(origin,destination)` as `(sum,count)`.
+///Returns the average among flows of their average rate together with an array of `FlowRate{origin,destination,rate}`.
+pub fn flow_rate_statistics(samples:&BTreeMap<(usize,usize),(f64,usize)>) -> (f64,ConfigurationValue)
+{
+	let rates : Vec<((usize,usize),f64)> = samples.iter().map(|(&flow,&(sum,count))|(flow,sum/count as f64)).collect();
+	let average = rates.iter().map(|(_flow,rate)|rate).sum::<f64>() / rates.len() as f64;
+	let content = rates.into_iter().map(|((origin,destination),rate)|ConfigurationValue::Object(String::from("FlowRate"),vec![
+		(String::from("origin"),ConfigurationValue::Number(origin as f64)),
+		(String::from("destination"),ConfigurationValue::Number(destination as f64)),
+		(String::from("rate"),ConfigurationValue::Number(rate)),
+	])).collect();
+	(average,ConfigurationValue::Array(content))
+}
@@ -0,0 +1,106 @@
+/*!
+
+Energy accounting of the routers and the links.
+
+The routers accept an `energy` key with the energy consumed by each of their events and their static power. The units are left to the user, for example picojoules, with the static power being the energy consumed in each cycle.
+
+```ignore
+router: Basic{
+	//...
+	energy: RouterEnergy{
+		buffer_write: 1.0,//writing a phit into a buffer, either an input or an output buffer.
+		buffer_read: 1.0,//reading a phit from a buffer.
+		crossbar_traversal: 2.0,//moving a phit from an input to an output through the crossbar.
+		allocation: 0.5,//each request granted by the allocator of the crossbar.
+		static_power: 10.0,//energy consumed by the router in each cycle, regardless of its activity.
+	},
+}
+```
+Each router adds its own static power, so routers with different models are accounted separately.
+
+Similarly, each `LinkClass` accepts `energy_per_phit`, the energy of a phit traversing a link of the class, and `static_power`, the energy consumed in each cycle by each of its directed links. The phits replayed by the link-level retry count as traversals.
+
+The `energy_statistics` of the results, shown when some of these costs is defined, contain the `total_energy` split into `dynamic_energy` and `static_energy`, their split between routers and links, and the `energy_per_delivered_bit`, which is 0 when nothing has been delivered. The bits of each phit are given by the `phit_bits` key of the simulation, defaulting to 1. They also include the number of events of each kind.
+
+*/
+
+use quantifiable_derive::Quantifiable;//the derive macro
+
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+
+///The energy consumed by the events of a router and its static power.
+#[derive(Debug,Clone,Quantifiable)]
+pub struct RouterEnergy
+{
+	///Energy of writing a phit into a buffer.
+	pub buffer_write: f64,
+	///Energy of reading a phit from a buffer.
+	pub buffer_read: f64,
+	///Energy of moving a phit through the crossbar.
+	pub crossbar_traversal: f64,
+	///Energy of each request granted by the allocator.
+	pub allocation: f64,
+	///Energy consumed in each cycle.
+	pub static_power: f64,
+}
+
+impl RouterEnergy
+{
+	pub fn new(cv:&ConfigurationValue) -> RouterEnergy
+	{
+		let mut buffer_write = 0.0;
+		let mut buffer_read = 0.0;
+		let mut crossbar_traversal = 0.0;
+		let mut allocation = 0.0;
+		let mut static_power = 0.0;
+		match_object_panic!(cv,"RouterEnergy",value,
+			"buffer_write" => buffer_write = value.as_f64().expect("bad value for buffer_write"),
+			"buffer_read" => buffer_read = value.as_f64().expect("bad value for buffer_read"),
+			"crossbar_traversal" => crossbar_traversal = value.as_f64().expect("bad value for crossbar_traversal"),
+			"allocation" => allocation = value.as_f64().expect("bad value for allocation"),
+			"static_power" => static_power = value.as_f64().expect("bad value for static_power"),
+		);
+		RouterEnergy{
+			buffer_write,
+			buffer_read,
+			crossbar_traversal,
+			allocation,
+			static_power,
+		}
+	}
+	///The energy consumed by the events counted in `counters`.
+	pub fn dynamic_energy(&self, counters:&EnergyCounters) -> f64
+	{
+		self.buffer_write*counters.buffer_writes as f64
+			+ self.buffer_read*counters.buffer_reads as f64
+			+ self.crossbar_traversal*counters.crossbar_traversals as f64
+			+ self.allocation*counters.allocations as f64
+	}
+}
+
+///The number of events consuming energy in a router.
+#[derive(Debug,Clone,Default,Quantifiable)]
+pub struct EnergyCounters
+{
+	///Phits written into any buffer of the router.
+	pub buffer_writes: usize,
+	///Phits read from any buffer of the router.
+	pub buffer_reads: usize,
+	///Phits moved from an input to an output.
+	pub crossbar_traversals: usize,
+	///Requests granted by the allocator.
+	pub allocations: usize,
+}
+
+impl EnergyCounters
+{
+	///Accumulate the counters of other router.
+	pub fn add(&mut self, other:&EnergyCounters)
+	{
+		self.buffer_writes+=other.buffer_writes;
+		self.buffer_reads+=other.buffer_reads;
+		self.crossbar_traversals+=other.crossbar_traversals;
+		self.allocations+=other.allocations;
+	}
+}
@@ -62,6 +62,8 @@ pub enum Event
 	Acknowledge{
 		///Location by which the phit was sent, containing the transmission status to be informed (such as credit counter).
 		location: Location,
+		///Location emitting the acknowledge. Required to distinguish among the rails of multi-homed servers.
+		source: Location,
 		// ///The virtual channel assigned to the phit for this hop
 		// virtual_channel: usize,
 		message: AcknowledgeMessage,
@@ -22,6 +22,7 @@ use crate::{Simulation,Plugs,source_location,error,match_object_panic};
 use crate::output::{create_output,OutputEnvironment,OutputEnvironmentEntry};
 use crate::config::{self,evaluate,flatten_configuration_value};
 use crate::error::{Error,ErrorKind,SourceLocation};
+use crate::topology::analysis::{analyze_configuration_topology,TopologyAnalysis,TopologyAnalysisOptions};
 
 #[derive(Debug,Clone,Copy,PartialEq)]
 pub enum Action
@@ -52,6 +53,14 @@ pub enum Action
 	Discard,
 	///Executes a few cycles of each simulation, to detect possible runtime failures.
 	QuickTest,
+	///Computes structural metrics of the topology of each experiment, without simulating.
+	///Writes the report of each experiment into the `topology.analysis` file of its run folder and a summary of all of them into `topology_analysis.csv`.
+	///See [`analysis`](crate::topology::analysis).
+	AnalyzeTopology,
+	///Computes the load of each channel for the pattern and routing given in the `channel_load` key of each experiment, without simulating.
+	///Writes the report of each experiment into the `channel.load` file of its run folder and a summary of all of them into `channel_load.csv`.
+	///See [`channel_load`](crate::routing::channel_load).
+	ChannelLoad,
 }
 
 impl FromStr for Action
@@ -75,6 +84,8 @@ impl FromStr for Action
 			"pack" => Ok(Action::Pack),
 			"discard" => Ok(Action::Discard),
 			"quick_test" => Ok(Action::QuickTest),
+			"analyze_topology" => Ok(Action::AnalyzeTopology),
+			"channel_load" => Ok(Action::ChannelLoad),
 			_ => Err(error!(bad_argument).with_message(format!("String {s} cannot be parsed as an Action."))),
 		}
 	}
@@ -1304,6 +1315,8 @@ impl<'a> Experiment<'a>
 			Action::Pack => (),
 			Action::Discard => (),
 			Action::QuickTest => (),
+			Action::AnalyzeTopology => (),
+			Action::ChannelLoad => (),
 		};
 
 		//Remove mutabiity to prevent mistakes.
@@ -1338,6 +1351,8 @@ impl<'a> Experiment<'a>
 		let mut delta_completed=0;
 		let sftp = self.remote_files.as_ref().map(|f|f.ssh2_session.as_ref().unwrap().sftp().unwrap());
 		let mut progress = ActionProgress::new(&action,end_index-start_index);
+		let mut topology_analyses = vec![];
+		let mut channel_loads = vec![];
 		for (experiment_index,experiment) in self.files.experiments.iter().enumerate().skip(start_index).take(end_index-start_index)
 		{
 			progress.inc(1);
@@ -1358,10 +1373,31 @@ impl<'a> Experiment<'a>
 				use Action::*;
 				match action
 				{
-					Local|LocalAndOutput|Slurm => fs::create_dir(&experiment_path).expect("Something went wrong when creating the run directory."),
+					Local|LocalAndOutput|Slurm|AnalyzeTopology|ChannelLoad => fs::create_dir(&experiment_path).expect("Something went wrong when creating the run directory."),
 					_ => (),
 				}
 			}
+			if let Action::AnalyzeTopology = action
+			{
+				//The analysis does not depend on the results, so it is done for every selected experiment.
+				let analysis = analyze_configuration_topology(experiment,self.plugs,&TopologyAnalysisOptions::default());
+				let analysis_path = experiment_path.join("topology.analysis");
+				let mut analysis_file = File::create(&analysis_path).map_err(|e|error!(file_system_error,e).with_message(format!("could not create {analysis_path:?}")))?;
+				writeln!(analysis_file,"{}",analysis.to_configuration_value()).map_err(|e|error!(file_system_error,e))?;
+				topology_analyses.push((experiment_index,analysis));
+				continue;
+			}
+			if let Action::ChannelLoad = action
+			{
+				//Only the construction of the simulation is required, as it computes the requested analyses.
+				let simulation=Simulation::new(experiment,self.plugs);
+				let channel_load = simulation.channel_load.unwrap_or_else(||panic!("experiment {} has no channel_load key",experiment_index));
+				let load_path = experiment_path.join("channel.load");
+				let mut load_file = File::create(&load_path).map_err(|e|error!(file_system_error,e).with_message(format!("could not create {load_path:?}")))?;
+				writeln!(load_file,"{}",channel_load).map_err(|e|error!(file_system_error,e))?;
+				channel_loads.push((experiment_index,channel_load));
+				continue;
+			}
 			let is_packed = if let ConfigurationValue::Experiments(ref a) = self.files.packed_results {
 				! matches!(a[experiment_index],ConfigurationValue::None)
 			} else {false};
@@ -1725,13 +1761,43 @@ impl<'a> Experiment<'a>
 							simulation.advance();
 						}
 					},
-					Action::Output | Action::RemoteCheck | Action::Push | Action::SlurmCancel | Action::Shell | Action::Pack | Action::Discard =>
+					Action::Output | Action::RemoteCheck | Action::Push | Action::SlurmCancel | Action::Shell | Action::Pack | Action::Discard | Action::AnalyzeTopology | Action::ChannelLoad =>
 					{
 					},
 				};
 			}
 		}
 		progress.finish();
+		if !topology_analyses.is_empty()
+		{
+			let max_distance = topology_analyses.iter().map(|(_,analysis)|analysis.diameter).max().unwrap();
+			let num_link_classes = topology_analyses.iter().map(|(_,analysis)|analysis.link_class_counts.len()).max().unwrap();
+			let csv_path = self.files.root.as_ref().unwrap().join("topology_analysis.csv");
+			let mut csv_file = File::create(&csv_path).map_err(|e|error!(file_system_error,e).with_message(format!("could not create {csv_path:?}")))?;
+			writeln!(csv_file,"experiment_index,{}",TopologyAnalysis::csv_header(max_distance,num_link_classes)).map_err(|e|error!(file_system_error,e))?;
+			for (experiment_index,analysis) in topology_analyses.iter()
+			{
+				writeln!(csv_file,"{},{}",experiment_index,analysis.csv_row(max_distance,num_link_classes)).map_err(|e|error!(file_system_error,e))?;
+			}
+			println!("Written the analysis of {} topologies into {:?}",topology_analyses.len(),csv_path);
+		}
+		if !channel_loads.is_empty()
+		{
+			let fields = ["maximum","maximum_router","maximum_port","average","throughput"];
+			let csv_path = self.files.root.as_ref().unwrap().join("channel_load.csv");
+			let mut csv_file = File::create(&csv_path).map_err(|e|error!(file_system_error,e).with_message(format!("could not create {csv_path:?}")))?;
+			writeln!(csv_file,"experiment_index,{}",fields.join(",")).map_err(|e|error!(file_system_error,e))?;
+			for (experiment_index,channel_load) in channel_loads.iter()
+			{
+				let values : Vec<String> = fields.iter().map(|field|match channel_load
+				{
+					ConfigurationValue::Object(_,pairs) => pairs.iter().find(|(name,_)|name==field).map(|(_,value)|value.to_string()).unwrap_or_default(),
+					_ => panic!("channel_load should be an object"),
+				}).collect();
+				writeln!(csv_file,"{},{}",experiment_index,values.join(",")).map_err(|e|error!(file_system_error,e))?;
+			}
+			println!("Written the channel load of {} experiments into {:?}",channel_loads.len(),csv_path);
+		}
 		if job.len()>0
 		{
 			let job_id=self.files.experiments.len();
@@ -25,6 +25,8 @@ Alternatively, consider whether the binary crate `caminos` fits your intended us
 * Router methods insert and acknowledge now return `Vec<EventGeneration>` and are responsible for their scheduling.
 * Renamed in Traffic nomenclature servers into tasks. This includes ServerTrafficState renamed into TaskTrafficState, and `server_state` into `task_state`. Old configuration names are still supported.
 * Added method `number_tasks`required for trait Traffic.
+* Added field `source` to `Event::Acknowledge`, the location emitting the acknowledgement.
+* Added field `target_router` to `Packet`. Routers must use `Packet::target_router` instead of computing it from `Topology::server_neighbour`.
 
 ## [0.5.0] to [0.6.0]
 * Removed unnecessary generic parameter TM from routers Basic and InputOutput. They now may select [TransmissionMechanisms](router::TransmissionMechanism) to employ.
@@ -95,6 +97,8 @@ The `directory_main` receives also an `Action`. In the crate `caminos` this is d
 * `slurm_cancel` executes a `scancel` with the job ids found in the journal file.
 * `shell` creates the experiment folder with default configuration files. Alternatively, when receiving `--source=another_experiment` it copies the configuration of the other experiment into this one.
 * `pack` forces the creation of a binary.results file and erases the verbose raw results files. In some extreme cases it can reduce a decent amount of space and sped up computations.
+* `analyze_topology` computes structural metrics of the topology of each experiment without simulating it, such as distance distribution, bisection, edge-disjoint paths, and spectral gap. It writes a `topology.analysis` file in the run folder of each experiment and a summary `topology_analysis.csv`.
+* `channel_load` computes the expected load of each channel for the pattern and routing given in the `channel_load` key of each experiment, without simulating. It writes a `channel.load` file in the run folder of each experiment and a summary `channel_load.csv`.
 
 
 # Configuration Syntax
@@ -156,6 +160,9 @@ Configuration
 		message_size: 16,//The size in phits of the messages created by the servers.
 	},
 	maximum_packet_size: 16,//Messages of greater length will be broken into several packets.
+	//phit_bits: 64,//optional, the bits of each phit, to report the energy per delivered bit. Defaults to 1.
+	//server_congestion_control: DCQCN,//optional, to regulate the injection of each server by the feedback from the network. See the congestion module.
+	//server_transport: Transport{timeout:1000},//optional, to retransmit the packets lost by the network. See the transport module.
 	router: Basic//The router is another object with a large description
 	{
 		//The number of virtual channels. The basic router sets a buffer for each virtual channel in each port, both at input and output.
@@ -171,6 +178,11 @@ Configuration
 		allow_request_busy_port: true,//whether to allow input buffer to make requests to ports that are transmitting
 		output_buffer_size:32,//Available phits in each output_buffer.
 		output_priorize_lowest_label: true,//whether arbiters give priority to requests with lowest label.
+		//transmission_mechanism: DAMQ{reserved_size:16},//optional, to share part of the buffer_size of each input port among its virtual channels.
+		//Other mechanisms are "Lossy", dropping the packets that do not fit, and PFC{xoff_threshold:48,xon_threshold:32}, pausing each virtual channel.
+		//ecn_threshold: 24,//optional, to mark the packets entering an output buffer with at least 24 phits, for the server_congestion_control.
+		//crossbar_speedup: 2,//optional, phits that each output virtual channel may receive from the inputs per cycle. Requires output buffers.
+		//energy: RouterEnergy{buffer_write:1.0,buffer_read:1.0,crossbar_traversal:2.0,allocation:0.5,static_power:10.0},//optional, the energy of each event of the router. See the energy module.
 	},
 	routing: ![//Algorithm to provide candidate exit ports.
 		Shortest { legend_name: "shortest" },
@@ -187,6 +199,14 @@ Configuration
 		LinkClass {
 			//In random regular graphs all router--router links have the same class.
 			delay:1,
+			//width:2,//optional, phits entering the link in each of its cycles. Defaults to 1.
+			//credit_delay:1,//optional, the delay of the credits returning through the link. By default equal to delay.
+			//credit_period:4,//optional, coalesce the credits released each 4 cycles into a single message. By default Basic returns each credit when released and InputOutput in the cycles of the link.
+			//phit_error_rate:0.0001,//optional, probability of a phit to arrive corrupted and be replayed by the link. Defaults to 0.
+			//replay_delay:2,//optional, cycles lost by each replay. By default delay+credit_delay.
+			//energy_per_phit:3.0,//optional, the energy of each phit traversing the link. Defaults to 0.
+			//static_power:1.0,//optional, the energy consumed by each link in each cycle. Defaults to 0.
+			//power_management: LinkPowerManagement{idle_timeout:100,wake_up_latency:20,rate_divisors:[4,2,1],rate_window:200},//optional, sleep states and rate levels. See the power module.
 		},
 		//The last class always correspond to the links between server and router
 		LinkClass { delay: 1},
@@ -337,6 +357,11 @@ pub mod error;
 pub mod measures;
 pub mod allocator;
 pub mod packet;
+pub mod throughput;
+pub mod congestion;
+pub mod transport;
+pub mod energy;
+pub mod power;
 
 use std::rc::Rc;
 use std::boxed::Box;
@@ -345,7 +370,7 @@ use std::env;
 use std::fs::{self,File};
 use std::io::prelude::*;
 use std::io::{stdout};
-use std::collections::{VecDeque,BTreeMap};
+use std::collections::{VecDeque,BTreeMap,BTreeSet};
 use std::ops::DerefMut;
 use std::path::{Path};
 use std::mem::{size_of};
@@ -353,7 +378,7 @@ use std::fmt::Debug;
 use std::cmp::Ordering;
 //use std::default::default;
 //use std::borrow::Cow;
-use rand::{rngs::StdRng,SeedableRng};
+use rand::{rngs::StdRng,SeedableRng,Rng};
 
 use config_parser::{ConfigurationValue,Expr};
 use topology::{Topology,new_topology,TopologyBuilderArgument,Location,
@@ -370,6 +395,10 @@ use config::flatten_configuration_value;
 use measures::{Statistics,ServerStatistics};
 use error::{Error,SourceLocation};
 use allocator::{Allocator,AllocatorBuilderArgument};
+use congestion::{CongestionControl,CongestionAck,UnacknowledgedPackets,new_congestion_control,flow_rate_statistics};
+use transport::{Transport,TransportControl};
+use energy::EnergyCounters;
+use power::{LinkPowerManagement,LinkPower,LinkSource};
 pub use packet::{Phit,Packet,Message,PacketExtraInfo,PacketRef};
 pub use event::Time;
 
@@ -379,10 +408,15 @@ pub struct Server
 {
 	///The index of the server in the network.
 	index: usize,
-	///To which router the server is connected + link class index. Although we could just compute with the topology each time...
-	port: (Location,usize),
-	///Known available capacity in the connected router.
-	router_status: Box<dyn router::StatusAtEmissor+'static>,
+	///To which routers the server is connected + link class index. Although we could just compute with the topology each time...
+	///There is one entry for each rail of the server, as given by `Topology::server_neighbours`.
+	ports: Vec<(Location,usize)>,
+	///Known available capacity in each of the connected routers.
+	router_status: Vec<Box<dyn router::StatusAtEmissor+'static>>,
+	///Whether the link of each rail is busy replaying a corrupted phit.
+	link_replay: LinkReplay,
+	///The rail being used to transmit the current packet, or the last one used.
+	current_rail: usize,
 	///Created messages but not sent.
 	stored_messages: VecDeque<Rc<Message>>,
 	///The packets of the message that have not yet been sent.
@@ -395,6 +429,42 @@ pub struct Server
 	consumed_phits: BTreeMap<*const Message,usize>,
 	///Statistics local to the server.
 	statistics: ServerStatistics,
+	///Whether packets are numbered and reassembled in order. Set by `Simulation::server_packet_sequencing`.
+	packet_sequencing: bool,
+	///Whether messages are given to the traffic only when they have been delivered in order. Set by `Simulation::server_reorder_buffer`.
+	reorder_buffer: bool,
+	///The sequence number to give to the next packet sent to each destination.
+	sent_sequences: BTreeMap<usize,usize>,
+	///The reassembly state of the packets received from each origin.
+	received_flows: BTreeMap<usize,ReceivedFlow>,
+	///Number of packets received that are waiting for some previous packet of their flow.
+	reorder_buffer_occupancy: usize,
+	///For each message we store the number of phits delivered in order, until the whole message is delivered.
+	delivered_phits: BTreeMap<*const Message,usize>,
+	///The controller deciding when the packets may be sent to each destination. Set by `Simulation::server_congestion_control`.
+	congestion_control: Option<Box<dyn CongestionControl>>,
+	///The packets waiting for their congestion acknowledgement, when there is a congestion controller but no transport.
+	unacknowledged: Option<UnacknowledgedPackets>,
+	///The state of the reliable transport. Set by `Simulation::server_transport`.
+	transport: Option<Transport>,
+}
+
+///The reassembly state at a destination server of the packets coming from some origin server.
+#[derive(Default,Quantifiable)]
+struct ReceivedFlow
+{
+	///The sequence number of the earliest packet that has not arrived yet.
+	expected: usize,
+	///The packets that have arrived before some previous packet, indexed by their sequence number.
+	held: BTreeMap<usize,HeldPacket>,
+}
+
+///A packet kept in a reorder buffer. The packet itself has been already consumed, we only need its message and size.
+#[derive(Quantifiable)]
+struct HeldPacket
+{
+	message: Rc<Message>,
+	size: usize,
 }
 
 impl Server
@@ -404,6 +474,23 @@ impl Server
 	{
 		self.statistics.track_consumed_phit(cycle);
 		statistics.track_consumed_phit(cycle);
+		if self.transport.is_some()
+		{
+			//The transport handles whole packets, as they may be duplicated.
+			if phit.is_end()
+			{
+				self.consume_transported_packet(&phit.packet,traffic,statistics,cycle,topology,rng);
+			}
+			return;
+		}
+		if phit.packet.control.is_some()
+		{
+			if phit.is_end()
+			{
+				self.consume_congestion_acknowledgement(&phit.packet,statistics,cycle);
+			}
+			return;
+		}
 		let message=phit.packet.message.clone();
 		let message_ptr=message.as_ref() as *const Message;
 		//println!("phit consumed at server {}: stats {:?}",self.index,statistics);
@@ -415,15 +502,7 @@ impl Server
 		if cp==message.size
 		{
 			//The whole message has been consumed
-			self.statistics.track_consumed_message(cycle);
-			statistics.track_consumed_message(cycle);
-			self.statistics.track_message_delay(cycle-message.creation_cycle,cycle);
-			statistics.track_message_delay(cycle-message.creation_cycle,cycle);
-			self.consumed_phits.remove(&message_ptr);
-			if !traffic.try_consume(self.index,message,cycle,topology,rng)
-			{
-				panic!("The traffic could not consume its own message.");
-			}
+			self.complete_message(message,traffic,statistics,cycle,topology,rng);
 			if !phit.is_end()
 			{
 				panic!("message was consumed by a non-ending phit.");
@@ -446,9 +525,300 @@ impl Server
 			{
 				println!("phit tail has been consuming without having consumed a whole packet.");
 			}
+			if self.packet_sequencing
+			{
+				self.reassemble(&phit.packet,traffic,statistics,cycle,topology,rng);
+			}
+			if self.unacknowledged.is_some()
+			{
+				let marked = *phit.packet.congestion_experienced.borrow();
+				self.push_control_packet(phit.packet.message.origin,TransportControl::Acknowledgement(phit.packet.sequence,marked),cycle);
+			}
 			phit.packet.destroy();//See the notes on the raw_packet feature.
 		}
 	}
+	///Consumes an acknowledgement of the congestion control when there is no transport. See the [congestion] module.
+	fn consume_congestion_acknowledgement(&mut self, packet:&PacketRef, statistics:&mut Statistics, cycle:Time)
+	{
+		statistics.track_consumed_packet(cycle,packet);
+		let origin = packet.message.origin;
+		if let Some(TransportControl::Acknowledgement(sequence,marked)) = packet.control
+		{
+			//The packets already considered lost have released their space.
+			if let Some((size,sent)) = self.unacknowledged.as_mut().expect("acknowledgement to a server without congestion control").acknowledge(origin,sequence)
+			{
+				let control = self.congestion_control.as_mut().unwrap();
+				let ack = CongestionAck{
+					destination: origin,
+					size,
+					marked,
+					round_trip: cycle-sent,
+				};
+				control.notify_ack(&ack,cycle);
+				statistics.track_congestion_ack(self.index,origin,control.rate(origin),cycle);
+			}
+		}
+		packet.destroy();//See the notes on the raw_packet feature.
+	}
+	///Called when all the phits of a message have been consumed.
+	fn complete_message(&mut self, message:Rc<Message>, traffic:&mut dyn Traffic, statistics:&mut Statistics, cycle:Time, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		let message_ptr=message.as_ref() as *const Message;
+		self.statistics.track_consumed_message(cycle);
+		statistics.track_consumed_message(cycle);
+		self.statistics.track_message_delay(cycle-message.creation_cycle,cycle);
+		statistics.track_message_delay(cycle-message.creation_cycle,cycle);
+		self.consumed_phits.remove(&message_ptr);
+		//With a reorder buffer the traffic gets the message when it is delivered in order.
+		if !self.reorder_buffer && !traffic.try_consume(self.index,message,cycle,topology,rng)
+		{
+			panic!("The traffic could not consume its own message.");
+		}
+	}
+	///Consumes a packet whose tail has arrived when using the reliable transport. See the [transport] module.
+	fn consume_transported_packet(&mut self, packet:&PacketRef, traffic:&mut dyn Traffic, statistics:&mut Statistics, cycle:Time, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		statistics.track_consumed_packet(cycle,packet);
+		let origin = packet.message.origin;
+		match packet.control
+		{
+			Some(TransportControl::Acknowledgement(sequence,marked)) =>
+			{
+				let acknowledged = self.transport.as_mut().unwrap().acknowledge(origin,sequence);
+				//Only the first acknowledgement of a packet still in the window reaches the congestion controller.
+				if let (Some((size,sent)),Some(control)) = (acknowledged,self.congestion_control.as_mut())
+				{
+					let ack = CongestionAck{
+						destination: origin,
+						size,
+						marked,
+						round_trip: cycle-sent,
+					};
+					control.notify_ack(&ack,cycle);
+					statistics.track_congestion_ack(self.index,origin,control.rate(origin),cycle);
+				}
+			},
+			Some(TransportControl::NegativeAcknowledgement(sequence)) =>
+			{
+				if self.transport.as_mut().unwrap().negative_acknowledge(origin,sequence)
+				{
+					self.retransmit(origin,sequence,statistics,cycle);
+					statistics.track_retransmission(false,cycle);
+				}
+			},
+			None =>
+			{
+				//Acknowledge even duplicates, since the previous acknowledgement may have been lost.
+				let marked = *packet.congestion_experienced.borrow();
+				self.push_control_packet(origin,TransportControl::Acknowledgement(packet.sequence,marked),cycle);
+				let flow = self.received_flows.entry(origin).or_default();
+				if packet.sequence < flow.expected || flow.held.contains_key(&packet.sequence)
+				{
+					statistics.track_duplicate_packet(cycle);
+				}
+				else
+				{
+					let expected = flow.expected;
+					if packet.sequence > expected && self.transport.as_mut().unwrap().request_missing(origin,expected)
+					{
+						self.push_control_packet(origin,TransportControl::NegativeAcknowledgement(expected),cycle);
+						statistics.track_negative_acknowledgement(cycle);
+					}
+					statistics.track_goodput(packet.size,cycle);
+					let message=packet.message.clone();
+					let message_ptr=message.as_ref() as *const Message;
+					let cp = self.consumed_phits.get(&message_ptr).unwrap_or(&0) + packet.size;
+					if cp==message.size
+					{
+						self.complete_message(message,traffic,statistics,cycle,topology,rng);
+					}
+					else
+					{
+						self.consumed_phits.insert(message_ptr,cp);
+					}
+					self.reassemble(packet,traffic,statistics,cycle,topology,rng);
+				}
+			},
+		}
+		packet.destroy();//See the notes on the raw_packet feature.
+	}
+	///Build a new packet of a message, ready to be stored in the server.
+	fn build_packet(&self, message:Rc<Message>, index:usize, size:usize, sequence:usize, control:Option<TransportControl>) -> PacketRef
+	{
+		Packet{
+			size,
+			routing_info: RefCell::new(RoutingInfo{source_server:Some(self.index),..RoutingInfo::new()}),
+			message,
+			index,
+			sequence,
+			cycle_into_network:RefCell::new(0),
+			target_router:RefCell::new(None),
+			congestion_experienced:RefCell::new(false),
+			control,
+			extra: RefCell::new(None),
+		}.into_ref()
+	}
+	///Split a message into packets, storing them after the other packets of the server.
+	fn packetize(&mut self, message:Rc<Message>, maximum_packet_size:usize)
+	{
+		let mut size=message.size;
+		let mut index=0;
+		while size>0
+		{
+			let ps=if size>maximum_packet_size
+			{
+				maximum_packet_size
+			}
+			else
+			{
+				size
+			};
+			let sequence = if self.packet_sequencing || self.unacknowledged.is_some() {
+				let next = self.sent_sequences.entry(message.destination).or_insert(0);
+				*next+=1;
+				*next-1
+			} else {0};
+			let packet = self.build_packet(message.clone(),index,ps,sequence,None);
+			self.stored_packets.push_back(packet);
+			size-=ps;
+			index+=1;
+		}
+	}
+	///Queue a control packet of the transport or the congestion control towards `destination`, to be sent before the data packets.
+	///Without a transport the acknowledgements have a single phit.
+	fn push_control_packet(&mut self, destination:usize, control:TransportControl, cycle:Time)
+	{
+		let size = self.transport.as_ref().map(|transport|transport.acknowledgement_size).unwrap_or(1);
+		let message = Rc::new(Message{
+			origin: self.index,
+			destination,
+			size,
+			creation_cycle: cycle,
+		});
+		let packet = self.build_packet(message,0,size,0,Some(control));
+		self.stored_packets.push_front(packet);
+	}
+	///Queue the retransmission of a packet pending of acknowledgement, to be sent before the other stored packets.
+	///The packet is considered lost, releasing its space in the congestion window.
+	fn retransmit(&mut self, destination:usize, sequence:usize, statistics:&mut Statistics, cycle:Time)
+	{
+		let released = self.transport.as_mut().unwrap().release(destination,sequence);
+		if let (Some(size),Some(control)) = (released,self.congestion_control.as_mut())
+		{
+			control.notify_loss(destination,size,cycle);
+			statistics.track_congestion_loss(cycle);
+		}
+		let pending = self.transport.as_ref().unwrap().pending(destination,sequence).expect("retransmitting a packet already acknowledged");
+		let packet = self.build_packet(pending.message.clone(),pending.index,pending.size,sequence,None);
+		self.stored_packets.push_front(packet);
+	}
+	///Puts a completely received packet into the reorder buffer and delivers all the packets of its flow that are now in order.
+	fn reassemble(&mut self, packet:&Packet, traffic:&mut dyn Traffic, statistics:&mut Statistics, cycle:Time, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		let flow = self.received_flows.entry(packet.message.origin).or_default();
+		if packet.sequence < flow.expected || flow.held.contains_key(&packet.sequence)
+		{
+			panic!("server {} received twice the packet with sequence {} from server {}",self.index,packet.sequence,packet.message.origin);
+		}
+		let distance = packet.sequence - flow.expected;
+		flow.held.insert(packet.sequence,HeldPacket{message:packet.message.clone(),size:packet.size});
+		self.reorder_buffer_occupancy+=1;
+		let mut in_order = vec![];
+		while let Some(held) = flow.held.remove(&flow.expected)
+		{
+			flow.expected+=1;
+			self.reorder_buffer_occupancy-=1;
+			in_order.push(held);
+		}
+		statistics.track_packet_reorder(distance,self.reorder_buffer_occupancy,cycle);
+		for held in in_order
+		{
+			let message_ptr = held.message.as_ref() as *const Message;
+			let dp = self.delivered_phits.get(&message_ptr).unwrap_or(&0) + held.size;
+			if dp==held.message.size
+			{
+				self.delivered_phits.remove(&message_ptr);
+				statistics.track_effective_message_delay(cycle-held.message.creation_cycle,cycle);
+				if self.reorder_buffer && !traffic.try_consume(self.index,held.message,cycle,topology,rng)
+				{
+					panic!("The traffic could not consume its own message.");
+				}
+			}
+			else
+			{
+				self.delivered_phits.insert(message_ptr,dp);
+			}
+		}
+	}
+}
+
+impl Server
+{
+	///Select the rail by which to inject the given leading phit.
+	fn select_rail(&self, phit:&Rc<Phit>, policy:&RailSelection, rng:&mut StdRng) -> usize
+	{
+		let amount = self.ports.len();
+		if amount==1
+		{
+			return 0;
+		}
+		match policy
+		{
+			RailSelection::First => 0,
+			RailSelection::Random => rng.gen_range(0..amount),
+			RailSelection::RoundRobin => (self.current_rail+1) % amount,
+			RailSelection::MostAvailableSpace =>
+			{
+				//Sum the space known for the virtual channels able to receive the phit, keeping the first rail on ties.
+				let mut best = 0;
+				let mut best_space = None;
+				for (rail,status) in self.router_status.iter().enumerate()
+				{
+					let space : usize = (0..status.num_virtual_channels()).filter(|&vc|status.can_transmit(phit,vc)).map(|vc|status.known_available_space_for_virtual_channel(vc).unwrap_or(0)).sum();
+					if best_space.map(|b|space>b).unwrap_or(true)
+					{
+						best = rail;
+						best_space = Some(space);
+					}
+				}
+				best
+			},
+		}
+	}
+}
+
+///How a multi-homed server selects among its rails to inject each packet. Set by the `server_rail_selection` key of the simulation.
+///See the [MultiHomed](topology::operations::MultiHomed) topology.
+#[derive(Debug)]
+pub enum RailSelection
+{
+	///Always use the first rail, which is the attachment given by `Topology::server_neighbour`. This is the default.
+	First,
+	///A random rail for each packet.
+	Random,
+	///Each packet uses the rail following the one used by the previous packet.
+	RoundRobin,
+	///The rail with more known available space in the router for the leading phit, adding over its usable virtual channels.
+	MostAvailableSpace,
+}
+
+pub fn new_rail_selection(cv: &ConfigurationValue) -> RailSelection
+{
+	if let ConfigurationValue::Object(cv_name, _cv_pairs)=cv
+	{
+		match cv_name.as_ref()
+		{
+			"First" => RailSelection::First,
+			"Random" => RailSelection::Random,
+			"RoundRobin" => RailSelection::RoundRobin,
+			"MostAvailableSpace" => RailSelection::MostAvailableSpace,
+			_ => panic!("Unknown rail selection {}",cv_name),
+		}
+	}
+	else
+	{
+		panic!("Trying to create a RailSelection from a non-Object");
+	}
 }
 
 //impl Quantifiable for Server
@@ -567,6 +937,43 @@ impl Network
 	}
 }
 
+///A read-only view of the occupation of the links of the whole network.
+///It is given to the routings that request it through [Routing::set_network_state], as an oracle of the queues of all the routers.
+///The simulator refreshes it at the beginning of each cycle, only when some routing keeps it.
+#[derive(Debug,Default)]
+pub struct NetworkState
+{
+	///`occupation[router][port]` is the number of phits sent through the port whose space at the other endpoint has not been released yet.
+	occupation: RefCell<Vec<Vec<usize>>>,
+}
+
+impl NetworkState
+{
+	///The number of phits sent by `router` through `port` that are in the link or occupying space at the other endpoint, as of the beginning of the current cycle.
+	///It is 0 before the first refresh.
+	pub fn occupation(&self, router:usize, port:usize) -> usize
+	{
+		self.occupation.borrow().get(router).and_then(|ports|ports.get(port).cloned()).unwrap_or(0)
+	}
+	///Read the current occupation from the routers of the network. The routers must not be borrowed.
+	fn refresh(&self, network:&Network)
+	{
+		let mut occupation = self.occupation.borrow_mut();
+		if occupation.len()!=network.routers.len()
+		{
+			*occupation = (0..network.routers.len()).map(|index|vec![0;network.topology.ports(index)]).collect();
+		}
+		for (index,router) in network.routers.iter().enumerate()
+		{
+			let router = router.borrow();
+			for (port,port_occupation) in occupation[index].iter_mut().enumerate()
+			{
+				*port_occupation = router::occupied_neighbour_space(&*router,port);
+			}
+		}
+	}
+}
+
 ///Description of common properties of sets of links.
 ///For example, the links to servers could have a different delay.
 ///The topologies can set additional classes. For example, a mesh/torus can differentiate horizontal/vertical links.
@@ -581,6 +988,26 @@ pub struct LinkClass
 	///A phit can enter the link only in those cycles multiple of `frequency_divisor`.
 	///By default it is set a value of 0, value which will be replaced with the global frequency divisor of the simulation (whose default is 1).
 	frequency_divisor: Time,
+	///Number of phits that can enter the link in each of its cycles. Defaults to 1.
+	///The bandwidth of the link is `width/frequency_divisor` phits per cycle.
+	pub width: usize,
+	///Cycles the credits need to return through the reverse link. By default the same as `delay`.
+	pub credit_delay: Time,
+	///The credits released in the receptor are coalesced into a single message sent in those cycles multiple of `credit_period`.
+	///A value of 1 sends a credit message per phit. When not given, the `Basic` router sends each credit as soon as it is released,
+	///while the `InputOutput` router sends them in the cycles of the link, as given by its `frequency_divisor`.
+	credit_period: Option<Time>,
+	///Probability of each transmitted phit to arrive corrupted. Defaults to 0.
+	///A corrupted phit is replayed by the emissor from its replay buffer, which keeps the link busy. The replay may also be corrupted.
+	pub phit_error_rate: f64,
+	///Cycles since the transmission of a corrupted phit until its replay. By default `delay+credit_delay`, the time to notify the error to the emissor.
+	pub replay_delay: Time,
+	///Energy consumed by each phit traversing the link, including the replays. Defaults to 0. See the [energy] module.
+	pub energy_per_phit: f64,
+	///Energy consumed by each directed link of the class in each cycle. Defaults to 0.
+	pub static_power: f64,
+	///The sleep states and rate levels of the links, if any. See the [power] module.
+	pub power_management: Option<LinkPowerManagement>,
 }
 
 impl LinkClass
@@ -589,14 +1016,48 @@ impl LinkClass
 	{
 		let mut delay=None;
 		let mut frequency_divisor = 0;
+		let mut width = 1;
+		let mut credit_delay=None;
+		let mut credit_period = None;
+		let mut phit_error_rate = 0.0;
+		let mut replay_delay=None;
+		let mut energy_per_phit = 0.0;
+		let mut static_power = 0.0;
+		let mut power_management = None;
 		match_object_panic!(cv,"LinkClass",value,
 			"delay" => delay=Some(value.as_time().expect("bad value for delay")),
 			"frequency_divisor" => frequency_divisor = value.as_time().expect("bad value for frequency_divisor"),
+			"width" => width = value.as_usize().expect("bad value for width"),
+			"credit_delay" => credit_delay=Some(value.as_time().expect("bad value for credit_delay")),
+			"credit_period" => credit_period = Some(value.as_time().expect("bad value for credit_period")),
+			"phit_error_rate" => phit_error_rate = value.as_f64().expect("bad value for phit_error_rate"),
+			"replay_delay" => replay_delay=Some(value.as_time().expect("bad value for replay_delay")),
+			"energy_per_phit" => energy_per_phit = value.as_f64().expect("bad value for energy_per_phit"),
+			"static_power" => static_power = value.as_f64().expect("bad value for static_power"),
+			"power_management" => power_management = Some(LinkPowerManagement::new(value)),
 		);
 		let delay=delay.expect("There were no delay");
+		let credit_delay=credit_delay.unwrap_or(delay);
+		if width==0
+		{
+			panic!("The width of a LinkClass must be positive");
+		}
+		if !(0.0..1.0).contains(&phit_error_rate)
+		{
+			panic!("The phit_error_rate must be in [0,1), but it is {}",phit_error_rate);
+		}
+		let replay_delay=replay_delay.unwrap_or(delay+credit_delay);
 		LinkClass{
 			delay,
 			frequency_divisor,
+			width,
+			credit_delay,
+			credit_period,
+			phit_error_rate,
+			replay_delay,
+			energy_per_phit,
+			static_power,
+			power_management,
 		}
 	}
 }
@@ -650,6 +1111,36 @@ impl SimulationShared
 			event,
 		}
 	}
+	/**
+		Schedule the return of credits, usually an `Event::Acknowledge`, through the reverse of a link of the given class.
+		The credits released in the same `credit_period` of the link are sent together in its next slot, arriving after the `credit_delay`.
+		Without a `credit_period` the credits are sent immediately.
+	**/
+	pub fn schedule_credit_return(&self, link_class:usize, event:Event) -> EventGeneration
+	{
+		let period = self.link_classes[link_class].credit_period.unwrap_or(1);
+		self.schedule_credit_return_with_period(link_class,period,event)
+	}
+	/**
+		Schedule the return of credits as `schedule_credit_return`, but sending them in the cycles of the link when it has no `credit_period`.
+	**/
+	pub fn schedule_credit_return_in_link_cycles(&self, link_class:usize, event:Event) -> EventGeneration
+	{
+		let link = &self.link_classes[link_class];
+		let period = link.credit_period.unwrap_or(link.frequency_divisor);
+		self.schedule_credit_return_with_period(link_class,period,event)
+	}
+	fn schedule_credit_return_with_period(&self, link_class:usize, period:Time, event:Event) -> EventGeneration
+	{
+		let link = &self.link_classes[link_class];
+		let slot = event::round_to_multiple(self.cycle,period);
+		let wait = slot - self.cycle;
+		EventGeneration{
+			delay: wait + link.credit_delay,
+			position: event::CyclePosition::Begin,
+			event,
+		}
+	}
 }
 
 /**
@@ -659,6 +1150,86 @@ pub struct SimulationMut
 {
 	///The random number generator itself, with its current state.
 	pub rng: StdRng,
+	///The link-level retries made in the links of each class since the last reset of the statistics.
+	pub link_retries: Vec<LinkRetryCounters>,
+	///The power states of the links with power management.
+	pub link_power: LinkPower,
+}
+
+///Counters of the replays of corrupted phits in the links of a class.
+#[derive(Clone,Debug,Default)]
+pub struct LinkRetryCounters
+{
+	///Number of corrupted transmissions.
+	pub retries: usize,
+	///Cycles in which the links have been busy replaying, which is the bandwidth lost.
+	pub replay_cycles: Time,
+}
+
+impl SimulationMut
+{
+	/**
+	Draws how many times a phit transmitted now through a link of class `link_class` arrives corrupted, tracking them into `link_retries`.
+	Returns the cycles spent replaying the phit, which delay its arrival. The emissors use it through [LinkReplay], to transmit nothing else through the link meanwhile.
+	**/
+	pub fn draw_link_replay(&mut self, link_classes:&[LinkClass], link_class:usize) -> Time
+	{
+		let link = &link_classes[link_class];
+		if link.phit_error_rate==0.0
+		{
+			return 0;
+		}
+		let mut retries = 0;
+		while self.rng.gen_bool(link.phit_error_rate)
+		{
+			retries+=1;
+		}
+		let replay_cycles = retries as Time * link.replay_delay;
+		let counters = &mut self.link_retries[link_class];
+		counters.retries+=retries;
+		counters.replay_cycles+=replay_cycles;
+		replay_cycles
+	}
+}
+
+///The cycle until which each link of an emissor, either a router or a server, is busy replaying a corrupted phit. See the `phit_error_rate` of [LinkClass].
+#[derive(Debug,Quantifiable)]
+pub struct LinkReplay
+{
+	///`until[link]` is the first cycle in which the link may transmit again.
+	until: Vec<Time>,
+}
+
+impl LinkReplay
+{
+	///The state of an emissor with `links` outgoing links, none of them replaying.
+	pub fn new(links:usize) -> LinkReplay
+	{
+		LinkReplay{
+			until: vec![0;links],
+		}
+	}
+	///The first cycle in which the link may transmit again.
+	pub fn busy_until(&self, link:usize) -> Time
+	{
+		self.until[link]
+	}
+	///Whether the link is busy replaying in the cycle `cycle`.
+	pub fn is_busy(&self, link:usize, cycle:Time) -> bool
+	{
+		self.until[link]>cycle
+	}
+	///Called when a phit is transmitted in the cycle `cycle` through the link, which is of class `link_class`. Draws its corruptions with [SimulationMut::draw_link_replay], keeping the link busy while replaying.
+	///Returns the delay to add to the arrival of the phit.
+	pub fn transmit(&mut self, link:usize, link_class:usize, link_classes:&[LinkClass], cycle:Time, mutable:&mut SimulationMut) -> Time
+	{
+		let replay = mutable.draw_link_replay(link_classes,link_class);
+		if replay>0
+		{
+			self.until[link]=cycle+replay+1;
+		}
+		replay
+	}
 }
 
 ///The object representing the whole simulation.
@@ -682,6 +1253,21 @@ pub struct Simulation<'a>
 	///Attempts to generate traffic that fails because of the limit are tracked into the `missed_generations` statistic.
 	///Note that packets are not generated until it is the turn for the message to be sent to a router.
 	pub server_queue_size: usize,
+	///How servers with several attachments select the one to inject each packet.
+	pub server_rail_selection: RailSelection,
+	///Whether to number the packets sent between each pair of servers, as set by the `server_packet_sequencing` key. Defaults to false.
+	///The destination server keeps the packets arriving out of order until the previous ones of their flow arrive, which is measured into `reorder_statistics`.
+	///Note that it requires to keep some state for each pair of communicating servers.
+	pub server_packet_sequencing: bool,
+	///Whether the messages are given to the traffic only after being delivered in order by the reorder buffer, as set by the `server_reorder_buffer` key. Defaults to false.
+	///Otherwise the traffic consumes each message as soon as all its phits have arrived. Enabling it implies `server_packet_sequencing`.
+	pub server_reorder_buffer: bool,
+	///The configuration of the congestion controller of each server, as set by the `server_congestion_control` key. Defaults to none.
+	///When enabled, the destination servers acknowledge each packet to its origin with a packet through the network, carrying the marks of the routers. See the [congestion] module.
+	pub server_congestion_control: Option<ConfigurationValue>,
+	///The configuration of the reliable transport of the servers, as set by the `server_transport` key. Defaults to none.
+	///When enabled the servers retransmit the packets not acknowledged by their destination. It implies `server_packet_sequencing`. See the [transport] module.
+	pub server_transport: Option<ConfigurationValue>,
 	///The queue of events guiding the simulation.
 	pub event_queue: EventQueue,
 	///The statistics being collected.
@@ -693,6 +1279,14 @@ pub struct Simulation<'a>
 	pub plugs: &'a Plugs,
 	///Number of cycles to wait between reports of memory usage.
 	pub memory_report_period: Option<Time>,
+	///The analytical bound of throughput, if requested by the `throughput_bound` key. See [throughput].
+	pub throughput_bound: Option<ConfigurationValue>,
+	///The analytical load of the channels, if requested by the `channel_load` key. See [routing::channel_load].
+	pub channel_load: Option<ConfigurationValue>,
+	///The view of the network given to the routing, if it has requested one.
+	pub network_state: Option<Rc<NetworkState>>,
+	///The number of bits of a phit, as set by the `phit_bits` key. Defaults to 1. It gives the `energy_per_delivered_bit` of the [energy] module.
+	pub phit_bits: usize,
 }
 
 impl<'a> Simulation<'a>
@@ -714,8 +1308,17 @@ impl<'a> Simulation<'a>
 		let mut statistics_packet_percentiles: Vec<u8> = vec![];
 		let mut statistics_packet_definitions:Vec< (Vec<Expr>,Vec<Expr>) > = vec![];
 		let mut server_queue_size = None;
+		let mut server_rail_selection = RailSelection::First;
+		let mut server_packet_sequencing = false;
+		let mut server_reorder_buffer = false;
+		let mut server_congestion_control = None;
+		let mut server_transport = None;
 		let mut memory_report_period = None;
 		let mut general_frequency_divisor = 1;
+		let mut throughput_bound = None;
+		let mut channel_load = None;
+		let mut routing_cv = None;
+		let mut phit_bits = 1;
 		match_object_panic!(cv,"Configuration",value,
 			"random_seed" => seed=Some(value.as_usize().expect("bad value for random_seed")),
 			"warmup" => warmup=Some(value.as_time().expect("bad value for warmup")),
@@ -724,8 +1327,16 @@ impl<'a> Simulation<'a>
 			"traffic" => traffic=Some(value),
 			"maximum_packet_size" => maximum_packet_size=Some(value.as_usize().expect("bad value for maximum_packet_size")),
 			"server_queue_size" => server_queue_size=Some(value.as_usize().expect("bad value for server_queue_size")),
+			"server_rail_selection" => server_rail_selection=new_rail_selection(value),
+			"server_packet_sequencing" => server_packet_sequencing=value.as_bool().expect("bad value for server_packet_sequencing"),
+			"server_reorder_buffer" => server_reorder_buffer=value.as_bool().expect("bad value for server_reorder_buffer"),
+			"server_congestion_control" => server_congestion_control=Some(value.clone()),
+			"server_transport" => server_transport=Some(value.clone()),
 			"router" => router_cfg=Some(value),
-			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,plugs})),
+			"routing" => {
+				routing_cv=Some(value);
+				routing=Some(new_routing(RoutingBuilderArgument{cv:value,plugs}));
+			},
 			"link_classes" => link_classes = Some(value.as_array().expect("bad value for link_classes").iter()
 				.map(LinkClass::new).collect()),
 			"statistics_temporal_step" => statistics_temporal_step=value.as_time().expect("bad value for statistics_temporal_step"),
@@ -768,7 +1379,11 @@ impl<'a> Simulation<'a>
 			}
 			"memory_report_period" => memory_report_period=Some(value.as_time().expect("bad value for memory_report_period")),
 			"general_frequency_divisor" => general_frequency_divisor = value.as_time().expect("bad value for general_frequency_divisor"),
+			"throughput_bound" => throughput_bound=Some(throughput::ThroughputBound::new(value,plugs)),
+			"channel_load" => channel_load=Some(routing::channel_load::ChannelLoad::new(value,plugs)),
+			"phit_bits" => phit_bits=value.as_usize().expect("bad value for phit_bits"),
 		);
+		assert!(phit_bits>0, "The phit_bits must be positive.");
 		let seed=seed.expect("There were no random_seed");
 		let warmup=warmup.expect("There were no warmup");
 		let measured=measured.expect("There were no measured");
@@ -777,6 +1392,7 @@ impl<'a> Simulation<'a>
 		let maximum_packet_size=maximum_packet_size.expect("There were no maximum_packet_size");
 		let server_queue_size = server_queue_size.unwrap_or(20);
 		assert!(server_queue_size>0, "we need space in the servers to store generated messages.");
+		let server_packet_sequencing = server_packet_sequencing || server_reorder_buffer || server_transport.is_some();
 		let router_cfg=router_cfg.expect("There were no router");
 		let mut routing=routing.expect("There were no routing");
 		let mut link_classes:Vec<LinkClass>=link_classes.expect("There were no link_classes");
@@ -795,6 +1411,11 @@ impl<'a> Simulation<'a>
 			rng:&mut rng,
 		});
 		topology.check_adjacency_consistency(Some(link_classes.len()));
+		//The analyses use their own generator to not alter the simulation.
+		let link_capacities : Vec<f64> = link_classes.iter().map(|link_class|link_class.width as f64/link_class.frequency_divisor as f64).collect();
+		let throughput_bound = throughput_bound.map(|mut bound|{
+			bound.compute(topology.as_ref(),&link_capacities,&mut StdRng::seed_from_u64(seed as u64))
+		});
 		routing.initialize(topology.as_ref(),&mut rng);
 		let num_routers=topology.num_routers();
 		let num_servers=topology.num_servers();
@@ -809,9 +1430,12 @@ impl<'a> Simulation<'a>
 			statistics_temporal_step,
 			rng:&mut rng,
 		})).collect();
-		let servers=(0..num_servers).map(|index|{
-			let port=topology.server_neighbour(index);
-			let router_status=match port.0
+		let network_state = Rc::new(NetworkState::default());
+		let network_state = if routing.set_network_state(&network_state) { Some(network_state) } else { None };
+		let routing_events = routing.initial_events();
+		let servers:Vec<Server>=(0..num_servers).map(|index|{
+			let ports=topology.server_neighbours(index);
+			let router_status=ports.iter().map(|port|match port.0
 			{
 				Location::RouterPort{
 					router_index,
@@ -829,19 +1453,41 @@ impl<'a> Simulation<'a>
 					router.build_emissor_status(router_port,&*topology)
 				}
 				_ => panic!("Server is not connected to router"),
+			}).collect();
+			let congestion_control = server_congestion_control.as_ref().map(new_congestion_control);
+			//With a transport the losses are detected by it.
+			let unacknowledged = match (&congestion_control,&server_transport)
+			{
+				(Some(control),None) => Some(UnacknowledgedPackets::new(control.loss_timeout())),
+				_ => None,
 			};
 			Server{
 				index,
-				port,
+				link_replay: LinkReplay::new(ports.len()),
+				ports,
 				router_status,
+				current_rail: 0,
 				stored_messages:VecDeque::new(),
 				stored_packets:VecDeque::new(),
 				stored_phits:VecDeque::new(),
 				outcoming_virtual_channel: None,
 				consumed_phits: BTreeMap::new(),
 				statistics: ServerStatistics::new(statistics_temporal_step),
+				packet_sequencing: server_packet_sequencing,
+				reorder_buffer: server_reorder_buffer,
+				sent_sequences: BTreeMap::new(),
+				received_flows: BTreeMap::new(),
+				reorder_buffer_occupancy: 0,
+				delivered_phits: BTreeMap::new(),
+				unacknowledged,
+				congestion_control,
+				transport: server_transport.as_ref().map(Transport::new),
 			}
 		}).collect();
+		let channel_load = channel_load.map(|mut channel_load|{
+			let virtual_channels = routers.first().map(|router|router.borrow().num_virtual_channels()).unwrap_or(1);
+			channel_load.compute(topology.as_ref(),routing_cv.unwrap(),plugs,virtual_channels,&link_capacities,&mut StdRng::seed_from_u64(seed as u64))
+		});
 		let traffic=new_traffic(TrafficBuilderArgument{
 			cv:traffic,
 			plugs,
@@ -853,8 +1499,29 @@ impl<'a> Simulation<'a>
 		{
 			println!("WARNING: Generating traffic over {} tasks when the topology has {} servers.",num_tasks,num_servers);
 		}
+		let link_retries = vec![LinkRetryCounters::default();link_classes.len()];
+		let mut link_power = LinkPower::default();
+		for router_index in 0..topology.num_routers()
+		{
+			for router_port in 0..topology.ports(router_index)
+			{
+				let (location,link_class) = topology.neighbour(router_index,router_port);
+				if let Location::None = location
+				{
+					continue;
+				}
+				link_power.add_link(LinkSource::RouterPort{router_index,router_port},&link_classes,link_class);
+			}
+		}
+		for (server_index,server) in servers.iter().enumerate()
+		{
+			for (rail,(_location,link_class)) in server.ports.iter().enumerate()
+			{
+				link_power.add_link(LinkSource::ServerRail{server:server_index,rail},&link_classes,*link_class);
+			}
+		}
 		let statistics=Statistics::new(statistics_temporal_step,statistics_server_percentiles,statistics_packet_percentiles,statistics_packet_definitions,topology.as_ref());
-		Simulation{
+		let mut simulation = Simulation{
 			configuration: cv.clone(),
 			seed,
 			shared: SimulationShared{
@@ -872,16 +1539,32 @@ impl<'a> Simulation<'a>
 			},
 			mutable: SimulationMut{
 				rng,
+				link_retries,
+				link_power,
 			},
 			warmup,
 			measured,
 			server_queue_size,
+			server_rail_selection,
+			server_packet_sequencing,
+			server_reorder_buffer,
+			server_congestion_control,
+			server_transport,
 			event_queue: EventQueue::new(1000),
 			statistics,
 			launch_configurations,
 			plugs,
 			memory_report_period,
+			throughput_bound,
+			channel_load,
+			network_state,
+			phit_bits,
+		};
+		for event in routing_events
+		{
+			simulation.event_queue.enqueue(event);
 		}
+		simulation
 	}
 	///Run the simulations until it finishes.
 	pub fn run(&mut self)
@@ -895,6 +1578,11 @@ impl<'a> Simulation<'a>
 			{
 				self.statistics.reset(self.shared.cycle,&mut self.shared.network);
 				self.shared.routing.reset_statistics(self.shared.cycle);
+				for counters in self.mutable.link_retries.iter_mut()
+				{
+					*counters = LinkRetryCounters::default();
+				}
+				self.mutable.link_power.reset(&self.shared.link_classes,self.shared.cycle);
 			}
 			if self.shared.traffic.is_finished()
 			{
@@ -906,6 +1594,10 @@ impl<'a> Simulation<'a>
 	///Execute a single cycle of the simulation.
 	fn advance(&mut self)
 	{
+		if let Some(ref network_state) = self.network_state
+		{
+			network_state.refresh(&self.shared.network);
+		}
 		let mut ievent=0;
 		//println!("Begin advance");
 		//while let Some(event) = self.event_queue.access_begin(ievent)
@@ -934,17 +1626,14 @@ impl<'a> Simulation<'a>
 				} =>
 				{
 					let target_server = phit.packet.message.destination;
-					let (target_location,_link_class)=self.shared.network.topology.server_neighbour(target_server);
-					let target_router=match target_location
-					{
-						Location::RouterPort{router_index,router_port:_} =>router_index,
-						_ => panic!("The server is not attached to a router"),
-					};
+					let target_router=phit.packet.target_router();
 					match new
 					{
 						&Location::RouterPort{router_index:router,router_port:port} =>
 						{
 							self.statistics.link_statistics[router][port].phit_arrivals+=1;
+							let (_,arrival_link_class) = self.shared.network.topology.neighbour(router,port);
+							self.statistics.track_link_traversal(arrival_link_class);
 							if phit.is_begin() && !self.statistics.packet_defined_statistics_definitions.is_empty()
 							{
 								let mut be = phit.packet.extra.borrow_mut();
@@ -976,7 +1665,11 @@ impl<'a> Simulation<'a>
 									self.statistics.track_phit_hop(phit,self.shared.cycle);
 									if phit.is_begin()
 									{
-										phit.packet.routing_info.borrow_mut().hops+=1;
+										{
+											let mut routing_info = phit.packet.routing_info.borrow_mut();
+											routing_info.hops+=1;
+											routing_info.arrival_virtual_channel = *phit.virtual_channel.borrow();
+										}
 										self.shared.routing.update_routing_info(&phit.packet.routing_info, self.shared.network.topology.as_ref(), router, port, target_router, Some(target_server), &mut self.mutable.rng);
 									}
 								},
@@ -989,6 +1682,11 @@ impl<'a> Simulation<'a>
 							{
 								panic!("Packet reached wrong server, {} instead of {}!\n",server,phit.packet.message.destination);
 							}
+							if let &Location::RouterPort{router_index,router_port} = previous
+							{
+								let (_,arrival_link_class) = self.shared.network.topology.neighbour(router_index,router_port);
+								self.statistics.track_link_traversal(arrival_link_class);
+							}
 							self.shared.network.servers[server].consume(phit.clone(),self.shared.traffic.deref_mut(),&mut self.statistics,self.shared.cycle,self.shared.network.topology.as_ref(),&mut self.mutable.rng);
 						}
 						&Location::None => panic!("Phit went nowhere previous={:?}",previous),
@@ -996,6 +1694,7 @@ impl<'a> Simulation<'a>
 				},
 				Event::Acknowledge{
 					location,
+					source,
 					//virtual_channel,
 					message: ack_message,
 				} => match location
@@ -1011,7 +1710,23 @@ impl<'a> Simulation<'a>
 							self.event_queue.enqueue(event);
 						}
 					},
-					Location::ServerPort(server) => self.shared.network.servers[server].router_status.acknowledge(ack_message),
+					Location::ServerPort(server) =>
+					{
+						let server = &mut self.shared.network.servers[server];
+						//Find the rail attached to the router port that sent the acknowledgement.
+						let rail = if server.ports.len()==1 { 0 } else {
+							match source
+							{
+								Location::RouterPort{router_index,router_port} => server.ports.iter().position(|port|match port.0
+								{
+									Location::RouterPort{router_index:rail_router,router_port:rail_port} => rail_router==router_index && rail_port==router_port,
+									_ => false,
+								}).expect("acknowledgement from a router not attached to the server"),
+								_ => panic!("acknowledgement to a multi-homed server from an unknown source"),
+							}
+						};
+						server.router_status[rail].acknowledge(ack_message)
+					},
 					//&Location::ServerPort(server) => TransmissionFromServer::acknowledge(self.shared.network.servers[server].router_status,ack_message),
 					_ => (),
 				},
@@ -1077,93 +1792,162 @@ impl<'a> Simulation<'a>
 		for (iserver,server) in self.shared.network.servers.iter_mut().enumerate()
 		{
 			//println!("credits of {} = {}",iserver,server.credits);
-			if let (Location::RouterPort{router_index: index,router_port: port},link_class)=server.port
+			if self.shared.traffic.should_generate(iserver,self.shared.cycle,&mut self.mutable.rng)
 			{
-				if self.shared.traffic.should_generate(iserver,self.shared.cycle,&mut self.mutable.rng)
-				{
-					if server.stored_messages.len()<self.server_queue_size {
-						match self.shared.traffic.generate_message(iserver,self.shared.cycle,self.shared.network.topology.as_ref(),&mut self.mutable.rng)
+				if server.stored_messages.len()<self.server_queue_size {
+					match self.shared.traffic.generate_message(iserver,self.shared.cycle,self.shared.network.topology.as_ref(),&mut self.mutable.rng)
+					{
+						Ok(message) =>
 						{
-							Ok(message) =>
+							if message.destination>=num_servers
 							{
-								if message.destination>=num_servers
-								{
-									panic!("Message sent to outside the network unexpectedly. destination={destination}",destination=message.destination);
-								}
-								if message.destination==iserver
-								{
-									panic!("Generated message to self unexpectedly.");
-								}
-								server.stored_messages.push_back(message);
-							},
-							Err(TrafficError::OriginOutsideTraffic) => (),
-							Err(TrafficError::SelfMessage) => (),
-							//Err(error) => panic!("An error happened when generating traffic: {:?}",error),
-						};
-					} else {
-						//There is no space in the server queue of messages.
-						server.statistics.track_missed_generation(self.shared.cycle);
-					}
+								panic!("Message sent to outside the network unexpectedly. destination={destination}",destination=message.destination);
+							}
+							if message.destination==iserver
+							{
+								panic!("Generated message to self unexpectedly.");
+							}
+							server.stored_messages.push_back(message);
+						},
+						Err(TrafficError::OriginOutsideTraffic) => (),
+						Err(TrafficError::SelfMessage) => (),
+						//Err(error) => panic!("An error happened when generating traffic: {:?}",error),
+					};
+				} else {
+					//There is no space in the server queue of messages.
+					server.statistics.track_missed_generation(self.shared.cycle);
 				}
-				if server.stored_packets.is_empty() && !server.stored_messages.is_empty()
+			}
+			let cycle = self.shared.cycle;
+			let expired = server.transport.as_mut().map(|transport|transport.expired(cycle)).unwrap_or_default();
+			for (destination,sequence) in expired
+			{
+				server.retransmit(destination,sequence,&mut self.statistics,cycle);
+				self.statistics.track_retransmission(true,cycle);
+			}
+			let lost = server.unacknowledged.as_mut().map(|unacknowledged|unacknowledged.expired(cycle)).unwrap_or_default();
+			for (destination,size) in lost
+			{
+				server.congestion_control.as_mut().unwrap().notify_loss(destination,size,cycle);
+				self.statistics.track_congestion_loss(cycle);
+			}
+			if server.stored_packets.is_empty() && !server.stored_messages.is_empty()
+			{
+				let message=server.stored_messages.pop_front().expect("There are not messages in queue");
+				server.packetize(message,self.shared.maximum_packet_size);
+			}
+			//With congestion control each packet waits until the controller of its destination allows it, while the packets towards other destinations may overtake it.
+			//The control packets and the retransmissions of the transport are never delayed.
+			let mut next_packet = None;
+			if server.stored_phits.is_empty()
+			{
+				let mut next_message = None;
+				if let Some(control) = server.congestion_control.as_ref()
 				{
-					let message=server.stored_messages.pop_front().expect("There are not messages in queue");
-					let mut size=message.size;
-					while size>0
+					let transport = server.transport.as_ref();
+					let maximum_packet_size = self.shared.maximum_packet_size;
+					//The destinations already found blocked, so that their later packets do not overtake the blocked one.
+					let mut blocked = BTreeSet::new();
+					let mut sendable = |destination:usize, size:usize| {
+						if blocked.contains(&destination) { return false; }
+						let allowed = control.can_send(destination,size,cycle);
+						if !allowed { blocked.insert(destination); }
+						allowed
+					};
+					next_packet = server.stored_packets.iter().position(|packet|packet.control.is_some()
+						|| transport.map(|transport|transport.is_retransmission(packet)).unwrap_or(false)
+						|| sendable(packet.message.destination,packet.size));
+					if next_packet.is_none()
 					{
-						let ps=if size>self.shared.maximum_packet_size
-						{
-							self.shared.maximum_packet_size
-						}
-						else
-						{
-							size
-						};
-						server.stored_packets.push_back(Packet{
-							size:ps,
-							routing_info: RefCell::new(RoutingInfo::new()),
-							message:message.clone(),
-							index:0,
-							cycle_into_network:RefCell::new(0),
-							extra: RefCell::new(None),
-						}.into_ref());
-						size-=ps;
+						next_message = server.stored_messages.iter().position(|message|sendable(message.destination,message.size.min(maximum_packet_size)));
 					}
 				}
-				if server.stored_phits.is_empty() && !server.stored_packets.is_empty()
+				else if !server.stored_packets.is_empty()
+				{
+					next_packet = Some(0);
+				}
+				if let Some(position) = next_message
+				{
+					let message = server.stored_messages.remove(position).expect("There are not messages in queue");
+					next_packet = Some(server.stored_packets.len());
+					server.packetize(message,self.shared.maximum_packet_size);
+				}
+			}
+			if let Some(position) = next_packet
+			{
+				let packet=server.stored_packets.remove(position).expect("There are not packets in queue");
+				if packet.control.is_none()
 				{
-					let packet=server.stored_packets.pop_front().expect("There are not packets in queue");
-					for index in 0..packet.size
+					//The retransmissions do not enter again into the congestion window.
+					let first = server.transport.as_mut().map(|transport|transport.track_sent(&packet,cycle)).unwrap_or(true);
+					if let (true,Some(control)) = (first,server.congestion_control.as_mut())
 					{
-						server.stored_phits.push_back(Rc::new(Phit{
-							packet:packet.clone(),
-							index,
-							virtual_channel: RefCell::new(None),
-						}));
+						control.notify_sent(packet.message.destination,packet.size,self.shared.cycle);
+					}
+					if let Some(unacknowledged) = server.unacknowledged.as_mut()
+					{
+						unacknowledged.track_sent(&packet,self.shared.cycle);
 					}
 				}
-				//if server.stored_phits.len()>0 && server.credits>0
-				//{
-				//	let phit=server.stored_phits.pop_front().expect("There are not phits");
-				//	let event=Event::PhitToLocation{
-				//		phit,
-				//		previous: Location::ServerPort(iserver),
-				//		new: Location::RouterPort{router_index:index,router_port:port},
-				//	};
-				//	self.statistics.created_phits+=1;
-				//	server.statistics.created_phits+=1;
-				//	self.event_queue.enqueue_begin(event,self.link_classes[link_class].delay);
-				//	server.credits-=1;
-				//}
-				if !server.stored_phits.is_empty()
+				for index in 0..packet.size
+				{
+					server.stored_phits.push_back(Rc::new(Phit{
+						packet:packet.clone(),
+						index,
+						virtual_channel: RefCell::new(None),
+					}));
+				}
+				let phit=server.stored_phits.front().expect("There are not phits");
+				server.current_rail = server.select_rail(phit,&self.server_rail_selection,&mut self.mutable.rng);
+				let injection_router = match server.ports[server.current_rail].0
+				{
+					Location::RouterPort{router_index,..} => router_index,
+					_ => panic!("Where goes this port?"),
+				};
+				//Eject by the attachment of the destination nearest to the injection router.
+				let topology = self.shared.network.topology.as_ref();
+				let target_router = topology.server_neighbours(packet.message.destination).iter().map(|(location,_link_class)|match location
+				{
+					&Location::RouterPort{router_index,..} => router_index,
+					_ => panic!("The server is not attached to a router"),
+				}).min_by_key(|&router|topology.distance(injection_router,router)).expect("The server has no attachments");
+				*packet.target_router.borrow_mut() = Some(target_router);
+			}
+			//if server.stored_phits.len()>0 && server.credits>0
+			//{
+			//	let phit=server.stored_phits.pop_front().expect("There are not phits");
+			//	let event=Event::PhitToLocation{
+			//		phit,
+			//		previous: Location::ServerPort(iserver),
+			//		new: Location::RouterPort{router_index:index,router_port:port},
+			//	};
+			//	self.statistics.created_phits+=1;
+			//	server.statistics.created_phits+=1;
+			//	self.event_queue.enqueue_begin(event,self.link_classes[link_class].delay);
+			//	server.credits-=1;
+			//}
+			if !server.stored_phits.is_empty()
+			{
+				let rail = server.current_rail;
+				let (index,port,link_class) = match server.ports[rail]
+				{
+					(Location::RouterPort{router_index,router_port},link_class) => (router_index,router_port,link_class),
+					_ => panic!("Where goes this port?"),
+				};
+				//The link can carry `width` phits in each of its cycles.
+				for _link_slot in 0..self.shared.link_classes[link_class].width
 				{
 					//Do not extract the phit until we know whether we can transmit it.
-					let phit=server.stored_phits.front().expect("There are not phits");
+					let phit = match server.stored_phits.front()
+					{
+						Some(phit) => phit,
+						None => break,
+					};
 					if let None = server.outcoming_virtual_channel
 					{
 						// Try to assign one
 						assert!(phit.is_begin(),"Not VC assigned for server--router while transmitting a middle phit.");
-						let status = &server.router_status;
+						let status = &server.router_status[rail];
 						for vc in  0..status.num_virtual_channels()
 						{
 							if status.can_transmit(phit,vc)
@@ -1174,37 +1958,37 @@ impl<'a> Simulation<'a>
 						}
 					}
 					// if self.shared.is_link_cycle(link_class) // XXX we cannot call this since we are mutating the servers.
-					if self.shared.cycle % self.shared.link_classes[link_class].frequency_divisor == 0
+					if self.shared.cycle % self.shared.link_classes[link_class].frequency_divisor != 0
+					{
+						break;
+					}
+					match server.outcoming_virtual_channel
 					{
-						if let Some(vc) = server.outcoming_virtual_channel
+						Some(vc) if server.router_status[rail].can_transmit(phit,vc) && !server.link_replay.is_busy(rail,self.shared.cycle) && self.mutable.link_power.ready(LinkSource::ServerRail{server:iserver,rail},&self.shared.link_classes,self.shared.cycle) =>
 						{
-							if server.router_status.can_transmit(phit,vc)
+							let phit=server.stored_phits.pop_front().expect("There are not phits");
+							*phit.virtual_channel.borrow_mut() = Some(vc);
+							if phit.is_end()
 							{
-								let phit=server.stored_phits.pop_front().expect("There are not phits");
-								*phit.virtual_channel.borrow_mut() = Some(vc);
-								if phit.is_end()
-								{
-									server.outcoming_virtual_channel = None;
-								}
-								let event=Event::PhitToLocation{
-									phit,
-									previous: Location::ServerPort(iserver),
-									new: Location::RouterPort{router_index:index,router_port:port},
-								};
-								//self.statistics.created_phits+=1;
-								self.statistics.track_created_phit(self.shared.cycle);
-								server.statistics.track_created_phit(self.shared.cycle);
-								self.event_queue.enqueue_begin(event,self.shared.link_classes[link_class].delay);
-								server.router_status.notify_outcoming_phit(vc,self.shared.cycle);
+								server.outcoming_virtual_channel = None;
 							}
-						}
+							let event=Event::PhitToLocation{
+								phit,
+								previous: Location::ServerPort(iserver),
+								new: Location::RouterPort{router_index:index,router_port:port},
+							};
+							//self.statistics.created_phits+=1;
+							self.statistics.track_created_phit(self.shared.cycle);
+							server.statistics.track_created_phit(self.shared.cycle);
+							let replay = server.link_replay.transmit(rail,link_class,&self.shared.link_classes,self.shared.cycle,&mut self.mutable);
+							self.event_queue.enqueue_begin(event,self.shared.link_classes[link_class].delay+replay);
+							server.router_status[rail].notify_outcoming_phit(vc,self.shared.cycle);
+							self.mutable.link_power.notify_transmission(LinkSource::ServerRail{server:iserver,rail},self.shared.cycle);
+						},
+						_ => break,
 					}
 				}
 			}
-			else
-			{
-				panic!("Where goes this port?");
-			}
 		}
 		//println!("Done generation");
 		self.event_queue.advance();
@@ -1222,6 +2006,32 @@ impl<'a> Simulation<'a>
 			}
 		}
 	}
+	///Count the directed links of each class, including those between servers and routers.
+	fn links_per_class(&self) -> Vec<usize>
+	{
+		let topology = self.shared.network.topology.as_ref();
+		let mut num_links = vec![0usize;self.shared.link_classes.len()];
+		for router_index in 0..topology.num_routers()
+		{
+			for port in 0..topology.ports(router_index)
+			{
+				let (location,link_class) = topology.neighbour(router_index,port);
+				if let Location::None = location
+				{
+					continue;
+				}
+				num_links[link_class]+=1;
+			}
+		}
+		for server in self.shared.network.servers.iter()
+		{
+			for (_location,link_class) in server.ports.iter()
+			{
+				num_links[*link_class]+=1;
+			}
+		}
+		num_links
+	}
 	///Get config value for the simulation results.
 	pub fn get_simulation_results(&self) -> ConfigurationValue
 	{
@@ -1284,6 +2094,153 @@ impl<'a> Simulation<'a>
 		{
 			result_content.push((String::from("routing_statistics"),content));
 		}
+		if self.server_packet_sequencing
+		{
+			let sequenced_packets:usize = measurement.reorder_distance_count.iter().sum();
+			let out_of_order_packets = sequenced_packets - measurement.reorder_distance_count.first().unwrap_or(&0);
+			let total_reorder_distance:usize = measurement.reorder_distance_count.iter().enumerate().map(|(distance,&count)|distance*count).sum();
+			let average_reorder_distance = if out_of_order_packets>0 { total_reorder_distance as f64/out_of_order_packets as f64 } else { 0.0 };
+			let maximum_reorder_buffer_occupancy = measurement.reorder_buffer_occupancy_count.len().saturating_sub(1);
+			let average_effective_message_delay=measurement.total_effective_message_delay as f64/measurement.delivered_messages as f64;
+			let reorder_distance_count=measurement.reorder_distance_count.iter().map(|&count|ConfigurationValue::Number(count as f64)).collect();
+			let reorder_buffer_occupancy_count=measurement.reorder_buffer_occupancy_count.iter().map(|&count|ConfigurationValue::Number(count as f64)).collect();
+			let reorder_content = vec![
+				(String::from("out_of_order_packets"),ConfigurationValue::Number(out_of_order_packets as f64)),
+				(String::from("average_reorder_distance"),ConfigurationValue::Number(average_reorder_distance)),
+				(String::from("reorder_distance_count"),ConfigurationValue::Array(reorder_distance_count)),
+				(String::from("reorder_buffer_occupancy_count"),ConfigurationValue::Array(reorder_buffer_occupancy_count)),
+				(String::from("maximum_reorder_buffer_occupancy"),ConfigurationValue::Number(maximum_reorder_buffer_occupancy as f64)),
+				(String::from("average_effective_message_delay"),ConfigurationValue::Number(average_effective_message_delay)),
+			];
+			result_content.push((String::from("reorder_statistics"),ConfigurationValue::Object(String::from("ReorderStatistics"),reorder_content)));
+		}
+		if self.server_congestion_control.is_some()
+		{
+			let marked_fraction = measurement.marked_packets as f64/measurement.consumed_packets as f64;
+			let (average_flow_rate,flow_rates) = flow_rate_statistics(&measurement.flow_rate_samples);
+			let congestion_content = vec![
+				(String::from("marked_packets"),ConfigurationValue::Number(measurement.marked_packets as f64)),
+				(String::from("marked_fraction"),ConfigurationValue::Number(marked_fraction)),
+				(String::from("congestion_acks"),ConfigurationValue::Number(measurement.congestion_acks as f64)),
+				(String::from("congestion_losses"),ConfigurationValue::Number(measurement.congestion_losses as f64)),
+				(String::from("average_flow_rate"),ConfigurationValue::Number(average_flow_rate)),
+				(String::from("flow_rates"),flow_rates),
+			];
+			result_content.push((String::from("congestion_statistics"),ConfigurationValue::Object(String::from("CongestionStatistics"),congestion_content)));
+		}
+		if self.server_transport.is_some()
+		{
+			let goodput=measurement.goodput_phits as f64/cycles as f64/num_servers as f64;
+			let transport_content = vec![
+				(String::from("goodput"),ConfigurationValue::Number(goodput)),
+				(String::from("retransmitted_packets"),ConfigurationValue::Number(measurement.retransmitted_packets as f64)),
+				(String::from("timeouts"),ConfigurationValue::Number(measurement.transport_timeouts as f64)),
+				(String::from("negative_acknowledgements"),ConfigurationValue::Number(measurement.negative_acknowledgements as f64)),
+				(String::from("duplicate_packets"),ConfigurationValue::Number(measurement.duplicate_packets as f64)),
+			];
+			result_content.push((String::from("transport_statistics"),ConfigurationValue::Object(String::from("TransportStatistics"),transport_content)));
+		}
+		if self.shared.link_classes.iter().any(|link|link.phit_error_rate>0.0)
+		{
+			//Normalize the cycles lost by the directed links of each class.
+			let num_links = self.links_per_class();
+			let link_retry_content = self.mutable.link_retries.iter().enumerate().map(|(link_class,counters)|{
+				let bandwidth_lost = if num_links[link_class]>0 { counters.replay_cycles as f64/cycles as f64/num_links[link_class] as f64 } else { 0.0 };
+				ConfigurationValue::Object(String::from("LinkRetryStatistics"),vec![
+					(String::from("link_class"),ConfigurationValue::Number(link_class as f64)),
+					(String::from("retries"),ConfigurationValue::Number(counters.retries as f64)),
+					(String::from("replay_cycles"),ConfigurationValue::Number(counters.replay_cycles as f64)),
+					(String::from("bandwidth_lost"),ConfigurationValue::Number(bandwidth_lost)),
+				])
+			}).collect();
+			result_content.push((String::from("link_retry_statistics"),ConfigurationValue::Array(link_retry_content)));
+		}
+		let link_power_counters = self.mutable.link_power.counters_per_class(&self.shared.link_classes,self.shared.cycle);
+		if self.shared.link_classes.iter().any(|link|link.power_management.is_some())
+		{
+			//Fractions of the time of the directed links of each class. The classes without links are skipped.
+			let num_links = self.links_per_class();
+			let link_power_content = self.shared.link_classes.iter().enumerate().filter(|(link_class,link)|link.power_management.is_some() && num_links[*link_class]>0).map(|(link_class,_)|{
+				let counters = &link_power_counters[link_class];
+				let link_cycles = (cycles*num_links[link_class] as Time) as f64;
+				let fraction = |value:Time| value as f64/link_cycles;
+				ConfigurationValue::Object(String::from("LinkPowerStatistics"),vec![
+					(String::from("link_class"),ConfigurationValue::Number(link_class as f64)),
+					(String::from("sleep_fraction"),ConfigurationValue::Number(fraction(counters.sleep_cycles))),
+					(String::from("waking_fraction"),ConfigurationValue::Number(fraction(counters.waking_cycles))),
+					(String::from("rate_fractions"),ConfigurationValue::Array(counters.level_cycles.iter().map(|&level_cycles|ConfigurationValue::Number(fraction(level_cycles))).collect())),
+					(String::from("wake_ups"),ConfigurationValue::Number(counters.wake_ups as f64)),
+					(String::from("rate_changes"),ConfigurationValue::Number(counters.rate_changes as f64)),
+				])
+			}).collect();
+			result_content.push((String::from("link_power_statistics"),ConfigurationValue::Array(link_power_content)));
+		}
+		//The counters, dynamic energy and static energy added over the routers with an energy model.
+		let router_energy = self.shared.network.routers.iter().fold(None,|acc:Option<(EnergyCounters,f64,f64)>,router|{
+			let router = router.borrow();
+			match router.energy()
+			{
+				Some((energy,counters)) =>
+				{
+					let (mut total_counters,mut dynamic_energy,mut static_energy) = acc.unwrap_or_default();
+					total_counters.add(counters);
+					dynamic_energy+=energy.dynamic_energy(counters);
+					static_energy+=energy.static_power*cycles as f64;
+					Some((total_counters,dynamic_energy,static_energy))
+				},
+				None => acc,
+			}
+		});
+		if router_energy.is_some() || self.shared.link_classes.iter().any(|link|link.energy_per_phit>0.0 || link.static_power>0.0)
+		{
+			let num_links = self.links_per_class();
+			let (counters,router_dynamic_energy,router_static_energy) = router_energy.unwrap_or_default();
+			let mut link_traversals = 0;
+			let mut link_dynamic_energy = 0.0;
+			let mut link_static_energy = 0.0;
+			for (link_class,link) in self.shared.link_classes.iter().enumerate()
+			{
+				//The replays traverse the link again.
+				let traversals = measurement.link_class_phits.get(link_class).unwrap_or(&0) + self.mutable.link_retries[link_class].retries;
+				link_traversals+=traversals;
+				link_dynamic_energy+=link.energy_per_phit*traversals as f64;
+				link_static_energy+=match &link.power_management
+				{
+					Some(management) => link_power_counters[link_class].static_energy(management,link.static_power),
+					None => link.static_power*(cycles*num_links[link_class] as Time) as f64,
+				};
+			}
+			let dynamic_energy = router_dynamic_energy + link_dynamic_energy;
+			let static_energy = router_static_energy + link_static_energy;
+			let total_energy = dynamic_energy + static_energy;
+			let delivered_bits = measurement.consumed_phits*self.phit_bits;
+			//Nothing delivered gives 0 instead of an infinite energy per bit.
+			let energy_per_delivered_bit = if delivered_bits>0 { total_energy/delivered_bits as f64 } else { 0.0 };
+			let energy_content = vec![
+				(String::from("total_energy"),ConfigurationValue::Number(total_energy)),
+				(String::from("dynamic_energy"),ConfigurationValue::Number(dynamic_energy)),
+				(String::from("static_energy"),ConfigurationValue::Number(static_energy)),
+				(String::from("router_dynamic_energy"),ConfigurationValue::Number(router_dynamic_energy)),
+				(String::from("router_static_energy"),ConfigurationValue::Number(router_static_energy)),
+				(String::from("link_dynamic_energy"),ConfigurationValue::Number(link_dynamic_energy)),
+				(String::from("link_static_energy"),ConfigurationValue::Number(link_static_energy)),
+				(String::from("energy_per_delivered_bit"),ConfigurationValue::Number(energy_per_delivered_bit)),
+				(String::from("buffer_writes"),ConfigurationValue::Number(counters.buffer_writes as f64)),
+				(String::from("buffer_reads"),ConfigurationValue::Number(counters.buffer_reads as f64)),
+				(String::from("crossbar_traversals"),ConfigurationValue::Number(counters.crossbar_traversals as f64)),
+				(String::from("allocations"),ConfigurationValue::Number(counters.allocations as f64)),
+				(String::from("link_traversals"),ConfigurationValue::Number(link_traversals as f64)),
+			];
+			result_content.push((String::from("energy_statistics"),ConfigurationValue::Object(String::from("EnergyStatistics"),energy_content)));
+		}
+		if let Some(content)=&self.throughput_bound
+		{
+			result_content.push((String::from("throughput_bound"),content.clone()));
+		}
+		if let Some(content)=&self.channel_load
+		{
+			result_content.push((String::from("channel_load"),content.clone()));
+		}
 		if let Some(content) = self.shared.network.routers.iter().enumerate().fold(None,|maybe_stat,(index,router)|router.borrow().aggregate_statistics(maybe_stat,index,self.shared.network.routers.len(),self.shared.cycle))
 		{
 			result_content.push((String::from("router_aggregated_statistics"),content));
@@ -1311,6 +2268,10 @@ impl<'a> Simulation<'a>
 			let mut average_packet_network_delay_collect = Vec::with_capacity(samples);
 			let mut average_packet_hops_collect = Vec::with_capacity(samples);
 			let mut virtual_channel_usage_collect = Vec::with_capacity(samples);
+			let mut average_effective_message_delay_collect = Vec::with_capacity(samples);
+			let mut average_flow_rate_collect = Vec::with_capacity(samples);
+			let mut goodput_collect = Vec::with_capacity(samples);
+			let mut flow_rates_collect = Vec::with_capacity(samples);
 			for measurement in self.statistics.temporal_statistics.iter()
 			{
 				let injected_load=measurement.created_phits as f64/step as f64/num_servers as f64;
@@ -1327,6 +2288,13 @@ impl<'a> Simulation<'a>
 					ConfigurationValue::Number(count as f64 / step as f64 / total_links as f64)
 				).collect();
 				virtual_channel_usage_collect.push(ConfigurationValue::Array(virtual_channel_usage));
+				let average_effective_message_delay=measurement.total_effective_message_delay as f64/measurement.delivered_messages as f64;
+				average_effective_message_delay_collect.push(ConfigurationValue::Number(average_effective_message_delay));
+				let (average_flow_rate,flow_rates) = flow_rate_statistics(&measurement.flow_rate_samples);
+				average_flow_rate_collect.push(ConfigurationValue::Number(average_flow_rate));
+				flow_rates_collect.push(flow_rates);
+				let goodput=measurement.goodput_phits as f64/step as f64/num_servers as f64;
+				goodput_collect.push(ConfigurationValue::Number(goodput));
 			};
 			let jscp_collect = self.shared.network.temporal_jain_server_consumed_phits()
 				.into_iter()
@@ -1336,7 +2304,7 @@ impl<'a> Simulation<'a>
 				.into_iter()
 				.map(|x|ConfigurationValue::Number(x))
 				.collect();
-			let temporal_content = vec![
+			let mut temporal_content = vec![
 				//(String::from("cycle"),ConfigurationValue::Number(self.shared.cycle as f64)),
 				(String::from("injected_load"),ConfigurationValue::Array(injected_load_collect)),
 				(String::from("accepted_load"),ConfigurationValue::Array(accepted_load_collect)),
@@ -1351,6 +2319,19 @@ impl<'a> Simulation<'a>
 				//(String::from("maximum_link_utilization"),ConfigurationValue::Number(maximum_link_utilization)),
 				//(String::from("git_id"),ConfigurationValue::Literal(format!("{}",git_id))),
 			];
+			if self.server_packet_sequencing
+			{
+				temporal_content.push((String::from("average_effective_message_delay"),ConfigurationValue::Array(average_effective_message_delay_collect)));
+			}
+			if self.server_congestion_control.is_some()
+			{
+				temporal_content.push((String::from("average_flow_rate"),ConfigurationValue::Array(average_flow_rate_collect)));
+				temporal_content.push((String::from("flow_rates"),ConfigurationValue::Array(flow_rates_collect)));
+			}
+			if self.server_transport.is_some()
+			{
+				temporal_content.push((String::from("goodput"),ConfigurationValue::Array(goodput_collect)));
+			}
 			result_content.push((String::from("temporal_statistics"),ConfigurationValue::Object(String::from("TemporalStatistics"),temporal_content)));
 		}
 		if !self.statistics.server_percentiles.is_empty()
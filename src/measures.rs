@@ -22,6 +22,12 @@ the message was created until the cycle in its consumption was completed. Note t
 * `server_average_missed_generations` counts the average of times a server has skipped generating a message because its internal queue is full. Under some assumptions a greater than 0 value means some flows have infinite latency. It may also mean that `server_queue_size` is not large enough.
 * `servers_with_missed_generations` counts the number of severs that have missed some generations. Under some assumptions this is couting the number of flows with infinite latency.
 * `virtual_channel_usage` is an array with the link utilization indexed by the virtual channel. This is, when a phit is transmitted by a link requesting a virtual channel `vc`, a `+1` is tracked into the index `vc`.
+* `reorder_statistics` only appears when `server_packet_sequencing` is enabled. It contains the number of `out_of_order_packets`, their `average_reorder_distance`, the histograms `reorder_distance_count` and `reorder_buffer_occupancy_count`, the `maximum_reorder_buffer_occupancy`, and the `average_effective_message_delay`. The effective delay of a message counts from its creation until all its packets have been delivered in order, thus including the wait in the reorder buffer.
+* `congestion_statistics` only appears when `server_congestion_control` is enabled. It contains the number of `marked_packets` among the consumed ones, their `marked_fraction`, the number of `congestion_acks` received by the origin servers, the number of `congestion_losses` of the packets never acknowledged, the `average_flow_rate` and an array `flow_rates` of `FlowRate{origin,destination,rate}` with the average rate of each flow sampled at its acknowledgements. The `temporal_statistics` then include the `average_flow_rate` and `flow_rates` of each period. See the [congestion](crate::congestion) module.
+* `transport_statistics` only appears when `server_transport` is enabled. It contains the `goodput`, the load of data phits consumed for the first time, which excludes the control packets and retransmitted duplicates counted in `accepted_load`. It also contains the number of `retransmitted_packets`, of `timeouts`, of `negative_acknowledgements` and of `duplicate_packets`. The `temporal_statistics` then include the `goodput` of each period. See the [transport](crate::transport) module.
+* `link_retry_statistics` only appears when some link class has a positive `phit_error_rate`. It is an array with a `LinkRetryStatistics{link_class,retries,replay_cycles,bandwidth_lost}` for each link class, where `bandwidth_lost` is the fraction of the cycles in which the links of the class have been busy replaying corrupted phits.
+* `energy_statistics` only appears when the router has an `energy` model or some link class has energy costs. It contains the `total_energy`, `dynamic_energy` and `static_energy`, their split into `router_dynamic_energy`, `router_static_energy`, `link_dynamic_energy` and `link_static_energy`, the `energy_per_delivered_bit`, and the number of `buffer_writes`, `buffer_reads`, `crossbar_traversals`, `allocations` and `link_traversals`. See the [energy](crate::energy) module.
+* `link_power_statistics` only appears when some link class has `power_management`. It is an array with a `LinkPowerStatistics{link_class,sleep_fraction,waking_fraction,rate_fractions,wake_ups,rate_changes}` for each such link class, where the fractions are of the cycles of its links, with `rate_fractions` having an entry per rate level. See the [power](crate::power) module.
 * `git_id` has an id of the CAMINOS binary, which is meaningful when building from a git repository.
 * `version_number` has the CAMINOS version as read from the Cargo.toml.
 
@@ -30,6 +36,7 @@ the message was created until the cycle in its consumption was completed. Note t
 
 use std::path::Path;
 use std::convert::TryInto;
+use std::collections::BTreeMap;
 
 use crate::{Quantifiable,Packet,Phit,Network,Topology,ConfigurationValue,Expr,Time};
 use crate::config;
@@ -188,6 +195,35 @@ pub struct StatisticMeasurement
 	///For each virtual channel `vc`, `virtual_channel_usage[vc]` counts the total number of times
 	///a phit has advanced by any link using that virtual channel.
 	pub virtual_channel_usage: Vec<usize>,
+	///Count of sequenced packets indexed by their reorder distance. This is, by how many sequence positions they have overtaken the earliest packet of their flow that has not arrived yet.
+	///The index 0 counts the packets arriving in order.
+	pub reorder_distance_count: Vec<usize>,
+	///Count of sequenced packet arrivals indexed by the number of packets in the reorder buffer of the destination server after the arrival.
+	pub reorder_buffer_occupancy_count: Vec<usize>,
+	///Number of sequenced messages whose packets have all been delivered in order.
+	pub delivered_messages: usize,
+	///Accumulated delay of the sequenced messages. From message creation to the in-order delivery of its last packet.
+	pub total_effective_message_delay: Time,
+	///Number of consumed packets that have been marked by some router as experiencing congestion.
+	pub marked_packets: usize,
+	///Number of congestion acknowledgements received by the origin servers.
+	pub congestion_acks: usize,
+	///Number of packets considered lost by the congestion controllers, for not being acknowledged in time.
+	pub congestion_losses: usize,
+	///For each flow `(origin,destination)` the sum of its rates sampled at each acknowledgement, together with the number of samples.
+	pub flow_rate_samples: BTreeMap<(usize,usize),(f64,usize)>,
+	///Number of phits of data packets consumed for the first time, excluding control packets and duplicates of the transport.
+	pub goodput_phits: usize,
+	///Number of packets retransmitted by the transport, either by timeout or by negative acknowledgement.
+	pub retransmitted_packets: usize,
+	///Number of retransmissions caused by the expiration of a timer.
+	pub transport_timeouts: usize,
+	///Number of negative acknowledgements sent by the destinations.
+	pub negative_acknowledgements: usize,
+	///Number of data packets received more than once.
+	pub duplicate_packets: usize,
+	///For each link class the number of phits that have traversed its links.
+	pub link_class_phits: Vec<usize>,
 }
 
 //impl StatisticMeasurement
@@ -400,11 +436,20 @@ impl Statistics
 			self.current_measurement.total_packet_per_hop_count.resize( hops+1, 0 );
 		}
 		self.current_measurement.total_packet_per_hop_count[hops]+=1;
+		let marked = *packet.congestion_experienced.borrow();
+		if marked
+		{
+			self.current_measurement.marked_packets+=1;
+		}
 		if let Some(m) = self.current_temporal_measurement(cycle)
 		{
 			m.consumed_packets+=1;
 			m.total_packet_network_delay+=network_delay;
 			m.total_packet_hops+=hops;
+			if marked
+			{
+				m.marked_packets+=1;
+			}
 		}
 		if !self.packet_percentiles.is_empty()
 		{
@@ -488,6 +533,108 @@ impl Statistics
 			m.total_message_delay+=delay;
 		}
 	}
+	/// Called when a server receives a sequenced packet.
+	/// `distance` is the number of sequence positions by which the packet has overtaken the earliest missing packet of its flow
+	/// and `occupancy` is the number of packets kept in the reorder buffer after processing the arrival.
+	pub fn track_packet_reorder(&mut self, distance:usize, occupancy:usize, cycle: Time)
+	{
+		increase_count(&mut self.current_measurement.reorder_distance_count,distance);
+		increase_count(&mut self.current_measurement.reorder_buffer_occupancy_count,occupancy);
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			increase_count(&mut m.reorder_distance_count,distance);
+			increase_count(&mut m.reorder_buffer_occupancy_count,occupancy);
+		}
+	}
+	/// Called when a server has delivered in order the last packet of a sequenced message.
+	pub fn track_effective_message_delay(&mut self, delay:Time, cycle: Time)
+	{
+		self.current_measurement.delivered_messages+=1;
+		self.current_measurement.total_effective_message_delay+=delay;
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.delivered_messages+=1;
+			m.total_effective_message_delay+=delay;
+		}
+	}
+	/// Called when the origin server receives the acknowledgement of a packet, with the rate of the flow after processing it.
+	pub fn track_congestion_ack(&mut self, origin:usize, destination:usize, rate:f64, cycle: Time)
+	{
+		self.current_measurement.congestion_acks+=1;
+		let sample = self.current_measurement.flow_rate_samples.entry((origin,destination)).or_insert((0.0,0));
+		sample.0+=rate;
+		sample.1+=1;
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.congestion_acks+=1;
+			let sample = m.flow_rate_samples.entry((origin,destination)).or_insert((0.0,0));
+			sample.0+=rate;
+			sample.1+=1;
+		}
+	}
+	/// Called when the congestion controller of a server considers a packet lost.
+	pub fn track_congestion_loss(&mut self, cycle: Time)
+	{
+		self.current_measurement.congestion_losses+=1;
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.congestion_losses+=1;
+		}
+	}
+	/// Called when a server consumes for the first time a data packet of the transport.
+	pub fn track_goodput(&mut self, size:usize, cycle: Time)
+	{
+		self.current_measurement.goodput_phits+=size;
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.goodput_phits+=size;
+		}
+	}
+	/// Called when a server retransmits a packet, with `timeout` telling whether it has been caused by a timer.
+	pub fn track_retransmission(&mut self, timeout:bool, cycle: Time)
+	{
+		self.current_measurement.retransmitted_packets+=1;
+		if timeout
+		{
+			self.current_measurement.transport_timeouts+=1;
+		}
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.retransmitted_packets+=1;
+			if timeout
+			{
+				m.transport_timeouts+=1;
+			}
+		}
+	}
+	/// Called when a destination server requests the retransmission of a missing packet.
+	pub fn track_negative_acknowledgement(&mut self, cycle: Time)
+	{
+		self.current_measurement.negative_acknowledgements+=1;
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.negative_acknowledgements+=1;
+		}
+	}
+	/// Called when a server receives again a data packet of the transport.
+	pub fn track_duplicate_packet(&mut self, cycle: Time)
+	{
+		self.current_measurement.duplicate_packets+=1;
+		if let Some(m) = self.current_temporal_measurement(cycle)
+		{
+			m.duplicate_packets+=1;
+		}
+	}
+	/// Called when a phit arrives through a link of class `link_class`, either to a router or to a server.
+	pub fn track_link_traversal(&mut self, link_class:usize)
+	{
+		let phits = &mut self.current_measurement.link_class_phits;
+		if phits.len() <= link_class
+		{
+			phits.resize(link_class+1, 0);
+		}
+		phits[link_class]+=1;
+	}
 	/// Called with a hop from router to router
 	pub fn track_phit_hop(&mut self, phit:&Phit, cycle: Time)
 	{
@@ -541,6 +688,16 @@ impl Statistics
 	}
 }
 
+///Increase by one the entry `index` of a histogram, extending it as required.
+fn increase_count(histogram:&mut Vec<usize>, index:usize)
+{
+	if histogram.len() <= index
+	{
+		histogram.resize(index+1, 0);
+	}
+	histogram[index]+=1;
+}
+
 ///The available statistical columns. Each column has a string for the header and a way to compute what to print each period.
 #[derive(Debug,Quantifiable)]
 #[allow(dead_code)]
@@ -17,6 +17,7 @@ use std::cell::RefCell;
 use std::ops::Deref;
 
 use crate::routing::RoutingInfo;
+use crate::transport::TransportControl;
 use crate::event::Time;
 
 ///Minimal unit to be processed by the network.
@@ -62,9 +63,19 @@ pub struct Packet
 	pub message: Rc<Message>,
 	///position inside the message
 	pub index: usize,
+	///Position among the packets sent from the origin server to the destination server.
+	///It is only set when the simulation enables `server_packet_sequencing`, being 0 otherwise.
+	pub sequence: usize,
 	///The cycle when the packet has touched the first router. This is, the packet leading phit has been inserted into a router.
 	///We set it to 0 if the packet has not entered the network yet.
 	pub cycle_into_network: RefCell<Time>,
+	///The router by which the packet is going to be ejected. It is set by the server when the packet is about to be injected.
+	///It matters for multi-homed destinations, which may be reached through several routers.
+	pub target_router: RefCell<Option<usize>>,
+	///Whether some router has marked the packet for having congestion, as in ECN. See the `ecn_threshold` key of the routers.
+	pub congestion_experienced: RefCell<bool>,
+	///The kind of control packet when generated by the transport layer, being `None` for data packets. See the `server_transport` key.
+	pub control: Option<TransportControl>,
 	///Extra info tracked for some special statistics.
 	pub extra: RefCell<Option<PacketExtraInfo>>,
 }
@@ -76,6 +87,11 @@ thread_local!{
 
 impl Packet
 {
+	///The router by which the packet is going to be ejected.
+	pub fn target_router(&self) -> usize
+	{
+		self.target_router.borrow().expect("The packet has not a target router yet.")
+	}
 	#[cfg(not(any(feature="raw_packet",feature="slab_packet")))]
 	pub fn into_ref(self) -> PacketRef {
 		PacketRef{inner:Rc::new(self)}
@@ -0,0 +1,370 @@
+/*!
+
+Power management of the links.
+
+A `LinkClass` may include a `power_management` key to make its links save power when they are not fully used.
+
+* A link that has not transmitted for `idle_timeout` cycles goes to sleep. The next phit to be sent wakes it up, which takes `wake_up_latency` cycles before the phit can enter the link.
+* A link can work at several rate levels, given as `rate_divisors` of its frequency from the slowest to the fastest level. A link at the level with divisor `d` only transmits in the cycles multiple of `d*frequency_divisor`. Each `rate_window` cycles the link measures its utilization at its current level, moving to the next faster level when it exceeds `upper_threshold` and to the next slower level when it is below `lower_threshold`. Links start at the fastest level.
+
+```ignore
+LinkClass{
+	delay: 1,
+	power_management: LinkPowerManagement{
+		idle_timeout: 100,//optional, cycles without transmitting before going to sleep. By default the links never sleep.
+		wake_up_latency: 20,//cycles to wake up.
+		sleep_power: 0.1,//optional, the static power while sleeping. Defaults to 0.
+		rate_divisors: [4,2,1],//optional, the rate levels. Defaults to [1], a single level.
+		rate_window: 200,//cycles between rate decisions. Required with several rate levels.
+		lower_threshold: 0.3,//optional, defaults to 0.25.
+		upper_threshold: 0.8,//optional, defaults to 0.75.
+	},
+}
+```
+
+The `link_power_statistics` of the results give for each link class with power management the fraction of the time its links have spent sleeping, waking up, and at each rate level, together with the number of wake-ups and rate changes.
+For the [energy](crate::energy) accounting, the `static_power` of the link class is divided by the divisor of the current rate level, waking up consumes the full `static_power`, and sleeping consumes the `sleep_power`.
+
+*/
+
+use std::collections::BTreeMap;
+
+use crate::config_parser::ConfigurationValue;
+use crate::event::Time;
+use crate::match_object_panic;
+use crate::LinkClass;
+
+///The configuration of the power management of a class of links.
+#[derive(Debug,Clone)]
+pub struct LinkPowerManagement
+{
+	///Cycles without transmitting before the link goes to sleep. None to never sleep.
+	pub idle_timeout: Option<Time>,
+	///Cycles since a phit requests a sleeping link until it can enter the link.
+	pub wake_up_latency: Time,
+	///The static power of a sleeping link.
+	pub sleep_power: f64,
+	///The divisors of the link frequency at each rate level, from the slowest to the fastest.
+	pub rate_divisors: Vec<Time>,
+	///Cycles between rate decisions.
+	pub rate_window: Time,
+	///The utilization below which the link goes to a slower level.
+	pub lower_threshold: f64,
+	///The utilization above which the link goes to a faster level.
+	pub upper_threshold: f64,
+}
+
+impl LinkPowerManagement
+{
+	pub fn new(cv:&ConfigurationValue) -> LinkPowerManagement
+	{
+		let mut idle_timeout = None;
+		let mut wake_up_latency = None;
+		let mut sleep_power = 0.0;
+		let mut rate_divisors = vec![1];
+		let mut rate_window = None;
+		let mut lower_threshold = 0.25;
+		let mut upper_threshold = 0.75;
+		match_object_panic!(cv,"LinkPowerManagement",value,
+			"idle_timeout" => idle_timeout = Some(value.as_time().expect("bad value for idle_timeout")),
+			"wake_up_latency" => wake_up_latency = Some(value.as_time().expect("bad value for wake_up_latency")),
+			"sleep_power" => sleep_power = value.as_f64().expect("bad value for sleep_power"),
+			"rate_divisors" => rate_divisors = value.as_array().expect("bad value for rate_divisors").iter().map(|v|v.as_time().expect("bad value in rate_divisors")).collect(),
+			"rate_window" => rate_window = Some(value.as_time().expect("bad value for rate_window")),
+			"lower_threshold" => lower_threshold = value.as_f64().expect("bad value for lower_threshold"),
+			"upper_threshold" => upper_threshold = value.as_f64().expect("bad value for upper_threshold"),
+		);
+		let wake_up_latency = if idle_timeout.is_some() { wake_up_latency.expect("There were no wake_up_latency") } else { wake_up_latency.unwrap_or(0) };
+		if rate_divisors.is_empty() || rate_divisors.contains(&0)
+		{
+			panic!("The rate_divisors of LinkPowerManagement must be a nonempty list of positive numbers.");
+		}
+		let rate_window = if rate_divisors.len()>1 { rate_window.expect("There were no rate_window") } else { rate_window.unwrap_or(1) };
+		if rate_window==0
+		{
+			panic!("The rate_window of LinkPowerManagement must be positive.");
+		}
+		if lower_threshold > upper_threshold
+		{
+			panic!("The lower_threshold ({}) of LinkPowerManagement cannot be greater than its upper_threshold ({}).",lower_threshold,upper_threshold);
+		}
+		LinkPowerManagement{
+			idle_timeout,
+			wake_up_latency,
+			sleep_power,
+			rate_divisors,
+			rate_window,
+			lower_threshold,
+			upper_threshold,
+		}
+	}
+}
+
+///The emitting endpoint of a directed link.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
+pub enum LinkSource
+{
+	RouterPort{
+		router_index: usize,
+		router_port: usize,
+	},
+	ServerRail{
+		server: usize,
+		rail: usize,
+	},
+}
+
+///The power state of a directed link, updated lazily when the emissor wants to transmit through it.
+#[derive(Debug,Clone)]
+pub struct LinkPowerState
+{
+	///The class of the link.
+	link_class: usize,
+	///The last cycle in which the link transmitted or finished waking up.
+	last_activity: Time,
+	///If waking up, the cycle in which it will be awake.
+	awake_at: Option<Time>,
+	///The current rate level.
+	level: usize,
+	///The first cycle of the current rate window.
+	window_begin: Time,
+	///Phits sent in the current rate window.
+	window_phits: usize,
+	///The cycle up to which the time per state has been accounted.
+	accounted_until: Time,
+	///The accounting of the time per state.
+	counters: LinkPowerCounters,
+}
+
+///Cycles spent by links in each power state.
+#[derive(Debug,Clone,Default)]
+pub struct LinkPowerCounters
+{
+	pub sleep_cycles: Time,
+	pub waking_cycles: Time,
+	///The cycles at each rate level, while awake.
+	pub level_cycles: Vec<Time>,
+	pub wake_ups: usize,
+	pub rate_changes: usize,
+}
+
+impl LinkPowerCounters
+{
+	///Accumulate the counters of another link.
+	pub fn add(&mut self, other:&LinkPowerCounters)
+	{
+		self.sleep_cycles+=other.sleep_cycles;
+		self.waking_cycles+=other.waking_cycles;
+		if self.level_cycles.len()<other.level_cycles.len()
+		{
+			self.level_cycles.resize(other.level_cycles.len(),0);
+		}
+		for (level,cycles) in other.level_cycles.iter().enumerate()
+		{
+			self.level_cycles[level]+=cycles;
+		}
+		self.wake_ups+=other.wake_ups;
+		self.rate_changes+=other.rate_changes;
+	}
+	///The static energy consumed during the accounted cycles by a link with the given static power.
+	pub fn static_energy(&self, management:&LinkPowerManagement, static_power:f64) -> f64
+	{
+		let awake:f64 = self.level_cycles.iter().zip(management.rate_divisors.iter()).map(|(&cycles,&divisor)|static_power*cycles as f64/divisor as f64).sum();
+		awake + static_power*self.waking_cycles as f64 + management.sleep_power*self.sleep_cycles as f64
+	}
+}
+
+impl LinkPowerState
+{
+	fn new(link_class:usize, management:&LinkPowerManagement) -> LinkPowerState
+	{
+		LinkPowerState{
+			link_class,
+			last_activity: 0,
+			awake_at: None,
+			level: management.rate_divisors.len()-1,
+			window_begin: 0,
+			window_phits: 0,
+			accounted_until: 0,
+			counters: LinkPowerCounters{
+				level_cycles: vec![0;management.rate_divisors.len()],
+				..Default::default()
+			},
+		}
+	}
+	///The cycle in which the link falls asleep if it does not transmit.
+	fn sleep_start(&self, management:&LinkPowerManagement) -> Option<Time>
+	{
+		management.idle_timeout.map(|timeout|(self.last_activity+timeout).max(self.accounted_until))
+	}
+	///Evaluate the rate windows finished by `until`.
+	fn update_rate(&mut self, link:&LinkClass, management:&LinkPowerManagement, until:Time)
+	{
+		let levels = management.rate_divisors.len();
+		if levels<=1
+		{
+			return;
+		}
+		while self.window_begin + management.rate_window <= until
+		{
+			let boundary = self.window_begin + management.rate_window;
+			let capacity = (management.rate_window as usize*link.width) as f64 / (link.frequency_divisor*management.rate_divisors[self.level]) as f64;
+			let utilization = self.window_phits as f64 / capacity;
+			let new_level = if utilization > management.upper_threshold && self.level+1 < levels
+			{
+				self.level+1
+			}
+			else if utilization < management.lower_threshold && self.level > 0
+			{
+				self.level-1
+			}
+			else
+			{
+				self.level
+			};
+			if new_level != self.level
+			{
+				self.counters.level_cycles[self.level] += boundary - self.accounted_until;
+				self.accounted_until = boundary;
+				self.level = new_level;
+				self.counters.rate_changes+=1;
+			}
+			self.window_begin = boundary;
+			self.window_phits = 0;
+			if self.level==0 || management.lower_threshold <= 0.0
+			{
+				//The remaining windows are empty and cannot change the level.
+				let windows = (until - self.window_begin)/management.rate_window;
+				self.window_begin += windows*management.rate_window;
+				break;
+			}
+		}
+	}
+	///Account the time in each state up to `cycle`.
+	fn flush(&mut self, link:&LinkClass, management:&LinkPowerManagement, cycle:Time)
+	{
+		if let Some(awake_at) = self.awake_at
+		{
+			let end = awake_at.min(cycle);
+			self.counters.waking_cycles += end - self.accounted_until;
+			self.accounted_until = end;
+			if cycle < awake_at
+			{
+				return;
+			}
+			self.awake_at = None;
+			self.last_activity = awake_at;
+			self.window_begin = awake_at;
+			self.window_phits = 0;
+		}
+		match self.sleep_start(management)
+		{
+			Some(sleep_start) if sleep_start <= cycle =>
+			{
+				self.update_rate(link,management,sleep_start);
+				self.counters.level_cycles[self.level] += sleep_start - self.accounted_until;
+				self.counters.sleep_cycles += cycle - sleep_start;
+			},
+			_ =>
+			{
+				self.update_rate(link,management,cycle);
+				self.counters.level_cycles[self.level] += cycle - self.accounted_until;
+			},
+		}
+		self.accounted_until = cycle;
+	}
+	///Whether the link can transmit at `cycle`. It is called when there is something to transmit, waking the link if it is sleeping.
+	fn ready(&mut self, link:&LinkClass, management:&LinkPowerManagement, cycle:Time) -> bool
+	{
+		self.flush(link,management,cycle);
+		if self.awake_at.is_some()
+		{
+			return false;
+		}
+		if let Some(timeout) = management.idle_timeout
+		{
+			if self.last_activity + timeout <= cycle
+			{
+				self.counters.wake_ups+=1;
+				if management.wake_up_latency > 0
+				{
+					self.awake_at = Some(cycle + management.wake_up_latency);
+					return false;
+				}
+				self.last_activity = cycle;
+				self.window_begin = cycle;
+				self.window_phits = 0;
+			}
+		}
+		cycle % (link.frequency_divisor*management.rate_divisors[self.level]) == 0
+	}
+}
+
+///The power state of all the links with power management.
+#[derive(Debug,Default)]
+pub struct LinkPower
+{
+	states: BTreeMap<LinkSource,LinkPowerState>,
+}
+
+impl LinkPower
+{
+	///Manage the link from `source` if its class has power management.
+	pub fn add_link(&mut self, source:LinkSource, link_classes:&[LinkClass], link_class:usize)
+	{
+		if let Some(management) = &link_classes[link_class].power_management
+		{
+			self.states.insert(source,LinkPowerState::new(link_class,management));
+		}
+	}
+	///Whether the link from `source` can transmit at `cycle`. Links without power management are always ready.
+	///It must only be called when there is something to transmit, as it wakes up the link.
+	pub fn ready(&mut self, source:LinkSource, link_classes:&[LinkClass], cycle:Time) -> bool
+	{
+		match self.states.get_mut(&source)
+		{
+			Some(state) =>
+			{
+				let link = &link_classes[state.link_class];
+				state.ready(link,link.power_management.as_ref().unwrap(),cycle)
+			},
+			None => true,
+		}
+	}
+	///Called when the link from `source` transmits a phit.
+	pub fn notify_transmission(&mut self, source:LinkSource, cycle:Time)
+	{
+		if let Some(state) = self.states.get_mut(&source)
+		{
+			state.last_activity = cycle;
+			state.window_phits += 1;
+		}
+	}
+	///Forget the accounted time, to begin the measurement at `cycle`.
+	pub fn reset(&mut self, link_classes:&[LinkClass], cycle:Time)
+	{
+		for state in self.states.values_mut()
+		{
+			let link = &link_classes[state.link_class];
+			state.flush(link,link.power_management.as_ref().unwrap(),cycle);
+			let levels = state.counters.level_cycles.len();
+			state.counters = LinkPowerCounters{
+				level_cycles: vec![0;levels],
+				..Default::default()
+			};
+		}
+	}
+	///The time spent in each state up to `cycle` by the links of each class. Classes without power management have empty counters.
+	pub fn counters_per_class(&self, link_classes:&[LinkClass], cycle:Time) -> Vec<LinkPowerCounters>
+	{
+		let mut result = vec![LinkPowerCounters::default();link_classes.len()];
+		for state in self.states.values()
+		{
+			let link = &link_classes[state.link_class];
+			let mut state = state.clone();
+			state.flush(link,link.power_management.as_ref().unwrap(),cycle);
+			result[state.link_class].add(&state.counters);
+		}
+		result
+	}
+}
@@ -1,22 +1,23 @@
 
 use std::cell::RefCell;
 use std::rc::{Rc,Weak};
-use std::ops::{Deref};
 use std::mem::{size_of};
 use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
 use std::convert::TryInto;
 
-use super::{Router,AbstractTransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument,new_transmission_mechanism,TransmissionMechanismBuilderArgument};
+use super::{Router,AbstractTransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument,new_transmission_mechanism,aggregate_flow_control_statistics,TransmissionMechanismBuilderArgument};
 use crate::config_parser::ConfigurationValue;
 use crate::topology::{Location,Topology};
-use crate::routing::CandidateEgress;
+use crate::routing::{CandidateEgress,CongestionInfo};
 use crate::policies::{RequestInfo,VirtualChannelPolicy,new_virtual_channel_policy,VCPolicyBuilderArgument};
 use crate::event::{Event,Eventful,EventGeneration,CyclePosition,Time};
-use crate::{Phit,Packet,SimulationShared,SimulationMut};
+use crate::{Phit,Packet,SimulationShared,SimulationMut,LinkReplay};
 use crate::quantify::Quantifiable;
 use crate::packet::PacketRef;
 //use crate::Plugs;
 use crate::match_object_panic;
+use crate::energy::{RouterEnergy,EnergyCounters};
+use crate::power::LinkSource;
 
 
 ///Strategy for the arbitration of the output port.
@@ -60,8 +61,20 @@ pub struct Basic
 	///In particular, [EnforceFlowControl] will filter them out.
 	///Defaults to false.
 	neglect_busy_output: bool,
+	///If some, the packets whose leading phit enters an output buffer with at least this many phits are marked as experiencing congestion.
+	///See the [congestion](crate::congestion) module.
+	ecn_threshold: Option<usize>,
+	///Phits that each virtual channel of an output port may move from the input buffers into its output buffer in a cycle. It is the internal speedup of the router.
+	///Requires output buffers. Defaults to 1.
+	crossbar_speedup: usize,
+	///The energy consumed by the events of the router, if configured. See the [energy](crate::energy) module.
+	energy: Option<RouterEnergy>,
+	///The events consuming energy since the last reset of the statistics.
+	energy_counters: EnergyCounters,
 	/// `transmission_port_status[port] = status`
 	transmission_port_status: Vec<Box<dyn StatusAtEmissor>>,
+	///Whether the link of each port is busy replaying a corrupted phit.
+	link_replay: LinkReplay,
 	/// `reception_port_space[port] = space`
 	reception_port_space: Vec<Box<dyn SpaceAtReceptor>>,
 	// The router to router mechanism employed.
@@ -141,6 +154,7 @@ impl Router for Basic
 	fn insert(&mut self, current_cycle:Time, phit:Rc<Phit>, port:usize, rng: &mut StdRng) -> Vec<EventGeneration>
 	{
 		self.reception_port_space[port].insert(phit,rng).expect("there was some problem on the insertion");
+		self.energy_counters.buffer_writes+=1;
 		if let Some(event) = self.schedule(current_cycle,0) {
 			vec![event]
 		} else {
@@ -199,6 +213,7 @@ impl Router for Basic
 		let mut reception_space_occupation_per_vc:Option<Vec<f64>> = Some(self.principal_measurement.reception_space_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
 		let mut output_buffer_occupation_per_vc:Option<Vec<f64>> = Some(self.principal_measurement.output_buffer_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
 		let mut temporal_statistics: Vec<  BasicRouterMeasurement  > = self.temporal_statistics.iter().map(|m|m.clone().into_mul(1f64/self.statistics_temporal_step as f64)).collect();
+		let mut previous_flow_control=None;
 		if let Some(previous)=statistics
 		{
 			if let ConfigurationValue::Object(cv_name,previous_pairs) = previous
@@ -330,6 +345,7 @@ impl Router for Basic
 							}
 							_ => panic!("bad value for temporal_statistics"),
 						},
+						"flow_control_statistics" => previous_flow_control=Some(value.clone()),
 						_ => panic!("Nothing to do with field {} in Basic statistics",name),
 					}
 				}
@@ -392,11 +408,20 @@ impl Router for Basic
 			];
 			result_content.push((String::from("temporal_statistics"),ConfigurationValue::Object(String::from("TemporalStatistics"),temporal_content)));
 		}
+		if let Some(flow_control)=aggregate_flow_control_statistics(previous_flow_control.as_ref(),&self.reception_port_space,cycle)
+		{
+			result_content.push((String::from("flow_control_statistics"),flow_control));
+		}
 		Some(ConfigurationValue::Object(String::from("Basic"),result_content))
 	}
 	fn reset_statistics(&mut self, next_cycle:Time)
 	{
 		self.principal_measurement.begin_cycle=next_cycle;
+		self.energy_counters=EnergyCounters::default();
+		for space in self.reception_port_space.iter_mut()
+		{
+			space.reset_flow_control_counters(next_cycle);
+		}
 		for x in self.principal_measurement.output_buffer_occupation_per_vc.iter_mut()
 		{
 			*x=0f64;
@@ -419,6 +444,10 @@ impl Router for Basic
 			unimplemented!()
 		}
 	}
+	fn energy(&self) -> Option<(&RouterEnergy,&EnergyCounters)>
+	{
+		self.energy.as_ref().map(|energy|(energy,&self.energy_counters))
+	}
 }
 
 impl Basic
@@ -448,9 +477,13 @@ impl Basic
 		let mut output_prioritize_lowest_label=None;
 		let mut output_buffer_size=None;
 		let mut transmission_mechanism=None;
+		let mut transmission_mechanism_cv=None;
 		let mut to_server_mechanism=None;
 		let mut from_server_mechanism=None;
 		let mut neglect_busy_output = false;
+		let mut ecn_threshold = None;
+		let mut crossbar_speedup = 1;
+		let mut energy = None;
 		match_object_panic!(cv,"Basic",value,
 			"virtual_channels" => match value
 			{
@@ -520,9 +553,17 @@ impl Basic
 				_ => panic!("bad value for output_prioritize_lowest_label"),
 			},
 			"neglect_busy_output" => neglect_busy_output = value.as_bool().expect("bad value for neglect_busy_output"),
+			"ecn_threshold" => ecn_threshold = Some(value.as_usize().expect("bad value for ecn_threshold")),
+			"crossbar_speedup" => crossbar_speedup = value.as_usize().expect("bad value for crossbar_speedup"),
+			"energy" => energy = Some(RouterEnergy::new(value)),
 			"transmission_mechanism" => match value
 			{
 				&ConfigurationValue::Literal(ref s) => transmission_mechanism = Some(s.to_string()),
+				ConfigurationValue::Object(name,_) =>
+				{
+					transmission_mechanism = Some(name.to_string());
+					transmission_mechanism_cv = Some(value);
+				},
 				_ => panic!("bad value for transmission_mechanism"),
 			},
 			"to_server_mechanism" => match value
@@ -542,6 +583,14 @@ impl Basic
 		//let routing=routing.expect("There were no routing");
 		let buffer_size=buffer_size.expect("There were no buffer_size");
 		let output_buffer_size=output_buffer_size.expect("There were no output_buffer_size");
+		if ecn_threshold.is_some() && output_buffer_size==0
+		{
+			panic!("The ecn_threshold of the Basic router requires output buffers.");
+		}
+		if crossbar_speedup==0 || (crossbar_speedup>1 && output_buffer_size==0)
+		{
+			panic!("The crossbar_speedup of the Basic router must be positive, and greater than 1 only with output buffers.");
+		}
 		let bubble=bubble.expect("There were no bubble");
 		let flit_size=flit_size.expect("There were no flit_size");
 		let intransit_priority=intransit_priority.expect("There were no intransit_priority");
@@ -562,8 +611,8 @@ impl Basic
 		let from_server_mechanism = from_server_mechanism.unwrap_or_else(||"SimpleVirtualChannels".to_string());
 		let to_server_mechanism = to_server_mechanism.unwrap_or_else(||"TransmissionToServer".to_string());
 		//let transmission_mechanism = super::SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
-		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",virtual_channels,buffer_size,size_to_send:flit_size};
-		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,..transmission_builder_argument});
+		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",cv:None,virtual_channels,buffer_size,size_to_send:flit_size};
+		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,cv:transmission_mechanism_cv,..transmission_builder_argument});
 		let to_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&to_server_mechanism,..transmission_builder_argument});
 		//let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
 		let from_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&from_server_mechanism,..transmission_builder_argument});
@@ -611,8 +660,13 @@ impl Basic
 			allow_request_busy_port,
 			output_prioritize_lowest_label,
 			neglect_busy_output,
+			ecn_threshold,
+			crossbar_speedup,
+			energy,
+			energy_counters: EnergyCounters::default(),
 			buffer_size,
 			transmission_port_status,
+			link_replay: LinkReplay::new(input_ports),
 			reception_port_space,
 			//transmission_mechanism,
 			//to_server_mechanism,
@@ -909,13 +963,18 @@ impl Eventful for Basic
 					{
 						undecided_channels+=1;
 						let target_server=phit.packet.message.destination;
-						let (target_location,_link_class)=topology.server_neighbour(target_server);
-						let target_router=match target_location
-						{
-							Location::RouterPort{router_index,router_port:_} =>router_index,
-							_ => panic!("The server is not attached to a router"),
+						let target_router=phit.packet.target_router();
+						let congestion=CongestionInfo{
+							router: self,
+							port_average_neighbour_queue_length: port_average_neighbour_queue_length.as_ref(),
+							port_last_transmission: port_last_transmission.as_ref(),
+							port_occupied_output_space: port_occupied_output_space.as_ref(),
+							port_available_output_space: port_available_output_space.as_ref(),
+							virtual_channel_occupied_output_space: virtual_channel_occupied_output_space.as_ref(),
+							virtual_channel_available_output_space: virtual_channel_available_output_space.as_ref(),
+							current_cycle: simulation.cycle,
 						};
-						let routing_candidates=simulation.routing.next(phit.packet.routing_info.borrow().deref(),simulation.network.topology.as_ref(),self.router_index,target_router,Some(target_server),amount_virtual_channels,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
+						let routing_candidates=simulation.routing.next_with_congestion(&phit.packet.routing_info,simulation.network.topology.as_ref(),self.router_index,target_router,Some(target_server),amount_virtual_channels,&congestion,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
 						let routing_idempotent = routing_candidates.idempotent;
 						if routing_candidates.len()==0
 						{
@@ -1094,6 +1153,7 @@ impl Eventful for Basic
 				if self.selected_output[entry_port][entry_vc].is_none()
 				{
 					self.selected_input[requested_port][requested_vc]=Some((packet.clone(),entry_port,entry_vc));
+					self.energy_counters.allocations+=1;
 					self.selected_output[entry_port][entry_vc] = Some((packet,requested_port,requested_vc));
 				}
 			};
@@ -1101,275 +1161,345 @@ impl Eventful for Basic
 
 		//-- For each output port decide which input actually uses it this cycle.
 		let mut events=vec![];
+		//Whether some link is replaying corrupted phits or waking up, which blocks it for some cycles.
+		let mut blocked_links=false;
 		for exit_port in 0..self.transmission_port_status.len()
 		{
 			let nvc=amount_virtual_channels;
-			//Gather the list of all vc that can advance
-			let mut cand=Vec::with_capacity(nvc);
-			let mut cand_in_transit=false;
-			let mut undo_selected_input=Vec::with_capacity(nvc);
-			for exit_vc in 0..nvc
+			if self.output_buffer_size>0
 			{
-				if let Some((ref entry_packet,entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
+				for exit_vc in 0..nvc
 				{
-					if self.output_buffer_size>0
+					//-- Move phits into the internal output space, up to `crossbar_speedup` phits per virtual channel.
+					//Note that it is possible when flit_size<packet_size for the packet to not be in that buffer. The output arbiter can decide to advance other virtual channel.
+					for crossbar_slot in 0..self.crossbar_speedup
 					{
-						//-- Move phits into the internal output space
-						//Note that it is possible when flit_size<packet_size for the packet to not be in that buffer. The output arbiter can decide to advance other virtual channel.
-						if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
+						if let Some((ref entry_packet,entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
 						{
-							if self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
+							if crossbar_slot>0 && self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
 							{
-								panic!("Trying to move into a full output buffer.");
+								//The allocation only guarantees space for the first phit.
+								break;
 							}
-							moved_phits+=1;
-							self.time_at_input_head[entry_port][entry_vc]=0;
-							*phit.virtual_channel.borrow_mut()=Some(exit_vc);
-							if let Some(message)=ack_message
+							if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
 							{
-								let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
-								events.push(EventGeneration{
-									delay: simulation.link_classes[previous_link_class].delay,
-									position:CyclePosition::Begin,
+								if self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
+								{
+									panic!("Trying to move into a full output buffer.");
+								}
+								moved_phits+=1;
+								self.time_at_input_head[entry_port][entry_vc]=0;
+								*phit.virtual_channel.borrow_mut()=Some(exit_vc);
+								if let Some(threshold)=self.ecn_threshold
+								{
+									if phit.is_begin() && self.output_buffers[exit_port][exit_vc].len()>=threshold
+									{
+										*phit.packet.congestion_experienced.borrow_mut()=true;
+									}
+								}
+								if let Some(message)=ack_message
+								{
+									let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
 									//event:Event::Acknowledge{location:previous_location,message:AcknowledgeMessage::ack_phit_clear_from_virtual_channel(entry_vc)},
-									event:Event::Acknowledge{location:previous_location,message},
-								});
-							}
-							if let Some((ref s_exit_packet,s_exit_port,s_exit_vc))=self.selected_output[entry_port][entry_vc]
-							{
-								let entry_packet_ptr = entry_packet.as_ref() as *const Packet;
-								let s_exit_packet_ptr = s_exit_packet.as_ref() as *const Packet;
-								if s_exit_packet_ptr!=entry_packet_ptr || s_exit_port!=exit_port || s_exit_vc!=exit_vc
+									let event = Event::Acknowledge{location:previous_location,source:Location::RouterPort{router_index:self.router_index,router_port:entry_port},message};
+									events.push(simulation.schedule_credit_return(previous_link_class,event));
+								}
+								if let Some((ref s_exit_packet,s_exit_port,s_exit_vc))=self.selected_output[entry_port][entry_vc]
 								{
-									panic!("Mismatch between selected input and selected output: selected_input[{}][{}]=({:?},{},{}) selected_output[{}][{}]=({:?},{},{}).",exit_port,exit_vc,entry_packet_ptr,entry_port,entry_vc,  entry_port,entry_vc,s_exit_packet_ptr,s_exit_port,s_exit_vc);
+									let entry_packet_ptr = entry_packet.as_ref() as *const Packet;
+									let s_exit_packet_ptr = s_exit_packet.as_ref() as *const Packet;
+									if s_exit_packet_ptr!=entry_packet_ptr || s_exit_port!=exit_port || s_exit_vc!=exit_vc
+									{
+										panic!("Mismatch between selected input and selected output: selected_input[{}][{}]=({:?},{},{}) selected_output[{}][{}]=({:?},{},{}).",exit_port,exit_vc,entry_packet_ptr,entry_port,entry_vc,  entry_port,entry_vc,s_exit_packet_ptr,s_exit_port,s_exit_vc);
+									}
 								}
-							}
-							if phit.is_end()
-							{
-								self.selected_input[exit_port][exit_vc]=None;
-								self.selected_output[entry_port][entry_vc]=None;
+								if phit.is_end()
+								{
+									self.selected_input[exit_port][exit_vc]=None;
+									self.selected_output[entry_port][entry_vc]=None;
+								}
+								else
+								{
+									self.selected_output[entry_port][entry_vc]=Some((entry_packet.clone(),exit_port,exit_vc));
+								}
+								self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc));
+								self.energy_counters.buffer_reads+=1;
+								self.energy_counters.crossbar_traversals+=1;
+								self.energy_counters.buffer_writes+=1;
 							}
 							else
 							{
-								self.selected_output[entry_port][entry_vc]=Some((entry_packet.clone(),exit_port,exit_vc));
+								if crossbar_slot==0 && self.flit_size>1
+								{
+									//We would like to panic if phit.packet.size<=flit_size, but we do not have the phit accesible.
+									println!("WARNING: There were no phit at the selected_input[{}][{}]=({},{}) of the router {}.",exit_port,exit_vc,entry_port,entry_vc,self.router_index);
+								}
+								break;
 							}
-							self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc));
 						}
 						else
 						{
-							if self.flit_size>1
-							{
-								//We would like to panic if phit.packet.size<=flit_size, but we do not have the phit accesible.
-								println!("WARNING: There were no phit at the selected_input[{}][{}]=({},{}) of the router {}.",exit_port,exit_vc,entry_port,entry_vc,self.router_index);
-							}
+							break;
 						}
 					}
-					else if let Some(phit)=self.reception_port_space[entry_port].front_virtual_channel(entry_vc)
+				}
+			}
+			//The link can carry `width` phits in each cycle.
+			let width = match simulation.network.topology.neighbour(self.router_index,exit_port)
+			{
+				(Location::None,_) => 1,
+				(_,link_class) => simulation.link_classes[link_class].width,
+			};
+			let mut undo_selected_input=Vec::with_capacity(nvc);
+			let mut selected_virtual_channels=Vec::with_capacity(width);
+			for _link_slot in 0..width
+			{
+				//Gather the list of all vc that can advance
+				let mut cand=Vec::with_capacity(nvc);
+				let mut cand_in_transit=false;
+				for exit_vc in 0..nvc
+				{
+					if self.output_buffer_size==0
 					{
-						if phit.is_begin()
+						if let Some((_,entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
 						{
-							undo_selected_input.push(exit_vc);
-						}
-						let bubble_in_use= self.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(self.router_index,entry_port,exit_port);
-						//if self.transmission_port_status[exit_port].can_transmit(&phit,exit_vc,transmit_auxiliar_info)
-						if self.can_phit_advance(&phit,exit_port,exit_vc,bubble_in_use)
-						{
-							//cand.push(exit_vc);
-							if cand_in_transit
-							{
-								if !phit.is_begin()
-								{
-									cand.push(exit_vc);
-								}
-							}
-							else
+							if let Some(phit)=self.reception_port_space[entry_port].front_virtual_channel(entry_vc)
 							{
 								if phit.is_begin()
 								{
-									cand.push(exit_vc);
+									undo_selected_input.push(exit_vc);
 								}
-								else
+								let bubble_in_use= self.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(self.router_index,entry_port,exit_port);
+								//if self.transmission_port_status[exit_port].can_transmit(&phit,exit_vc,transmit_auxiliar_info)
+								if self.can_phit_advance(&phit,exit_port,exit_vc,bubble_in_use)
 								{
-									cand=vec![exit_vc];
-									cand_in_transit=true;
+									//cand.push(exit_vc);
+									if cand_in_transit
+									{
+										if !phit.is_begin()
+										{
+											cand.push(exit_vc);
+										}
+									}
+									else
+									{
+										if phit.is_begin()
+										{
+											cand.push(exit_vc);
+										}
+										else
+										{
+											cand=vec![exit_vc];
+											cand_in_transit=true;
+										}
+									}
 								}
 							}
 						}
 					}
-				}
-				if self.output_buffer_size>0
-				{
-					//Candidates when using output ports.
-					if let Some( (phit,(entry_port,_entry_vc))) = self.output_buffers[exit_port][exit_vc].front()
+					if self.output_buffer_size>0
 					{
-						let bubble_in_use= self.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(self.router_index,entry_port,exit_port);
-						let status=&self.transmission_port_status[exit_port];
-						let can_transmit = if bubble_in_use
-						{
-							//self.transmission_port_status[exit_port].can_transmit_whole_packet(&phit,exit_vc)
-							if let Some(space)=status.known_available_space_for_virtual_channel(exit_vc)
-							{
-								status.can_transmit(&phit,exit_vc) && space>= phit.packet.size + self.maximum_packet_size
-							}
-							else
-							{
-								panic!("Basic router requires knowledge of available space to apply bubble.");
-							}
-						}
-						else
+						//Candidates when using output ports.
+						if let Some( (phit,(entry_port,_entry_vc))) = self.output_buffers[exit_port][exit_vc].front()
 						{
-							status.can_transmit(&phit,exit_vc)
-						};
-						if can_transmit
-						{
-							if cand_in_transit
+							let bubble_in_use= self.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(self.router_index,entry_port,exit_port);
+							let status=&self.transmission_port_status[exit_port];
+							let can_transmit = if bubble_in_use
 							{
-								if !phit.is_begin()
+								//self.transmission_port_status[exit_port].can_transmit_whole_packet(&phit,exit_vc)
+								if let Some(space)=status.known_available_space_for_virtual_channel(exit_vc)
+								{
+									status.can_transmit(&phit,exit_vc) && space>= phit.packet.size + self.maximum_packet_size
+								}
+								else
 								{
-									cand.push(exit_vc);
+									panic!("Basic router requires knowledge of available space to apply bubble.");
 								}
 							}
 							else
 							{
-								if phit.is_begin()
+								status.can_transmit(&phit,exit_vc)
+							};
+							if can_transmit
+							{
+								if cand_in_transit
 								{
-									cand.push(exit_vc);
+									if !phit.is_begin()
+									{
+										cand.push(exit_vc);
+									}
 								}
 								else
 								{
-									cand=vec![exit_vc];
-									cand_in_transit=true;
+									if phit.is_begin()
+									{
+										cand.push(exit_vc);
+									}
+									else
+									{
+										cand=vec![exit_vc];
+										cand_in_transit=true;
+									}
 								}
 							}
-						}
-						else
-						{
-							if 0<phit.index && phit.index<self.flit_size
+							else
 							{
-								panic!("cannot transmit phit (index={}) but it should (flit_size={})",phit.index,self.flit_size);
+								if 0<phit.index && phit.index<self.flit_size
+								{
+									panic!("cannot transmit phit (index={}) but it should (flit_size={})",phit.index,self.flit_size);
+								}
 							}
 						}
 					}
 				}
-			}
-			//for selected_virtual_channel in 0..nvc
-			let selected_virtual_channel = if !cand.is_empty()
-			{
-				//Then select one of the vc candidates (either in input or output buffer) to actually use the physical port.
-				let selected_virtual_channel = match self.output_arbiter
+				if self.link_replay.is_busy(exit_port,simulation.cycle)
 				{
-					//OutputArbiter::Random=> cand[mutable.rng.borrow_mut().gen_range(0,cand.len())],//rand-0.4
-					OutputArbiter::Random=> cand[mutable.rng.gen_range(0..cand.len())],//rand-0.8
-					OutputArbiter::Token{ref mut port_token}=>
-					{
-						//Or by tokens as in fsin
-						//let nvc=self.virtual_ports[exit_port].len() as i64;
-						let nvc= amount_virtual_channels as i64;
-						let token= port_token[exit_port] as i64;
-						let mut best=0;
-						let mut bestd=nvc;
-						for vc in cand
-						{
-							let mut d:i64 = vc as i64 - token;
-							if d<0
-							{
-								d+=nvc;
-							}
-							if d<bestd
-							{
-								best=vc;
-								bestd=d;
-							}
-						}
-						port_token[exit_port]=best;
-						best
-					},
-				};
-				//move phits around.
-				let (phit,original_port) = if self.output_buffer_size>0
+					//The link is busy replaying a corrupted phit.
+					cand.clear();
+					blocked_links=true;
+				}
+				else if !cand.is_empty() && !mutable.link_power.ready(LinkSource::RouterPort{router_index:self.router_index,router_port:exit_port},&simulation.link_classes,simulation.cycle)
 				{
-					//If we get the phit from an output buffer there is little to do.
-					let (phit,(entry_port,_entry_vc))=self.output_buffers[exit_port][selected_virtual_channel].pop().expect("incorrect selected_input");
-					(phit,entry_port)
+					//The link is sleeping, waking up, or working at a reduced rate.
+					cand.clear();
+					blocked_links=true;
 				}
-				else
+				//for selected_virtual_channel in 0..nvc
+				let selected_virtual_channel = if !cand.is_empty()
 				{
-					//If we get the phit from an input buffer we have to send acks to the previous router and take care of sending the packet in one piece.
-					if let Some((ref packet,iport,entry_vc))=self.selected_input[exit_port][selected_virtual_channel]
+					//Then select one of the vc candidates (either in input or output buffer) to actually use the physical port.
+					let selected_virtual_channel = match self.output_arbiter
 					{
-						if let Ok((phit,ack_message)) = self.reception_port_space[iport].extract(entry_vc)
+						//OutputArbiter::Random=> cand[mutable.rng.borrow_mut().gen_range(0,cand.len())],//rand-0.4
+						OutputArbiter::Random=> cand[mutable.rng.gen_range(0..cand.len())],//rand-0.8
+						OutputArbiter::Token{ref mut port_token}=>
 						{
-							moved_phits+=1;
-							self.time_at_input_head[iport][entry_vc]=0;
-							//phit.virtual_channel.replace(Some(selected_virtual_channel));
-							*phit.virtual_channel.borrow_mut()=Some(selected_virtual_channel);
-							if let Some(message)=ack_message
+							//Or by tokens as in fsin
+							//let nvc=self.virtual_ports[exit_port].len() as i64;
+							let nvc= amount_virtual_channels as i64;
+							let token= port_token[exit_port] as i64;
+							let mut best=0;
+							let mut bestd=nvc;
+							for vc in cand
 							{
-								let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,iport);
-								events.push(EventGeneration{
-									delay: simulation.link_classes[previous_link_class].delay,
-									position:CyclePosition::Begin,
-									//event:Event::PhitClearAcknowledge{location:previous_location,virtual_channel:entry_vc},
-									event:Event::Acknowledge{location:previous_location,message},
-								});
+								let mut d:i64 = vc as i64 - token;
+								if d<0
+								{
+									d+=nvc;
+								}
+								if d<bestd
+								{
+									best=vc;
+									bestd=d;
+								}
 							}
-							if phit.is_end()
+							port_token[exit_port]=best;
+							best
+						},
+					};
+					//move phits around.
+					let (phit,original_port) = if self.output_buffer_size>0
+					{
+						//If we get the phit from an output buffer there is little to do.
+						let (phit,(entry_port,_entry_vc))=self.output_buffers[exit_port][selected_virtual_channel].pop().expect("incorrect selected_input");
+						self.energy_counters.buffer_reads+=1;
+						(phit,entry_port)
+					}
+					else
+					{
+						//If we get the phit from an input buffer we have to send acks to the previous router and take care of sending the packet in one piece.
+						if let Some((ref packet,iport,entry_vc))=self.selected_input[exit_port][selected_virtual_channel]
+						{
+							if let Ok((phit,ack_message)) = self.reception_port_space[iport].extract(entry_vc)
 							{
-								self.selected_input[exit_port][selected_virtual_channel]=None;
-								self.selected_output[iport][entry_vc]=None;
+								moved_phits+=1;
+								self.energy_counters.buffer_reads+=1;
+								self.energy_counters.crossbar_traversals+=1;
+								self.time_at_input_head[iport][entry_vc]=0;
+								//phit.virtual_channel.replace(Some(selected_virtual_channel));
+								*phit.virtual_channel.borrow_mut()=Some(selected_virtual_channel);
+								if let Some(message)=ack_message
+								{
+									let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,iport);
+									//event:Event::PhitClearAcknowledge{location:previous_location,virtual_channel:entry_vc},
+									let event = Event::Acknowledge{location:previous_location,source:Location::RouterPort{router_index:self.router_index,router_port:iport},message};
+									events.push(simulation.schedule_credit_return(previous_link_class,event));
+								}
+								if phit.is_end()
+								{
+									self.selected_input[exit_port][selected_virtual_channel]=None;
+									self.selected_output[iport][entry_vc]=None;
+								}
+								else
+								{
+									self.selected_output[iport][entry_vc]=Some((packet.clone(),exit_port,selected_virtual_channel));
+								}
+								(phit,iport)
 							}
 							else
 							{
-								self.selected_output[iport][entry_vc]=Some((packet.clone(),exit_port,selected_virtual_channel));
+								panic!("There were no phit at the selected_input[{}][{}]=({},{}), and somehow it is selected",exit_port,selected_virtual_channel,iport,entry_vc);
 							}
-							(phit,iport)
 						}
 						else
 						{
-							panic!("There were no phit at the selected_input[{}][{}]=({},{}), and somehow it is selected",exit_port,selected_virtual_channel,iport,entry_vc);
+							panic!("incorrect selected_input")
 						}
-					}
-					else
-					{
-						panic!("incorrect selected_input")
-					}
-				};
-				let (new_location,link_class)=simulation.network.topology.neighbour(self.router_index,exit_port);
-				//Send the phit to the other link endpoint.
-				events.push(EventGeneration{
-					delay: simulation.link_classes[link_class].delay,
-					position:CyclePosition::Begin,
-					event:Event::PhitToLocation{
-						phit: phit.clone(),
-						previous: Location::RouterPort{
-							router_index: self.router_index,
-							router_port: original_port,
+					};
+					let (new_location,link_class)=simulation.network.topology.neighbour(self.router_index,exit_port);
+					let replay = self.link_replay.transmit(exit_port,link_class,&simulation.link_classes,simulation.cycle,mutable);
+					//Send the phit to the other link endpoint.
+					events.push(EventGeneration{
+						delay: simulation.link_classes[link_class].delay+replay,
+						position:CyclePosition::Begin,
+						event:Event::PhitToLocation{
+							phit: phit.clone(),
+							previous: Location::RouterPort{
+								router_index: self.router_index,
+								router_port: original_port,
+							},
+							new: new_location,
 						},
-						new: new_location,
-					},
-				});
-				self.transmission_port_status[exit_port].notify_outcoming_phit(selected_virtual_channel,simulation.cycle);
-				if phit.is_end()
-				{
-					if let OutputArbiter::Token{ref mut port_token}=self.output_arbiter
+					});
+					self.transmission_port_status[exit_port].notify_outcoming_phit(selected_virtual_channel,simulation.cycle);
+					mutable.link_power.notify_transmission(LinkSource::RouterPort{router_index:self.router_index,router_port:exit_port},simulation.cycle);
+					if phit.is_end()
 					{
-						port_token[exit_port]=(port_token[exit_port]+1)%amount_virtual_channels;
+						if let OutputArbiter::Token{ref mut port_token}=self.output_arbiter
+						{
+							port_token[exit_port]=(port_token[exit_port]+1)%amount_virtual_channels;
+						}
 					}
+					Some(selected_virtual_channel)
+				} else {None};
+				match selected_virtual_channel
+				{
+					Some(virtual_channel) => selected_virtual_channels.push(virtual_channel),
+					None => break,
 				}
-				Some(selected_virtual_channel)
-			} else {None};
+			}
 			for other_virtual_channel in undo_selected_input
 			{
-				if Some(other_virtual_channel) != selected_virtual_channel
+				if !selected_virtual_channels.contains(&other_virtual_channel)
 				{
 					//Packets that have not started to move can change their decision at the next cycle
 					self.selected_input[exit_port][other_virtual_channel]=None;
 				}
 			}
 		}
+		//-- Send the control messages of the receptors, such as pause frames.
+		for (port,space) in self.reception_port_space.iter_mut().enumerate()
+		{
+			for message in space.take_control_messages(simulation.cycle)
+			{
+				let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,port);
+				let event = Event::Acknowledge{location:previous_location,source:Location::RouterPort{router_index:self.router_index,router_port:port},message};
+				events.push(simulation.schedule_credit_return(previous_link_class,event));
+			}
+		}
 		self.next_events.pop();//remove the event that was served.
 		//TODO: what to do with probabilistic requests???
-		if undecided_channels>0 || moved_phits>0 || !events.is_empty() || request_len>0
+		if undecided_channels>0 || moved_phits>0 || !events.is_empty() || request_len>0 || blocked_links
 		//if undecided_channels>0 || moved_phits>0 || events.len()>0
 		//if true
 		{
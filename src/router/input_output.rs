@@ -1,19 +1,20 @@
 use std::cell::RefCell;
 use std::rc::{Rc,Weak};
-use std::ops::Deref;
 use std::mem::size_of;
 use ::rand::{Rng,rngs::StdRng};
-use super::{Router,AbstractTransmissionMechanism,TransmissionMechanismBuilderArgument,new_transmission_mechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage};
+use super::{Router,AbstractTransmissionMechanism,TransmissionMechanismBuilderArgument,new_transmission_mechanism,aggregate_flow_control_statistics,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage};
 use crate::allocator::{Allocator,VCARequest,AllocatorBuilderArgument, new_allocator};
 use crate::config_parser::ConfigurationValue;
 use crate::router::RouterBuilderArgument;
 use crate::topology::{Location,Topology};
-use crate::routing::CandidateEgress;
+use crate::routing::{CandidateEgress,CongestionInfo};
 use crate::policies::{RequestInfo,VirtualChannelPolicy,new_virtual_channel_policy,VCPolicyBuilderArgument};
 use crate::event::{self,Event,Eventful,EventGeneration,CyclePosition,Time};
-use crate::{Phit,SimulationShared,SimulationMut};
+use crate::{Phit,SimulationShared,SimulationMut,LinkReplay};
 use crate::quantify::Quantifiable;
 use crate::match_object_panic;
+use crate::energy::{RouterEnergy,EnergyCounters};
+use crate::power::LinkSource;
 
 
 ///Strategy for the arbitration of the output port.
@@ -55,8 +56,13 @@ pub struct InputOutput
 	///In particular, [EnforceFlowControl] will filter them out.
 	///Defaults to false.
 	neglect_busy_output: bool,
+	///If some, the packets whose leading phit enters an output buffer with at least this many phits are marked as experiencing congestion.
+	///See the [congestion](crate::congestion) module.
+	ecn_threshold: Option<usize>,
 	/// `transmission_port_status[port] = status`
 	transmission_port_status: Vec<Box<dyn StatusAtEmissor>>,
+	///Whether the link of each port is busy replaying a corrupted phit.
+	link_replay: LinkReplay,
 	/// `reception_port_space[port] = space`
 	reception_port_space: Vec<Box<dyn SpaceAtReceptor>>,
 	/// The server to router mechanism employed.
@@ -87,6 +93,13 @@ pub struct InputOutput
 	///Divisor of the cycles in which the crossbar operates.
 	///Without other overrides, the quotient `general_frequency_divisor/crossbar_frequency_divisor` is the internal speedup.
 	crossbar_frequency_divisor: Time,
+	///Phits that each virtual channel of an output port may receive through the crossbar in each of its cycles. Defaults to 1.
+	///It multiplies the internal speedup without changing the clock of the simulation.
+	crossbar_speedup: usize,
+	///The energy consumed by the events of the router, if configured. See the [energy](crate::energy) module.
+	energy: Option<RouterEnergy>,
+	///The events consuming energy since the last reset of the statistics.
+	energy_counters: EnergyCounters,
 
 	//allocator:
 	///The allocator for the croosbar.
@@ -108,6 +121,7 @@ impl Router for InputOutput
 	fn insert(&mut self, current_cycle:Time, phit:Rc<Phit>, port:usize, rng: &mut StdRng) -> Vec<EventGeneration>
 	{
 		self.reception_port_space[port].insert(phit,rng).expect("there was some problem on the insertion");
+		self.energy_counters.buffer_writes+=1;
 		if let Some(event) = self.schedule(current_cycle,0) {
 			vec![event]
 		} else {
@@ -168,6 +182,7 @@ impl Router for InputOutput
 		let cycle_span = cycle - self.statistics_begin_cycle;
 		let mut reception_space_occupation_per_vc:Option<Vec<f64>> = Some(self.statistics_reception_space_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
 		let mut output_buffer_occupation_per_vc:Option<Vec<f64>> = Some(self.statistics_output_buffer_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect());
+		let mut previous_flow_control=None;
 		if let Some(previous)=statistics
 		{
 			if let ConfigurationValue::Object(cv_name,previous_pairs) = previous
@@ -230,6 +245,7 @@ impl Router for InputOutput
 							}
 							_ => panic!("bad value for average_output_buffer_occupation_per_vc"),
 						},
+						"flow_control_statistics" => previous_flow_control=Some(value.clone()),
 						_ => panic!("Nothing to do with field {} in InputOutput statistics",name),
 					}
 				}
@@ -276,12 +292,21 @@ impl Router for InputOutput
 			}
 			result_content.push((String::from("average_reception_space_occupation_per_vc"),ConfigurationValue::Array(content.iter().map(|x|ConfigurationValue::Number(*x)).collect())));
 		}
+		if let Some(flow_control)=aggregate_flow_control_statistics(previous_flow_control.as_ref(),&self.reception_port_space,cycle)
+		{
+			result_content.push((String::from("flow_control_statistics"),flow_control));
+		}
 		Some(ConfigurationValue::Object(String::from("InputOutput"),result_content))
 	}
 
 	fn reset_statistics(&mut self, next_cycle:Time)
 	{
 		self.statistics_begin_cycle=next_cycle;
+		self.energy_counters=EnergyCounters::default();
+		for space in self.reception_port_space.iter_mut()
+		{
+			space.reset_flow_control_counters(next_cycle);
+		}
 		for x in self.statistics_output_buffer_occupation_per_vc.iter_mut()
 		{
 			*x=0f64;
@@ -302,6 +327,10 @@ impl Router for InputOutput
 			unimplemented!()
 		}
 	}
+	fn energy(&self) -> Option<(&RouterEnergy,&EnergyCounters)>
+	{
+		self.energy.as_ref().map(|energy|(energy,&self.energy_counters))
+	}
 }
 
 
@@ -332,11 +361,15 @@ impl InputOutput
 		let mut output_buffer_size=None;
 		let mut allocator_value=None;
 		let mut transmission_mechanism=None;
+		let mut transmission_mechanism_cv=None;
 		let mut to_server_mechanism=None;
 		let mut from_server_mechanism=None;
 		let mut crossbar_delay: Time =0;
 		let mut neglect_busy_output = false;
+		let mut ecn_threshold = None;
 		let mut crossbar_frequency_divisor = general_frequency_divisor;
+		let mut crossbar_speedup = 1;
+		let mut energy = None;
 		match_object_panic!(cv,["InputOutput","InputOutputMonocycle"],value,
 			"virtual_channels" => match value
 			{
@@ -397,9 +430,15 @@ impl InputOutput
 			};
 */
 			"neglect_busy_output" => neglect_busy_output = value.as_bool().expect("bad value for neglect_busy_output"),
+			"ecn_threshold" => ecn_threshold = Some(value.as_usize().expect("bad value for ecn_threshold")),
 			"transmission_mechanism" => match value
 			{
 				&ConfigurationValue::Literal(ref s) => transmission_mechanism = Some(s.to_string()),
+				ConfigurationValue::Object(name,_) =>
+				{
+					transmission_mechanism = Some(name.to_string());
+					transmission_mechanism_cv = Some(value);
+				},
 				_ => panic!("bad value for transmission_mechanism"),
 			},
 			"to_server_mechanism" => match value
@@ -414,7 +453,13 @@ impl InputOutput
 			},
 			"allocator" => allocator_value=Some(value.clone()),
 			"crossbar_frequency_divisor" => crossbar_frequency_divisor = value.as_time().expect("bad value for crossbar_frequency_divisor"),
+			"crossbar_speedup" => crossbar_speedup = value.as_usize().expect("bad value for crossbar_speedup"),
+			"energy" => energy = Some(RouterEnergy::new(value)),
 		);
+		if crossbar_speedup==0
+		{
+			panic!("The crossbar_speedup of the InputOutput router must be positive.");
+		}
 		//let sides=sides.expect("There were no sides");
 		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
 		let virtual_channel_policies=virtual_channel_policies.expect("There were no virtual_channel_policies");
@@ -448,8 +493,8 @@ impl InputOutput
 		let from_server_mechanism = from_server_mechanism.unwrap_or_else(||"SimpleVirtualChannels".to_string());
 		let to_server_mechanism = to_server_mechanism.unwrap_or_else(||"TransmissionToServer".to_string());
 		//let transmission_mechanism = super::SimpleVirtualChannels::new(virtual_channels,buffer_size,flit_size);
-		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",virtual_channels,buffer_size,size_to_send:flit_size};
-		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,..transmission_builder_argument});
+		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",cv:None,virtual_channels,buffer_size,size_to_send:flit_size};
+		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,cv:transmission_mechanism_cv,..transmission_builder_argument});
 		let to_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&to_server_mechanism,..transmission_builder_argument});
 		//let from_server_mechanism = TransmissionFromServer::new(virtual_channels,buffer_size,flit_size);
 		let from_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&from_server_mechanism,..transmission_builder_argument});
@@ -494,9 +539,11 @@ impl InputOutput
 			allow_request_busy_port,
 //			output_priorize_lowest_label,
 			neglect_busy_output,
+			ecn_threshold,
 			buffer_size,
 			crossbar_delay,
 			transmission_port_status,
+			link_replay: LinkReplay::new(input_ports),
 			reception_port_space,
 			from_server_mechanism,
 			output_buffer_size,
@@ -509,6 +556,9 @@ impl InputOutput
 			output_arbiter: OutputArbiter::Token{port_token: vec![0;input_ports]},
 			maximum_packet_size,
 			crossbar_frequency_divisor,
+			crossbar_speedup,
+			energy,
+			energy_counters: EnergyCounters::default(),
 			crossbar_allocator: allocator,
 			statistics_begin_cycle: 0,
 			statistics_output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
@@ -727,13 +777,18 @@ impl Eventful for InputOutput
 					{
 						undecided_channels+=1;
 						let target_server=phit.packet.message.destination;
-						let (target_location,_link_class)=topology.server_neighbour(target_server);
-						let target_router=match target_location
-						{
-							Location::RouterPort{router_index,router_port:_} =>router_index,
-							_ => panic!("The server is not attached to a router"),
+						let target_router=phit.packet.target_router();
+						let congestion=CongestionInfo{
+							router: self,
+							port_average_neighbour_queue_length: port_average_neighbour_queue_length.as_ref(),
+							port_last_transmission: port_last_transmission.as_ref(),
+							port_occupied_output_space: port_occupied_output_space.as_ref(),
+							port_available_output_space: port_available_output_space.as_ref(),
+							virtual_channel_occupied_output_space: virtual_channel_occupied_output_space.as_ref(),
+							virtual_channel_available_output_space: virtual_channel_available_output_space.as_ref(),
+							current_cycle: simulation.cycle,
 						};
-						let routing_candidates=simulation.routing.next(phit.packet.routing_info.borrow().deref(),simulation.network.topology.as_ref(),self.router_index,target_router,Some(target_server),amount_virtual_channels,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
+						let routing_candidates=simulation.routing.next_with_congestion(&phit.packet.routing_info,simulation.network.topology.as_ref(),self.router_index,target_router,Some(target_server),amount_virtual_channels,&congestion,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
 						let routing_idempotent = routing_candidates.idempotent;
 						if routing_candidates.len()==0
 						{
@@ -882,6 +937,7 @@ impl Eventful for InputOutput
 		for VCARequest{entry_port,entry_vc,requested_port,requested_vc,..} in request_it
 		{
 			self.selected_input[requested_port][requested_vc]=Some((entry_port,entry_vc));
+			self.energy_counters.allocations+=1;
 			self.selected_output[entry_port][entry_vc]=Some((requested_port,requested_vc));
 		}
 
@@ -892,72 +948,106 @@ impl Eventful for InputOutput
 			let nvc=amount_virtual_channels;
 			for exit_vc in 0..nvc
 			{
-				if let Some((entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
+				//-- Move phits into the internal output space, up to `crossbar_speedup` phits per virtual channel.
+				//Note that it is possible when flit_size<packet_size for the packet to not be in that buffer. The output arbiter can decide to advance other virtual channel.
+				for crossbar_slot in 0..self.crossbar_speedup
 				{
-					//-- Move phits into the internal output space
-					//Note that it is possible when flit_size<packet_size for the packet to not be in that buffer. The output arbiter can decide to advance other virtual channel.
-					if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
+					if let Some((entry_port,entry_vc))=self.selected_input[exit_port][exit_vc]
 					{
-						// For the check with crossbar delay look into PhitToOutput::process.
-						if self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
+						if crossbar_slot>0 && self.output_buffers[exit_port][exit_vc].len()+self.output_buffer_phits_traversing_crossbar[exit_port][exit_vc]>=self.output_buffer_size
 						{
-							panic!("Trying to move into a full output buffer.");
-						}
-						moved_input_phits+=1;
-						self.time_at_input_head[entry_port][entry_vc]=0;
-						*phit.virtual_channel.borrow_mut()=Some(exit_vc);
-						if let Some(message)=ack_message
-						{
-							// If the crossbar operates at higher frequency (aka internal speedup) then it would send acks at greater rate than allowed.
-							// We allow sending several events in the same cycle of the link. Acks should have few bits and be possible to be aggregated.
-							let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
-							let event = Event::Acknowledge{location:previous_location,message};
-							events.push(simulation.schedule_link_arrival( previous_link_class, event ));
+							//The allocation only guarantees space for the first phit.
+							break;
 						}
-						if phit.is_end()
+						if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
 						{
-							self.selected_input[exit_port][exit_vc]=None;
-							self.selected_output[entry_port][entry_vc]=None;
+							// For the check with crossbar delay look into PhitToOutput::process.
+							if self.output_buffers[exit_port][exit_vc].len()>=self.output_buffer_size
+							{
+								panic!("Trying to move into a full output buffer.");
+							}
+							moved_input_phits+=1;
+							self.energy_counters.buffer_reads+=1;
+							self.energy_counters.crossbar_traversals+=1;
+							self.energy_counters.buffer_writes+=1;
+							self.time_at_input_head[entry_port][entry_vc]=0;
+							*phit.virtual_channel.borrow_mut()=Some(exit_vc);
+							if let Some(threshold)=self.ecn_threshold
+							{
+								let occupation = self.output_buffers[exit_port][exit_vc].len() + self.output_buffer_phits_traversing_crossbar[exit_port][exit_vc];
+								if phit.is_begin() && occupation>=threshold
+								{
+									*phit.packet.congestion_experienced.borrow_mut()=true;
+								}
+							}
+							if let Some(message)=ack_message
+							{
+								// If the crossbar operates at higher frequency (aka internal speedup) then it would send acks at greater rate than allowed.
+								// We allow sending several events in the same cycle of the link. Acks should have few bits and be possible to be aggregated.
+								let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,entry_port);
+								let event = Event::Acknowledge{location:previous_location,source:Location::RouterPort{router_index:self.router_index,router_port:entry_port},message};
+								events.push(simulation.schedule_credit_return_in_link_cycles( previous_link_class, event ));
+							}
+							if phit.is_end()
+							{
+								self.selected_input[exit_port][exit_vc]=None;
+								self.selected_output[entry_port][entry_vc]=None;
+							}
+							else
+							{
+								self.selected_output[entry_port][entry_vc]=Some((exit_port,exit_vc));
+							}
+							if self.crossbar_delay==0 {
+								self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc));
+								let mut output_scheduler = self.output_schedulers[exit_port].borrow_mut();
+								if let Some(event) = output_scheduler.schedule(simulation.cycle,0) {
+									events.push(event);
+								}
+							} else {
+								let event = Rc::<RefCell<internal::PhitToOutput>>::from(internal::PhitToOutputArgument{
+									//router: self.self_rc.upgrade().unwrap(),
+									router: self,
+									exit_port,
+									exit_vc,
+									entry_port,
+									entry_vc,
+									phit,
+								});
+								events.push(EventGeneration{
+									delay: self.crossbar_delay,
+									position:CyclePosition::Begin,
+									event: Event::Generic(event),
+								});
+							}
 						}
 						else
 						{
-							self.selected_output[entry_port][entry_vc]=Some((exit_port,exit_vc));
-						}
-						if self.crossbar_delay==0 {
-							self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,entry_vc));
-							let mut output_scheduler = self.output_schedulers[exit_port].borrow_mut();
-							if let Some(event) = output_scheduler.schedule(simulation.cycle,0) {
-								events.push(event);
+							if self.flit_size>1
+							{
+								//XXX We seem to easily reach this region when using different frequencies.
+								//We would like to panic if phit.packet.size<=flit_size, but we do not have the phit accesible.
+								//println!("WARNING: There were no phit at the selected_input[{}][{}]=({},{}) of the router {}.",exit_port,exit_vc,entry_port,entry_vc,self.router_index);
 							}
-						} else {
-							let event = Rc::<RefCell<internal::PhitToOutput>>::from(internal::PhitToOutputArgument{
-								//router: self.self_rc.upgrade().unwrap(),
-								router: self,
-								exit_port,
-								exit_vc,
-								entry_port,
-								entry_vc,
-								phit,
-							});
-							events.push(EventGeneration{
-								delay: self.crossbar_delay,
-								position:CyclePosition::Begin,
-								event: Event::Generic(event),
-							});
+							break;
 						}
 					}
 					else
 					{
-						if self.flit_size>1
-						{
-							//XXX We seem to easily reach this region when using different frequencies.
-							//We would like to panic if phit.packet.size<=flit_size, but we do not have the phit accesible.
-							//println!("WARNING: There were no phit at the selected_input[{}][{}]=({},{}) of the router {}.",exit_port,exit_vc,entry_port,entry_vc,self.router_index);
-						}
+						break;
 					}
 				}
 			}
 		}
+		//-- Send the control messages of the receptors, such as pause frames.
+		for (port,space) in self.reception_port_space.iter_mut().enumerate()
+		{
+			for message in space.take_control_messages(simulation.cycle)
+			{
+				let (previous_location,previous_link_class)=simulation.network.topology.neighbour(self.router_index,port);
+				let event = Event::Acknowledge{location:previous_location,source:Location::RouterPort{router_index:self.router_index,router_port:port},message};
+				events.push(simulation.schedule_credit_return_in_link_cycles(previous_link_class,event));
+			}
+		}
 		self.next_events.pop();//remove the event that was served.
 		//TODO: what to do with probabilistic requests???
 		//if undecided_channels>0 || moved_phits>0 || events.len()>0 || request.len()>0
@@ -1128,136 +1218,166 @@ mod internal
 		{
 			let mut events=vec![];
 			let mut router = self.router.borrow_mut();
+			if router.link_replay.is_busy(self.exit_port,simulation.cycle)
+			{
+				let replay_until = router.link_replay.busy_until(self.exit_port);
+				//The link is busy replaying a corrupted phit. Try again when it finishes.
+				drop(router);
+				self.pending_event = false;
+				return self.schedule(simulation.cycle,replay_until-simulation.cycle).into_iter().collect();
+			}
 			let nvc= self.amount_virtual_channels;
-			//Gather the list of all vc that can advance
-			let mut cand=Vec::with_capacity(nvc);
-			let mut cand_in_transit=false;
-//			let mut undo_selected_input=Vec::with_capacity(nvc);
-			//let is_link_cycle = simulation.is_link_cycle(link_class);
-			for exit_vc in 0..nvc
+			//Whether the link is sleeping, waking up, or working at a reduced rate.
+			let mut blocked_link=false;
+			//The link can carry `width` phits in each of its cycles.
+			for _link_slot in 0..self.link.width
 			{
-				//Candidates when using output ports.
-				if let Some( (phit,(entry_port,_entry_vc))) = router.output_buffers[self.exit_port][exit_vc].front()
+				if router.link_replay.is_busy(self.exit_port,simulation.cycle)
 				{
-					let bubble_in_use= router.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(router.router_index,entry_port,self.exit_port);
-					let status=&router.transmission_port_status[self.exit_port];
-					let can_transmit = if bubble_in_use
-					{
-						//router.transmission_port_status[self.exit_port].can_transmit_whole_packet(&phit,exit_vc)
-						if let Some(space)=status.known_available_space_for_virtual_channel(exit_vc)
-						{
-							status.can_transmit(&phit,exit_vc) && space>= phit.packet.size + router.maximum_packet_size
-						}
-						else
-						{
-							panic!("InputOutput router requires knowledge of available space to apply bubble.");
-						}
-					}
-					else
-					{
-						status.can_transmit(&phit,exit_vc)
-					};
-					if can_transmit
+					break;
+				}
+				//Gather the list of all vc that can advance
+				let mut cand=Vec::with_capacity(nvc);
+				let mut cand_in_transit=false;
+//			let mut undo_selected_input=Vec::with_capacity(nvc);
+				//let is_link_cycle = simulation.is_link_cycle(link_class);
+				for exit_vc in 0..nvc
+				{
+					//Candidates when using output ports.
+					if let Some( (phit,(entry_port,_entry_vc))) = router.output_buffers[self.exit_port][exit_vc].front()
 					{
-						if cand_in_transit
+						let bubble_in_use= router.bubble && phit.is_begin() && simulation.network.topology.is_direction_change(router.router_index,entry_port,self.exit_port);
+						let status=&router.transmission_port_status[self.exit_port];
+						let can_transmit = if bubble_in_use
 						{
-							if !phit.is_begin()
+							//router.transmission_port_status[self.exit_port].can_transmit_whole_packet(&phit,exit_vc)
+							if let Some(space)=status.known_available_space_for_virtual_channel(exit_vc)
+							{
+								status.can_transmit(&phit,exit_vc) && space>= phit.packet.size + router.maximum_packet_size
+							}
+							else
 							{
-								cand.push(exit_vc);
+								panic!("InputOutput router requires knowledge of available space to apply bubble.");
 							}
 						}
 						else
 						{
-							if phit.is_begin()
+							status.can_transmit(&phit,exit_vc)
+						};
+						if can_transmit
+						{
+							if cand_in_transit
 							{
-								cand.push(exit_vc);
+								if !phit.is_begin()
+								{
+									cand.push(exit_vc);
+								}
 							}
 							else
 							{
-								cand=vec![exit_vc];
-								cand_in_transit=true;
+								if phit.is_begin()
+								{
+									cand.push(exit_vc);
+								}
+								else
+								{
+									cand=vec![exit_vc];
+									cand_in_transit=true;
+								}
 							}
 						}
-					}
-					else
-					{
-						if 0<phit.index && phit.index<router.flit_size
+						else
 						{
-							panic!("cannot transmit phit (index={}) but it should (flit_size={})",phit.index,router.flit_size);
+							if 0<phit.index && phit.index<router.flit_size
+							{
+								panic!("cannot transmit phit (index={}) but it should (flit_size={})",phit.index,router.flit_size);
+							}
 						}
 					}
 				}
-			}
-			//for selected_virtual_channel in 0..nvc
-			if !cand.is_empty()
-			{
-				//Then select one of the vc candidates (either in input or output buffer) to actually use the physical port.
-				let selected_virtual_channel = match router.output_arbiter
+				if !cand.is_empty() && !mutable.link_power.ready(LinkSource::RouterPort{router_index:router.router_index,router_port:self.exit_port},&simulation.link_classes,simulation.cycle)
+				{
+					blocked_link=true;
+					break;
+				}
+				//for selected_virtual_channel in 0..nvc
+				if !cand.is_empty()
 				{
-					OutputArbiter::Random=> cand[mutable.rng.gen_range(0..cand.len())],
-					OutputArbiter::Token{ref mut port_token}=>
+					//Then select one of the vc candidates (either in input or output buffer) to actually use the physical port.
+					let selected_virtual_channel = match router.output_arbiter
 					{
-						//Or by tokens as in fsin
-						//let nvc=router.virtual_ports[self.exit_port].len() as i64;
-						let nvc= self.amount_virtual_channels as i64;
-						let token= port_token[self.exit_port] as i64;
-						let mut best=0;
-						let mut bestd=nvc;
-						for vc in cand
+						OutputArbiter::Random=> cand[mutable.rng.gen_range(0..cand.len())],
+						OutputArbiter::Token{ref mut port_token}=>
 						{
-							let mut d:i64 = vc as i64 - token;
-							if d<0
+							//Or by tokens as in fsin
+							//let nvc=router.virtual_ports[self.exit_port].len() as i64;
+							let nvc= self.amount_virtual_channels as i64;
+							let token= port_token[self.exit_port] as i64;
+							let mut best=0;
+							let mut bestd=nvc;
+							for vc in cand
 							{
-								d+=nvc;
-							}
-							if d<bestd
-							{
-								best=vc;
-								bestd=d;
+								let mut d:i64 = vc as i64 - token;
+								if d<0
+								{
+									d+=nvc;
+								}
+								if d<bestd
+								{
+									best=vc;
+									bestd=d;
+								}
 							}
-						}
-						port_token[self.exit_port]=best;
-						best
-					},
-				};
-				//move phits around.
-				let (phit,original_port) =
-				{
-					//If we get the phit from an output buffer there is little to do.
-					let (phit,(entry_port,_entry_vc))=router.output_buffers[self.exit_port][selected_virtual_channel].pop().expect("incorrect selected_input");
-					(phit,entry_port)
-				};
-				//Send the phit to the other link endpoint.
-				let (new_location,_link_class)=simulation.network.topology.neighbour(router.router_index,self.exit_port);
-				//let link = &simulation.link_classes[link_class];
-				events.push(EventGeneration{
-					delay: self.link.delay,
-					position:CyclePosition::Begin,
-					event:Event::PhitToLocation{
-						phit: phit.clone(),
-						previous: Location::RouterPort{
-							router_index: router.router_index,
-							router_port: original_port,
+							port_token[self.exit_port]=best;
+							best
 						},
-						new: new_location,
-					},
-				});
-				//next_delay = Some(next_delay.unwrap_or(link.frequency_divisor).min(link.frequency_divisor));
-				router.transmission_port_status[self.exit_port].notify_outcoming_phit(selected_virtual_channel,simulation.cycle);
-				if phit.is_end()
-				{
-					if let OutputArbiter::Token{ref mut port_token}=router.output_arbiter
+					};
+					//move phits around.
+					let (phit,original_port) =
 					{
-						port_token[self.exit_port]=(port_token[self.exit_port]+1)% self.amount_virtual_channels;
+						//If we get the phit from an output buffer there is little to do.
+						let (phit,(entry_port,_entry_vc))=router.output_buffers[self.exit_port][selected_virtual_channel].pop().expect("incorrect selected_input");
+						router.energy_counters.buffer_reads+=1;
+						(phit,entry_port)
+					};
+					//Send the phit to the other link endpoint.
+					let (new_location,link_class)=simulation.network.topology.neighbour(router.router_index,self.exit_port);
+					//let link = &simulation.link_classes[link_class];
+					let replay = router.link_replay.transmit(self.exit_port,link_class,&simulation.link_classes,simulation.cycle,mutable);
+					events.push(EventGeneration{
+						delay: self.link.delay+replay,
+						position:CyclePosition::Begin,
+						event:Event::PhitToLocation{
+							phit: phit.clone(),
+							previous: Location::RouterPort{
+								router_index: router.router_index,
+								router_port: original_port,
+							},
+							new: new_location,
+						},
+					});
+					//next_delay = Some(next_delay.unwrap_or(link.frequency_divisor).min(link.frequency_divisor));
+					router.transmission_port_status[self.exit_port].notify_outcoming_phit(selected_virtual_channel,simulation.cycle);
+					mutable.link_power.notify_transmission(LinkSource::RouterPort{router_index:router.router_index,router_port:self.exit_port},simulation.cycle);
+					if phit.is_end()
+					{
+						if let OutputArbiter::Token{ref mut port_token}=router.output_arbiter
+						{
+							port_token[self.exit_port]=(port_token[self.exit_port]+1)% self.amount_virtual_channels;
+						}
 					}
 				}
+				else
+				{
+					break;
+				}
 			}
 			drop(router);//to be able to mutate self
 			self.pending_event = false;
 			// XXX we should avoid to reschedule when it is not necessary.
 			// Are we sure that if we have not being able to advance and nothing changes then we are indefinitely idle?
 			// It is important that the acks received by the router may trigger the scheduling.
-			if !events.is_empty()
+			if !events.is_empty() || blocked_link
 			{
 				if let Some(event) = self.schedule(simulation.cycle,1)
 				{
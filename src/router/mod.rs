@@ -1,6 +1,7 @@
 
 pub mod basic;
 pub mod input_output;
+pub mod output_queued;
 
 use std::rc::{Rc};
 use std::cell::RefCell;
@@ -11,14 +12,16 @@ use ::rand::{Rng,rngs::StdRng};
 
 use quantifiable_derive::Quantifiable;//the derive macro
 
-use crate::{Phit,Packet,Plugs,error,source_location};
+use crate::{Phit,Packet,Plugs,error,source_location,match_object_panic};
 use self::basic::Basic;
 use self::input_output::InputOutput;
+use self::output_queued::OutputQueued;
 use crate::config_parser::ConfigurationValue;
 use crate::topology::{Topology};
 use crate::event::{Eventful,Time,EventGeneration};
 use crate::quantify::Quantifiable;
 use crate::error::{Error,SourceLocation};
+use crate::energy::{RouterEnergy,EnergyCounters};
 
 /// Those terms for modules that define a router.
 pub mod prelude
@@ -67,6 +70,26 @@ pub trait Router: Eventful + Quantifiable
 	///Build a status for an element that sends packets directly to the router ports.
 	///This is intended to build the status of the servers.
 	fn build_emissor_status(&self, port:usize, topology:&dyn Topology) -> Box<dyn StatusAtEmissor+'static>;
+	///The energy model of the router, if it has one, together with the events counted since the last reset of the statistics.
+	///See the [energy](crate::energy) module.
+	///By default the router has no energy model.
+	fn energy(&self) -> Option<(&RouterEnergy,&EnergyCounters)>
+	{
+		None
+	}
+}
+
+///The number of phits sent by `router` through `port` whose space at the other endpoint has not been released yet, according to its credits.
+pub fn occupied_neighbour_space(router:&dyn Router, port:usize) -> usize
+{
+	match router.get_status_at_emisor(port)
+	{
+		Some(status) => (0..status.num_virtual_channels()).map(|vc|{
+			let available = status.known_available_space_for_virtual_channel(vc).unwrap_or(0);
+			router.get_maximum_credits_towards(port,vc).map(|maximum|maximum.saturating_sub(available)).unwrap_or(0)
+		}).sum(),
+		None => 0,
+	}
 }
 
 #[non_exhaustive]
@@ -107,6 +130,7 @@ pub fn new_router(arg:RouterBuilderArgument) -> Rc<RefCell<dyn Router>>
 			//"Basic" => Basic::<SimpleVirtualChannels>::new(arg.router_index, arg.cv, arg.plugs, arg.topology, arg.maximum_packet_size),
 			"Basic" => Basic::new(arg),
 			"InputOutput" | "InputOutputMonocycle" => InputOutput::new(arg),
+			"OutputQueued" => OutputQueued::new(arg),
 			_ => panic!("Unknown router {}",cv_name),
 		}
 	}
@@ -264,6 +288,74 @@ pub trait SpaceAtReceptor
 	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>;
 	///Consult current number of phits in space dedicated to a virtual channel.
 	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>;
+	///Take the messages the receptor generated by itself to be sent to the emissor, such as PFC pause frames.
+	///The router calls it at the end of each cycle it processes.
+	fn take_control_messages(&mut self, _cycle:Time) -> Vec<AcknowledgeMessage>
+	{
+		vec![]
+	}
+	///The counters of dropped packets and paused cycles, for receptors that may drop or pause.
+	fn flow_control_counters(&self, _cycle:Time) -> Option<FlowControlCounters>
+	{
+		None
+	}
+	///Restart the counters of `flow_control_counters`.
+	fn reset_flow_control_counters(&mut self, _next_cycle:Time)
+	{
+	}
+}
+
+///Counters of a receptor that may drop packets or pause its emissor.
+#[derive(Clone,Debug,Default)]
+pub struct FlowControlCounters
+{
+	///Packets dropped for not fitting in the buffer.
+	pub dropped_packets: usize,
+	///Phits of the dropped packets.
+	pub dropped_phits: usize,
+	///Cycles in which the emissor has been requested to pause, for each virtual channel.
+	pub paused_cycles: Vec<Time>,
+}
+
+///Adds the `FlowControlCounters` of the receptors of a router to the `FlowControlStatistics` of the previous routers.
+///Returns `None` when there are no previous statistics and no receptor has counters.
+pub fn aggregate_flow_control_statistics(previous:Option<&ConfigurationValue>, spaces:&[Box<dyn SpaceAtReceptor>], cycle:Time) -> Option<ConfigurationValue>
+{
+	let mut total = FlowControlCounters::default();
+	let mut any = false;
+	if let Some(previous) = previous
+	{
+		any = true;
+		match_object_panic!(previous,"FlowControlStatistics",value,
+			"dropped_packets" => total.dropped_packets = value.as_usize().expect("bad value for dropped_packets"),
+			"dropped_phits" => total.dropped_phits = value.as_usize().expect("bad value for dropped_phits"),
+			"paused_cycles_per_vc" => total.paused_cycles = value.as_array().expect("bad value for paused_cycles_per_vc").iter()
+				.map(|v|v.as_time().expect("bad value in paused_cycles_per_vc")).collect(),
+		);
+	}
+	for counters in spaces.iter().filter_map(|space|space.flow_control_counters(cycle))
+	{
+		any = true;
+		total.dropped_packets += counters.dropped_packets;
+		total.dropped_phits += counters.dropped_phits;
+		if total.paused_cycles.len() < counters.paused_cycles.len()
+		{
+			total.paused_cycles.resize(counters.paused_cycles.len(),0);
+		}
+		for (t,c) in total.paused_cycles.iter_mut().zip(counters.paused_cycles.iter())
+		{
+			*t += c;
+		}
+	}
+	if !any
+	{
+		return None;
+	}
+	Some(ConfigurationValue::Object(String::from("FlowControlStatistics"),vec![
+		(String::from("dropped_packets"),ConfigurationValue::Number(total.dropped_packets as f64)),
+		(String::from("dropped_phits"),ConfigurationValue::Number(total.dropped_phits as f64)),
+		(String::from("paused_cycles_per_vc"),ConfigurationValue::Array(total.paused_cycles.iter().map(|&x|ConfigurationValue::Number(x as f64)).collect())),
+	]))
 }
 
 ///A message send from the receptor to the emissor when the receptor state changes.
@@ -273,6 +365,8 @@ pub struct AcknowledgeMessage
 {
 	virtual_channel: Option<usize>,
 	set_available_size: Option<usize>,
+	///Whether to pause (`Some(true)`) or resume (`Some(false)`) the transmission through the virtual channel.
+	pause: Option<bool>,
 }
 
 impl AcknowledgeMessage
@@ -282,6 +376,7 @@ impl AcknowledgeMessage
 		AcknowledgeMessage{
 			virtual_channel: None,
 			set_available_size: None,
+			pause: None,
 		}
 	}
 	pub fn ack_phit_clear_from_virtual_channel(virtual_channel:usize)->AcknowledgeMessage
@@ -289,6 +384,7 @@ impl AcknowledgeMessage
 		AcknowledgeMessage{
 			virtual_channel: Some(virtual_channel),
 			set_available_size: None,
+			pause: None,
 		}
 	}
 	pub fn ack_fix_available_size(amount:usize)->AcknowledgeMessage
@@ -296,6 +392,16 @@ impl AcknowledgeMessage
 		AcknowledgeMessage{
 			virtual_channel: None,
 			set_available_size: Some(amount),
+			pause: None,
+		}
+	}
+	///A PFC frame, to pause (XOFF) or resume (XON) the transmission through a virtual channel.
+	pub fn ack_pause(virtual_channel:usize, pause:bool)->AcknowledgeMessage
+	{
+		AcknowledgeMessage{
+			virtual_channel: Some(virtual_channel),
+			set_available_size: None,
+			pause: Some(pause),
 		}
 	}
 }
@@ -341,6 +447,8 @@ pub struct TransmissionMechanismBuilderArgument<'a>
 {
 	//cv: &'a ConfigurationValue,
 	name: &'a str,
+	///The configuration of the mechanism when it was given as an object, as in `DAMQ{reserved_size:16}`.
+	cv: Option<&'a ConfigurationValue>,
 	virtual_channels: usize,
 	buffer_size: usize,
 	size_to_send: usize,
@@ -372,6 +480,9 @@ pub fn new_transmission_mechanism(arg:TransmissionMechanismBuilderArgument) -> B
 		"SimpleVirtualChannels" => Box::new(SimpleVirtualChannels::new(arg.virtual_channels, arg.buffer_size, arg.size_to_send)),
 		"TransmissionToServer" => Box::new(TransmissionToServer() ),
 		"TransmissionFromOblivious" => Box::new(TransmissionFromOblivious::new(arg.virtual_channels, arg.buffer_size, arg.size_to_send)),
+		"DAMQ" => Box::new(DynamicallyAllocatedMultiQueue::new(arg)),
+		"Lossy" => Box::new(LossyVirtualChannels::new(arg)),
+		"PFC" => Box::new(PriorityFlowControl::new(arg)),
 		x => panic!("Unknown transission mechanism {}",x),
 	}
 }
@@ -581,6 +692,538 @@ impl TransmissionMechanism for SimpleVirtualChannels
 	}
 }
 
+///A dynamically allocated multi-queue (DAMQ) mechanism.
+///Each port has a pool of `buffer_size-reserved_size` phits shared by all its virtual channels plus `reserved_size` phits reserved to each virtual channel.
+///Thus a single virtual channel may hold up to `buffer_size` phits, but only when the others are not using the shared pool.
+///The phits of a virtual channel first take its reserved space and then the shared pool. Since each virtual channel is a queue, the emissor knows which space is released by each acknowledge from its per virtual channel counters.
+///It is selected with `transmission_mechanism: DAMQ{reserved_size:16}`, with `reserved_size` defaulting to the `flit_size`, or just `transmission_mechanism: "DAMQ"`.
+struct DynamicallyAllocatedMultiQueue
+{
+	///The number of virtual channels = number of queues.
+	virtual_channels: usize,
+	///The space reserved to each virtual channel.
+	reserved_size: usize,
+	///The space shared by all the virtual channels.
+	shared_size: usize,
+	///Credits required in the next router's virtual port to begin the transmission
+	flit_size: usize,
+}
+
+impl DynamicallyAllocatedMultiQueue
+{
+	fn new(arg:TransmissionMechanismBuilderArgument) -> DynamicallyAllocatedMultiQueue
+	{
+		let mut reserved_size=arg.size_to_send;
+		if let Some(cv)=arg.cv
+		{
+			match_object_panic!(cv,"DAMQ",value,
+				"reserved_size" => reserved_size=value.as_usize().expect("bad value for reserved_size"),
+			);
+		}
+		if reserved_size>arg.buffer_size
+		{
+			panic!("The reserved_size ({}) of DAMQ cannot exceed the buffer_size ({})",reserved_size,arg.buffer_size);
+		}
+		DynamicallyAllocatedMultiQueue{
+			virtual_channels: arg.virtual_channels,
+			reserved_size,
+			shared_size: arg.buffer_size-reserved_size,
+			flit_size: arg.size_to_send,
+		}
+	}
+}
+
+impl TransmissionMechanism for DynamicallyAllocatedMultiQueue
+{
+	type StatusAtEmissor = SharedCreditCounter;
+	type SpaceAtReceptor = SharedBuffers;
+
+	fn new_status_at_emissor(&self)-> SharedCreditCounter
+	{
+		SharedCreditCounter{
+			occupation: vec![0;self.virtual_channels],
+			reserved_size: self.reserved_size,
+			shared_credits: self.shared_size,
+			last_transmission: 0,
+			flit_size: self.flit_size,
+		}
+	}
+
+	fn new_space_at_receptor(&self)-> SharedBuffers
+	{
+		SharedBuffers{
+			buffers: (0..self.virtual_channels).map(|_|Buffer{phits: VecDeque::new()}).collect(),
+			reserved_size: self.reserved_size,
+			shared_size: self.shared_size,
+		}
+	}
+}
+
+///The status of a DAMQ receptor. It counts the phits sent through each virtual channel and not yet acknowledged.
+///The phits over the `reserved_size` of a virtual channel are those taking credits from the shared pool.
+#[derive(Quantifiable)]
+struct SharedCreditCounter
+{
+	///The phits in each virtual channel of the receptor or in flight towards it.
+	occupation: Vec<usize>,
+	///The space reserved to each virtual channel.
+	reserved_size: usize,
+	///The known available space in the shared pool of the receptor.
+	shared_credits: usize,
+	///Cycle in which the last phit was trasmitted out of this port.
+	last_transmission: Time,
+	///Credits required in the next router's virtual port to begin the transmission
+	flit_size: usize,
+}
+
+impl StatusAtEmissor for SharedCreditCounter
+{
+	fn num_virtual_channels(&self)->usize
+	{
+		self.occupation.len()
+	}
+
+	fn acknowledge(&mut self, message:AcknowledgeMessage)
+	{
+		let virtual_channel = message.virtual_channel.expect("there is no virtual channel in the message");
+		if self.occupation[virtual_channel]>self.reserved_size
+		{
+			self.shared_credits+=1;
+		}
+		self.occupation[virtual_channel]-=1;
+	}
+
+	fn notify_outcoming_phit(&mut self, virtual_channel: usize, cycle:Time)
+	{
+		if self.occupation[virtual_channel]>=self.reserved_size
+		{
+			self.shared_credits-=1;
+		}
+		self.occupation[virtual_channel]+=1;
+		self.last_transmission=cycle;
+	}
+
+	fn can_transmit(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		let mut necessary_credits=1;
+		if phit.is_begin()
+		{
+			necessary_credits=self.flit_size;
+		}
+		self.known_available_space_for_virtual_channel(virtual_channel).unwrap()>=necessary_credits
+	}
+
+	fn can_transmit_whole_packet(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		let necessary_credits=phit.packet.size - phit.index;
+		self.known_available_space_for_virtual_channel(virtual_channel).unwrap()>=necessary_credits
+	}
+
+	fn known_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>
+	{
+		Some(self.reserved_size.saturating_sub(self.occupation[virtual_channel]) + self.shared_credits)
+	}
+
+	fn get_last_transmission(&self)->Time
+	{
+		self.last_transmission
+	}
+}
+
+///The queues of a DAMQ receptor, one for each virtual channel, sharing part of their space.
+pub struct SharedBuffers
+{
+	///The phits in the transit queue that came from the previous router.
+	buffers: Vec<Buffer>,
+	///The space reserved to each virtual channel.
+	reserved_size: usize,
+	///The space shared by all the virtual channels.
+	shared_size: usize,
+}
+
+impl SharedBuffers
+{
+	///The amount of phits currently stored in the shared pool.
+	fn shared_occupation(&self) -> usize
+	{
+		self.buffers.iter().map(|buffer|buffer.len().saturating_sub(self.reserved_size)).sum()
+	}
+}
+
+impl SpaceAtReceptor for SharedBuffers
+{
+	fn insert(&mut self, phit:Rc<Phit>, _rng: &mut StdRng) -> Result<(),Error>
+	{
+		let vc=match *phit.virtual_channel.borrow()
+		{
+			Some(vc) => vc,
+			None => return Err(error!(undetermined).with_message("DAMQ requires the phits to come with a virtual channel".to_string())),
+		};
+		if self.buffers[vc].len()>=self.reserved_size && self.shared_occupation()>=self.shared_size
+		{
+			return Err(error!(undetermined).with_message(format!("There is no space in the virtual channel {} nor in the shared pool of the DAMQ",vc)));
+		}
+		self.buffers[vc].push(phit);
+		Ok(())
+	}
+
+	fn front_iter(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		Box::new(self.buffers.iter().filter_map(|b|b.front()).collect::<Vec<_>>().into_iter())
+	}
+
+	fn front_virtual_channel(&self,virtual_channel:usize) -> Option<Rc<Phit>>
+	{
+		self.buffers[virtual_channel].front()
+	}
+
+	fn extract(&mut self, virtual_channel:usize) -> Result<(Rc<Phit>,Option<AcknowledgeMessage>),Error>
+	{
+		match self.buffers[virtual_channel].pop()
+		{
+			Some(phit) =>
+			{
+				let message=AcknowledgeMessage::ack_phit_clear_from_virtual_channel(virtual_channel);
+				Ok((phit,Some(message)))
+			},
+			_ => Err(error!(undetermined)),
+		}
+	}
+
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		Box::new(self.buffers.iter().flat_map(|buffer|buffer.iter_phits()).collect::<Vec<_>>().into_iter())
+	}
+	///Only the reserved space is dedicated to the virtual channel.
+	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.reserved_size.saturating_sub(self.buffers[virtual_channel].len()))
+	}
+	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffers[virtual_channel].len())
+	}
+}
+
+///A lossy mechanism, as Ethernet without flow control.
+///The emissor sends whenever it wants and the receptor drops the packets that do not fit entirely in the `buffer_size` of their virtual channel.
+///The dropped packets are counted in the `flow_control_statistics` of the routers.
+///It is selected with `transmission_mechanism: "Lossy"`.
+struct LossyVirtualChannels
+{
+	///The number of virtual channels = number of buffers.
+	virtual_channels: usize,
+	///The size of each buffer.
+	buffer_size: usize,
+}
+
+impl LossyVirtualChannels
+{
+	fn new(arg:TransmissionMechanismBuilderArgument) -> LossyVirtualChannels
+	{
+		if let Some(cv)=arg.cv
+		{
+			match_object_panic!(cv,"Lossy",_value);
+		}
+		LossyVirtualChannels{
+			virtual_channels: arg.virtual_channels,
+			buffer_size: arg.buffer_size,
+		}
+	}
+}
+
+impl TransmissionMechanism for LossyVirtualChannels
+{
+	type StatusAtEmissor = PauseStatus;
+	type SpaceAtReceptor = DroppingBuffers;
+
+	fn new_status_at_emissor(&self)-> PauseStatus
+	{
+		PauseStatus{
+			paused: vec![false;self.virtual_channels],
+			buffer_size: self.buffer_size,
+			last_transmission: 0,
+		}
+	}
+
+	fn new_space_at_receptor(&self)-> DroppingBuffers
+	{
+		DroppingBuffers::new(self.virtual_channels,self.buffer_size,None)
+	}
+}
+
+///Priority Flow Control (PFC), as in IEEE 802.1Qbb, with each virtual channel being a priority class.
+///The receptor sends a pause frame (XOFF) for a virtual channel when its buffer reaches `xoff_threshold` phits and a resume frame (XON) when it falls to `xon_threshold` phits.
+///The emissor stops beginning new packets in paused virtual channels, but it completes the packet being sent.
+///Hence the space over `xoff_threshold` must cover the phits in flight, the remaining of a packet, and the delay of the frames. Otherwise, the receptor drops the packets that do not fit.
+///The frames travel as the credits, following the `credit_delay` and `credit_period` of the link class.
+///It is selected with `transmission_mechanism: PFC{xoff_threshold:48, xon_threshold:32}`. By default the thresholds are half and a quarter of the `buffer_size`.
+struct PriorityFlowControl
+{
+	///The number of virtual channels = number of priority classes.
+	virtual_channels: usize,
+	///The size of each buffer.
+	buffer_size: usize,
+	///Occupation of a buffer at which its virtual channel is paused.
+	xoff_threshold: usize,
+	///Occupation of a buffer at which its virtual channel is resumed.
+	xon_threshold: usize,
+}
+
+impl PriorityFlowControl
+{
+	fn new(arg:TransmissionMechanismBuilderArgument) -> PriorityFlowControl
+	{
+		let mut xoff_threshold=arg.buffer_size/2;
+		let mut xon_threshold=arg.buffer_size/4;
+		if let Some(cv)=arg.cv
+		{
+			match_object_panic!(cv,"PFC",value,
+				"xoff_threshold" => xoff_threshold=value.as_usize().expect("bad value for xoff_threshold"),
+				"xon_threshold" => xon_threshold=value.as_usize().expect("bad value for xon_threshold"),
+			);
+		}
+		if xon_threshold>=xoff_threshold || xoff_threshold>arg.buffer_size
+		{
+			panic!("PFC requires xon_threshold ({}) < xoff_threshold ({}) <= buffer_size ({})",xon_threshold,xoff_threshold,arg.buffer_size);
+		}
+		PriorityFlowControl{
+			virtual_channels: arg.virtual_channels,
+			buffer_size: arg.buffer_size,
+			xoff_threshold,
+			xon_threshold,
+		}
+	}
+}
+
+impl TransmissionMechanism for PriorityFlowControl
+{
+	type StatusAtEmissor = PauseStatus;
+	type SpaceAtReceptor = DroppingBuffers;
+
+	fn new_status_at_emissor(&self)-> PauseStatus
+	{
+		PauseStatus{
+			paused: vec![false;self.virtual_channels],
+			buffer_size: self.buffer_size,
+			last_transmission: 0,
+		}
+	}
+
+	fn new_space_at_receptor(&self)-> DroppingBuffers
+	{
+		DroppingBuffers::new(self.virtual_channels,self.buffer_size,Some((self.xoff_threshold,self.xon_threshold)))
+	}
+}
+
+///The status of a receptor without credits. It only knows whether each virtual channel has been paused.
+///It reports the whole `buffer_size` as available for the virtual channels not paused and none for the paused ones.
+#[derive(Quantifiable)]
+struct PauseStatus
+{
+	///Whether the receptor has paused each virtual channel.
+	paused: Vec<bool>,
+	///The size of each buffer of the receptor.
+	buffer_size: usize,
+	///Cycle in which the last phit was trasmitted out of this port.
+	last_transmission: Time,
+}
+
+impl StatusAtEmissor for PauseStatus
+{
+	fn num_virtual_channels(&self)->usize
+	{
+		self.paused.len()
+	}
+
+	fn acknowledge(&mut self, message:AcknowledgeMessage)
+	{
+		if let Some(pause)=message.pause
+		{
+			self.paused[message.virtual_channel.expect("there is no virtual channel in the message")]=pause;
+		}
+	}
+
+	fn notify_outcoming_phit(&mut self, _virtual_channel: usize, cycle:Time)
+	{
+		self.last_transmission=cycle;
+	}
+
+	fn can_transmit(&self, phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		!self.paused[virtual_channel] || !phit.is_begin()
+	}
+
+	fn can_transmit_whole_packet(&self, _phit:&Rc<Phit>, virtual_channel:usize)->bool
+	{
+		!self.paused[virtual_channel]
+	}
+
+	fn known_available_space_for_virtual_channel(&self,virtual_channel:usize)->Option<usize>
+	{
+		Some(if self.paused[virtual_channel] {0} else {self.buffer_size})
+	}
+
+	fn get_last_transmission(&self)->Time
+	{
+		self.last_transmission
+	}
+}
+
+///A buffer for each virtual channel dropping the packets that do not fit, and optionally asking the emissor to pause.
+pub struct DroppingBuffers
+{
+	///The phits in the transit queue that came from the previous router.
+	buffers: Vec<Buffer>,
+	///The size of each buffer.
+	buffer_size: usize,
+	///The `(xoff,xon)` thresholds when using PFC.
+	thresholds: Option<(usize,usize)>,
+	///Whether the packet currently arriving by each virtual channel is being dropped.
+	dropping: Vec<bool>,
+	///Whether each virtual channel is currently paused.
+	paused: Vec<bool>,
+	///The cycle in which the current pause of each virtual channel began.
+	paused_since: Vec<Option<Time>>,
+	///The pause frames generated on insertion, to be taken by the router.
+	pending_messages: Vec<AcknowledgeMessage>,
+	counters: FlowControlCounters,
+}
+
+impl DroppingBuffers
+{
+	fn new(virtual_channels:usize, buffer_size:usize, thresholds:Option<(usize,usize)>) -> DroppingBuffers
+	{
+		DroppingBuffers{
+			buffers: (0..virtual_channels).map(|_|Buffer{phits: VecDeque::new()}).collect(),
+			buffer_size,
+			thresholds,
+			dropping: vec![false;virtual_channels],
+			paused: vec![false;virtual_channels],
+			paused_since: vec![None;virtual_channels],
+			pending_messages: vec![],
+			counters: FlowControlCounters{paused_cycles:vec![0;virtual_channels],..Default::default()},
+		}
+	}
+}
+
+impl SpaceAtReceptor for DroppingBuffers
+{
+	fn insert(&mut self, phit:Rc<Phit>, _rng: &mut StdRng) -> Result<(),Error>
+	{
+		let vc=match *phit.virtual_channel.borrow()
+		{
+			Some(vc) => vc,
+			None => return Err(error!(undetermined).with_message("Dropping buffers require the phits to come with a virtual channel".to_string())),
+		};
+		if phit.is_begin() && self.buffers[vc].len()+phit.packet.size>self.buffer_size
+		{
+			self.dropping[vc]=true;
+			self.counters.dropped_packets+=1;
+		}
+		if self.dropping[vc]
+		{
+			self.counters.dropped_phits+=1;
+			if phit.is_end()
+			{
+				self.dropping[vc]=false;
+			}
+			return Ok(());
+		}
+		self.buffers[vc].push(phit);
+		if let Some((xoff,_xon))=self.thresholds
+		{
+			if !self.paused[vc] && self.buffers[vc].len()>=xoff
+			{
+				self.paused[vc]=true;
+				self.pending_messages.push(AcknowledgeMessage::ack_pause(vc,true));
+			}
+		}
+		Ok(())
+	}
+
+	fn front_iter(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		Box::new(self.buffers.iter().filter_map(|b|b.front()).collect::<Vec<_>>().into_iter())
+	}
+
+	fn front_virtual_channel(&self,virtual_channel:usize) -> Option<Rc<Phit>>
+	{
+		self.buffers[virtual_channel].front()
+	}
+
+	fn extract(&mut self, virtual_channel:usize) -> Result<(Rc<Phit>,Option<AcknowledgeMessage>),Error>
+	{
+		match self.buffers[virtual_channel].pop()
+		{
+			Some(phit) =>
+			{
+				let mut message=None;
+				if let Some((_xoff,xon))=self.thresholds
+				{
+					if self.paused[virtual_channel] && self.buffers[virtual_channel].len()<=xon
+					{
+						self.paused[virtual_channel]=false;
+						message=Some(AcknowledgeMessage::ack_pause(virtual_channel,false));
+					}
+				}
+				Ok((phit,message))
+			},
+			_ => Err(error!(undetermined)),
+		}
+	}
+
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		Box::new(self.buffers.iter().flat_map(|buffer|buffer.iter_phits()).collect::<Vec<_>>().into_iter())
+	}
+	fn available_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffer_size - self.buffers[virtual_channel].len())
+	}
+	fn occupied_dedicated_space(&self, virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffers[virtual_channel].len())
+	}
+	fn take_control_messages(&mut self, cycle:Time) -> Vec<AcknowledgeMessage>
+	{
+		for (vc,&paused) in self.paused.iter().enumerate()
+		{
+			match (paused,self.paused_since[vc])
+			{
+				(true,None) => self.paused_since[vc]=Some(cycle),
+				(false,Some(since)) =>
+				{
+					self.counters.paused_cycles[vc]+=cycle-since;
+					self.paused_since[vc]=None;
+				},
+				_ => (),
+			}
+		}
+		std::mem::take(&mut self.pending_messages)
+	}
+	fn flow_control_counters(&self, cycle:Time) -> Option<FlowControlCounters>
+	{
+		let mut counters = self.counters.clone();
+		for (total,since) in counters.paused_cycles.iter_mut().zip(self.paused_since.iter())
+		{
+			if let Some(since)=since
+			{
+				*total += cycle-since;
+			}
+		}
+		Some(counters)
+	}
+	fn reset_flow_control_counters(&mut self, next_cycle:Time)
+	{
+		self.counters = FlowControlCounters{paused_cycles:vec![0;self.buffers.len()],..Default::default()};
+		for since in self.paused_since.iter_mut().flatten()
+		{
+			*since = next_cycle;
+		}
+	}
+}
+
 ///For senders that not care about the receptor or phantom senders that do not actually send anything.
 #[derive(Quantifiable)]
 struct EmptyStatus();
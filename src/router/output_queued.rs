@@ -0,0 +1,795 @@
+use std::cell::RefCell;
+use std::rc::{Rc,Weak};
+use std::mem::size_of;
+use ::rand::{Rng,rngs::StdRng,prelude::SliceRandom};
+
+use super::{Router,AbstractTransmissionMechanism,StatusAtEmissor,SpaceAtReceptor,AugmentedBuffer,AcknowledgeMessage,RouterBuilderArgument,new_transmission_mechanism,aggregate_flow_control_statistics,TransmissionMechanismBuilderArgument};
+use crate::config_parser::ConfigurationValue;
+use crate::topology::{Location,Topology};
+use crate::routing::{CandidateEgress,CongestionInfo};
+use crate::policies::{RequestInfo,VirtualChannelPolicy,new_virtual_channel_policy,VCPolicyBuilderArgument};
+use crate::event::{Event,Eventful,EventGeneration,CyclePosition,Time};
+use crate::{Phit,SimulationShared,SimulationMut,LinkReplay};
+use crate::quantify::Quantifiable;
+use crate::match_object_panic;
+use crate::energy::{RouterEnergy,EnergyCounters};
+use crate::power::LinkSource;
+
+
+///The order in which an output port serves the phits of its virtual channels.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+enum OutputScheduler
+{
+	///The phit that entered the output port first, regardless of its virtual channel.
+	Fifo,
+	///The virtual channels take turns, passing the turn at the end of each packet.
+	RoundRobin,
+	///The lowest virtual channel with a phit ready to go.
+	StrictPriority,
+}
+
+/**
+A router whose phits cross the crossbar into queues at the output ports, either an ideal output-queued switch or a combined input-output queued (CIOQ) switch with a given speedup.
+
+The phits arriving through a link are stored in the input buffers, as required by the [TransmissionMechanism](super::TransmissionMechanism) of the link. When the packet at the head of an input virtual channel is granted an output virtual channel its phits move through the crossbar into the queue of that output. Without a `speedup` any number of phits may cross the crossbar in a cycle, which is the ideal output-queued switch, only limited by the space in the output queues. With a `speedup` of `s` each input port may send and each output port may receive at most `s` phits per cycle, the inputs being matched in a random order each cycle.
+
+Each output port selects every cycle which of its virtual channels sends a phit through the link, according to its `output_scheduler`.
+
+```ignore
+OutputQueued{
+	virtual_channels: 4,
+	virtual_channel_policies: [EnforceFlowControl,Random],
+	buffer_size: 64,//the size of each input buffer.
+	flit_size: 16,
+	output_buffer_size: 64,//optional, the size of each output queue. By default the output queues are unbounded.
+	speedup: 2,//optional, phits per cycle that each input may send and each output may receive through the crossbar. By default unlimited, as in an ideal output-queued switch.
+	output_scheduler: RoundRobin,//optional, among FIFO, RoundRobin, and StrictPriority. Defaults to RoundRobin.
+	//transmission_mechanism, to_server_mechanism, from_server_mechanism, and energy as in the Basic router.
+}
+```
+
+The router statistics include the average occupation of the queues, the `average_crossbar_phits` moved per port and cycle, and the `average_blocked_input_heads`, the average number of input virtual channels per port holding a phit that has not advanced in a cycle, to evaluate the head-of-line blocking.
+**/
+pub struct OutputQueued
+{
+	///Weak pointer to itself, see <https://users.rust-lang.org/t/making-a-rc-refcell-trait2-from-rc-refcell-trait1/16086/3>
+	self_rc: Weak<RefCell<OutputQueued>>,
+	///When is the next scheduled event. Stack with the soonner event the last.
+	next_events: Vec<Time>,
+	///The cycle number of the last time OutputQueued::process was called.
+	last_process_at_cycle: Option<Time>,
+	///Its index in the topology
+	router_index: usize,
+	///The mechanism to select virtual channels
+	virtual_channel_policies: Vec<Box<dyn VirtualChannelPolicy>>,
+	///Credits required in the next router's virtual port to begin the transmission
+	flit_size: usize,
+	///Size of each input buffer.
+	buffer_size: usize,
+	///If some, the size of each output queue. Otherwise they are unbounded.
+	output_buffer_size: Option<usize>,
+	///If some, the phits that each input port may send and each output port may receive through the crossbar in a cycle. Otherwise it is unlimited.
+	speedup: Option<usize>,
+	///How each output port selects the virtual channel sending through the link.
+	output_scheduler: OutputScheduler,
+	///The virtual channel with the turn at each output port, for the `RoundRobin` scheduler.
+	port_token: Vec<usize>,
+	///The energy consumed by the events of the router, if configured. See the [energy](crate::energy) module.
+	energy: Option<RouterEnergy>,
+	///The events consuming energy since the last reset of the statistics.
+	energy_counters: EnergyCounters,
+	/// `transmission_port_status[port] = status`
+	transmission_port_status: Vec<Box<dyn StatusAtEmissor>>,
+	///Whether the link of each port is busy replaying a corrupted phit.
+	link_replay: LinkReplay,
+	/// `reception_port_space[port] = space`
+	reception_port_space: Vec<Box<dyn SpaceAtReceptor>>,
+	/// The server to router mechanism employed.
+	/// This will be used to build the status at the servers.
+	from_server_mechanism: Box<dyn AbstractTransmissionMechanism>,
+	///The output queues indexed as `[output_port][output_vc]`.
+	///Phits are stored with their entry port and the cycle in which they entered the queue.
+	output_buffers: Vec<Vec<AugmentedBuffer<(usize,Time)>>>,
+	///If not None then the input port+virtual_channel which is writing into this output queue.
+	selected_input: Vec<Vec<Option<(usize,usize)>>>,
+	///If not None then the output port+virtual_channel into which go all the phits of this input virtual channel, since they are part of the same packet.
+	selected_output: Vec<Vec<Option<(usize,usize)>>>,
+	///Number of cycles that the current phit, if any, in the head of a given (port,virtual channel) input buffer the phit has been waiting.
+	time_at_input_head: Vec<Vec<usize>>,
+
+	//statistics:
+	///The first cycle included in the statistics.
+	statistics_begin_cycle: Time,
+	///Accumulated over time, averaged per port.
+	statistics_output_buffer_occupation_per_vc: Vec<f64>,
+	///Accumulated over time, averaged per port.
+	statistics_reception_space_occupation_per_vc: Vec<f64>,
+	///Input virtual channels whose head phit has not advanced, accumulated over time.
+	statistics_blocked_input_heads: usize,
+	///Input virtual channels whose head phit did not advance in the last processed cycle.
+	last_blocked_input_heads: usize,
+	///Phits moved through the crossbar.
+	statistics_crossbar_phits: usize,
+}
+
+impl Router for OutputQueued
+{
+	fn insert(&mut self, current_cycle:Time, phit:Rc<Phit>, port:usize, rng: &mut StdRng) -> Vec<EventGeneration>
+	{
+		self.reception_port_space[port].insert(phit,rng).expect("there was some problem on the insertion");
+		self.energy_counters.buffer_writes+=1;
+		if let Some(event) = self.schedule(current_cycle,0) {
+			vec![event]
+		} else {
+			vec![]
+		}
+	}
+	fn acknowledge(&mut self, current_cycle:Time, port:usize, ack_message:AcknowledgeMessage) -> Vec<EventGeneration>
+	{
+		self.transmission_port_status[port].acknowledge(ack_message);
+		if let Some(event) = self.schedule(current_cycle,0) {
+			vec![event]
+		} else {
+			vec![]
+		}
+	}
+	fn num_virtual_channels(&self) -> usize
+	{
+		self.transmission_port_status[0].num_virtual_channels()
+	}
+	fn virtual_port_size(&self, _port:usize, _virtual_channel:usize) -> usize
+	{
+		self.buffer_size
+	}
+	fn iter_phits(&self) -> Box<dyn Iterator<Item=Rc<Phit>>>
+	{
+		Box::new(self.reception_port_space.iter().flat_map(|space|space.iter_phits()).chain(
+			self.output_buffers.iter().flat_map(|port|port.iter().flat_map(|buffer|buffer.iter_phits()))
+		).collect::<Vec<_>>().into_iter())
+	}
+	fn get_status_at_emisor(&self, port:usize) -> Option<&dyn StatusAtEmissor>
+	{
+		Some(&*self.transmission_port_status[port])
+	}
+	fn get_maximum_credits_towards(&self, _port:usize, _virtual_channel:usize) -> Option<usize>
+	{
+		Some(self.buffer_size)
+	}
+	fn get_index(&self)->Option<usize>
+	{
+		Some(self.router_index)
+	}
+	fn aggregate_statistics(&self, statistics:Option<ConfigurationValue>, router_index:usize, total_routers:usize, cycle:Time) -> Option<ConfigurationValue>
+	{
+		let cycle_span = cycle - self.statistics_begin_cycle;
+		let port_cycles = (cycle_span as usize*self.reception_port_space.len()) as f64;
+		let mut output_buffer_occupation_per_vc:Vec<f64> = self.statistics_output_buffer_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect();
+		let mut reception_space_occupation_per_vc:Vec<f64> = self.statistics_reception_space_occupation_per_vc.iter().map(|x|x/cycle_span as f64).collect();
+		let mut blocked_input_heads = self.statistics_blocked_input_heads as f64 / port_cycles;
+		let mut crossbar_phits = self.statistics_crossbar_phits as f64 / port_cycles;
+		let mut previous_flow_control=None;
+		if let Some(previous)=statistics
+		{
+			if let ConfigurationValue::Object(cv_name,previous_pairs) = previous
+			{
+				if cv_name!="OutputQueued"
+				{
+					panic!("incompatible statistics, should be `OutputQueued` object not `{}`",cv_name);
+				}
+				for (name,value) in previous_pairs
+				{
+					match name.as_str()
+					{
+						"average_output_buffer_occupation_per_vc" => for (c,p) in output_buffer_occupation_per_vc.iter_mut().zip(value.as_array().expect("bad value for average_output_buffer_occupation_per_vc"))
+						{
+							*c += p.as_f64().expect("bad value in average_output_buffer_occupation_per_vc");
+						},
+						"average_reception_space_occupation_per_vc" => for (c,p) in reception_space_occupation_per_vc.iter_mut().zip(value.as_array().expect("bad value for average_reception_space_occupation_per_vc"))
+						{
+							*c += p.as_f64().expect("bad value in average_reception_space_occupation_per_vc");
+						},
+						"average_blocked_input_heads" => blocked_input_heads += value.as_f64().expect("bad value for average_blocked_input_heads"),
+						"average_crossbar_phits" => crossbar_phits += value.as_f64().expect("bad value for average_crossbar_phits"),
+						"flow_control_statistics" => previous_flow_control=Some(value),
+						_ => panic!("Nothing to do with field {} in OutputQueued statistics",name),
+					}
+				}
+			}
+			else
+			{
+				panic!("received incompatible statistics");
+			}
+		}
+		if router_index+1==total_routers
+		{
+			let factor=1f64 / total_routers as f64;
+			for x in output_buffer_occupation_per_vc.iter_mut().chain(reception_space_occupation_per_vc.iter_mut())
+			{
+				*x *= factor;
+			}
+			blocked_input_heads *= factor;
+			crossbar_phits *= factor;
+		}
+		let mut result_content : Vec<(String,ConfigurationValue)> = vec![
+			(String::from("average_output_buffer_occupation_per_vc"),ConfigurationValue::Array(output_buffer_occupation_per_vc.iter().map(|x|ConfigurationValue::Number(*x)).collect())),
+			(String::from("average_reception_space_occupation_per_vc"),ConfigurationValue::Array(reception_space_occupation_per_vc.iter().map(|x|ConfigurationValue::Number(*x)).collect())),
+			(String::from("average_blocked_input_heads"),ConfigurationValue::Number(blocked_input_heads)),
+			(String::from("average_crossbar_phits"),ConfigurationValue::Number(crossbar_phits)),
+		];
+		if let Some(flow_control)=aggregate_flow_control_statistics(previous_flow_control.as_ref(),&self.reception_port_space,cycle)
+		{
+			result_content.push((String::from("flow_control_statistics"),flow_control));
+		}
+		Some(ConfigurationValue::Object(String::from("OutputQueued"),result_content))
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.statistics_begin_cycle=next_cycle;
+		self.energy_counters=EnergyCounters::default();
+		for space in self.reception_port_space.iter_mut()
+		{
+			space.reset_flow_control_counters(next_cycle);
+		}
+		for x in self.statistics_output_buffer_occupation_per_vc.iter_mut()
+		{
+			*x=0f64;
+		}
+		for x in self.statistics_reception_space_occupation_per_vc.iter_mut()
+		{
+			*x=0f64;
+		}
+		self.statistics_blocked_input_heads=0;
+		self.statistics_crossbar_phits=0;
+	}
+	fn build_emissor_status(&self, port:usize, topology:&dyn Topology) -> Box<dyn StatusAtEmissor+'static>
+	{
+		if let (Location::ServerPort(_server),_link_class)=topology.neighbour(self.router_index,port)
+		{
+			self.from_server_mechanism.new_status_at_emissor()
+		}
+		else
+		{
+			unimplemented!()
+		}
+	}
+	fn energy(&self) -> Option<(&RouterEnergy,&EnergyCounters)>
+	{
+		self.energy.as_ref().map(|energy|(energy,&self.energy_counters))
+	}
+}
+
+impl OutputQueued
+{
+	pub fn new(arg:RouterBuilderArgument) -> Rc<RefCell<OutputQueued>>
+	{
+		let RouterBuilderArgument{
+			router_index,
+			cv,
+			plugs,
+			topology,
+			..
+		} = arg;
+		let mut virtual_channels=None;
+		let mut virtual_channel_policies=None;
+		let mut buffer_size=None;
+		let mut flit_size=None;
+		let mut output_buffer_size=None;
+		let mut speedup=None;
+		let mut output_scheduler=OutputScheduler::RoundRobin;
+		let mut transmission_mechanism=None;
+		let mut transmission_mechanism_cv=None;
+		let mut to_server_mechanism=None;
+		let mut from_server_mechanism=None;
+		let mut energy=None;
+		match_object_panic!(cv,"OutputQueued",value,
+			"virtual_channels" => virtual_channels=Some(value.as_usize().expect("bad value for virtual_channels")),
+			"virtual_channel_policies" => virtual_channel_policies=Some(value.as_array().expect("bad value for virtual_channel_policies").iter().map(
+				|cv|new_virtual_channel_policy(VCPolicyBuilderArgument{
+				cv,
+				plugs
+			})).collect::<Vec<_>>()),
+			"buffer_size" => buffer_size=Some(value.as_usize().expect("bad value for buffer_size")),
+			"flit_size" => flit_size=Some(value.as_usize().expect("bad value for flit_size")),
+			"output_buffer_size" => output_buffer_size=Some(value.as_usize().expect("bad value for output_buffer_size")),
+			"speedup" => speedup=Some(value.as_usize().expect("bad value for speedup")),
+			"output_scheduler" => output_scheduler = match value
+			{
+				ConfigurationValue::Object(name,_) | ConfigurationValue::Literal(name) => match name.as_ref()
+				{
+					"FIFO" => OutputScheduler::Fifo,
+					"RoundRobin" => OutputScheduler::RoundRobin,
+					"StrictPriority" => OutputScheduler::StrictPriority,
+					_ => panic!("Unknown output_scheduler {}",name),
+				},
+				_ => panic!("bad value for output_scheduler"),
+			},
+			"energy" => energy = Some(RouterEnergy::new(value)),
+			"transmission_mechanism" => match value
+			{
+				ConfigurationValue::Literal(s) => transmission_mechanism = Some(s.to_string()),
+				ConfigurationValue::Object(name,_) =>
+				{
+					transmission_mechanism = Some(name.to_string());
+					transmission_mechanism_cv = Some(value);
+				},
+				_ => panic!("bad value for transmission_mechanism"),
+			},
+			"to_server_mechanism" => to_server_mechanism = Some(value.as_str().expect("bad value for to_server_mechanism").to_string()),
+			"from_server_mechanism" => from_server_mechanism = Some(value.as_str().expect("bad value for from_server_mechanism").to_string()),
+		);
+		let virtual_channels=virtual_channels.expect("There were no virtual_channels");
+		let virtual_channel_policies=virtual_channel_policies.expect("There were no virtual_channel_policies");
+		let buffer_size=buffer_size.expect("There were no buffer_size");
+		let flit_size=flit_size.expect("There were no flit_size");
+		if speedup==Some(0)
+		{
+			panic!("The speedup of the OutputQueued router must be positive.");
+		}
+		if let Some(size)=output_buffer_size
+		{
+			if size<flit_size
+			{
+				panic!("The output_buffer_size ({}) of the OutputQueued router must be at least its flit_size ({}).",size,flit_size);
+			}
+		}
+		let ports=topology.ports(router_index);
+		let transmission_mechanism = transmission_mechanism.unwrap_or_else(||"SimpleVirtualChannels".to_string());
+		let from_server_mechanism = from_server_mechanism.unwrap_or_else(||"SimpleVirtualChannels".to_string());
+		let to_server_mechanism = to_server_mechanism.unwrap_or_else(||"TransmissionToServer".to_string());
+		let transmission_builder_argument = TransmissionMechanismBuilderArgument{name:"",cv:None,virtual_channels,buffer_size,size_to_send:flit_size};
+		let transmission_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&transmission_mechanism,cv:transmission_mechanism_cv,..transmission_builder_argument});
+		let to_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&to_server_mechanism,..transmission_builder_argument});
+		let from_server_mechanism = new_transmission_mechanism(TransmissionMechanismBuilderArgument{name:&from_server_mechanism,..transmission_builder_argument});
+		let transmission_port_status = (0..ports).map(|p|
+			if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
+			{
+				to_server_mechanism.new_status_at_emissor()
+			}
+			else
+			{
+				transmission_mechanism.new_status_at_emissor()
+			}
+		).collect();
+		let reception_port_space = (0..ports).map(|p|
+			if let (Location::ServerPort(_server),_link_class)=topology.neighbour(router_index,p)
+			{
+				from_server_mechanism.new_space_at_receptor()
+			}
+			else
+			{
+				transmission_mechanism.new_space_at_receptor()
+			}
+		).collect();
+		let r=Rc::new(RefCell::new(OutputQueued{
+			self_rc: Weak::new(),
+			next_events: vec![],
+			last_process_at_cycle: None,
+			router_index,
+			virtual_channel_policies,
+			flit_size,
+			buffer_size,
+			output_buffer_size,
+			speedup,
+			output_scheduler,
+			port_token: vec![0;ports],
+			energy,
+			energy_counters: EnergyCounters::default(),
+			transmission_port_status,
+			link_replay: LinkReplay::new(ports),
+			reception_port_space,
+			from_server_mechanism,
+			output_buffers: (0..ports).map(|_|(0..virtual_channels).map(|_|AugmentedBuffer::new()).collect()).collect(),
+			selected_input: vec![vec![None;virtual_channels];ports],
+			selected_output: vec![vec![None;virtual_channels];ports],
+			time_at_input_head: vec![vec![0;virtual_channels];ports],
+			statistics_begin_cycle: 0,
+			statistics_output_buffer_occupation_per_vc: vec![0f64;virtual_channels],
+			statistics_reception_space_occupation_per_vc: vec![0f64;virtual_channels],
+			statistics_blocked_input_heads: 0,
+			last_blocked_input_heads: 0,
+			statistics_crossbar_phits: 0,
+		}));
+		r.borrow_mut().self_rc=Rc::<_>::downgrade(&r);
+		r
+	}
+	///The phits that still fit in the output queue `[port][virtual_channel]`.
+	fn available_output_space(&self, port:usize, virtual_channel:usize) -> usize
+	{
+		match self.output_buffer_size
+		{
+			Some(size) => size.saturating_sub(self.output_buffers[port][virtual_channel].len()),
+			None => usize::MAX,
+		}
+	}
+	fn gather_cycle_statistics(&mut self, cycles_span:Time)
+	{
+		let ports = self.reception_port_space.len() as f64;
+		for port_space in self.reception_port_space.iter()
+		{
+			for (vc,occupation) in self.statistics_reception_space_occupation_per_vc.iter_mut().enumerate()
+			{
+				*occupation += (port_space.occupied_dedicated_space(vc).unwrap_or(0)*cycles_span as usize) as f64 / ports;
+			}
+		}
+		for output_port in self.output_buffers.iter()
+		{
+			for (vc,buffer) in output_port.iter().enumerate()
+			{
+				self.statistics_output_buffer_occupation_per_vc[vc] += (buffer.len()*cycles_span as usize) as f64 / ports;
+			}
+		}
+		//The heads that were blocked in the last processed cycle have remained blocked until now.
+		self.statistics_blocked_input_heads += self.last_blocked_input_heads*cycles_span as usize;
+	}
+}
+
+impl Eventful for OutputQueued
+{
+	///Do all things that must be done in a cycle, if any.
+	fn process(&mut self, simulation:&SimulationShared, mutable:&mut SimulationMut) -> Vec<EventGeneration>
+	{
+		let mut cycles_span = 1;//cycles since last checked
+		if let Some(last)=self.last_process_at_cycle
+		{
+			if last >= simulation.cycle
+			{
+				panic!("Trying to process at cycle {} a router::OutputQueued already processed at {}",simulation.cycle,last);
+			}
+			cycles_span = simulation.cycle - last;
+		}
+		self.last_process_at_cycle = Some(simulation.cycle);
+		self.gather_cycle_statistics(cycles_span);
+		let topology = simulation.network.topology.as_ref();
+		let amount_virtual_channels=self.num_virtual_channels();
+		let ports=self.reception_port_space.len();
+
+		//-- Precompute whatever policies ask for.
+		let server_ports : Option<Vec<usize>> = if self.virtual_channel_policies.iter().any(|policy|policy.need_server_ports())
+		{
+			Some((0..ports).filter(|&p|
+				matches!(topology.neighbour(self.router_index,p), (Location::ServerPort(_server),_link_class))
+			).collect())
+		}
+		else
+		{
+			None
+		};
+		let port_last_transmission:Option<Vec<Time>> = if self.virtual_channel_policies.iter().any(|policy|policy.need_port_last_transmission())
+		{
+			Some(self.transmission_port_status.iter().map(|p|p.get_last_transmission()).collect())
+		}
+		else
+		{
+			None
+		};
+		let port_average_neighbour_queue_length:Option<Vec<f32>> = if self.virtual_channel_policies.iter().any(|policy|policy.need_port_average_queue_length())
+		{
+			Some(self.transmission_port_status.iter().map(|p|{
+				let total=(0..amount_virtual_channels).map(|vc|{
+					let available = p.known_available_space_for_virtual_channel(vc).expect("needs to know available space");
+					//When the neighbour is a server it may have longer queue.
+					self.buffer_size.saturating_sub(available)
+				}).sum::<usize>();
+				(total as f32) / (amount_virtual_channels as f32)
+			}).collect())
+		}
+		else
+		{
+			None
+		};
+		let virtual_channel_occupied_output_space:Vec<Vec<usize>> = self.output_buffers.iter().map(|p|p.iter().map(|b|b.len()).collect()).collect();
+		let virtual_channel_available_output_space:Vec<Vec<usize>> = (0..ports).map(|port|(0..amount_virtual_channels).map(|vc|self.available_output_space(port,vc)).collect()).collect();
+		let port_occupied_output_space:Vec<usize> = virtual_channel_occupied_output_space.iter().map(|p|p.iter().sum()).collect();
+		let port_available_output_space:Vec<usize> = virtual_channel_available_output_space.iter().map(|p|p.iter().fold(0usize,|total,space|total.saturating_add(*space))).collect();
+
+		//-- Routing and allocation of output virtual channels to the packets at the head of the input buffers.
+		let mut undecided_channels=0;//just as indicator if the router has pending work.
+		let mut request:Vec<(usize,usize,usize,usize)>=vec![];
+		for entry_port in 0..ports
+		{
+			for phit in self.reception_port_space[entry_port].front_iter()
+			{
+				let entry_vc = phit.virtual_channel.borrow().expect("it should have an associated virtual channel");
+				self.time_at_input_head[entry_port][entry_vc]+=1;
+				if self.selected_output[entry_port][entry_vc].is_some()
+				{
+					continue;
+				}
+				undecided_channels+=1;
+				let target_server=phit.packet.message.destination;
+				let target_router=phit.packet.target_router();
+				let congestion=CongestionInfo{
+					router: self,
+					port_average_neighbour_queue_length: port_average_neighbour_queue_length.as_ref(),
+					port_last_transmission: port_last_transmission.as_ref(),
+					port_occupied_output_space: Some(&port_occupied_output_space),
+					port_available_output_space: Some(&port_available_output_space),
+					virtual_channel_occupied_output_space: Some(&virtual_channel_occupied_output_space),
+					virtual_channel_available_output_space: Some(&virtual_channel_available_output_space),
+					current_cycle: simulation.cycle,
+				};
+				let routing_candidates=simulation.routing.next_with_congestion(&phit.packet.routing_info,topology,self.router_index,target_router,Some(target_server),amount_virtual_channels,&congestion,&mut mutable.rng).unwrap_or_else(|e|panic!("Error {} while routing.",e));
+				let routing_idempotent = routing_candidates.idempotent;
+				if routing_candidates.len()==0
+				{
+					if routing_idempotent
+					{
+						panic!("There are no choices for packet {:?} entry_port={} entry_vc={} in router {} towards server {}",phit.packet,entry_port,entry_vc,self.router_index,target_server);
+					}
+					//There are currently no good port choices, but there may be in the future.
+					continue;
+				}
+				//An output virtual channel can be granted when no other packet is writing into it and it has space for a flit.
+				let mut good_ports=routing_candidates.into_iter().map(|candidate|{
+					let allowed = self.selected_input[candidate.port][candidate.virtual_channel].is_none() && self.available_output_space(candidate.port,candidate.virtual_channel)>=self.flit_size;
+					CandidateEgress{router_allows:Some(allowed), ..candidate}
+				}).collect::<Vec<_>>();
+				let request_info=RequestInfo{
+					target_router_index: target_router,
+					entry_port,
+					entry_virtual_channel: entry_vc,
+					performed_hops: phit.packet.routing_info.borrow().hops,
+					server_ports: server_ports.as_ref(),
+					port_average_neighbour_queue_length: port_average_neighbour_queue_length.as_ref(),
+					port_last_transmission: port_last_transmission.as_ref(),
+					port_occupied_output_space: Some(&port_occupied_output_space),
+					port_available_output_space: Some(&port_available_output_space),
+					virtual_channel_occupied_output_space: Some(&virtual_channel_occupied_output_space),
+					virtual_channel_available_output_space: Some(&virtual_channel_available_output_space),
+					time_at_front: Some(self.time_at_input_head[entry_port][entry_vc]),
+					current_cycle: simulation.cycle,
+					phit: phit.clone(),
+				};
+				for vcp in self.virtual_channel_policies.iter()
+				{
+					good_ports=vcp.filter(good_ports,self,&request_info,topology,&mut mutable.rng);
+					if good_ports.is_empty()
+					{
+						break;//No need to check other policies.
+					}
+				}
+				for candidate in good_ports.into_iter()
+				{
+					simulation.routing.performed_request(&candidate,&phit.packet.routing_info,topology,self.router_index,target_router,Some(target_server),amount_virtual_channels,&mut mutable.rng);
+					if self.selected_input[candidate.port][candidate.virtual_channel].is_none()
+					{
+						request.push((entry_port,entry_vc,candidate.port,candidate.virtual_channel));
+					}
+				}
+			}
+		}
+		let request_len = request.len();
+		request.shuffle(&mut mutable.rng);
+		for (entry_port,entry_vc,requested_port,requested_vc) in request
+		{
+			if self.selected_input[requested_port][requested_vc].is_none() && self.selected_output[entry_port][entry_vc].is_none()
+			{
+				self.selected_input[requested_port][requested_vc]=Some((entry_port,entry_vc));
+				self.selected_output[entry_port][entry_vc]=Some((requested_port,requested_vc));
+				self.energy_counters.allocations+=1;
+			}
+		}
+
+		//-- Move phits through the crossbar into the output queues.
+		//Each round every input port may send a phit, until no input can send more or the speedup is exhausted.
+		let mut events=vec![];
+		let limit = self.speedup.unwrap_or(usize::MAX);
+		let mut input_phits = vec![0;ports];
+		let mut output_phits = vec![0;ports];
+		let mut moved_from = vec![vec![false;amount_virtual_channels];ports];
+		let mut input_order:Vec<usize> = (0..ports).collect();
+		input_order.shuffle(&mut mutable.rng);
+		let first_vc = mutable.rng.gen_range(0..amount_virtual_channels);
+		let mut moved_phits=0;//another indicator of pending work.
+		loop
+		{
+			let mut moved_in_round=false;
+			for &entry_port in input_order.iter()
+			{
+				if input_phits[entry_port]>=limit
+				{
+					continue;
+				}
+				for vc_offset in 0..amount_virtual_channels
+				{
+					let entry_vc = (first_vc+vc_offset)%amount_virtual_channels;
+					let (exit_port,exit_vc) = match self.selected_output[entry_port][entry_vc]
+					{
+						Some(selection) => selection,
+						None => continue,
+					};
+					if output_phits[exit_port]>=limit || self.available_output_space(exit_port,exit_vc)==0
+					{
+						continue;
+					}
+					if let Ok((phit,ack_message)) = self.reception_port_space[entry_port].extract(entry_vc)
+					{
+						self.time_at_input_head[entry_port][entry_vc]=0;
+						*phit.virtual_channel.borrow_mut()=Some(exit_vc);
+						if let Some(message)=ack_message
+						{
+							let (previous_location,previous_link_class)=topology.neighbour(self.router_index,entry_port);
+							let event = Event::Acknowledge{location:previous_location,source:Location::RouterPort{router_index:self.router_index,router_port:entry_port},message};
+							events.push(simulation.schedule_credit_return(previous_link_class,event));
+						}
+						if phit.is_end()
+						{
+							self.selected_input[exit_port][exit_vc]=None;
+							self.selected_output[entry_port][entry_vc]=None;
+						}
+						self.output_buffers[exit_port][exit_vc].push(phit,(entry_port,simulation.cycle));
+						self.energy_counters.buffer_reads+=1;
+						self.energy_counters.crossbar_traversals+=1;
+						self.energy_counters.buffer_writes+=1;
+						self.statistics_crossbar_phits+=1;
+						moved_from[entry_port][entry_vc]=true;
+						input_phits[entry_port]+=1;
+						output_phits[exit_port]+=1;
+						moved_phits+=1;
+						moved_in_round=true;
+						break;
+					}
+				}
+			}
+			if !moved_in_round
+			{
+				break;
+			}
+		}
+		self.last_blocked_input_heads = (0..ports).map(|port|(0..amount_virtual_channels).filter(|&vc|
+			!moved_from[port][vc] && self.reception_port_space[port].front_virtual_channel(vc).is_some()
+		).count()).sum();
+
+		//-- For each output port decide which virtual channel sends through the link.
+		//Whether some link is replaying corrupted phits or waking up, which blocks it for some cycles.
+		let mut blocked_links=false;
+		for exit_port in 0..ports
+		{
+			let (new_location,link_class)=topology.neighbour(self.router_index,exit_port);
+			let width = match new_location
+			{
+				Location::None => continue,
+				_ => simulation.link_classes[link_class].width,
+			};
+			let link_source = LinkSource::RouterPort{router_index:self.router_index,router_port:exit_port};
+			for _link_slot in 0..width
+			{
+				if self.link_replay.is_busy(exit_port,simulation.cycle)
+				{
+					//The link is busy replaying a corrupted phit.
+					blocked_links=true;
+					break;
+				}
+				//Gather the virtual channels that can transmit, keeping the flits in progress together.
+				let mut cand=Vec::with_capacity(amount_virtual_channels);
+				let mut cand_in_transit=false;
+				for exit_vc in 0..amount_virtual_channels
+				{
+					if let Some((phit,_)) = self.output_buffers[exit_port][exit_vc].front()
+					{
+						if self.transmission_port_status[exit_port].can_transmit(&phit,exit_vc)
+						{
+							if cand_in_transit
+							{
+								if !phit.is_begin()
+								{
+									cand.push(exit_vc);
+								}
+							}
+							else if phit.is_begin()
+							{
+								cand.push(exit_vc);
+							}
+							else
+							{
+								cand=vec![exit_vc];
+								cand_in_transit=true;
+							}
+						}
+						else if 0<phit.index && phit.index<self.flit_size
+						{
+							panic!("cannot transmit phit (index={}) but it should (flit_size={})",phit.index,self.flit_size);
+						}
+					}
+				}
+				if cand.is_empty()
+				{
+					break;
+				}
+				if !mutable.link_power.ready(link_source,&simulation.link_classes,simulation.cycle)
+				{
+					//The link is sleeping, waking up, or working at a reduced rate.
+					blocked_links=true;
+					break;
+				}
+				let selected_virtual_channel = match self.output_scheduler
+				{
+					OutputScheduler::Fifo => *cand.iter().min_by_key(|&&vc|self.output_buffers[exit_port][vc].front().map(|(_,(_,cycle))|cycle)).unwrap(),
+					OutputScheduler::RoundRobin =>
+					{
+						let token = self.port_token[exit_port];
+						*cand.iter().min_by_key(|&&vc|(vc+amount_virtual_channels-token)%amount_virtual_channels).unwrap()
+					},
+					OutputScheduler::StrictPriority => cand[0],
+				};
+				let (phit,(entry_port,_)) = self.output_buffers[exit_port][selected_virtual_channel].pop().expect("incorrect candidate");
+				self.energy_counters.buffer_reads+=1;
+				let replay = self.link_replay.transmit(exit_port,link_class,&simulation.link_classes,simulation.cycle,mutable);
+				//Send the phit to the other link endpoint.
+				events.push(EventGeneration{
+					delay: simulation.link_classes[link_class].delay+replay,
+					position:CyclePosition::Begin,
+					event:Event::PhitToLocation{
+						phit: phit.clone(),
+						previous: Location::RouterPort{
+							router_index: self.router_index,
+							router_port: entry_port,
+						},
+						new: new_location.clone(),
+					},
+				});
+				self.transmission_port_status[exit_port].notify_outcoming_phit(selected_virtual_channel,simulation.cycle);
+				mutable.link_power.notify_transmission(link_source,simulation.cycle);
+				if phit.is_end()
+				{
+					self.port_token[exit_port]=(selected_virtual_channel+1)%amount_virtual_channels;
+				}
+			}
+		}
+		//-- Send the control messages of the receptors, such as pause frames.
+		for (port,space) in self.reception_port_space.iter_mut().enumerate()
+		{
+			for message in space.take_control_messages(simulation.cycle)
+			{
+				let (previous_location,previous_link_class)=topology.neighbour(self.router_index,port);
+				let event = Event::Acknowledge{location:previous_location,source:Location::RouterPort{router_index:self.router_index,router_port:port},message};
+				events.push(simulation.schedule_credit_return(previous_link_class,event));
+			}
+		}
+		self.next_events.pop();//remove the event that was served.
+		if undecided_channels>0 || moved_phits>0 || !events.is_empty() || request_len>0 || blocked_links
+		{
+			//Repeat at next cycle
+			if let Some(event) = self.schedule(simulation.cycle,1)
+			{
+				events.push(event);
+			}
+		}
+		events
+	}
+	fn as_eventful(&self)->Weak<RefCell<dyn Eventful>>
+	{
+		self.self_rc.clone()
+	}
+	fn schedule(&mut self, current_cycle:Time, delay:Time) -> Option<EventGeneration>
+	{
+		let target = current_cycle+delay;
+		if self.next_events.is_empty() || target<*self.next_events.last().unwrap() {
+			self.next_events.push(target);
+			let event = Event::Generic(self.as_eventful().upgrade().expect("missing component"));
+			Some(EventGeneration{
+				delay: target-current_cycle,
+				position: CyclePosition::End,
+				event,
+			})
+		} else {
+			None
+		}
+	}
+}
+
+impl Quantifiable for OutputQueued
+{
+	fn total_memory(&self) -> usize
+	{
+		size_of::<OutputQueued>()
+	}
+	fn print_memory_breakdown(&self)
+	{
+		unimplemented!();
+	}
+	fn forecast_total_memory(&self) -> usize
+	{
+		unimplemented!();
+	}
+}
@@ -0,0 +1,182 @@
+/*!
+
+Routings for the topologies with an [AlgebraicTopology] description, such as the SlimFly, the Projective, and the LeviProjective.
+They compute the minimal routes from the algebraic structure instead of building distance matrices.
+For adaptive routing use `UGAL`, which draws its intermediates from the [AlgebraicTopology] of the topology.
+
+* AlgebraicMinimal
+
+*/
+
+use ::rand::rngs::StdRng;
+
+use crate::match_object_panic;
+use crate::config_parser::ConfigurationValue;
+use crate::topology::{Topology,Location,AlgebraicTopology};
+use crate::routing::prelude::*;
+
+fn get_algebraic(topology:&dyn Topology) -> &dyn AlgebraicTopology
+{
+	topology.algebraic_topology().expect("This routing requires a topology with an algebraic description, such as SlimFly, Projective, or LeviProjective.")
+}
+
+///The ports of `current_router` that begin a minimal path towards `target_router`.
+fn minimal_ports(topology:&dyn Topology, current_router:usize, target_router:usize) -> Vec<usize>
+{
+	let algebraic = get_algebraic(topology);
+	match topology.distance(current_router,target_router)
+	{
+		0 => vec![],
+		1 => vec![algebraic.port_towards(current_router,target_router).expect("neighbour routers without a port between them")],
+		2 => algebraic.common_neighbours(current_router,target_router).into_iter().map(|middle|
+			algebraic.port_towards(current_router,middle).expect("neighbour routers without a port between them")
+		).collect(),
+		//Only the LeviProjective has longer minimal paths. Every neighbour is then at distance 2 from the target.
+		distance => topology.neighbour_router_iter(current_router).filter(|item|
+			topology.distance(item.neighbour_router,target_router)+1==distance
+		).map(|item|item.port_index).collect(),
+	}
+}
+
+///The candidates to deliver the packet into `target_server`, using any virtual channel.
+fn server_candidates(topology:&dyn Topology, current_router:usize, target_server:Option<usize>, num_virtual_channels:usize) -> Result<RoutingNextCandidates,Error>
+{
+	let target_server = target_server.expect("target server was not given.");
+	for port in topology.degree(current_router)..topology.ports(current_router)
+	{
+		if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,port)
+		{
+			if server==target_server
+			{
+				return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(port,vc)).collect(),idempotent:true});
+			}
+		}
+	}
+	unreachable!();
+}
+
+///The virtual channels allowed in the hop of index `hop`.
+///By default the hop `k` goes by the virtual channel `k`, which avoids deadlock.
+fn hop_virtual_channels(channels:&Option<Vec<Vec<usize>>>, hop:usize, num_virtual_channels:usize) -> Vec<usize>
+{
+	match channels
+	{
+		Some(ref channels) =>
+		{
+			if hop>=channels.len()
+			{
+				panic!("There are no channels defined for the hop {}",hop);
+			}
+			channels[hop].clone()
+		},
+		None =>
+		{
+			if hop>=num_virtual_channels
+			{
+				panic!("The routing requires at least {} virtual channels to make the hop {}",hop+1,hop);
+			}
+			vec![hop]
+		},
+	}
+}
+
+fn parse_channels(value:&ConfigurationValue) -> Vec<Vec<usize>>
+{
+	value.as_array().expect("bad value in channels").iter()
+		.map(|vcs_this_hop| vcs_this_hop.as_array().expect("bad value in channels").iter()
+			.map(|vc| vc.as_usize().expect("bad value in channels")).collect()
+		).collect()
+}
+
+///Minimal routing for topologies with an algebraic description.
+///The virtual channel to use in each hop is given explicitly, by default the hop `k` uses the virtual channel `k`.
+#[derive(Debug)]
+pub struct AlgebraicMinimal
+{
+	///`channels[k]` is the list of virtual channels to use in the `k`-th hop between routers.
+	///When not given the `k`-th hop uses the virtual channel `k`.
+	channels: Option<Vec<Vec<usize>>>,
+}
+
+impl Routing for AlgebraicMinimal
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			return server_candidates(topology,current_router,target_server,num_virtual_channels);
+		}
+		let distance = topology.distance(current_router,target_router);
+		let vcs = hop_virtual_channels(&self.channels,routing_info.hops,num_virtual_channels);
+		let candidates = minimal_ports(topology,current_router,target_router).into_iter().flat_map(|port|
+			vcs.iter().map(move |&vc|{
+				let mut egress = CandidateEgress::new(port,vc);
+				egress.estimated_remaining_hops = Some(distance);
+				egress
+			})
+		).collect();
+		Ok(RoutingNextCandidates{candidates,idempotent:true})
+	}
+}
+
+impl AlgebraicMinimal
+{
+	pub fn new(arg: RoutingBuilderArgument) -> AlgebraicMinimal
+	{
+		let mut channels = None;
+		match_object_panic!(arg.cv,"AlgebraicMinimal",value,
+			"channels" => channels=Some(parse_channels(value)),
+		);
+		AlgebraicMinimal{
+			channels,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::SeedableRng;
+	use crate::Plugs;
+	use crate::topology::{new_topology,TopologyBuilderArgument};
+	/// Checks the algebraic computations against the adjacencies of the topologies.
+	#[test]
+	fn algebraic_matches_adjacency()
+	{
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(0);
+		let prime = |name:&str,p:f64| ConfigurationValue::Object(name.to_string(),vec![
+			("prime".to_string(),ConfigurationValue::Number(p)),
+			("servers_per_router".to_string(),ConfigurationValue::Number(1.0)),
+		]);
+		for cv in [prime("SlimFly",5.0),prime("SlimFly",7.0),prime("Projective",3.0),prime("LeviProjective",3.0)]
+		{
+			let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+			let algebraic = get_algebraic(topology.as_ref());
+			let n = topology.num_routers();
+			for origin in 0..n
+			{
+				let neighbours : Vec<(usize,usize)> = topology.neighbour_router_iter(origin).map(|item|(item.neighbour_router,item.port_index)).collect();
+				for destination in 0..n
+				{
+					let port = neighbours.iter().find(|(router,_)|*router==destination).map(|(_,port)|*port);
+					assert_eq!(algebraic.port_towards(origin,destination),port,"{:?} port from {} to {}",cv,origin,destination);
+					if origin==destination
+					{
+						continue;
+					}
+					let mut expected : Vec<usize> = neighbours.iter().map(|(router,_)|*router).filter(|&middle|middle!=destination && topology.neighbour_router_iter(middle).any(|item|item.neighbour_router==destination)).collect();
+					let mut computed = algebraic.common_neighbours(origin,destination);
+					expected.sort();
+					computed.sort();
+					assert_eq!(computed,expected,"{:?} middles from {} to {}",cv,origin,destination);
+					assert!(!minimal_ports(topology.as_ref(),origin,destination).is_empty(),"{:?} no minimal route from {} to {}",cv,origin,destination);
+					if let Some(intermediates) = algebraic.valiant_intermediates(origin,destination)
+					{
+						assert!(!intermediates.is_empty() && intermediates.iter().all(|&middle|middle!=origin && middle!=destination),"{:?} bad intermediates from {} to {}",cv,origin,destination);
+					}
+				}
+			}
+		}
+	}
+}
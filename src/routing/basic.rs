@@ -7,10 +7,12 @@ Implementation of basic routing algorithms.
 * Valiant
 * Mindless
 * WeighedShortest
+* UGAL
 
 */
 
 use std::cell::RefCell;
+use std::rc::Rc;
 use ::rand::{rngs::StdRng,Rng};
 
 use crate::match_object_panic;
@@ -128,89 +130,15 @@ impl Routing for Valiant
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
-		//let (target_location,_link_class)=topology.server_neighbour(target_server);
-		//let target_router=match target_location
-		//{
-		//	Location::RouterPort{router_index,router_port:_} =>router_index,
-		//	_ => panic!("The server is not attached to a router"),
-		//};
-		let distance=topology.distance(current_router,target_router);
-		if distance==0
-		{
-			let target_server = target_server.expect("target server was not given.");
-			for i in 0..topology.ports(current_router)
-			{
-				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
-				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
-				{
-					if server==target_server
-					{
-						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
-						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
-						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true})
-					}
-				}
-			}
-			unreachable!();
-		}
-		let meta=routing_info.meta.as_ref().unwrap();
-		match routing_info.selections
-		{
-			None =>
-			{
-				//self.second.next(&meta[1].borrow(),topology,current_router,target_server,num_virtual_channels,rng)
-				let base=self.second.next(&meta[1].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
-				let idempotent = base.idempotent;
-				let r=base.into_iter().filter(|egress|!self.first_reserved_virtual_channels.contains(&egress.virtual_channel)).collect();
-				Ok(RoutingNextCandidates{candidates:r,idempotent})
-			}
-			Some(ref s) =>
-			{
-				let middle=s[0] as usize;
-				let middle_server=
-				{
-					let mut x=None;
-					for i in 0..topology.ports(middle)
-					{
-						if let (Location::ServerPort(server),_link_class)=topology.neighbour(middle,i)
-						{
-							x=Some(server);
-							break;
-						}
-					}
-					x
-				};
-				let second_distance=topology.distance(middle,target_router);//Only exact if the base routing is shortest.
-				//self.first.next(&meta[0].borrow(),topology,current_router,middle_server,num_virtual_channels,rng).into_iter().filter(|egress|!self.second_reserved_virtual_channels.contains(&egress.virtual_channel)).collect()
-				let base = self.first.next(&meta[0].borrow(),topology,current_router,middle,middle_server,num_virtual_channels,rng)?;
-				let idempotent = base.idempotent;
-				let r=base.into_iter().filter_map(|mut egress|{
-					if self.second_reserved_virtual_channels.contains(&egress.virtual_channel) { None } else {
-						if let Some(ref mut eh)=egress.estimated_remaining_hops
-						{
-							*eh += second_distance;
-						}
-						Some(egress)
-					}
-				}).collect();
-				Ok(RoutingNextCandidates{candidates:r,idempotent})
-			}
-		}
-		// let num_ports=topology.ports(current_router);
-		// let mut r=Vec::with_capacity(num_ports*num_virtual_channels);
-		// for i in 0..num_ports
-		// {
-		// 	//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
-		// 	if let (Location::RouterPort{router_index,router_port:_},_link_class)=topology.neighbour(current_router,i)
-		// 	{
-		// 		if distance-1==topology.distance(router_index,target_router)
-		// 		{
-		// 			r.extend((0..num_virtual_channels).map(|vc|(i,vc)));
-		// 		}
-		// 	}
-		// }
-		// //println!("From router {} to router {} distance={} cand={}",current_router,target_router,distance,r.len());
-		// r
+		self.next_with_base(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,target_router,target_server|
+			routing.next(&info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)
+		)
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_base(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,target_router,target_server|
+			routing.next_with_congestion(info,topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)
+		)
 	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
 	{
@@ -316,6 +244,18 @@ impl Routing for Valiant
 			pattern.initialize(size,size,topology,rng);
 		}
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		let first = self.first.set_network_state(state);
+		let second = self.second.set_network_state(state);
+		first || second
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		let mut events = self.first.initial_events();
+		events.extend(self.second.initial_events());
+		events
+	}
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng)
 	{
 		//TODO: recurse over routings
@@ -324,6 +264,95 @@ impl Routing for Valiant
 
 impl Valiant
 {
+	///The candidates of `next`, obtaining those of the routing of the current stage with `base_next(routing,routing_info,target_router,target_server)`.
+	#[allow(clippy::too_many_arguments)]
+	fn next_with_base<F>(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, mut base_next:F) -> Result<RoutingNextCandidates,Error>
+		where F: FnMut(&dyn Routing,&RefCell<RoutingInfo>,usize,Option<usize>) -> Result<RoutingNextCandidates,Error>
+	{
+		//let (target_location,_link_class)=topology.server_neighbour(target_server);
+		//let target_router=match target_location
+		//{
+		//	Location::RouterPort{router_index,router_port:_} =>router_index,
+		//	_ => panic!("The server is not attached to a router"),
+		//};
+		let distance=topology.distance(current_router,target_router);
+		if distance==0
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
+						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true})
+					}
+				}
+			}
+			unreachable!();
+		}
+		let meta=routing_info.meta.as_ref().unwrap();
+		match routing_info.selections
+		{
+			None =>
+			{
+				//self.second.next(&meta[1].borrow(),topology,current_router,target_server,num_virtual_channels,rng)
+				let base=base_next(&*self.second,&meta[1],target_router,target_server)?;
+				let idempotent = base.idempotent;
+				let r=base.into_iter().filter(|egress|!self.first_reserved_virtual_channels.contains(&egress.virtual_channel)).collect();
+				Ok(RoutingNextCandidates{candidates:r,idempotent})
+			}
+			Some(ref s) =>
+			{
+				let middle=s[0] as usize;
+				let middle_server=
+				{
+					let mut x=None;
+					for i in 0..topology.ports(middle)
+					{
+						if let (Location::ServerPort(server),_link_class)=topology.neighbour(middle,i)
+						{
+							x=Some(server);
+							break;
+						}
+					}
+					x
+				};
+				let second_distance=topology.distance(middle,target_router);//Only exact if the base routing is shortest.
+				//self.first.next(&meta[0].borrow(),topology,current_router,middle_server,num_virtual_channels,rng).into_iter().filter(|egress|!self.second_reserved_virtual_channels.contains(&egress.virtual_channel)).collect()
+				let base = base_next(&*self.first,&meta[0],middle,middle_server)?;
+				let idempotent = base.idempotent;
+				let r=base.into_iter().filter_map(|mut egress|{
+					if self.second_reserved_virtual_channels.contains(&egress.virtual_channel) { None } else {
+						if let Some(ref mut eh)=egress.estimated_remaining_hops
+						{
+							*eh += second_distance;
+						}
+						Some(egress)
+					}
+				}).collect();
+				Ok(RoutingNextCandidates{candidates:r,idempotent})
+			}
+		}
+		// let num_ports=topology.ports(current_router);
+		// let mut r=Vec::with_capacity(num_ports*num_virtual_channels);
+		// for i in 0..num_ports
+		// {
+		// 	//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
+		// 	if let (Location::RouterPort{router_index,router_port:_},_link_class)=topology.neighbour(current_router,i)
+		// 	{
+		// 		if distance-1==topology.distance(router_index,target_router)
+		// 		{
+		// 			r.extend((0..num_virtual_channels).map(|vc|(i,vc)));
+		// 		}
+		// 	}
+		// }
+		// //println!("From router {} to router {} distance={} cand={}",current_router,target_router,distance,r.len());
+		// r
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> Valiant
 	{
 		//let mut order=None;
@@ -522,3 +551,253 @@ impl WeighedShortest
 	}
 }
 
+
+///Universal Globally-Adaptive Load-balanced routing (UGAL) for any topology.
+///It compares the minimal route against Valiant routes through some random intermediate routers, each with cost its length times its queue.
+///The minimal route is taken when `minimal_cost <= bias*valiant_cost + threshold`, and the decision is kept for the whole route.
+///With UGAL-L (`global: false`) the decision is made at the first hop with the local view of the router, taking as queue the least occupied first link of the route.
+///With UGAL-G (`global: true`) the decision is made when the packet enters the network with the [NetworkState] given by the simulator. The queue of a route is the sum of the occupations of its links, following at each router the least occupied minimal link.
+///The candidates use all the virtual channels; use operations such as `ChannelsPerHop` to avoid deadlock.
+#[derive(Debug)]
+pub struct UGAL
+{
+	///Whether to use the occupation of the whole route (UGAL-G) or only of the first link (UGAL-L).
+	global: bool,
+	///Number of random intermediate routers considered.
+	intermediates: usize,
+	///Added to the cost of the Valiant route before comparing.
+	threshold: f64,
+	///Multiplies the cost of the Valiant route before comparing.
+	bias: f64,
+	///The view of the queues of the network. Only for UGAL-G.
+	network_state: Option<Rc<NetworkState>>,
+	///Whether to include the decisions in the results.
+	enabled_statistics: bool,
+	///Number of packets that took the minimal and a Valiant route.
+	tracked_decisions: RefCell<[i64;2]>,
+}
+
+///Value of the selection while UGAL-L has not decided the route.
+const UGAL_UNDECIDED: i32 = -2;
+
+//The selections of the routing info store `[intermediate]`, with -1 for none and UGAL_UNDECIDED before the decision.
+//The intermediate is set to -1 when the packet goes minimally or once the intermediate router has been reached.
+impl Routing for UGAL
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		//Without a decision, as when called without congestion information, go minimally.
+		let intermediate = routing_info.selections.as_ref().map(|s|s[0]).unwrap_or(-1);
+		let (next_target,remain) = if intermediate>=0
+		{
+			let intermediate = intermediate as usize;
+			(intermediate, topology.distance(current_router,intermediate) + topology.distance(intermediate,target_router))
+		}
+		else
+		{
+			(target_router, topology.distance(current_router,target_router))
+		};
+		let mut candidates = vec![];
+		for port in Self::minimal_ports(topology,current_router,next_target)
+		{
+			candidates.extend((0..num_virtual_channels).map(|vc|{
+				let mut egress = CandidateEgress::new(port,vc);
+				egress.estimated_remaining_hops = Some(remain);
+				egress
+			}));
+		}
+		Ok(RoutingNextCandidates{candidates,idempotent:true})
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let undecided = routing_info.borrow().selections.as_ref().map(|s|s[0]==UGAL_UNDECIDED).unwrap_or(false);
+		if undecided && current_router!=target_router
+		{
+			//The least occupied first link of the route towards `goal` times the length of the route.
+			let cost = |goal:usize, length:usize| {
+				let queue = Self::minimal_ports(topology,current_router,goal).into_iter().map(|port|congestion.port_occupation(port)).min().expect("no minimal port towards the goal");
+				(length*queue) as f64
+			};
+			let minimal_cost = cost(target_router,topology.distance(current_router,target_router));
+			let selection = self.choose(topology,current_router,target_router,minimal_cost,rng,|intermediate|{
+				cost(intermediate,topology.distance(current_router,intermediate)+topology.distance(intermediate,target_router))
+			});
+			routing_info.borrow_mut().selections = Some(vec![selection]);
+		}
+		self.next(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, _target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let selection = if !self.global
+		{
+			UGAL_UNDECIDED
+		}
+		else if current_router==target_router
+		{
+			-1
+		}
+		else
+		{
+			let minimal_cost = self.global_route_cost(topology,current_router,None,target_router);
+			self.choose(topology,current_router,target_router,minimal_cost,rng,|intermediate|{
+				self.global_route_cost(topology,current_router,Some(intermediate),target_router)
+			})
+		};
+		routing_info.borrow_mut().selections = Some(vec![selection]);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, current_router:usize, _current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		let mut bri = routing_info.borrow_mut();
+		let selections = bri.selections.as_mut().expect("The routing info has not been initialized");
+		if selections[0]==current_router as i32 || selections[0]==UGAL_UNDECIDED
+		{
+			selections[0] = -1;
+		}
+	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		if self.global
+		{
+			self.network_state = Some(state.clone());
+		}
+		self.global
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		if self.enabled_statistics
+		{
+			let tracked_decisions = self.tracked_decisions.borrow();
+			let content = vec![
+				(String::from("minimal_decisions"),ConfigurationValue::Number(tracked_decisions[0] as f64)),
+				(String::from("nonminimal_decisions"),ConfigurationValue::Number(tracked_decisions[1] as f64)),
+			];
+			Some(ConfigurationValue::Object(String::from("UGALStatistics"),content))
+		}
+		else
+		{
+			None
+		}
+	}
+	fn reset_statistics(&mut self, _next_cycle:Time)
+	{
+		*self.tracked_decisions.borrow_mut() = [0,0];
+	}
+}
+
+impl UGAL
+{
+	pub fn new(arg: RoutingBuilderArgument) -> UGAL
+	{
+		let mut global = None;
+		let mut intermediates = 1;
+		let mut threshold = 0.0;
+		let mut bias = 1.0;
+		let mut enabled_statistics = false;
+		match_object_panic!(arg.cv,"UGAL",value,
+			"global" => global=Some(value.as_bool().expect("bad value for global")),
+			"intermediates" => intermediates=value.as_usize().expect("bad value for intermediates"),
+			"threshold" => threshold=value.as_f64().expect("bad value for threshold"),
+			"bias" => bias=value.as_f64().expect("bad value for bias"),
+			"enable_statistics" => enabled_statistics=value.as_bool().expect("bad value for enable_statistics"),
+		);
+		let global = global.expect("There were no global");
+		UGAL{
+			global,
+			intermediates,
+			threshold,
+			bias,
+			network_state: None,
+			enabled_statistics,
+			tracked_decisions: RefCell::new([0,0]),
+		}
+	}
+	///The ports of `current_router` that begin a shortest path towards `target_router`.
+	fn minimal_ports(topology:&dyn Topology, current_router:usize, target_router:usize) -> Vec<usize>
+	{
+		let distance = topology.distance(current_router,target_router);
+		topology.neighbour_router_iter(current_router).filter(|item|
+			topology.distance(item.neighbour_router,target_router)+1==distance
+		).map(|item|item.port_index).collect()
+	}
+	///Compare the minimal route with the routes through random intermediates, whose cost is given by `valiant_cost`.
+	///When the topology has an algebraic description the intermediates are drawn from its `valiant_intermediates`.
+	///Returns the selected intermediate, or -1 for the minimal route.
+	fn choose<F:Fn(usize)->f64>(&self, topology:&dyn Topology, current_router:usize, target_router:usize, minimal_cost:f64, rng:&mut StdRng, valiant_cost:F) -> i32
+	{
+		let available = topology.algebraic_topology().and_then(|algebraic|algebraic.valiant_intermediates(current_router,target_router));
+		let num_available = available.as_ref().map(|available|available.len()).unwrap_or_else(||topology.num_routers());
+		let mut best : Option<(f64,usize)> = None;
+		for _ in 0..self.intermediates
+		{
+			if num_available==0
+			{
+				break;
+			}
+			let index = rng.gen_range(0..num_available);
+			let intermediate = available.as_ref().map(|available|available[index]).unwrap_or(index);
+			if intermediate==current_router || intermediate==target_router
+			{
+				continue;
+			}
+			let cost = valiant_cost(intermediate);
+			if best.map(|(best_cost,_)|cost<best_cost).unwrap_or(true)
+			{
+				best = Some((cost,intermediate));
+			}
+		}
+		let selection = match best
+		{
+			Some((cost,intermediate)) if minimal_cost > self.bias*cost + self.threshold => intermediate as i32,
+			_ => -1,
+		};
+		if self.enabled_statistics
+		{
+			let index = if selection>=0 {1} else {0};
+			self.tracked_decisions.borrow_mut()[index]+=1;
+		}
+		selection
+	}
+	///The length times the sum of the occupations of the route from `source` to `target` through `intermediate`.
+	fn global_route_cost(&self, topology:&dyn Topology, source:usize, intermediate:Option<usize>, target:usize) -> f64
+	{
+		let state = self.network_state.as_ref().expect("UGAL-G requires the network state, but it has not been given to the routing. Check that every routing wrapping it forwards set_network_state.");
+		let goals = match intermediate
+		{
+			Some(intermediate) => vec![intermediate,target],
+			None => vec![target],
+		};
+		let mut length = 0;
+		let mut queue = 0;
+		let mut current = source;
+		for goal in goals
+		{
+			while current!=goal
+			{
+				let port = Self::minimal_ports(topology,current,goal).into_iter().min_by_key(|&port|state.occupation(current,port)).expect("no minimal port towards the goal");
+				queue += state.occupation(current,port);
+				length += 1;
+				current = match topology.neighbour(current,port).0
+				{
+					Location::RouterPort{router_index,..} => router_index,
+					_ => panic!("a minimal port that is not towards a router"),
+				};
+			}
+		}
+		(length*queue) as f64
+	}
+}
@@ -0,0 +1,330 @@
+/*!
+
+Analytical computation of the load of the channels for oblivious routings, without simulating.
+
+The demands of a pattern are routed through every path the routing may offer. At each router the packet is split uniformly among the ports of the candidates returned by [`Routing::next`].
+Candidates to the same port are merged, continuing with the first of them. Random decisions inside the routing, such as the intermediate of Valiant, are taken with a random number generator. Each pair of servers is routed with `routing_samples` independent generators, each carrying a fraction `1/routing_samples` of the demand, so the load is averaged over those decisions.
+Since only the `Routing` trait is employed, plugged routings can also be analyzed. Adaptive routings relying on the router policies are treated as if the policy were to choose randomly.
+
+It is enabled by the key `channel_load` in the main configuration, which gets added to the result as an object `ChannelLoad`. It can also be computed for all the experiments, without simulating, with the action `channel_load`.
+
+```ignore
+channel_load: ChannelLoad{
+	pattern: Uniform,//the pattern whose load is computed. Usually the same as the one of the traffic.
+	samples: 100,//how many destinations are sampled from each server. Defaults to 100. Random patterns require many samples for the maximum to be accurate.
+	routing_samples: 100,//how many independent random generators are used to route each pair. Defaults to 16. Routings with random decisions, such as Valiant, require many of them.
+	virtual_channels: 4,//the number of virtual channels given to the routing. Defaults to the number of virtual channels of the routers.
+	path_limit: 100000,//maximum number of paths to enumerate for each pair. Beyond it the remaining branches are followed by a random walk. Defaults to 100000.
+}
+```
+
+The result contains
+* `loads`: an array with the load of each port of each router, including the ports towards servers.
+* `maximum`: the greatest load of a link between two routers.
+* `maximum_router`, `maximum_port`: the router and port of the link with maximum load.
+* `average`: the average load of the links between routers.
+* `throughput`: the largest injection load that would not saturate any link between routers, considering the capacity of each link class.
+
+Loads are given in phits per cycle when each server injects one phit per cycle.
+
+*/
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use ::rand::{Rng,SeedableRng,rngs::StdRng};
+
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+use crate::pattern::{Pattern,new_pattern,PatternBuilderArgument};
+use crate::routing::{Routing,RoutingInfo,CandidateEgress,new_routing,RoutingBuilderArgument};
+use crate::topology::{Topology,Location};
+use crate::Plugs;
+
+///The configuration of the computation of the load of the channels. See the [module documentation](self).
+#[derive(Debug)]
+pub struct ChannelLoad
+{
+	pattern: Box<dyn Pattern>,
+	samples: usize,
+	routing_samples: usize,
+	virtual_channels: Option<usize>,
+	path_limit: usize,
+}
+
+impl ChannelLoad
+{
+	pub fn new(cv:&ConfigurationValue, plugs:&Plugs) -> ChannelLoad
+	{
+		let mut pattern = None;
+		let mut samples = 100;
+		let mut routing_samples = 16;
+		let mut virtual_channels = None;
+		let mut path_limit = 100000;
+		match_object_panic!(cv,"ChannelLoad",value,
+			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs})),
+			"samples" => samples=value.as_usize().expect("bad value for samples"),
+			"routing_samples" => routing_samples=value.as_usize().expect("bad value for routing_samples"),
+			"virtual_channels" => virtual_channels=Some(value.as_usize().expect("bad value for virtual_channels")),
+			"path_limit" => path_limit=value.as_usize().expect("bad value for path_limit"),
+		);
+		let pattern = pattern.expect("There were no pattern");
+		assert!(samples>0, "ChannelLoad requires some sample.");
+		assert!(routing_samples>0, "ChannelLoad requires some routing sample.");
+		ChannelLoad{
+			pattern,
+			samples,
+			routing_samples,
+			virtual_channels,
+			path_limit,
+		}
+	}
+	/**
+	Computes the load of the channels when using the routing defined by `routing_cv`.
+	A new routing is built to not alter the state of the one being simulated.
+	`default_virtual_channels` is used when the `virtual_channels` key is not given, and `link_capacities[c]` is the capacity in phits per cycle of the links of class `c`.
+	Returns the `ChannelLoad` object to include in the results.
+	**/
+	pub fn compute(&mut self, topology:&dyn Topology, routing_cv:&ConfigurationValue, plugs:&Plugs, default_virtual_channels:usize, link_capacities:&[f64], rng:&mut StdRng) -> ConfigurationValue
+	{
+		let mut routing = new_routing(RoutingBuilderArgument{cv:routing_cv,plugs});
+		routing.initialize(topology,rng);
+		let num_servers = topology.num_servers();
+		self.pattern.initialize(num_servers,num_servers,topology,rng);
+		let weight = 1.0/self.samples as f64;
+		let mut demands : BTreeMap<(usize,usize),f64> = BTreeMap::new();
+		for origin in 0..num_servers
+		{
+			for _ in 0..self.samples
+			{
+				let destination = self.pattern.get_destination(origin,topology,rng);
+				*demands.entry((origin,destination)).or_insert(0.0) += weight;
+			}
+		}
+		let mut loads : Vec<Vec<f64>> = (0..topology.num_routers()).map(|router|vec![0.0;topology.ports(router)]).collect();
+		for (&(origin,destination),&demand) in demands.iter()
+		{
+			//Each routing sample replays the pair with its own generator, so its random decisions are independent of the others.
+			for _ in 0..self.routing_samples
+			{
+				let enumeration = PathEnumeration{
+					routing: routing.as_ref(),
+					topology,
+					origin_router: server_router(topology,origin),
+					target_router: server_router(topology,destination),
+					target_server: destination,
+					virtual_channels: self.virtual_channels.unwrap_or(default_virtual_channels),
+					pair_rng: StdRng::seed_from_u64(rng.gen()),
+				};
+				let mut budget = self.path_limit;
+				enumeration.explore(&mut vec![],demand/self.routing_samples as f64,&mut budget,&mut loads);
+			}
+		}
+		//Statistics over the links between routers.
+		let mut maximum = 0.0;
+		let mut maximum_link = (0,0);
+		let mut total = 0.0;
+		let mut links = 0;
+		let mut throughput = f64::INFINITY;
+		for (router,router_loads) in loads.iter().enumerate()
+		{
+			for (port,&load) in router_loads.iter().enumerate()
+			{
+				if let (Location::RouterPort{..},link_class) = topology.neighbour(router,port)
+				{
+					total += load;
+					links += 1;
+					if load > maximum
+					{
+						maximum = load;
+						maximum_link = (router,port);
+					}
+					if load > 0.0
+					{
+						throughput = throughput.min(link_capacities[link_class]/load);
+					}
+				}
+			}
+		}
+		let average = if links>0 { total/links as f64 } else { 0.0 };
+		let number = |x:f64| ConfigurationValue::Number(x);
+		ConfigurationValue::Object(String::from("ChannelLoad"),vec![
+			(String::from("maximum"),number(maximum)),
+			(String::from("maximum_router"),number(maximum_link.0 as f64)),
+			(String::from("maximum_port"),number(maximum_link.1 as f64)),
+			(String::from("average"),number(average)),
+			(String::from("throughput"),number(throughput)),
+			(String::from("loads"),ConfigurationValue::Array(loads.iter().map(|router_loads|
+				ConfigurationValue::Array(router_loads.iter().map(|&load|number(load)).collect())
+			).collect())),
+		])
+	}
+}
+
+///The router to which the first attachment of a server is connected.
+fn server_router(topology:&dyn Topology, server:usize) -> usize
+{
+	match topology.server_neighbour(server).0
+	{
+		Location::RouterPort{router_index,router_port:_} => router_index,
+		_ => panic!("The server {} is not attached to a router",server),
+	}
+}
+
+///The paths of a packet between two servers.
+///As the `RoutingInfo` cannot be copied, each branch is obtained by repeating the route from the origin, with the same random generator, following a prefix of choices.
+struct PathEnumeration<'a>
+{
+	routing: &'a dyn Routing,
+	topology: &'a dyn Topology,
+	origin_router: usize,
+	target_router: usize,
+	target_server: usize,
+	virtual_channels: usize,
+	pair_rng: StdRng,
+}
+
+impl PathEnumeration<'_>
+{
+	/**
+	Routes a packet following the ports selected by `prefix` at the routers with several ports available. Routers with a single port available do not consume entries of the prefix.
+	Returns the list of `(router,port)` traversed, and the number of ports available if the prefix ended before reaching the destination.
+	With `random_tail` the ports after the prefix are selected randomly instead.
+	**/
+	fn replay(&self, prefix:&[usize], random_tail:bool) -> (Vec<(usize,usize)>,Option<usize>)
+	{
+		let mut rng = self.pair_rng.clone();
+		let target_server = Some(self.target_server);
+		let routing_info = RefCell::new(RoutingInfo::new());
+		let mut current = self.origin_router;
+		self.routing.initialize_routing_info(&routing_info,self.topology,current,self.target_router,target_server,&mut rng);
+		let mut choices = prefix.iter();
+		let mut links = vec![];
+		//A generous limit to detect routings that never reach the destination.
+		let hop_limit = 10*self.topology.num_routers() + 10;
+		loop
+		{
+			if links.len() > hop_limit
+			{
+				panic!("The routing has not reached router {} from router {} in {} hops.",self.target_router,self.origin_router,hop_limit);
+			}
+			let candidates = self.routing.next(&routing_info.borrow(),self.topology,current,self.target_router,target_server,self.virtual_channels,&mut rng).unwrap_or_else(|e|panic!("Error {} while routing.",e)).candidates;
+			let mut distinct : Vec<&CandidateEgress> = vec![];
+			for candidate in candidates.iter()
+			{
+				if !distinct.iter().any(|other|other.port==candidate.port)
+				{
+					distinct.push(candidate);
+				}
+			}
+			if distinct.is_empty()
+			{
+				panic!("There are no candidates at router {} towards router {}.",current,self.target_router);
+			}
+			let index = if distinct.len()==1 { 0 } else if let Some(&index) = choices.next() { index } else if random_tail { rng.gen_range(0..distinct.len()) } else {
+				return (links,Some(distinct.len()));
+			};
+			let candidate = distinct[index];
+			self.routing.performed_request(candidate,&routing_info,self.topology,current,self.target_router,target_server,self.virtual_channels,&mut rng);
+			links.push((current,candidate.port));
+			match self.topology.neighbour(current,candidate.port).0
+			{
+				Location::ServerPort(_) => return (links,None),
+				Location::RouterPort{router_index,router_port} =>
+				{
+					routing_info.borrow_mut().hops+=1;
+					self.routing.update_routing_info(&routing_info,self.topology,router_index,router_port,self.target_router,target_server,&mut rng);
+					current = router_index;
+				},
+				Location::None => panic!("The routing has selected the disconnected port {} of router {}.",candidate.port,current),
+			}
+		}
+	}
+	///Adds `probability` to the load of the links of the paths beginning with `prefix`, split uniformly at each branch.
+	///Each replay consumes one unit of the `budget`; once exhausted the branches are completed by random walks.
+	fn explore(&self, prefix:&mut Vec<usize>, probability:f64, budget:&mut usize, loads:&mut [Vec<f64>])
+	{
+		let random_tail = *budget==0;
+		*budget = budget.saturating_sub(1);
+		let (links,branches) = self.replay(prefix,random_tail);
+		match branches
+		{
+			None => for (router,port) in links
+			{
+				loads[router][port] += probability;
+			},
+			Some(amount) => for index in 0..amount
+			{
+				prefix.push(index);
+				self.explore(prefix,probability/amount as f64,budget,loads);
+				prefix.pop();
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::topology::{new_topology,TopologyBuilderArgument};
+	use crate::config_parser;
+	fn parse(text:&str) -> ConfigurationValue
+	{
+		match config_parser::parse(text).unwrap()
+		{
+			config_parser::Token::Value(value) => value,
+			_ => panic!("Not a value"),
+		}
+	}
+	#[test]
+	fn mesh_dimension_order()
+	{
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(1);
+		let topology = new_topology(TopologyBuilderArgument{cv:&parse("Mesh{sides:[4,4],servers_per_router:1}"),plugs:&plugs,rng:&mut rng});
+		//Transpose in a 4x4 mesh with DOR. The most loaded channels are those leaving the diagonal routers towards the turn.
+		let mut channel_load = ChannelLoad::new(&parse("ChannelLoad{pattern:CartesianTransform{sides:[4,4],permute:[1,0]},samples:1}"),&plugs);
+		let result = channel_load.compute(topology.as_ref(),&parse("DOR{order:[0,1]}"),&plugs,1,&[1.0,1.0,1.0],&mut rng);
+		let get = |key:&str| match result { ConfigurationValue::Object(_,ref pairs) => pairs.iter().find(|(name,_)|name==key).unwrap().1.as_f64().unwrap(), _ => panic!() };
+		assert_eq!(get("maximum"),3.0);
+		assert_eq!(get("throughput"),1.0/3.0);
+		//Shortest splits among the minimal paths, which for the transpose is better balanced.
+		let mut channel_load = ChannelLoad::new(&parse("ChannelLoad{pattern:CartesianTransform{sides:[4,4],permute:[1,0]},samples:1}"),&plugs);
+		let result = channel_load.compute(topology.as_ref(),&parse("Shortest"),&plugs,1,&[1.0,1.0,1.0],&mut rng);
+		let get = |key:&str| match result { ConfigurationValue::Object(_,ref pairs) => pairs.iter().find(|(name,_)|name==key).unwrap().1.as_f64().unwrap(), _ => panic!() };
+		assert!(get("maximum")<3.0);
+		//Every packet is ejected exactly once.
+		if let ConfigurationValue::Object(_,ref pairs) = result
+		{
+			let loads = pairs.iter().find(|(name,_)|name=="loads").unwrap().1.as_array().unwrap();
+			let mut ejected = 0.0;
+			for (router,router_loads) in loads.iter().enumerate()
+			{
+				for (port,load) in router_loads.as_array().unwrap().iter().enumerate()
+				{
+					if let (Location::ServerPort(_),_) = topology.neighbour(router,port)
+					{
+						ejected += load.as_f64().unwrap();
+					}
+				}
+			}
+			assert!( (ejected-16.0).abs()<1e-9 );
+		}
+	}
+	#[test]
+	fn valiant_doubles_the_load()
+	{
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(1);
+		let topology = new_topology(TopologyBuilderArgument{cv:&parse("Hamming{sides:[8],servers_per_router:1}"),plugs:&plugs,rng:&mut rng});
+		//In the complete graph of 8 routers each phase of Valiant is uniform traffic, including the router itself, making 2/8 per link for any permutation.
+		//Without independent samples of the intermediate every pair would load a single Valiant path.
+		let mut channel_load = ChannelLoad::new(&parse("ChannelLoad{pattern:CartesianTransform{sides:[8],shift:[1]},samples:1,routing_samples:10000}"),&plugs);
+		let result = channel_load.compute(topology.as_ref(),&parse("Valiant{first:Shortest,second:Shortest}"),&plugs,1,&[1.0,1.0,1.0],&mut rng);
+		let get = |key:&str| match result { ConfigurationValue::Object(_,ref pairs) => pairs.iter().find(|(name,_)|name==key).unwrap().1.as_f64().unwrap(), _ => panic!() };
+		let expected = 2.0/8.0;
+		assert!( (get("average")-expected).abs() < 0.01*expected, "average load {} instead of {}", get("average"), expected );
+		assert!( (get("maximum")-expected).abs() < 0.1*expected, "maximum load {} instead of {}", get("maximum"), expected );
+	}
+}
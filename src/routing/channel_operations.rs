@@ -10,6 +10,7 @@ Implementations of operations on routings that modify the virtual channel to use
 */
 
 use std::cell::RefCell;
+use std::rc::Rc;
 
 use ::rand::{rngs::StdRng};
 
@@ -35,11 +36,14 @@ impl Routing for ChannelsPerHop
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
 		//println!("{}",topology.diameter());
-		let vcs = &self.channels[routing_info.hops];
 		let candidates = self.routing.next(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
-		let idempotent = candidates.idempotent;
-		let r = candidates.into_iter().filter(|c|vcs.contains(&c.virtual_channel)).collect();
-		Ok(RoutingNextCandidates{candidates:r,idempotent})
+		Ok(self.filter_candidates(candidates,routing_info.hops))
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let candidates = self.routing.next_with_congestion(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)?;
+		let hops = routing_info.borrow().hops;
+		Ok(self.filter_candidates(candidates,hops))
 	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
 	{
@@ -53,6 +57,14 @@ impl Routing for ChannelsPerHop
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		self.routing.set_network_state(state)
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		self.routing.initial_events()
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,routing_info,topology,current_router,target_router,target_server,num_virtual_channels,rng);
@@ -69,6 +81,14 @@ impl Routing for ChannelsPerHop
 
 impl ChannelsPerHop
 {
+	///Keep the candidates using the virtual channels of the hop.
+	fn filter_candidates(&self, candidates:RoutingNextCandidates, hops:usize) -> RoutingNextCandidates
+	{
+		let vcs = &self.channels[hops];
+		let idempotent = candidates.idempotent;
+		let candidates = candidates.into_iter().filter(|c|vcs.contains(&c.virtual_channel)).collect();
+		RoutingNextCandidates{candidates,idempotent}
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> ChannelsPerHop
 	{
 		let mut routing =None;
@@ -110,31 +130,18 @@ impl Routing for ChannelsPerHopPerLinkClass
 	{
 		//println!("{}",topology.diameter());
 		let candidates = self.routing.next(&routing_info.meta.as_ref().unwrap()[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
-		let idempotent = candidates.idempotent;
-		let hops = &routing_info.selections.as_ref().unwrap();
-		let r = candidates.into_iter().filter(|c|{
-			let (_next_location,link_class)=topology.neighbour(current_router,c.port);
-			let h = if self.use_total_hops
-			{
-				routing_info.hops
-			}else{
-				hops[link_class] as usize
-			};
-
-			//println!("h={} link_class={} channels={:?}",h,link_class,self.channels[link_class]);
-			if self.channels[link_class].len()<=h
-			{
-				panic!("Already given {} hops by link class {}",h,link_class);
-			}
-			//self.channels[link_class].len()>h && self.channels[link_class][h].contains(&c.virtual_channel)
-			self.channels[link_class][h].contains(&c.virtual_channel)
-		}).collect();
-		Ok(RoutingNextCandidates{candidates:r,idempotent})
+		Ok(self.filter_candidates(candidates,routing_info,topology,current_router))
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let info = routing_info.borrow();
+		let candidates = self.routing.next_with_congestion(&info.meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)?;
+		Ok(self.filter_candidates(candidates,&info,topology,current_router))
 	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
 	{
 		let mut info = routing_info.borrow_mut();
-		info.meta=Some(vec![ RefCell::new(RoutingInfo::new())]);
+		info.meta=Some(vec![ RefCell::new(RoutingInfo{source_server:info.source_server,..RoutingInfo::new()})]);
 		info.selections = Some(vec![0;self.channels.len()]);
 		self.routing.initialize_routing_info(&info.meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,rng);
 	}
@@ -159,6 +166,14 @@ impl Routing for ChannelsPerHopPerLinkClass
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		self.routing.set_network_state(state)
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		self.routing.initial_events()
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,&routing_info.borrow().meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,rng);
@@ -175,6 +190,30 @@ impl Routing for ChannelsPerHopPerLinkClass
 
 impl ChannelsPerHopPerLinkClass
 {
+	///Keep the candidates using the virtual channels of the hop in the class of their link.
+	fn filter_candidates(&self, candidates:RoutingNextCandidates, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize) -> RoutingNextCandidates
+	{
+		let idempotent = candidates.idempotent;
+		let hops = &routing_info.selections.as_ref().unwrap();
+		let r = candidates.into_iter().filter(|c|{
+			let (_next_location,link_class)=topology.neighbour(current_router,c.port);
+			let h = if self.use_total_hops
+			{
+				routing_info.hops
+			}else{
+				hops[link_class] as usize
+			};
+
+			//println!("h={} link_class={} channels={:?}",h,link_class,self.channels[link_class]);
+			if self.channels[link_class].len()<=h
+			{
+				panic!("Already given {} hops by link class {}",h,link_class);
+			}
+			//self.channels[link_class].len()>h && self.channels[link_class][h].contains(&c.virtual_channel)
+			self.channels[link_class][h].contains(&c.virtual_channel)
+		}).collect();
+		RoutingNextCandidates{candidates:r,idempotent}
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> ChannelsPerHopPerLinkClass
 	{
 		let mut routing =None;
@@ -227,22 +266,18 @@ impl Routing for AscendantChannelsWithLinkClass
 	{
 		//println!("{}",topology.diameter());
 		let candidates = self.routing.next(&routing_info.meta.as_ref().unwrap()[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
-		let idempotent = candidates.idempotent;
-		let hops_since = &routing_info.selections.as_ref().unwrap();
-		let r = candidates.into_iter().filter(|c|{
-			let (_next_location,link_class)=topology.neighbour(current_router,c.port);
-			if link_class>= self.bases.len() { return true; }
-			//let h = hops_since[link_class] as usize;
-			let vc = (link_class..self.bases.len()).rev().fold(0, |x,class| x*self.bases[class]+(hops_since[class] as usize) );
-			//if link_class==0 && vc!=hops_since[1] as usize{ println!("hops_since={:?} link_class={} vc={}",hops_since,link_class,vc); }
-			c.virtual_channel == vc
-		}).collect();
-		Ok(RoutingNextCandidates{candidates:r,idempotent})
+		Ok(self.filter_candidates(candidates,routing_info,topology,current_router))
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let info = routing_info.borrow();
+		let candidates = self.routing.next_with_congestion(&info.meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)?;
+		Ok(self.filter_candidates(candidates,&info,topology,current_router))
 	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
 	{
 		let mut info = routing_info.borrow_mut();
-		info.meta=Some(vec![ RefCell::new(RoutingInfo::new())]);
+		info.meta=Some(vec![ RefCell::new(RoutingInfo{source_server:info.source_server,..RoutingInfo::new()})]);
 		info.selections = Some(vec![0;self.bases.len()]);
 		self.routing.initialize_routing_info(&info.meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,rng);
 	}
@@ -271,6 +306,14 @@ impl Routing for AscendantChannelsWithLinkClass
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		self.routing.set_network_state(state)
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		self.routing.initial_events()
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,&routing_info.borrow().meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,rng);
@@ -287,6 +330,21 @@ impl Routing for AscendantChannelsWithLinkClass
 
 impl AscendantChannelsWithLinkClass
 {
+	///Keep the candidates whose virtual channel corresponds to the hops given since the last hop in a higher link class.
+	fn filter_candidates(&self, candidates:RoutingNextCandidates, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize) -> RoutingNextCandidates
+	{
+		let idempotent = candidates.idempotent;
+		let hops_since = &routing_info.selections.as_ref().unwrap();
+		let r = candidates.into_iter().filter(|c|{
+			let (_next_location,link_class)=topology.neighbour(current_router,c.port);
+			if link_class>= self.bases.len() { return true; }
+			//let h = hops_since[link_class] as usize;
+			let vc = (link_class..self.bases.len()).rev().fold(0, |x,class| x*self.bases[class]+(hops_since[class] as usize) );
+			//if link_class==0 && vc!=hops_since[1] as usize{ println!("hops_since={:?} link_class={} vc={}",hops_since,link_class,vc); }
+			c.virtual_channel == vc
+		}).collect();
+		RoutingNextCandidates{candidates:r,idempotent}
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> AscendantChannelsWithLinkClass
 	{
 		let mut routing =None;
@@ -322,19 +380,13 @@ impl Routing for ChannelMap
 		//println!("{}",topology.diameter());
 		//let vcs = &self.channels[routing_info.hops];
 		let candidates = self.routing.next(routing_info,topology,current_router,target_router,target_server,self.map.len(),rng)?;
-		let idempotent = candidates.idempotent;
 		//candidates.into_iter().filter(|c|vcs.contains(&c.virtual_channel)).collect()
-		let mut r=Vec::with_capacity(candidates.len());
-		for can in candidates.into_iter()
-		{
-			for vc in self.map[can.virtual_channel].iter()
-			{
-				let mut new = can.clone();
-				new.virtual_channel = *vc;
-				r.push(new);
-			}
-		}
-		Ok(RoutingNextCandidates{candidates:r,idempotent})
+		Ok(self.map_candidates(candidates))
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let candidates = self.routing.next_with_congestion(routing_info,topology,current_router,target_router,target_server,self.map.len(),congestion,rng)?;
+		Ok(self.map_candidates(candidates))
 	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
 	{
@@ -348,6 +400,14 @@ impl Routing for ChannelMap
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		self.routing.set_network_state(state)
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		self.routing.initial_events()
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		self.routing.performed_request(requested,routing_info,topology,current_router,target_router,target_server,self.map.len(),rng);
@@ -364,6 +424,22 @@ impl Routing for ChannelMap
 
 impl ChannelMap
 {
+	///Replace each candidate with one candidate for each virtual channel its channel is mapped to.
+	fn map_candidates(&self, candidates:RoutingNextCandidates) -> RoutingNextCandidates
+	{
+		let idempotent = candidates.idempotent;
+		let mut r=Vec::with_capacity(candidates.len());
+		for can in candidates.into_iter()
+		{
+			for vc in self.map[can.virtual_channel].iter()
+			{
+				let mut new = can.clone();
+				new.virtual_channel = *vc;
+				r.push(new);
+			}
+		}
+		RoutingNextCandidates{candidates:r,idempotent}
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> ChannelMap
 	{
 		let mut routing =None;
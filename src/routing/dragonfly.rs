@@ -0,0 +1,687 @@
+/*!
+
+Progressive adaptive routings for networks made of groups of routers, such as the `Dragonfly` and the `Megafly`.
+
+* OLM
+* PiggyBack
+* ContentionCounters
+
+The groups are detected from the link classes of the topology: links of class 0 join routers of the same group and links of class 1 join different groups.
+The candidates use all the virtual channels; use operations such as `ChannelsPerHop` to avoid deadlock.
+
+*/
+
+use std::cell::RefCell;
+use std::rc::{Rc,Weak};
+
+use ::rand::{rngs::StdRng,Rng};
+
+use crate::match_object_panic;
+use crate::config_parser::ConfigurationValue;
+use crate::routing::prelude::*;
+use crate::topology::{Topology,Location};
+use crate::matrix::Matrix;
+use crate::event::Eventful;
+use crate::router::occupied_neighbour_space;
+use crate::{SimulationShared,SimulationMut};
+
+///The link class of the links inside a group.
+const LOCAL_LINK_CLASS: usize = 0;
+///The link class of the links between groups.
+const GLOBAL_LINK_CLASS: usize = 1;
+
+///The groups of routers of a topology, being the components of its local links.
+#[derive(Debug)]
+pub struct GroupStructure
+{
+	///`group[router]` is the index of the group containing `router`.
+	group: Vec<usize>,
+	///`group_distance.get(router,group)` is the least distance from `router` to a router of `group`.
+	group_distance: Matrix<usize>,
+	///`global_links[group]` are the `(router,port,other_group)` of the global links leaving `group`.
+	global_links: Vec<Vec<(usize,usize,usize)>>,
+}
+
+impl GroupStructure
+{
+	pub fn new(topology:&dyn Topology) -> GroupStructure
+	{
+		let n = topology.num_routers();
+		let mut group : Vec<Option<usize>> = vec![None;n];
+		let mut number_of_groups = 0;
+		for root in 0..n
+		{
+			if group[root].is_some()
+			{
+				continue;
+			}
+			group[root] = Some(number_of_groups);
+			let mut stack = vec![root];
+			while let Some(router) = stack.pop()
+			{
+				for item in topology.neighbour_router_iter(router)
+				{
+					if item.link_class==LOCAL_LINK_CLASS && group[item.neighbour_router].is_none()
+					{
+						group[item.neighbour_router] = Some(number_of_groups);
+						stack.push(item.neighbour_router);
+					}
+				}
+			}
+			number_of_groups += 1;
+		}
+		let group : Vec<usize> = group.into_iter().map(|g|g.expect("router without group")).collect();
+		let mut group_distance = Matrix::constant(usize::MAX,n,number_of_groups);
+		let mut global_links = vec![vec![];number_of_groups];
+		for router in 0..n
+		{
+			for other in 0..n
+			{
+				let distance = topology.distance(router,other);
+				let entry = group_distance.get_mut(router,group[other]);
+				if distance < *entry
+				{
+					*entry = distance;
+				}
+			}
+			for item in topology.neighbour_router_iter(router)
+			{
+				if item.link_class==GLOBAL_LINK_CLASS
+				{
+					global_links[group[router]].push((router,item.port_index,group[item.neighbour_router]));
+				}
+			}
+		}
+		GroupStructure{
+			group,
+			group_distance,
+			global_links,
+		}
+	}
+	pub fn number_of_groups(&self) -> usize
+	{
+		self.global_links.len()
+	}
+	///The index of the group containing `router`.
+	pub fn group(&self, router:usize) -> usize
+	{
+		self.group[router]
+	}
+	///The `(router,port)` of the global links from `group` to `other_group`.
+	pub fn links_between(&self, group:usize, other_group:usize) -> impl Iterator<Item=(usize,usize)> + '_
+	{
+		self.global_links[group].iter().filter(move |&&(_,_,other)|other==other_group).map(|&(router,port,_)|(router,port))
+	}
+	///The ports of `current_router` that begin a shortest path towards `target_router`.
+	pub fn minimal_ports(&self, topology:&dyn Topology, current_router:usize, target_router:usize) -> Vec<usize>
+	{
+		let distance = topology.distance(current_router,target_router);
+		topology.neighbour_router_iter(current_router).filter(|item|
+			topology.distance(item.neighbour_router,target_router)+1==distance
+		).map(|item|item.port_index).collect()
+	}
+	///The ports of `current_router` that begin a shortest path towards any router of `group`.
+	pub fn ports_towards_group(&self, topology:&dyn Topology, current_router:usize, group:usize) -> Vec<usize>
+	{
+		let distance = *self.group_distance.get(current_router,group);
+		topology.neighbour_router_iter(current_router).filter(|item|
+			*self.group_distance.get(item.neighbour_router,group)+1==distance
+		).map(|item|item.port_index).collect()
+	}
+}
+
+///The candidates to deliver the packet to `target_server`, attached to `current_router`.
+fn ejection_candidates(topology:&dyn Topology, current_router:usize, target_server:Option<usize>, num_virtual_channels:usize) -> RoutingNextCandidates
+{
+	let target_server = target_server.expect("target server was not given.");
+	for i in 0..topology.ports(current_router)
+	{
+		if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+		{
+			if server==target_server
+			{
+				return RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true};
+			}
+		}
+	}
+	unreachable!();
+}
+
+///All the virtual channels of the given ports.
+fn port_candidates(ports:&[usize], num_virtual_channels:usize) -> Vec<CandidateEgress>
+{
+	ports.iter().flat_map(|&port|(0..num_virtual_channels).map(move |vc|CandidateEgress::new(port,vc))).collect()
+}
+
+///The router from which the packet has come into `current_port`, and the class of that link.
+fn previous_router(topology:&dyn Topology, current_router:usize, current_port:usize) -> (usize,usize)
+{
+	match topology.neighbour(current_router,current_port)
+	{
+		(Location::RouterPort{router_index,..},link_class) => (router_index,link_class),
+		_ => panic!("The packet has not come from a router"),
+	}
+}
+
+///Counters of the misrouting taken by the packets.
+#[derive(Debug,Default)]
+struct MisroutingStatistics
+{
+	///Number of packets that have been routed.
+	packets: i64,
+	///Number of nonminimal hops through global links.
+	global_misroutes: i64,
+	///Number of nonminimal hops through local links.
+	local_misroutes: i64,
+}
+
+impl MisroutingStatistics
+{
+	fn configuration_value(&self) -> ConfigurationValue
+	{
+		let content = vec![
+			(String::from("packets"),ConfigurationValue::Number(self.packets as f64)),
+			(String::from("global_misroutes"),ConfigurationValue::Number(self.global_misroutes as f64)),
+			(String::from("local_misroutes"),ConfigurationValue::Number(self.local_misroutes as f64)),
+		];
+		ConfigurationValue::Object(String::from("MisroutingStatistics"),content)
+	}
+	///Count the hop into `current_router` through `current_port` if it does not approach `target_router`.
+	fn track_hop(&mut self, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize) -> Option<usize>
+	{
+		let (previous,link_class) = previous_router(topology,current_router,current_port);
+		if topology.distance(current_router,target_router) < topology.distance(previous,target_router)
+		{
+			return None;
+		}
+		match link_class
+		{
+			GLOBAL_LINK_CLASS => self.global_misroutes+=1,
+			LOCAL_LINK_CLASS => self.local_misroutes+=1,
+			_ => (),
+		}
+		Some(link_class)
+	}
+}
+
+//The opportunistic routings OLM and ContentionCounters store in the selections `[global_allowed, local_allowed]`.
+//A global misroute is allowed while the packet is in its source group, and a local misroute once in each group.
+
+///Initial selections of an opportunistic misrouting.
+fn opportunistic_selections() -> Vec<i32>
+{
+	vec![1,1]
+}
+
+///Update the selections of an opportunistic misrouting after a hop, tracking it into the statistics.
+fn opportunistic_update(selections:&mut [i32], statistics:&RefCell<MisroutingStatistics>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize)
+{
+	let misroute = statistics.borrow_mut().track_hop(topology,current_router,current_port,target_router);
+	let (_previous,link_class) = previous_router(topology,current_router,current_port);
+	if link_class==GLOBAL_LINK_CLASS
+	{
+		//A new group, in which the packet may misroute locally again.
+		selections[0] = 0;
+		selections[1] = 1;
+	}
+	else if misroute.is_some()
+	{
+		selections[1] = 0;
+	}
+}
+
+///The minimal ports and the ports that an opportunistic misrouting may take instead.
+fn opportunistic_ports(groups:&GroupStructure, topology:&dyn Topology, selections:&[i32], current_router:usize, target_router:usize) -> (Vec<usize>,Vec<usize>)
+{
+	let minimal = groups.minimal_ports(topology,current_router,target_router);
+	let current_group = groups.group(current_router);
+	let target_group = groups.group(target_router);
+	let nonminimal = topology.neighbour_router_iter(current_router).filter(|item|{
+		if minimal.contains(&item.port_index)
+		{
+			return false;
+		}
+		match item.link_class
+		{
+			GLOBAL_LINK_CLASS => selections[0]==1 && current_group!=target_group && groups.group(item.neighbour_router)!=target_group,
+			LOCAL_LINK_CLASS => selections[1]==1,
+			_ => false,
+		}
+	}).map(|item|item.port_index).collect();
+	(minimal,nonminimal)
+}
+
+///Opportunistic Local Misrouting (OLM).
+///At every hop the packet may be misrouted through a port whose occupation plus `threshold` is lower than the occupation of every minimal port.
+///It may take a global misroute from any router of its source group and a local misroute in each group.
+#[derive(Debug)]
+pub struct OLM
+{
+	///The groups of the topology, built in `initialize`.
+	groups: Option<GroupStructure>,
+	///Added to the occupation of the nonminimal ports before comparing.
+	threshold: usize,
+	///Whether to include the misrouting in the results.
+	enabled_statistics: bool,
+	statistics: RefCell<MisroutingStatistics>,
+}
+
+impl Routing for OLM
+{
+	fn next(&self, _routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			return Ok(ejection_candidates(topology,current_router,target_server,num_virtual_channels));
+		}
+		//Without congestion information go minimally.
+		let groups = self.groups.as_ref().expect("OLM has not been initialized");
+		let minimal = groups.minimal_ports(topology,current_router,target_router);
+		Ok(RoutingNextCandidates{candidates:port_candidates(&minimal,num_virtual_channels),idempotent:true})
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			return self.next(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng);
+		}
+		let groups = self.groups.as_ref().expect("OLM has not been initialized");
+		let bri = routing_info.borrow();
+		let selections = bri.selections.as_ref().expect("The routing info has not been initialized");
+		let (minimal,nonminimal) = opportunistic_ports(groups,topology,selections,current_router,target_router);
+		let minimal_occupation = minimal.iter().map(|&port|congestion.port_occupation(port)).min().expect("no minimal port");
+		let misroute : Vec<usize> = nonminimal.into_iter().filter(|&port|congestion.port_occupation(port)+self.threshold < minimal_occupation).collect();
+		let ports = if misroute.is_empty() { minimal } else { misroute };
+		//The occupations change from one cycle to the next.
+		Ok(RoutingNextCandidates{candidates:port_candidates(&ports,num_virtual_channels),idempotent:false})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		routing_info.borrow_mut().selections = Some(opportunistic_selections());
+		self.statistics.borrow_mut().packets+=1;
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		let mut bri = routing_info.borrow_mut();
+		let selections = bri.selections.as_mut().expect("The routing info has not been initialized");
+		opportunistic_update(selections,&self.statistics,topology,current_router,current_port,target_router);
+	}
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		self.groups = Some(GroupStructure::new(topology));
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		if self.enabled_statistics { Some(self.statistics.borrow().configuration_value()) } else { None }
+	}
+	fn reset_statistics(&mut self, _next_cycle:Time)
+	{
+		*self.statistics.borrow_mut() = MisroutingStatistics::default();
+	}
+}
+
+impl OLM
+{
+	pub fn new(arg: RoutingBuilderArgument) -> OLM
+	{
+		let mut threshold = 0;
+		let mut enabled_statistics = false;
+		match_object_panic!(arg.cv,"OLM",value,
+			"threshold" => threshold=value.as_usize().expect("bad value for threshold"),
+			"enable_statistics" => enabled_statistics=value.as_bool().expect("bad value for enable_statistics"),
+		);
+		OLM{
+			groups: None,
+			threshold,
+			enabled_statistics,
+			statistics: RefCell::new(MisroutingStatistics::default()),
+		}
+	}
+}
+
+///Contention-counter based misrouting.
+///Each router counts, for each of its ports, the packets it holds whose minimal route continues through that port.
+///A packet is misrouted when the counters of all its minimal ports exceed `threshold`, through the ports whose counter does not exceed it.
+///It may take a global misroute from any router of its source group and a local misroute in each group.
+#[derive(Debug)]
+pub struct ContentionCounters
+{
+	///The groups of the topology, built in `initialize`.
+	groups: Option<GroupStructure>,
+	///The contention a minimal port may have without misrouting.
+	threshold: usize,
+	///`counters[router][port]` is the number of packets in `router` whose minimal route continues through `port`.
+	counters: RefCell<Vec<Vec<usize>>>,
+	///Whether to include the misrouting in the results.
+	enabled_statistics: bool,
+	statistics: RefCell<MisroutingStatistics>,
+}
+
+//Besides the opportunistic selections, ContentionCounters appends the router in which the packet is being counted and the counted ports.
+impl Routing for ContentionCounters
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			return Ok(ejection_candidates(topology,current_router,target_server,num_virtual_channels));
+		}
+		let groups = self.groups.as_ref().expect("ContentionCounters has not been initialized");
+		let selections = routing_info.selections.as_ref().expect("The routing info has not been initialized");
+		let (minimal,nonminimal) = opportunistic_ports(groups,topology,selections,current_router,target_router);
+		let counters = self.counters.borrow();
+		let contention = minimal.iter().map(|&port|counters[current_router][port]).min().expect("no minimal port");
+		let ports = if contention > self.threshold
+		{
+			let misroute : Vec<usize> = nonminimal.into_iter().filter(|&port|counters[current_router][port]<=self.threshold).collect();
+			if misroute.is_empty() { minimal } else { misroute }
+		}
+		else
+		{
+			minimal
+		};
+		//The counters change as the packets move.
+		Ok(RoutingNextCandidates{candidates:port_candidates(&ports,num_virtual_channels),idempotent:false})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		let mut selections = opportunistic_selections();
+		self.count(&mut selections,topology,current_router,target_router);
+		routing_info.borrow_mut().selections = Some(selections);
+		self.statistics.borrow_mut().packets+=1;
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		let mut bri = routing_info.borrow_mut();
+		let selections = bri.selections.as_mut().expect("The routing info has not been initialized");
+		if selections.len()>2
+		{
+			let router = selections[2] as usize;
+			let mut counters = self.counters.borrow_mut();
+			for &port in selections[3..].iter()
+			{
+				counters[router][port as usize]-=1;
+			}
+		}
+		opportunistic_update(selections,&self.statistics,topology,current_router,current_port,target_router);
+		self.count(selections,topology,current_router,target_router);
+	}
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		self.groups = Some(GroupStructure::new(topology));
+		*self.counters.borrow_mut() = (0..topology.num_routers()).map(|router|vec![0;topology.ports(router)]).collect();
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		if self.enabled_statistics { Some(self.statistics.borrow().configuration_value()) } else { None }
+	}
+	fn reset_statistics(&mut self, _next_cycle:Time)
+	{
+		*self.statistics.borrow_mut() = MisroutingStatistics::default();
+	}
+}
+
+impl ContentionCounters
+{
+	pub fn new(arg: RoutingBuilderArgument) -> ContentionCounters
+	{
+		let mut threshold = None;
+		let mut enabled_statistics = false;
+		match_object_panic!(arg.cv,"ContentionCounters",value,
+			"threshold" => threshold=Some(value.as_usize().expect("bad value for threshold")),
+			"enable_statistics" => enabled_statistics=value.as_bool().expect("bad value for enable_statistics"),
+		);
+		let threshold = threshold.expect("There were no threshold");
+		ContentionCounters{
+			groups: None,
+			threshold,
+			counters: RefCell::new(vec![]),
+			enabled_statistics,
+			statistics: RefCell::new(MisroutingStatistics::default()),
+		}
+	}
+	///Count the packet in the minimal ports of `current_router`, recording them into the selections.
+	fn count(&self, selections:&mut Vec<i32>, topology:&dyn Topology, current_router:usize, target_router:usize)
+	{
+		selections.truncate(2);
+		if current_router==target_router
+		{
+			return;
+		}
+		let groups = self.groups.as_ref().expect("ContentionCounters has not been initialized");
+		let minimal = groups.minimal_ports(topology,current_router,target_router);
+		let mut counters = self.counters.borrow_mut();
+		selections.push(current_router as i32);
+		for port in minimal
+		{
+			counters[current_router][port]+=1;
+			selections.push(port as i32);
+		}
+	}
+}
+
+///The saturation of the global links of each group, refreshed periodically as a generic event.
+///A global link is saturated when its occupation is greater than `saturation_factor` times the average occupation of the global links of its group.
+#[derive(Debug)]
+pub struct GlobalLinkBroadcast
+{
+	self_rc: Weak<RefCell<GlobalLinkBroadcast>>,
+	///Cycles between broadcasts.
+	period: Time,
+	saturation_factor: f64,
+	///`global_links[group]` are the `(router,port)` of the global links leaving `group`.
+	global_links: Vec<Vec<(usize,usize)>>,
+	///`saturated[router][port]` as of the last broadcast.
+	saturated: Vec<Vec<bool>>,
+}
+
+impl GlobalLinkBroadcast
+{
+	pub fn new(topology:&dyn Topology, groups:&GroupStructure, period:Time, saturation_factor:f64) -> Rc<RefCell<GlobalLinkBroadcast>>
+	{
+		let global_links = groups.global_links.iter().map(|links|links.iter().map(|&(router,port,_)|(router,port)).collect()).collect();
+		let saturated = (0..topology.num_routers()).map(|router|vec![false;topology.ports(router)]).collect();
+		let broadcast = Rc::new(RefCell::new(GlobalLinkBroadcast{
+			self_rc: Weak::new(),
+			period,
+			saturation_factor,
+			global_links,
+			saturated,
+		}));
+		broadcast.borrow_mut().self_rc = Rc::downgrade(&broadcast);
+		broadcast
+	}
+	pub fn is_saturated(&self, router:usize, port:usize) -> bool
+	{
+		self.saturated[router][port]
+	}
+}
+
+impl Eventful for GlobalLinkBroadcast
+{
+	fn process(&mut self, simulation:&SimulationShared, _simulation_mut:&mut SimulationMut) -> Vec<EventGeneration>
+	{
+		for links in self.global_links.iter()
+		{
+			if links.is_empty()
+			{
+				continue;
+			}
+			let occupations : Vec<usize> = links.iter().map(|&(router,port)|occupied_neighbour_space(&*simulation.network.routers[router].borrow(),port)).collect();
+			let average = occupations.iter().sum::<usize>() as f64 / links.len() as f64;
+			for (&(router,port),&occupation) in links.iter().zip(occupations.iter())
+			{
+				self.saturated[router][port] = occupation as f64 > self.saturation_factor*average;
+			}
+		}
+		self.schedule(simulation.cycle,self.period).into_iter().collect()
+	}
+	fn as_eventful(&self)->Weak<RefCell<dyn Eventful>>
+	{
+		self.self_rc.clone()
+	}
+}
+
+///Value of the selection while PiggyBack has not decided the route.
+const PIGGYBACK_UNDECIDED: i32 = -2;
+
+///PiggyBack routing.
+///The routers of each group receive periodically which of the global links of the group are saturated.
+///At the first hop the packet is sent through a random intermediate group when all the minimal global links are saturated, or when the local occupation of the minimal ports is greater than twice the occupation of the ports towards the intermediate group plus `threshold`.
+///The intermediate group is not taken when all its global links from the source group are saturated.
+#[derive(Debug)]
+pub struct PiggyBack
+{
+	///The groups of the topology, built in `initialize`.
+	groups: Option<GroupStructure>,
+	///The broadcast of the saturated links, built in `initialize`.
+	broadcast: Option<Rc<RefCell<GlobalLinkBroadcast>>>,
+	///Cycles between broadcasts.
+	broadcast_period: Time,
+	saturation_factor: f64,
+	///Added to the cost of the nonminimal route before comparing.
+	threshold: usize,
+	///Whether to include the misrouting in the results.
+	enabled_statistics: bool,
+	statistics: RefCell<MisroutingStatistics>,
+}
+
+//The selections of the routing info store `[intermediate_group]`, with -1 for none and PIGGYBACK_UNDECIDED before the decision.
+//The intermediate group is set to -1 once the packet reaches it.
+impl Routing for PiggyBack
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			return Ok(ejection_candidates(topology,current_router,target_server,num_virtual_channels));
+		}
+		let groups = self.groups.as_ref().expect("PiggyBack has not been initialized");
+		//Without a decision, as when called without congestion information, go minimally.
+		let intermediate = routing_info.selections.as_ref().map(|s|s[0]).unwrap_or(-1);
+		let ports = if intermediate>=0
+		{
+			groups.ports_towards_group(topology,current_router,intermediate as usize)
+		}
+		else
+		{
+			groups.minimal_ports(topology,current_router,target_router)
+		};
+		Ok(RoutingNextCandidates{candidates:port_candidates(&ports,num_virtual_channels),idempotent:true})
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let undecided = routing_info.borrow().selections.as_ref().map(|s|s[0]==PIGGYBACK_UNDECIDED).unwrap_or(false);
+		if undecided
+		{
+			let selection = self.decide(topology,current_router,target_router,congestion,rng);
+			routing_info.borrow_mut().selections = Some(vec![selection]);
+		}
+		self.next(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, current_router:usize, target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		let groups = self.groups.as_ref().expect("PiggyBack has not been initialized");
+		let selection = if groups.group(current_router)==groups.group(target_router) { -1 } else { PIGGYBACK_UNDECIDED };
+		routing_info.borrow_mut().selections = Some(vec![selection]);
+		self.statistics.borrow_mut().packets+=1;
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		let groups = self.groups.as_ref().expect("PiggyBack has not been initialized");
+		//Both the global hop into the intermediate group and the local hops towards it are misroutes.
+		self.statistics.borrow_mut().track_hop(topology,current_router,current_port,target_router);
+		let mut bri = routing_info.borrow_mut();
+		let selections = bri.selections.as_mut().expect("The routing info has not been initialized");
+		if selections[0]==groups.group(current_router) as i32 || selections[0]==PIGGYBACK_UNDECIDED
+		{
+			selections[0] = -1;
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		let groups = GroupStructure::new(topology);
+		self.broadcast = Some(GlobalLinkBroadcast::new(topology,&groups,self.broadcast_period,self.saturation_factor));
+		self.groups = Some(groups);
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		let broadcast = self.broadcast.as_ref().expect("PiggyBack has not been initialized");
+		let event = broadcast.borrow_mut().schedule(0,0);
+		event.into_iter().collect()
+	}
+	fn statistics(&self, _cycle:Time) -> Option<ConfigurationValue>
+	{
+		if self.enabled_statistics { Some(self.statistics.borrow().configuration_value()) } else { None }
+	}
+	fn reset_statistics(&mut self, _next_cycle:Time)
+	{
+		*self.statistics.borrow_mut() = MisroutingStatistics::default();
+	}
+}
+
+impl PiggyBack
+{
+	pub fn new(arg: RoutingBuilderArgument) -> PiggyBack
+	{
+		let mut broadcast_period = 10;
+		let mut saturation_factor = 2.0;
+		let mut threshold = 0;
+		let mut enabled_statistics = false;
+		match_object_panic!(arg.cv,"PiggyBack",value,
+			"broadcast_period" => broadcast_period=value.as_time().expect("bad value for broadcast_period"),
+			"saturation_factor" => saturation_factor=value.as_f64().expect("bad value for saturation_factor"),
+			"threshold" => threshold=value.as_usize().expect("bad value for threshold"),
+			"enable_statistics" => enabled_statistics=value.as_bool().expect("bad value for enable_statistics"),
+		);
+		if broadcast_period==0
+		{
+			panic!("The broadcast_period of PiggyBack must be at least 1");
+		}
+		PiggyBack{
+			groups: None,
+			broadcast: None,
+			broadcast_period,
+			saturation_factor,
+			threshold,
+			enabled_statistics,
+			statistics: RefCell::new(MisroutingStatistics::default()),
+		}
+	}
+	///Choose between the minimal route and a random intermediate group. Returns the intermediate group, or -1 for the minimal route.
+	fn decide(&self, topology:&dyn Topology, current_router:usize, target_router:usize, congestion:&CongestionInfo, rng:&mut StdRng) -> i32
+	{
+		let groups = self.groups.as_ref().expect("PiggyBack has not been initialized");
+		let source_group = groups.group(current_router);
+		let target_group = groups.group(target_router);
+		if groups.number_of_groups()<=2
+		{
+			return -1;
+		}
+		let mut intermediate = rng.gen_range(0..groups.number_of_groups()-2);
+		for excluded in [source_group.min(target_group),source_group.max(target_group)]
+		{
+			if intermediate>=excluded
+			{
+				intermediate+=1;
+			}
+		}
+		let broadcast = self.broadcast.as_ref().expect("PiggyBack has not been initialized").borrow();
+		let all_saturated = |group:usize| groups.links_between(source_group,group).all(|(router,port)|broadcast.is_saturated(router,port));
+		if all_saturated(intermediate)
+		{
+			return -1;
+		}
+		let occupation = |ports:Vec<usize>| ports.into_iter().map(|port|congestion.port_occupation(port)).min().expect("no port towards the goal");
+		let minimal_occupation = occupation(groups.minimal_ports(topology,current_router,target_router));
+		let nonminimal_occupation = occupation(groups.ports_towards_group(topology,current_router,intermediate));
+		if all_saturated(target_group) || minimal_occupation > 2*nonminimal_occupation + self.threshold
+		{
+			intermediate as i32
+		}
+		else
+		{
+			-1
+		}
+	}
+}
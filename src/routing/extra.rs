@@ -5,16 +5,23 @@ Extra implementations of routing operations
 * Sum (struct SumRouting)
 * Stubborn
 * EachLengthSourceAdaptiveRouting
+* FlowHash
+* Duato
 
 */
 
 use std::cell::RefCell;
+use std::rc::Rc;
 use std::convert::TryFrom;
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash,Hasher};
 
 use ::rand::{rngs::StdRng,Rng};
 
 use crate::match_object_panic;
 use crate::config_parser::ConfigurationValue;
+use crate::NetworkState;
 use crate::routing::*;
 use crate::topology::{Topology,Location};
 
@@ -85,86 +92,15 @@ impl Routing for SumRouting
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
-		//let (target_location,_link_class)=topology.server_neighbour(target_server);
-		//let target_router=match target_location
-		//{
-		//	Location::RouterPort{router_index,router_port:_} =>router_index,
-		//	_ => panic!("The server is not attached to a router"),
-		//};
-		let distance=topology.distance(current_router,target_router);
-		if distance==0
-		{
-			let target_server = target_server.expect("target server was not given.");
-			for i in 0..topology.ports(current_router)
-			{
-				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
-				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
-				{
-					if server==target_server
-					{
-						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
-						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
-						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
-					}
-				}
-			}
-			unreachable!();
-		}
-		let meta=routing_info.meta.as_ref().unwrap();
-		let r = match routing_info.selections
-		{
-			None =>
-			{
-				unreachable!();
-			}
-			Some(ref s) =>
-			{
-				//let both = if let &SumRoutingPolicy::TryBoth=&self.policy { routing_info.hops==0 } else { false };
-				//if both
-				if s.len()>=2
-				{
-					//let avc0=&self.first_allowed_virtual_channels;
-					let avc0=&self.allowed_virtual_channels[0];
-					//let el0=self.first_extra_label;
-					let el0=self.extra_label[0];
-					//let r0=self.first_routing.next(&meta[0].borrow(),topology,current_router,target_server,avc0.len(),rng).into_iter().map( |candidate| CandidateEgress{virtual_channel:avc0[candidate.virtual_channel],label:candidate.label+el0,annotation:Some(RoutingAnnotation{values:vec![0],meta:vec![candidate.annotation]}),..candidate} );
-					let r0=self.routing[0].next(&meta[0].borrow(),topology,current_router,target_router,target_server,avc0.len(),rng)?.into_iter().map( |candidate| CandidateEgress{virtual_channel:avc0[candidate.virtual_channel],label:candidate.label+el0,annotation:Some(RoutingAnnotation{values:vec![0],meta:vec![candidate.annotation]}),..candidate} );
-					//let avc1=&self.second_allowed_virtual_channels;
-					let avc1=&self.allowed_virtual_channels[1];
-					//let el1=self.second_extra_label;
-					let el1=self.extra_label[1];
-					//let r1=self.second_routing.next(&meta[1].borrow(),topology,current_router,target_server,avc1.len(),rng).into_iter().map( |candidate| CandidateEgress{virtual_channel:avc1[candidate.virtual_channel],label:candidate.label+el1,annotation:Some(RoutingAnnotation{values:vec![1],meta:vec![candidate.annotation]}),..candidate} );
-					let r1=self.routing[1].next(&meta[1].borrow(),topology,current_router,target_router,target_server,avc1.len(),rng)?.into_iter().map( |candidate| CandidateEgress{virtual_channel:avc1[candidate.virtual_channel],label:candidate.label+el1,annotation:Some(RoutingAnnotation{values:vec![1],meta:vec![candidate.annotation]}),..candidate} );
-					match self.policy
-					{
-						SumRoutingPolicy::SecondWhenFirstEmpty =>
-						{
-							let r : Vec<_> =r0.collect();
-							if r.is_empty() { r1.collect() } else { r }
-						}
-						_ => r0.chain(r1).collect()
-					}
-				}
-				else
-				{
-					let index=s[0] as usize;
-					//let routing=if s[0]==0 { &self.first_routing } else { &self.second_routing };
-					let routing = &self.routing[index];
-					//let allowed_virtual_channels=if s[0]==0 { &self.first_allowed_virtual_channels } else { &self.second_allowed_virtual_channels };
-					let allowed_virtual_channels = &self.allowed_virtual_channels[index];
-					//let extra_label = if s[0]==0 { self.first_extra_label } else { self.second_extra_label };
-					let extra_label = self.extra_label[index];
-					let r=routing.next(&meta[index].borrow(),topology,current_router,target_router,target_server,allowed_virtual_channels.len(),rng)?;
-					//r.into_iter().map( |(x,c)| (x,allowed_virtual_channels[c]) ).collect()
-					r.into_iter()
-					//.map( |candidate| CandidateEgress{virtual_channel:allowed_virtual_channels[candidate.virtual_channel],label:candidate.label+extra_label,..candidate} ).collect()
-					// We need to keep the annotation to have a coherent state able to relay the annotation of the subrouting.
-					.map( |candidate| CandidateEgress{virtual_channel:allowed_virtual_channels[candidate.virtual_channel],label:candidate.label+extra_label,annotation:Some(RoutingAnnotation{values:vec![s[0]],meta:vec![candidate.annotation]}),..candidate} ).collect()
-				}
-			}
-		};
-		//FIXME: we can recover idempotence in some cases.
-		Ok(RoutingNextCandidates{candidates:r,idempotent:false})
+		self.next_with_base(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,num_virtual_channels|
+			routing.next(&info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)
+		)
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_base(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,num_virtual_channels|
+			routing.next_with_congestion(info,topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)
+		)
 	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
 	{
@@ -259,6 +195,18 @@ impl Routing for SumRouting
 		self.routing[0].initialize(topology,rng);
 		self.routing[1].initialize(topology,rng);
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		let first = self.routing[0].set_network_state(state);
+		let second = self.routing[1].set_network_state(state);
+		first || second
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		let mut events = self.routing[0].initial_events();
+		events.extend(self.routing[1].initial_events());
+		events
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		use sum_routing_internal::{SumRoutingSelection,SumRoutingCase::*};
@@ -329,6 +277,92 @@ impl Routing for SumRouting
 
 impl SumRouting
 {
+	///The candidates of `next`, obtaining those of each routing with `base_next(routing,routing_info,num_virtual_channels)`.
+	#[allow(clippy::too_many_arguments)]
+	fn next_with_base<F>(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, mut base_next:F) -> Result<RoutingNextCandidates,Error>
+		where F: FnMut(&dyn Routing,&RefCell<RoutingInfo>,usize) -> Result<RoutingNextCandidates,Error>
+	{
+		//let (target_location,_link_class)=topology.server_neighbour(target_server);
+		//let target_router=match target_location
+		//{
+		//	Location::RouterPort{router_index,router_port:_} =>router_index,
+		//	_ => panic!("The server is not attached to a router"),
+		//};
+		let distance=topology.distance(current_router,target_router);
+		if distance==0
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
+						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let meta=routing_info.meta.as_ref().unwrap();
+		let r = match routing_info.selections
+		{
+			None =>
+			{
+				unreachable!();
+			}
+			Some(ref s) =>
+			{
+				//let both = if let &SumRoutingPolicy::TryBoth=&self.policy { routing_info.hops==0 } else { false };
+				//if both
+				if s.len()>=2
+				{
+					//let avc0=&self.first_allowed_virtual_channels;
+					let avc0=&self.allowed_virtual_channels[0];
+					//let el0=self.first_extra_label;
+					let el0=self.extra_label[0];
+					//let r0=self.first_routing.next(&meta[0].borrow(),topology,current_router,target_server,avc0.len(),rng).into_iter().map( |candidate| CandidateEgress{virtual_channel:avc0[candidate.virtual_channel],label:candidate.label+el0,annotation:Some(RoutingAnnotation{values:vec![0],meta:vec![candidate.annotation]}),..candidate} );
+					let r0=base_next(&*self.routing[0],&meta[0],avc0.len())?.into_iter().map( |candidate| CandidateEgress{virtual_channel:avc0[candidate.virtual_channel],label:candidate.label+el0,annotation:Some(RoutingAnnotation{values:vec![0],meta:vec![candidate.annotation]}),..candidate} );
+					//let avc1=&self.second_allowed_virtual_channels;
+					let avc1=&self.allowed_virtual_channels[1];
+					//let el1=self.second_extra_label;
+					let el1=self.extra_label[1];
+					//let r1=self.second_routing.next(&meta[1].borrow(),topology,current_router,target_server,avc1.len(),rng).into_iter().map( |candidate| CandidateEgress{virtual_channel:avc1[candidate.virtual_channel],label:candidate.label+el1,annotation:Some(RoutingAnnotation{values:vec![1],meta:vec![candidate.annotation]}),..candidate} );
+					let r1=base_next(&*self.routing[1],&meta[1],avc1.len())?.into_iter().map( |candidate| CandidateEgress{virtual_channel:avc1[candidate.virtual_channel],label:candidate.label+el1,annotation:Some(RoutingAnnotation{values:vec![1],meta:vec![candidate.annotation]}),..candidate} );
+					match self.policy
+					{
+						SumRoutingPolicy::SecondWhenFirstEmpty =>
+						{
+							let r : Vec<_> =r0.collect();
+							if r.is_empty() { r1.collect() } else { r }
+						}
+						_ => r0.chain(r1).collect()
+					}
+				}
+				else
+				{
+					let index=s[0] as usize;
+					//let routing=if s[0]==0 { &self.first_routing } else { &self.second_routing };
+					let routing = &self.routing[index];
+					//let allowed_virtual_channels=if s[0]==0 { &self.first_allowed_virtual_channels } else { &self.second_allowed_virtual_channels };
+					let allowed_virtual_channels = &self.allowed_virtual_channels[index];
+					//let extra_label = if s[0]==0 { self.first_extra_label } else { self.second_extra_label };
+					let extra_label = self.extra_label[index];
+					let r=base_next(&**routing,&meta[index],allowed_virtual_channels.len())?;
+					//r.into_iter().map( |(x,c)| (x,allowed_virtual_channels[c]) ).collect()
+					r.into_iter()
+					//.map( |candidate| CandidateEgress{virtual_channel:allowed_virtual_channels[candidate.virtual_channel],label:candidate.label+extra_label,..candidate} ).collect()
+					// We need to keep the annotation to have a coherent state able to relay the annotation of the subrouting.
+					.map( |candidate| CandidateEgress{virtual_channel:allowed_virtual_channels[candidate.virtual_channel],label:candidate.label+extra_label,annotation:Some(RoutingAnnotation{values:vec![s[0]],meta:vec![candidate.annotation]}),..candidate} ).collect()
+				}
+			}
+		};
+		//FIXME: we can recover idempotence in some cases.
+		Ok(RoutingNextCandidates{candidates:r,idempotent:false})
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> SumRouting
 	{
 		let mut policy=None;
@@ -470,9 +504,20 @@ impl Routing for Stubborn
 		//return self.routing.next(&routing_info.meta.as_ref().unwrap()[0].borrow(),topology,current_router,target_server,num_virtual_channels,rng).into_iter().map(|candidate|CandidateEgress{annotation:Some(RoutingAnnotation{values:vec![candidate.label],meta:vec![candidate.annotation]}),..candidate}).collect()
 		return Ok(RoutingNextCandidates{candidates:self.routing.next(&routing_info.meta.as_ref().unwrap()[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?.into_iter().map(|candidate|CandidateEgress{annotation:Some(RoutingAnnotation{values:vec![candidate.label],meta:vec![candidate.annotation]}),..candidate}).collect(),idempotent:false})
 	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let info = routing_info.borrow();
+		if target_router==current_router || info.selections.is_some()
+		{
+			//Either towards the server or repeating the stored request.
+			return self.next(&info,topology,current_router,target_router,target_server,num_virtual_channels,rng);
+		}
+		let candidates = self.routing.next_with_congestion(&info.meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)?;
+		Ok(RoutingNextCandidates{candidates:candidates.into_iter().map(|candidate|CandidateEgress{annotation:Some(RoutingAnnotation{values:vec![candidate.label],meta:vec![candidate.annotation]}),..candidate}).collect(),idempotent:false})
+	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
 	{
-		let meta_routing_info=RefCell::new(RoutingInfo::new());
+		let meta_routing_info=RefCell::new(RoutingInfo{source_server:routing_info.borrow().source_server,..RoutingInfo::new()});
 		self.routing.initialize_routing_info(&meta_routing_info, topology, current_router, target_router, target_server, rng);
 		routing_info.borrow_mut().meta = Some(vec![meta_routing_info]);
 	}
@@ -486,6 +531,14 @@ impl Routing for Stubborn
 	{
 		self.routing.initialize(topology,rng);
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		self.routing.set_network_state(state)
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		self.routing.initial_events()
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		let &CandidateEgress{port,virtual_channel,ref annotation,..} = requested;
@@ -530,6 +583,21 @@ pub struct EachLengthSourceAdaptiveRouting
 	pub routing: Box<dyn InstantiableSourceRouting>,
 }
 
+impl EachLengthSourceAdaptiveRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> EachLengthSourceAdaptiveRouting
+	{
+		let mut routing = None;
+		match_object_panic!(arg.cv,"EachLengthSourceAdaptiveRouting",value,
+			"paths" => routing=Some(new_source_routing(RoutingBuilderArgument{cv:value,..arg})),
+		);
+		let routing = routing.expect("There were no paths");
+		EachLengthSourceAdaptiveRouting{
+			routing,
+		}
+	}
+}
+
 impl Routing for EachLengthSourceAdaptiveRouting
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
@@ -661,3 +729,534 @@ impl Routing for EachLengthSourceAdaptiveRouting
 	}
 }
 
+
+
+///The state of a flow in `FlowHash`.
+#[derive(Debug,Default)]
+struct FlowHashFlow
+{
+	///The sequence number for the next packet injected.
+	next_sequence: i32,
+	///The greatest sequence number of the packets that have arrived to the destination router.
+	highest_arrived: Option<i32>,
+	///The index of the current flowlet.
+	flowlet: i32,
+	///The cycle in which the last packet of the flow was routed at its source router.
+	last_cycle: Option<Time>,
+}
+
+///Statistics of `FlowHash`.
+#[derive(Debug,Default)]
+struct FlowHashStatistics
+{
+	packets: i64,
+	flowlets: i64,
+	out_of_order_packets: i64,
+	///Sum over the out of order packets of how many sequence numbers behind the greatest arrived they were.
+	total_reorder_distance: i64,
+	maximum_reorder_distance: i64,
+}
+
+///Pins each flow to a single port among the candidates of the wrapped routing, as in ECMP.
+///A flow is the pair of source and destination servers. With `flowlet_gap`, a new flowlet of the flow begins when its packets are routed at the source router after more than `flowlet_gap` idle cycles, and each flowlet may take different ports.
+///At each hop the port is selected among the ports of the candidates, in increasing order, by a hash of the flow, the flowlet, a random salt, and the hop number.
+///The packets of each flow are numbered at injection, and those reaching the destination router after a later packet of its flow are counted as out of order.
+///Stores `[hash, sequence, flowlet_pending, arrived, source, destination]` into the selections and the routing info of the wrapped routing in the meta.
+#[derive(Debug)]
+pub struct FlowHash
+{
+	routing: Box<dyn Routing>,
+	flowlet_gap: Option<Time>,
+	///Random value included in the hashes, set at initialization.
+	salt: u64,
+	///The state of each flow, indexed by `(source,destination)`.
+	flows: RefCell<BTreeMap<(usize,usize),FlowHashFlow>>,
+	///Whether to include the reordering in the results.
+	enabled_statistics: bool,
+	statistics: RefCell<FlowHashStatistics>,
+}
+
+impl Routing for FlowHash
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let candidates = self.routing.next(&routing_info.meta.as_ref().expect("The routing info has not been initialized")[0].borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
+		Ok(self.pin(candidates,routing_info))
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let pending = routing_info.borrow().selections.as_ref().map(|s|s[2]==1).unwrap_or(false);
+		if pending
+		{
+			let mut bri = routing_info.borrow_mut();
+			let selections = bri.selections.as_mut().unwrap();
+			let key = (selections[4] as usize,selections[5] as usize);
+			let mut flows = self.flows.borrow_mut();
+			let flow = flows.get_mut(&key).expect("missing flow");
+			let gap = self.flowlet_gap.expect("pending flowlet without gap");
+			if let Some(last_cycle) = flow.last_cycle
+			{
+				if congestion.current_cycle > last_cycle + gap
+				{
+					flow.flowlet += 1;
+					self.statistics.borrow_mut().flowlets += 1;
+				}
+			}
+			flow.last_cycle = Some(congestion.current_cycle);
+			selections[0] = self.flow_hash(key,flow.flowlet);
+			selections[2] = 0;
+		}
+		let bri = routing_info.borrow();
+		let candidates = self.routing.next_with_congestion(&bri.meta.as_ref().expect("The routing info has not been initialized")[0],topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)?;
+		Ok(self.pin(candidates,&bri))
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let source_server = routing_info.borrow().source_server;
+		let meta_routing_info=RefCell::new(RoutingInfo{source_server,..RoutingInfo::new()});
+		self.routing.initialize_routing_info(&meta_routing_info, topology, current_router, target_router, target_server, rng);
+		let key = (source_server.unwrap_or(current_router),target_server.unwrap_or(target_router));
+		let mut flows = self.flows.borrow_mut();
+		let is_new = !flows.contains_key(&key);
+		let flow = flows.entry(key).or_default();
+		let sequence = flow.next_sequence;
+		flow.next_sequence += 1;
+		let hash = self.flow_hash(key,flow.flowlet);
+		let pending = if self.flowlet_gap.is_some() {1} else {0};
+		{
+			let mut statistics = self.statistics.borrow_mut();
+			statistics.packets += 1;
+			if is_new
+			{
+				statistics.flowlets += 1;
+			}
+		}
+		drop(flows);
+		let mut bri = routing_info.borrow_mut();
+		bri.meta = Some(vec![meta_routing_info]);
+		bri.selections = Some(vec![hash,sequence,pending,0,key.0 as i32,key.1 as i32]);
+		if current_router==target_router
+		{
+			self.arrive(bri.selections.as_mut().unwrap());
+		}
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let mut bri = routing_info.borrow_mut();
+		let hops = bri.hops;
+		{
+			let meta = &bri.meta.as_ref().expect("The routing info has not been initialized")[0];
+			meta.borrow_mut().hops = hops;
+			self.routing.update_routing_info(meta,topology,current_router,current_port,target_router,target_server,rng);
+		}
+		if current_router==target_router
+		{
+			self.arrive(bri.selections.as_mut().expect("The routing info has not been initialized"));
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.initialize(topology,rng);
+		self.salt = rng.gen();
+	}
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
+	{
+		self.routing.performed_request(requested,&routing_info.borrow().meta.as_ref().unwrap()[0],topology,current_router,target_router,target_server,num_virtual_channels,rng);
+	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		self.routing.set_network_state(state)
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		self.routing.initial_events()
+	}
+	fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+	{
+		if !self.enabled_statistics
+		{
+			return self.routing.statistics(cycle);
+		}
+		let statistics = self.statistics.borrow();
+		let average_reorder_distance = if statistics.out_of_order_packets>0 { statistics.total_reorder_distance as f64 / statistics.out_of_order_packets as f64 } else { 0.0 };
+		let mut content = vec![
+			(String::from("packets"),ConfigurationValue::Number(statistics.packets as f64)),
+			(String::from("flowlets"),ConfigurationValue::Number(statistics.flowlets as f64)),
+			(String::from("out_of_order_packets"),ConfigurationValue::Number(statistics.out_of_order_packets as f64)),
+			(String::from("average_reorder_distance"),ConfigurationValue::Number(average_reorder_distance)),
+			(String::from("maximum_reorder_distance"),ConfigurationValue::Number(statistics.maximum_reorder_distance as f64)),
+		];
+		if let Some(inner) = self.routing.statistics(cycle)
+		{
+			content.push((String::from("routing"),inner));
+		}
+		Some(ConfigurationValue::Object(String::from("FlowHashStatistics"),content))
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		*self.statistics.borrow_mut() = FlowHashStatistics::default();
+		self.routing.reset_statistics(next_cycle);
+	}
+}
+
+impl FlowHash
+{
+	pub fn new(arg: RoutingBuilderArgument) -> FlowHash
+	{
+		let mut routing = None;
+		let mut flowlet_gap = None;
+		let mut enabled_statistics = false;
+		match_object_panic!(arg.cv,"FlowHash",value,
+			"routing" => routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+			"flowlet_gap" => flowlet_gap=Some(value.as_time().expect("bad value for flowlet_gap")),
+			"enable_statistics" => enabled_statistics=value.as_bool().expect("bad value for enable_statistics"),
+		);
+		let routing = routing.expect("There were no routing");
+		FlowHash{
+			routing,
+			flowlet_gap,
+			salt: 0,
+			flows: RefCell::new(BTreeMap::new()),
+			enabled_statistics,
+			statistics: RefCell::new(FlowHashStatistics::default()),
+		}
+	}
+	///The hash of a flowlet, as a nonnegative `i32`.
+	fn flow_hash(&self, key:(usize,usize), flowlet:i32) -> i32
+	{
+		let mut hasher = DefaultHasher::new();
+		(self.salt,key,flowlet).hash(&mut hasher);
+		(hasher.finish()>>33) as i32
+	}
+	///Keep the candidates of the port selected by the hash.
+	fn pin(&self, candidates:RoutingNextCandidates, routing_info:&RoutingInfo) -> RoutingNextCandidates
+	{
+		let idempotent = candidates.idempotent;
+		let mut ports : Vec<usize> = candidates.candidates.iter().map(|candidate|candidate.port).collect();
+		ports.sort_unstable();
+		ports.dedup();
+		if ports.len()<=1
+		{
+			return candidates;
+		}
+		let hash = routing_info.selections.as_ref().expect("The routing info has not been initialized")[0];
+		let mut hasher = DefaultHasher::new();
+		(hash,routing_info.hops).hash(&mut hasher);
+		let port = ports[(hasher.finish()%ports.len() as u64) as usize];
+		let candidates = candidates.into_iter().filter(|candidate|candidate.port==port).collect();
+		RoutingNextCandidates{candidates,idempotent}
+	}
+	///Account the arrival of the packet to its destination router.
+	fn arrive(&self, selections:&mut [i32])
+	{
+		if selections[3]==1
+		{
+			return;
+		}
+		selections[3] = 1;
+		let key = (selections[4] as usize,selections[5] as usize);
+		let sequence = selections[1];
+		let mut flows = self.flows.borrow_mut();
+		let flow = flows.get_mut(&key).expect("missing flow");
+		match flow.highest_arrived
+		{
+			Some(highest) if highest > sequence =>
+			{
+				let distance = (highest - sequence) as i64;
+				let mut statistics = self.statistics.borrow_mut();
+				statistics.out_of_order_packets += 1;
+				statistics.total_reorder_distance += distance;
+				statistics.maximum_reorder_distance = statistics.maximum_reorder_distance.max(distance);
+			},
+			_ => flow.highest_arrived = Some(sequence),
+		}
+	}
+}
+
+///Duato's escape channels construction. Combines a fully adaptive routing with a deadlock-free escape routing, such as `DOR` or `UpDown`.
+///The virtual channels in `escape_virtual_channels` are reserved to the escape routing and those in `adaptive_virtual_channels` to the adaptive routing.
+///At every hop the candidates of both routings are offered, the escape ones being computed from the current router towards the target.
+///After a hop through an escape channel the packet may return to the adaptive channels if `return_to_adaptive` is set; otherwise it remains in the escape channels until its destination.
+///Stores `[mode, requested, last]` into the selections, with `mode` 1 when only the escape routing is allowed, and the routing infos of the adaptive and escape routings in the meta.
+///The escape routing is checked at `initialize` to connect every router to every router with servers.
+#[derive(Debug)]
+pub struct Duato
+{
+	///The adaptive and the escape routings.
+	routing: [Box<dyn Routing>;2],
+	allowed_virtual_channels: [Vec<usize>;2],
+	extra_label: [i32;2],
+	return_to_adaptive: bool,
+	check_escape: bool,
+	enabled_statistics: bool,
+	///The hops given by each routing.
+	tracked_hops: RefCell<[i64;2]>,
+	///The number of adaptive hops following an escape hop.
+	tracked_returns: RefCell<i64>,
+}
+
+impl Routing for Duato
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.candidates(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,None,rng)
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.candidates(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,Some(congestion),rng)
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let mut bri=routing_info.borrow_mut();
+		let meta : Vec<_> = self.routing.iter().map(|routing|{
+			let sub_info = RefCell::new(RoutingInfo{source_server:bri.source_server,..RoutingInfo::new()});
+			routing.initialize_routing_info(&sub_info,topology,current_router,target_router,target_server,rng);
+			sub_info
+		}).collect();
+		bri.meta=Some(meta);
+		bri.selections=Some(vec![0,-1,-1]);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let mut bri=routing_info.borrow_mut();
+		let (mode,requested,last) = match bri.selections
+		{
+			Some(ref s) => (s[0],s[1],s[2]),
+			None => panic!("The routing info has not been initialized"),
+		};
+		if requested<0
+		{
+			panic!("Duato routing was not informed of the request made at the previous router");
+		}
+		let kind = requested as usize;
+		if self.enabled_statistics
+		{
+			self.tracked_hops.borrow_mut()[kind]+=1;
+			if kind==0 && last==1
+			{
+				*self.tracked_returns.borrow_mut()+=1;
+			}
+		}
+		let source_server = bri.source_server;
+		let meta=bri.meta.as_mut().unwrap();
+		meta[kind].borrow_mut().hops+=1;
+		self.routing[kind].update_routing_info(&meta[kind],topology,current_router,current_port,target_router,target_server,rng);
+		let mode = if kind==1 && !self.return_to_adaptive { 1 } else { mode };
+		//The routing not used in this hop begins again from the current router.
+		let other = 1-kind;
+		if other==1 || mode==0
+		{
+			let sub_info = RefCell::new(RoutingInfo{source_server,..RoutingInfo::new()});
+			self.routing[other].initialize_routing_info(&sub_info,topology,current_router,target_router,target_server,rng);
+			meta[other] = sub_info;
+		}
+		bri.selections=Some(vec![mode,-1,requested]);
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing[0].initialize(topology,rng);
+		self.routing[1].initialize(topology,rng);
+		if self.check_escape
+		{
+			self.check_escape_connectivity(topology,rng);
+		}
+	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		let adaptive = self.routing[0].set_network_state(state);
+		let escape = self.routing[1].set_network_state(state);
+		adaptive || escape
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		let mut events = self.routing[0].initial_events();
+		events.extend(self.routing[1].initial_events());
+		events
+	}
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _num_virtual_channels:usize, rng:&mut StdRng)
+	{
+		if let Some(annotation) = requested.annotation.as_ref()
+		{
+			let kind = annotation.values[0];
+			let mut bri=routing_info.borrow_mut();
+			bri.selections.as_mut().expect("The routing info has not been initialized")[1] = kind;
+			let kind = kind as usize;
+			let mut sub_requested = requested.clone();
+			sub_requested.annotation = annotation.meta[0].clone();
+			let meta=bri.meta.as_ref().unwrap();
+			self.routing[kind].performed_request(&sub_requested,&meta[kind],topology,current_router,target_router,target_server,self.allowed_virtual_channels[kind].len(),rng);
+		}
+	}
+	fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+	{
+		if self.enabled_statistics {
+			let tracked_hops = self.tracked_hops.borrow();
+			let mut content = vec![
+				(String::from("adaptive_hops"),ConfigurationValue::Number(tracked_hops[0] as f64)),
+				(String::from("escape_hops"),ConfigurationValue::Number(tracked_hops[1] as f64)),
+				(String::from("returns_to_adaptive"),ConfigurationValue::Number(*self.tracked_returns.borrow() as f64)),
+			];
+			if let Some(inner)=self.routing[0].statistics(cycle)
+			{
+				content.push( (String::from("adaptive_statistics"),inner) );
+			}
+			if let Some(inner)=self.routing[1].statistics(cycle)
+			{
+				content.push( (String::from("escape_statistics"),inner) );
+			}
+			Some(ConfigurationValue::Object(String::from("DuatoStatistics"),content))
+		} else {
+			None
+		}
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		*self.tracked_hops.borrow_mut() = [0,0];
+		*self.tracked_returns.borrow_mut() = 0;
+		self.routing[0].reset_statistics(next_cycle);
+		self.routing[1].reset_statistics(next_cycle);
+	}
+}
+
+impl Duato
+{
+	pub fn new(arg: RoutingBuilderArgument) -> Duato
+	{
+		let mut adaptive_routing=None;
+		let mut escape_routing=None;
+		let mut adaptive_virtual_channels=None;
+		let mut escape_virtual_channels=None;
+		let mut adaptive_extra_label=0i32;
+		let mut escape_extra_label=1i32;
+		let mut return_to_adaptive=true;
+		let mut check_escape=true;
+		let mut enabled_statistics=false;
+		match_object_panic!(arg.cv,"Duato",value,
+			"adaptive_routing" => adaptive_routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+			"escape_routing" => escape_routing=Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+			"adaptive_virtual_channels" => adaptive_virtual_channels = Some(value.as_array()
+				.expect("bad value for adaptive_virtual_channels").iter()
+				.map(|v|v.as_usize().expect("bad value in adaptive_virtual_channels")).collect::<Vec<usize>>()),
+			"escape_virtual_channels" => escape_virtual_channels = Some(value.as_array()
+				.expect("bad value for escape_virtual_channels").iter()
+				.map(|v|v.as_usize().expect("bad value in escape_virtual_channels")).collect::<Vec<usize>>()),
+			"adaptive_extra_label" => adaptive_extra_label = value.as_i32().expect("bad value for adaptive_extra_label"),
+			"escape_extra_label" => escape_extra_label = value.as_i32().expect("bad value for escape_extra_label"),
+			"return_to_adaptive" => return_to_adaptive = value.as_bool().expect("bad value for return_to_adaptive"),
+			"check_escape" => check_escape = value.as_bool().expect("bad value for check_escape"),
+			"enable_statistics" => enabled_statistics = value.as_bool().expect("bad value for enable_statistics"),
+		);
+		let adaptive_routing=adaptive_routing.expect("There were no adaptive_routing");
+		let escape_routing=escape_routing.expect("There were no escape_routing");
+		let adaptive_virtual_channels=adaptive_virtual_channels.expect("There were no adaptive_virtual_channels");
+		let escape_virtual_channels=escape_virtual_channels.expect("There were no escape_virtual_channels");
+		if escape_virtual_channels.is_empty()
+		{
+			panic!("Duato requires some escape virtual channel");
+		}
+		if let Some(vc) = escape_virtual_channels.iter().find(|vc|adaptive_virtual_channels.contains(vc))
+		{
+			panic!("The virtual channel {} cannot be both adaptive and escape in Duato",vc);
+		}
+		Duato{
+			routing: [adaptive_routing,escape_routing],
+			allowed_virtual_channels: [adaptive_virtual_channels,escape_virtual_channels],
+			extra_label: [adaptive_extra_label,escape_extra_label],
+			return_to_adaptive,
+			check_escape,
+			enabled_statistics,
+			tracked_hops: RefCell::new([0,0]),
+			tracked_returns: RefCell::new(0),
+		}
+	}
+	///The candidates of the allowed routings, with their virtual channels mapped into the reserved ones.
+	///Each candidate is annotated with 0 if it is adaptive and 1 if it is escape.
+	#[allow(clippy::too_many_arguments)]
+	fn candidates(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:Option<&CongestionInfo>, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let distance=topology.distance(current_router,target_router);
+		if distance==0
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let meta=routing_info.meta.as_ref().expect("The routing info has not been initialized");
+		let mode=routing_info.selections.as_ref().expect("The routing info has not been initialized")[0] as usize;
+		let mut r = vec![];
+		for kind in mode..2
+		{
+			let allowed_virtual_channels = &self.allowed_virtual_channels[kind];
+			let extra_label = self.extra_label[kind];
+			let candidates = match congestion
+			{
+				Some(congestion) => self.routing[kind].next_with_congestion(&meta[kind],topology,current_router,target_router,target_server,allowed_virtual_channels.len(),congestion,rng)?,
+				None => self.routing[kind].next(&meta[kind].borrow(),topology,current_router,target_router,target_server,allowed_virtual_channels.len(),rng)?,
+			};
+			r.extend(candidates.into_iter().map( |candidate| CandidateEgress{virtual_channel:allowed_virtual_channels[candidate.virtual_channel],label:candidate.label+extra_label,annotation:Some(RoutingAnnotation{values:vec![kind as i32],meta:vec![candidate.annotation]}),..candidate} ));
+		}
+		Ok(RoutingNextCandidates{candidates:r,idempotent:false})
+	}
+	///Follows the escape routing from every router to every router with servers, panicking if it does not arrive.
+	///At each router one of the escape candidates is taken at random.
+	fn check_escape_connectivity(&self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		let escape = &self.routing[1];
+		let num_virtual_channels = self.allowed_virtual_channels[1].len();
+		let num_routers = topology.num_routers();
+		let router_server = |router:usize| (0..topology.ports(router)).find_map(|port|match topology.neighbour(router,port)
+		{
+			(Location::ServerPort(server),_link_class) => Some(server),
+			_ => None,
+		});
+		for target_router in 0..num_routers
+		{
+			let target_server = match router_server(target_router)
+			{
+				Some(server) => server,
+				None => continue,
+			};
+			for source_router in 0..num_routers
+			{
+				let routing_info = RefCell::new(RoutingInfo{source_server:router_server(source_router),..RoutingInfo::new()});
+				escape.initialize_routing_info(&routing_info,topology,source_router,target_router,Some(target_server),rng);
+				let mut current_router = source_router;
+				while current_router != target_router
+				{
+					if routing_info.borrow().hops >= num_routers
+					{
+						panic!("The escape routing of Duato does not reach router {} from router {} in {} hops",target_router,source_router,num_routers);
+					}
+					let candidates = match escape.next(&routing_info.borrow(),topology,current_router,target_router,Some(target_server),num_virtual_channels,rng)
+					{
+						Ok(candidates) => candidates.candidates,
+						Err(error) => panic!("The escape routing of Duato failed at router {} towards router {}: {}",current_router,target_router,error),
+					};
+					if candidates.is_empty()
+					{
+						panic!("The escape routing of Duato has no candidates at router {} towards router {}",current_router,target_router);
+					}
+					let candidate = &candidates[rng.gen_range(0..candidates.len())];
+					escape.performed_request(candidate,&routing_info,topology,current_router,target_router,Some(target_server),num_virtual_channels,rng);
+					let (next_router,next_port) = match topology.neighbour(current_router,candidate.port)
+					{
+						(Location::RouterPort{router_index,router_port},_link_class) => (router_index,router_port),
+						_ => panic!("The escape routing of Duato leaves the network at router {} towards router {}",current_router,target_router),
+					};
+					routing_info.borrow_mut().hops+=1;
+					escape.update_routing_info(&routing_info,topology,next_router,next_port,target_router,Some(target_server),rng);
+					current_router = next_router;
+				}
+			}
+		}
+	}
+}
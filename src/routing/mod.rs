@@ -7,29 +7,41 @@ see [`new_routing`](fn.new_routing.html) for documentation on the configuration
 
 */
 
-/// Contains Shortest, Valiant, Mindless, WeighedShortest.
+/// Contains Shortest, Valiant, Mindless, WeighedShortest, UGAL.
 pub mod basic;
-/// Contains Sum, Stubborn, EachLengthSourceAdaptiveRouting
+/// Contains Sum, Stubborn, EachLengthSourceAdaptiveRouting, FlowHash, Duato
 pub mod extra;
 /// Contains ChannelsPerHop, ChannelsPerHopPerLinkClass, ChannelMap, AscendantChannelsWithLinkClass
 pub mod channel_operations;
-/// Contains UpDown, UpDownStar.
+/// Contains UpDown, UpDownStar, BalancedUpDown.
 pub mod updown;
 pub mod polarized;
+/// Contains AlgebraicMinimal.
+pub mod algebraic;
+pub mod channel_load;
+/// Contains KShortestPaths, EdgeDisjointPaths, RandomPaths, SourceSelectionRouting.
+pub mod source;
+/// Contains OLM, PiggyBack, ContentionCounters.
+pub mod dragonfly;
+/// Contains TableRouting.
+pub mod table;
 
 use std::cell::RefCell;
+use std::rc::Rc;
 use std::fmt::Debug;
 use std::convert::TryFrom;
 
 use ::rand::{rngs::StdRng,Rng,prelude::SliceRandom};
 
 use crate::config_parser::ConfigurationValue;
-use crate::topology::cartesian::{DOR,O1TURN,ValiantDOR,OmniDimensionalDeroute, DimWAR, GENERALTURN, Valiant4Hamming};
+use crate::topology::cartesian::{DOR,O1TURN,ValiantDOR,OmniDimensionalDeroute, DimWAR, DatelineAdaptive, GENERALTURN, Valiant4Hamming};
 use crate::topology::dragonfly::{PAR, Valiant4Dragonfly};
 use crate::topology::{Topology,Location};
+use crate::router::{Router,occupied_neighbour_space};
 pub use crate::event::Time;
+use crate::event::EventGeneration;
 use quantifiable_derive::Quantifiable;//the derive macro
-use crate::{Plugs};
+use crate::{Plugs,NetworkState,match_object_panic};
 pub use crate::error::Error;
 
 pub use self::basic::*;
@@ -37,10 +49,16 @@ pub use self::extra::*;
 pub use self::channel_operations::*;
 pub use self::updown::*;
 pub use self::polarized::Polarized;
+pub use self::algebraic::*;
+pub use self::source::*;
+pub use self::dragonfly::*;
+pub use self::table::*;
 
 pub mod prelude
 {
-	pub use super::{new_routing,Routing,RoutingInfo,RoutingNextCandidates,CandidateEgress,RoutingBuilderArgument,Error,Time};
+	pub use super::{new_routing,Routing,RoutingInfo,RoutingNextCandidates,CandidateEgress,CongestionInfo,RoutingBuilderArgument,Error,Time};
+	pub use crate::event::EventGeneration;
+	pub use crate::NetworkState;
 }
 
 ///Information stored in the packet for the `Routing` algorithms to operate.
@@ -50,6 +68,10 @@ pub struct RoutingInfo
 {
 	///Number of edges traversed (Router--Router). It is computed by the advance routine of the simulator.
 	pub hops: usize,
+	///The server that generated the packet. It is set by the simulator before calling `initialize_routing_info`.
+	pub source_server: Option<usize>,
+	///The virtual channel by which the packet has entered the current router. It is set by the simulator before calling `update_routing_info`.
+	pub arrival_virtual_channel: Option<usize>,
 
 	//All the remaining fields are used and computed by the Routing employed.
 	///Difference in coordinates from origin to destination
@@ -72,6 +94,8 @@ impl RoutingInfo
 	{
 		RoutingInfo{
 			hops: 0,
+			source_server: None,
+			arrival_virtual_channel: None,
 			routing_record: None,
 			selected_path: None,
 			selections: None,
@@ -168,9 +192,46 @@ impl RoutingNextCandidates
 	}
 }
 
+///The view of the congestion at the current router given to `Routing::next_with_congestion`.
+///It contains the same measures gathered for the virtual channel policies in `RequestInfo`. Those not required by the policies of the router may be `None`.
+pub struct CongestionInfo<'a>
+{
+	///The router performing the routing.
+	pub router: &'a dyn Router,
+	///For each port the average queue length in the queues of the port in the neighbour router.
+	pub port_average_neighbour_queue_length: Option<&'a Vec<f32>>,
+	///For each port a timestamp of the last time that it was used.
+	pub port_last_transmission: Option<&'a Vec<Time>>,
+	///Number of phits currently in the output space of the current router at the indexed port.
+	pub port_occupied_output_space: Option<&'a Vec<usize>>,
+	///Number of available phits in the output space of the current router at the indexed port.
+	pub port_available_output_space: Option<&'a Vec<usize>>,
+	///Number of phits currently in the output space allocated to a virtual channel. Index by `[port_index][virtual_channel]`.
+	pub virtual_channel_occupied_output_space: Option<&'a Vec<Vec<usize>>>,
+	///Number of available phits in the output space allocated to a virtual channel. Index by `[port_index][virtual_channel]`.
+	pub virtual_channel_available_output_space: Option<&'a Vec<Vec<usize>>>,
+	///The current cycle of the simulation.
+	pub current_cycle: Time,
+}
+
+impl CongestionInfo<'_>
+{
+	///The number of phits sent through `port` whose space at the neighbour has not been released yet. It is always available, since it is read from the credits of the router.
+	pub fn neighbour_occupation(&self, port:usize) -> usize
+	{
+		occupied_neighbour_space(self.router,port)
+	}
+	///The phits in the output space of `port`, if any, plus those at the neighbour.
+	pub fn port_occupation(&self, port:usize) -> usize
+	{
+		self.port_occupied_output_space.map(|space|space[port]).unwrap_or(0) + self.neighbour_occupation(port)
+	}
+}
+
 ///A routing algorithm to provide candidate routes when the `Router` requires.
 ///It may store/use information in the RoutingInfo.
-///A `Routing` does not receive information about the state of buffers or similar. Such a mechanism should be given as a `VirtualChannelPolicy`.
+///A `Routing` is not required to use information about the state of buffers or similar. Such a mechanism is usually given as a `VirtualChannelPolicy`.
+///Routings may use the local view of the router by implementing `next_with_congestion` or the global view by requesting it with `set_network_state`.
 pub trait Routing : Debug
 {
 	/// Compute the list of allowed exits.
@@ -181,6 +242,14 @@ pub trait Routing : Debug
 	/// `num_virtual_channels` is the number of virtual channels dedicated to this routing.
 	/// `rng` is the global generator of random numbers.
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>;
+	///Congestion-aware variant of `next`, which is the one called by the routers.
+	///It receives the occupation views of the current router in `congestion` and the routing info in its `RefCell`, so that it may store adaptive decisions into it.
+	///By default it ignores the view and calls `next`. Routings containing other routings may forward the view to them.
+	#[allow(clippy::too_many_arguments)]
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, _congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)
+	}
 	//fn initialize_routing_info(&self, routing_info:&mut RoutingInfo, topology:&dyn Topology, current_router:usize, target_server:usize);
 	///Initialize the routing info of the packet. Called when the first phit of the packet leaves the server and enters a router.
 	fn initialize_routing_info(&self, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng) {}
@@ -190,6 +259,12 @@ pub trait Routing : Debug
 	fn initialize(&mut self, _topology:&dyn Topology, _rng: &mut StdRng) {}
 	///To be called by the router when one of the candidates is requested.
 	fn performed_request(&self, _requested:&CandidateEgress, _routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng) {}
+	///Receive a read-only view of the occupation of the whole network, for routings requiring an oracle of the queues.
+	///Return true to keep it, so that the simulator refreshes it each cycle. Routings containing other routings should pass it to them.
+	fn set_network_state(&mut self, _state:&Rc<NetworkState>) -> bool { false }
+	///Events to be inserted in the queue when the simulation begins, for components of the routing that must be executed periodically, such as broadcasts of congestion.
+	///Called after `initialize`. Routings containing other routings should include their events.
+	fn initial_events(&self) -> Vec<EventGeneration> { vec![] }
 	///To optionally write routing statistics into the simulation output.
 	fn statistics(&self,_cycle:Time) -> Option<ConfigurationValue>{ None }
 	///Clears all collected statistics
@@ -233,6 +308,25 @@ Valiant{
 }
 ```
 
+UGAL chooses once between the minimal route and Valiant routes, comparing their lengths times their queues.
+With `global: false` (UGAL-L) it decides at the first hop with the queues of the first link of each route, as seen by the router.
+With `global: true` (UGAL-G) it decides at injection, adding the queues of all the links of each route from a view of the whole network refreshed each cycle.
+The minimal route is taken when `minimal_cost <= bias*valiant_cost + threshold`. It uses all the virtual channels, so it should be combined with `ChannelsPerHop` or similar to avoid deadlock.
+In topologies with an algebraic description the intermediates are drawn from `AlgebraicTopology::valiant_intermediates`. In the `SlimFly` these are the routers outside the groups of the source and the destination.
+
+See Singh, A. (2005). Load-Balanced Routing in Interconnection Networks. PhD thesis, Stanford University.
+
+```ignore
+UGAL{
+	global: false,
+	intermediates: 2,//defaults to 1
+	threshold: 0,//defaults to 0
+	bias: 1,//defaults to 1
+	enable_statistics: true,//defaults to false. Builds `UGALStatistics{minimal_decisions,nonminimal_decisions}` in the results.
+	legend_name: "UGAL-L",
+}
+```
+
 As a routing that gives both short, long routes, and many intermediates we have the Polarized routing. It is recommended to have some mechanism to select among those routes based on network measures such as queue occupation.
 
 - Camarero, C., Martínez, C., & Beivide, R. (2021, August). Polarized routing: an efficient and versatile algorithm for large direct networks. In 2021 IEEE Symposium on High-Performance Interconnects (HOTI) (pp. 52-59). IEEE.
@@ -274,6 +368,15 @@ UpDown{
 }
 ```
 
+`UpDown` offers every port reducing the up/down distance. `BalancedUpDown` selects a single one of them with `DModK` or `Hash`, keeping each flow in a single path and so in order, or the ones with the least occupation at the neighbour, estimated from the credits, with `Adaptive`.
+The fields for `Hash` can be `SourceServer`, `DestinationServer`, `SourceRouter`, and `DestinationRouter`, and are hashed with a random salt.
+```ignore
+BalancedUpDown{
+	selection: Hash{fields:[SourceServer,DestinationServer]},//or DModK, or Adaptive
+	legend_name: "ECMP up/down routing",
+}
+```
+
 There is a `Mindless` routing without parameters that includes all neighbours as candidates until reaching destination. Can be though as a random walk, if additionally the router would make its decisions randomly.
 
 ## Operations
@@ -349,6 +452,53 @@ Stubborn{
 }
 ```
 
+### FlowHash
+Pins each flow, the pair of source and destination servers, to a single port among the candidates of the wrapped routing, selected by a hash as in ECMP.
+With `flowlet_gap` the flow is split into flowlets when its packets are injected after more than that many idle cycles, and each flowlet is hashed separately.
+With `enable_statistics` it builds `FlowHashStatistics{packets,flowlets,out_of_order_packets,average_reorder_distance,maximum_reorder_distance}` in the results, counting the packets reaching their destination router after a later packet of their flow.
+```ignore
+FlowHash{
+	routing: Shortest,
+	flowlet_gap: 50,//optional, by default each flow is a single flowlet.
+	enable_statistics: true,
+	legend_name: "ECMP minimal",
+}
+```
+
+### Duato
+Duato's escape channels for fully adaptive routing. The `adaptive_routing` may use its virtual channels freely while the `escape_routing` must be deadlock-free on its own, such as `DOR` in a mesh or `UpDown`.
+Both routings are offered at every hop, each over its own virtual channels, with the escape routing computed from the current router. After an escape hop the packet may use again the adaptive channels unless `return_to_adaptive` is false.
+At initialization the escape routing is followed from every router to every router with servers, failing if some does not arrive. This check can be disabled with `check_escape:false`.
+The labels of the escape candidates are increased by `escape_extra_label`, 1 by default, so that policies such as `LowestLabel` prefer the adaptive channels.
+With `enable_statistics` it builds `DuatoStatistics{adaptive_hops,escape_hops,returns_to_adaptive}`.
+```ignore
+Duato{
+	adaptive_routing: Shortest,
+	escape_routing: DOR{order:[0,1]},
+	adaptive_virtual_channels: [1,2],
+	escape_virtual_channels: [0],
+	adaptive_extra_label: 0,//optional
+	escape_extra_label: 1,//optional
+	return_to_adaptive: true,//optional
+	check_escape: true,//optional
+	enable_statistics: true,
+	legend_name: "fully adaptive minimal with DOR escape",
+}
+```
+
+### TableRouting
+Routes by forwarding tables, as the linear forwarding tables computed by subnet managers. The tables are read from `filename`, with a line `router destination port [port ...]` for each entry; any of the ports may be used.
+The `destination` of the entries is either `Router`, the default, or `Server`. With `Router` the ejection port at the target router is given by the topology, while with `Server` it may be given in the table.
+When a `routing` is given, the tables are instead computed from the candidates of that routing and written into `filename`, so that they can be compared with those of a subnet manager or used in later simulations.
+```ignore
+TableRouting{
+	filename: "lft.txt",
+	destination: Server,//optional
+	routing: UpDown,//optional, to compute and dump the tables instead of loading them
+	legend_name: "tables of up/down",
+}
+```
+
 ## Cartesian-specific routings
 
 ### DOR
@@ -386,6 +536,22 @@ OmniDimensionalDeroute{
 }
 ```
 
+### DatelineAdaptive
+
+Minimal fully adaptive routing for `Torus` and `Mesh`, with a DOR escape. The escape uses a pair of virtual channels per dimension, the first while the remaining path crosses the wrap-around link of the dimension and the second afterwards.
+With `enable_statistics` it reports the hops and the link utilization of each dimension.
+
+```ignore
+DatelineAdaptive{
+	order: [0,1],//optional, the DOR order of the escape
+	dateline_virtual_channels: [[0,1],[0,1]],//a pair for each dimension, or a single pair for all
+	adaptive_virtual_channels: [2],
+	escape_extra_label: 1,//optional, the label increment of the escape candidates
+	enable_statistics: true,
+	legend_name: "minimal adaptive with dateline DOR escape",
+}
+```
+
 ### ValiantDOR
 
 A proposal by Valiant for Cartesian topologies. It randomizes all-but-one coordinates, followed by a DOR starting by the non-randomized coordinate.
@@ -400,6 +566,105 @@ ValiantDOR{
 }
 ```
 
+## Routings for SlimFly and projective topologies
+
+These routings require a topology with an algebraic description: `SlimFly`, `Projective`, or `LeviProjective`. Minimal paths are computed from the coordinates of the routers, without distance matrices.
+
+### AlgebraicMinimal
+
+Minimal routing with explicit virtual channels. By default the `k`-th hop uses the virtual channel `k`, requiring as many virtual channels as the diameter.
+
+```ignore
+AlgebraicMinimal{
+	//channels: [[0],[1]],//optional, the virtual channels of each hop between routers.
+	legend_name: "minimal",
+}
+```
+
+## Source routings
+
+These routings compute at initialization a set of paths for each pair of routers, and the path of each packet is chosen at the source router.
+
+### Path sets
+
+The path sets can be used by themselves as a routing, choosing a random path for each packet.
+```ignore
+KShortestPaths{
+	k: 4,//the k shortest loopless paths, by the algorithm of Yen.
+}
+EdgeDisjointPaths{
+	k: 3,//optional, as many as possible by default. Paths without common links.
+}
+RandomPaths{
+	amount: 4,//paths through random intermediate routers, removing loops and repetitions.
+	include_minimal: true,//defaults to false.
+}
+```
+
+### SourceRouting
+
+Chooses a path of the set at the source according to `selection`, which can be `Random`, `RoundRobin`, or `LeastRecentlyCongested`.
+With `LeastRecentlyCongested` all the paths are offered at the source, labelled by the last time they could not be taken. It requires the router to use the `EnforceFlowControl` and `LowestLabel` policies.
+```ignore
+SourceRouting{
+	paths: KShortestPaths{k:4},
+	selection: LeastRecentlyCongested,
+	legend_name: "4-shortest paths avoiding the recently congested",
+}
+```
+
+### SourceAdaptiveRouting
+
+Stores `amount` random paths of the set in the packet, with every first hop among them being a candidate.
+```ignore
+SourceAdaptiveRouting{
+	paths: KShortestPaths{k:8},
+	amount: 3,
+}
+```
+
+### EachLengthSourceAdaptiveRouting
+
+Stores a random path of each length in the packet, labelled by how much longer it is than the minimum.
+```ignore
+EachLengthSourceAdaptiveRouting{
+	paths: RandomPaths{amount:4,include_minimal:true},
+}
+```
+
+## Progressive adaptive routings for dragonfly-like topologies
+
+These routings work on topologies whose links of class 0 join routers of the same group and whose links of class 1 join different groups, as `Dragonfly` and `Megafly`.
+They use all the virtual channels, so they should be combined with `ChannelsPerHop` or similar to avoid deadlock.
+With `enable_statistics: true` they build `MisroutingStatistics{packets,global_misroutes,local_misroutes}` in the results, counting the hops that do not approach the destination.
+
+OLM (Opportunistic Local Misrouting) may misroute at any hop through a port whose occupation plus `threshold` is lower than the one of every minimal port. It allows a global misroute from any router of the source group and a local misroute in each group.
+```ignore
+OLM{
+	threshold: 0,//defaults to 0
+	enable_statistics: true,
+}
+```
+
+PiggyBack decides at the first hop between the minimal route and a random intermediate group. The saturation of the global links of each group is broadcast every `broadcast_period` cycles, being saturated the links with more than `saturation_factor` times the average occupation of the global links of their group.
+The packet is sent through the intermediate group when the minimal global links are saturated or when the minimal ports have more than twice the occupation of the ports towards the intermediate group plus `threshold`.
+```ignore
+PiggyBack{
+	broadcast_period: 10,//defaults to 10
+	saturation_factor: 2,//defaults to 2
+	threshold: 0,//defaults to 0
+	enable_statistics: true,
+}
+```
+
+ContentionCounters counts in each router the packets whose minimal route continues through each port. A packet is misrouted as in OLM when the counters of its minimal ports exceed `threshold`, through the ports whose counter does not exceed it.
+```ignore
+ContentionCounters{
+	threshold: 4,
+	enable_statistics: true,
+}
+```
+
 */
 pub fn new_routing(arg: RoutingBuilderArgument) -> Box<dyn Routing>
 {
@@ -417,25 +682,41 @@ pub fn new_routing(arg: RoutingBuilderArgument) -> Box<dyn Routing>
 			"GeneralTurn" => Box::new(GENERALTURN::new(arg)),
 			"OmniDimensionalDeroute" => Box::new(OmniDimensionalDeroute::new(arg)),
 			"DimWAR" => Box::new(DimWAR::new(arg)),
+			"DatelineAdaptive" => Box::new(DatelineAdaptive::new(arg)),
 			"Valiant4Hamming" => Box::new(Valiant4Hamming::new(arg)),
 			"Valiant4Dragonfly" => Box::new(Valiant4Dragonfly::new(arg)),
 			"PAR" => Box::new(PAR::new(arg)),
 			"Shortest" => Box::new(Shortest::new(arg)),
 			"Valiant" => Box::new(Valiant::new(arg)),
+			"UGAL" => Box::new(UGAL::new(arg)),
 			"ValiantDOR" => Box::new(ValiantDOR::new(arg)),
 			"Polarized" => Box::new(Polarized::new(arg)),
 			"Sum" => Box::new(SumRouting::new(arg)),
 			"Mindless" => Box::new(Mindless::new(arg)),
 			"WeighedShortest" => Box::new(WeighedShortest::new(arg)),
 			"Stubborn" => Box::new(Stubborn::new(arg)),
+			"FlowHash" => Box::new(FlowHash::new(arg)),
+			"Duato" => Box::new(Duato::new(arg)),
+			"TableRouting" => Box::new(TableRouting::new(arg)),
 			"UpDown" => Box::new(UpDown::new(arg)),
 			"UpDownStar" => Box::new(ExplicitUpDown::new(arg)),
 			"UpDownDerouting" => Box::new(UpDownDerouting::new(arg)),
+			"BalancedUpDown" => Box::new(BalancedUpDown::new(arg)),
 			"ChannelsPerHop" => Box::new(ChannelsPerHop::new(arg)),
 			"ChannelsPerHopPerLinkClass" => Box::new(ChannelsPerHopPerLinkClass::new(arg)),
 			"AscendantChannelsWithLinkClass" => Box::new(AscendantChannelsWithLinkClass::new(arg)),
 			"ChannelMap" => Box::new(ChannelMap::new(arg)),
 			"Dragonfly2Colors" => Box::new(crate::topology::dragonfly::Dragonfly2ColorsRouting::new(arg)),
+			"AlgebraicMinimal" => Box::new(AlgebraicMinimal::new(arg)),
+			"KShortestPaths" => Box::new(KShortestPaths::new(arg)),
+			"EdgeDisjointPaths" => Box::new(EdgeDisjointPaths::new(arg)),
+			"RandomPaths" => Box::new(RandomPaths::new(arg)),
+			"SourceRouting" => Box::new(SourceSelectionRouting::new(arg)),
+			"SourceAdaptiveRouting" => Box::new(SourceAdaptiveRouting::new(arg)),
+			"EachLengthSourceAdaptiveRouting" => Box::new(EachLengthSourceAdaptiveRouting::new(arg)),
+			"OLM" => Box::new(OLM::new(arg)),
+			"PiggyBack" => Box::new(PiggyBack::new(arg)),
+			"ContentionCounters" => Box::new(ContentionCounters::new(arg)),
 			_ => panic!("Unknown Routing {}",cv_name),
 		}
 	}
@@ -447,8 +728,7 @@ pub fn new_routing(arg: RoutingBuilderArgument) -> Box<dyn Routing>
 
 
 ///Trait for `Routing`s that build the whole route at source.
-///This includes routings such as K-shortest paths. But I have all my implementations depending on a private algorithm, so they are not yet here.
-///They will all be released when the dependency is formally published.
+///This includes routings such as K-shortest paths. See the `source` module for the implementations.
 pub trait SourceRouting
 {
 	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng);
@@ -554,6 +834,25 @@ pub struct SourceAdaptiveRouting
 	pub amount: usize,
 }
 
+impl SourceAdaptiveRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> SourceAdaptiveRouting
+	{
+		let mut routing = None;
+		let mut amount = None;
+		match_object_panic!(arg.cv,"SourceAdaptiveRouting",value,
+			"paths" => routing=Some(new_source_routing(RoutingBuilderArgument{cv:value,..arg})),
+			"amount" => amount=Some(value.as_usize().expect("bad value for amount")),
+		);
+		let routing = routing.expect("There were no paths");
+		let amount = amount.expect("There were no amount");
+		SourceAdaptiveRouting{
+			routing,
+			amount,
+		}
+	}
+}
+
 impl Routing for SourceAdaptiveRouting
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
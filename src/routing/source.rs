@@ -0,0 +1,661 @@
+/*!
+
+Generators of path sets for the source routings and the routing selecting among them at the source router.
+
+* KShortestPaths
+* EdgeDisjointPaths
+* RandomPaths
+* SourceSelectionRouting
+
+The path sets can be used directly as a routing, choosing a random path at source, or inside `SourceRouting`, `SourceAdaptiveRouting`, and `EachLengthSourceAdaptiveRouting`.
+
+*/
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+
+use ::rand::{rngs::StdRng,Rng,prelude::SliceRandom};
+
+use crate::match_object_panic;
+use crate::config_parser::ConfigurationValue;
+use crate::routing::*;
+use crate::topology::{Topology,Location};
+
+///Build a generator of paths from its configuration. Each one is also a `Routing`, see [`new_routing`](fn.new_routing.html) for the syntax.
+pub fn new_source_routing(arg: RoutingBuilderArgument) -> Box<dyn InstantiableSourceRouting>
+{
+	if let ConfigurationValue::Object(cv_name, _cv_pairs)=arg.cv
+	{
+		match cv_name.as_ref()
+		{
+			"KShortestPaths" => Box::new(KShortestPaths::new(arg)),
+			"EdgeDisjointPaths" => Box::new(EdgeDisjointPaths::new(arg)),
+			"RandomPaths" => Box::new(RandomPaths::new(arg)),
+			_ => panic!("Unknown SourceRouting {}",cv_name),
+		}
+	}
+	else
+	{
+		panic!("Trying to create a SourceRouting from a non-Object");
+	}
+}
+
+///The list of neighbour routers of each router, without repetitions. The order of each list is shuffled, to break ties between paths of the same length in a random but fixed way.
+fn router_adjacency(topology:&dyn Topology, rng: &mut StdRng) -> Vec<Vec<usize>>
+{
+	(0..topology.num_routers()).map(|router|{
+		let mut neighbours : Vec<usize> = topology.neighbour_router_iter(router).map(|item|item.neighbour_router).filter(|&neighbour|neighbour!=router).collect();
+		neighbours.sort_unstable();
+		neighbours.dedup();
+		neighbours.shuffle(rng);
+		neighbours
+	}).collect()
+}
+
+///Breadth-first search of a path from `source` to `target` avoiding the routers in `removed_routers` and the directed links in `removed_links`.
+fn shortest_path_avoiding(adjacency:&[Vec<usize>], source:usize, target:usize, removed_routers:&[bool], removed_links:&[(usize,usize)]) -> Option<Vec<usize>>
+{
+	let n = adjacency.len();
+	let mut parent = vec![None;n];
+	let mut visited = vec![false;n];
+	visited[source]=true;
+	let mut queue = VecDeque::from(vec![source]);
+	while let Some(current) = queue.pop_front()
+	{
+		if current==target
+		{
+			let mut path = vec![target];
+			let mut router = target;
+			while let Some(previous) = parent[router]
+			{
+				path.push(previous);
+				router = previous;
+			}
+			path.reverse();
+			return Some(path);
+		}
+		for &neighbour in adjacency[current].iter()
+		{
+			if !visited[neighbour] && !removed_routers[neighbour] && !removed_links.contains(&(current,neighbour))
+			{
+				visited[neighbour]=true;
+				parent[neighbour]=Some(current);
+				queue.push_back(neighbour);
+			}
+		}
+	}
+	None
+}
+
+///Remove the cycles of a walk, keeping for each router the part after its last visit.
+fn remove_loops(walk:&[usize]) -> Vec<usize>
+{
+	let mut path : Vec<usize> = Vec::with_capacity(walk.len());
+	for &router in walk
+	{
+		if let Some(position) = path.iter().position(|&r|r==router)
+		{
+			path.truncate(position);
+		}
+		path.push(router);
+	}
+	path
+}
+
+///Collect the paths of every pair of routers into the table indexed by `source*num_routers+target`.
+fn build_path_table<F:FnMut(usize,usize)->Vec<Vec<usize>>>(num_routers:usize, mut paths_between:F) -> Vec<Vec<Vec<usize>>>
+{
+	let mut table = Vec::with_capacity(num_routers*num_routers);
+	for source in 0..num_routers
+	{
+		for target in 0..num_routers
+		{
+			if source==target
+			{
+				table.push(vec![vec![source]]);
+			}
+			else
+			{
+				table.push(paths_between(source,target));
+			}
+		}
+	}
+	table
+}
+
+///The `k` shortest loopless paths between each pair of routers, computed with the algorithm of Yen on the hop count.
+///Ties between paths of the same length are broken randomly when the routing is initialized.
+///The paths for all the pairs are computed at initialization, which may take a while in large networks.
+#[derive(Debug)]
+pub struct KShortestPaths
+{
+	///Number of paths to find for each pair.
+	k: usize,
+	///`paths[source*num_routers+target]` is the list of paths from `source` to `target`, in non-decreasing length.
+	paths: Vec<Vec<Vec<usize>>>,
+	num_routers: usize,
+}
+
+impl SourceRouting for KShortestPaths
+{
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		let adjacency = router_adjacency(topology,rng);
+		self.num_routers = topology.num_routers();
+		let k = self.k;
+		self.paths = build_path_table(self.num_routers,|source,target|yen_k_shortest_paths(&adjacency,source,target,k));
+	}
+	fn get_paths(&self, source:usize, target:usize) -> &Vec<Vec<usize>>
+	{
+		&self.paths[source*self.num_routers+target]
+	}
+}
+
+impl KShortestPaths
+{
+	pub fn new(arg: RoutingBuilderArgument) -> KShortestPaths
+	{
+		let mut k = None;
+		match_object_panic!(arg.cv,"KShortestPaths",value,
+			"k" => k=Some(value.as_usize().expect("bad value for k")),
+		);
+		let k = k.expect("There were no k");
+		if k==0
+		{
+			panic!("KShortestPaths requires k to be at least 1");
+		}
+		KShortestPaths{
+			k,
+			paths: vec![],
+			num_routers: 0,
+		}
+	}
+}
+
+///Up to `k` loopless paths from `source` to `target` with the least number of hops.
+pub fn yen_k_shortest_paths(adjacency:&[Vec<usize>], source:usize, target:usize, k:usize) -> Vec<Vec<usize>>
+{
+	let n = adjacency.len();
+	let mut found : Vec<Vec<usize>> = match shortest_path_avoiding(adjacency,source,target,&vec![false;n],&[])
+	{
+		Some(path) => vec![path],
+		None => return vec![],
+	};
+	let mut candidates : Vec<Vec<usize>> = vec![];
+	while found.len()<k
+	{
+		let previous = found.last().unwrap().clone();
+		let mut removed_routers = vec![false;n];
+		for spur_index in 0..previous.len()-1
+		{
+			let spur_router = previous[spur_index];
+			let root = &previous[..=spur_index];
+			let removed_links : Vec<(usize,usize)> = found.iter().filter(|path|path.len()>spur_index+1 && &path[..=spur_index]==root).map(|path|(path[spur_index],path[spur_index+1])).collect();
+			if let Some(spur_path) = shortest_path_avoiding(adjacency,spur_router,target,&removed_routers,&removed_links)
+			{
+				let mut total = root[..spur_index].to_vec();
+				total.extend(spur_path);
+				if !found.contains(&total) && !candidates.contains(&total)
+				{
+					candidates.push(total);
+				}
+			}
+			//The routers of the root cannot appear again in the following spur paths.
+			removed_routers[spur_router]=true;
+		}
+		//Take the first of the shortest candidates.
+		match (0..candidates.len()).min_by_key(|&index|candidates[index].len())
+		{
+			Some(best) => found.push(candidates.remove(best)),
+			None => break,
+		}
+	}
+	found
+}
+
+///A maximum set of paths between each pair of routers not sharing any link, up to `k` of them.
+///They are found as unit-capacity flows along shortest augmenting paths, so shorter paths are preferred.
+///Each direction of a link is considered a different link.
+#[derive(Debug)]
+pub struct EdgeDisjointPaths
+{
+	///Maximum number of paths for each pair.
+	k: Option<usize>,
+	///`paths[source*num_routers+target]` is the list of paths from `source` to `target`, in non-decreasing length.
+	paths: Vec<Vec<Vec<usize>>>,
+	num_routers: usize,
+}
+
+impl SourceRouting for EdgeDisjointPaths
+{
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		let adjacency = router_adjacency(topology,rng);
+		self.num_routers = topology.num_routers();
+		let k = self.k.unwrap_or(usize::MAX);
+		self.paths = build_path_table(self.num_routers,|source,target|edge_disjoint_paths(&adjacency,source,target,k));
+	}
+	fn get_paths(&self, source:usize, target:usize) -> &Vec<Vec<usize>>
+	{
+		&self.paths[source*self.num_routers+target]
+	}
+}
+
+impl EdgeDisjointPaths
+{
+	pub fn new(arg: RoutingBuilderArgument) -> EdgeDisjointPaths
+	{
+		let mut k = None;
+		match_object_panic!(arg.cv,"EdgeDisjointPaths",value,
+			"k" => k=Some(value.as_usize().expect("bad value for k")),
+		);
+		EdgeDisjointPaths{
+			k,
+			paths: vec![],
+			num_routers: 0,
+		}
+	}
+}
+
+///Up to `k` paths from `source` to `target` without common directed links.
+pub fn edge_disjoint_paths(adjacency:&[Vec<usize>], source:usize, target:usize, k:usize) -> Vec<Vec<usize>>
+{
+	let n = adjacency.len();
+	//`flow[router][index]` is the flow in the link towards `adjacency[router][index]`, as +1, 0, or -1.
+	let mut flow : Vec<Vec<i8>> = adjacency.iter().map(|neighbours|vec![0;neighbours.len()]).collect();
+	let index_of = |from:usize, to:usize| adjacency[from].iter().position(|&r|r==to).expect("asymmetric adjacency");
+	let mut amount = 0;
+	while amount<k
+	{
+		//Search for an augmenting path in the residual graph.
+		let mut parent = vec![None;n];
+		let mut visited = vec![false;n];
+		visited[source]=true;
+		let mut queue = VecDeque::from(vec![source]);
+		while let Some(current) = queue.pop_front()
+		{
+			if current==target
+			{
+				break;
+			}
+			for (index,&neighbour) in adjacency[current].iter().enumerate()
+			{
+				if !visited[neighbour] && flow[current][index]<1
+				{
+					visited[neighbour]=true;
+					parent[neighbour]=Some(current);
+					queue.push_back(neighbour);
+				}
+			}
+		}
+		if !visited[target]
+		{
+			break;
+		}
+		let mut router = target;
+		while let Some(previous) = parent[router]
+		{
+			let forward = index_of(previous,router);
+			let backward = index_of(router,previous);
+			flow[previous][forward]+=1;
+			flow[router][backward]-=1;
+			router = previous;
+		}
+		amount+=1;
+	}
+	//Decompose the flow into paths.
+	let mut paths = Vec::with_capacity(amount);
+	for _ in 0..amount
+	{
+		let mut walk = vec![source];
+		let mut current = source;
+		while current!=target
+		{
+			let index = flow[current].iter().position(|&f|f==1).expect("broken flow decomposition");
+			let next = adjacency[current][index];
+			flow[current][index]=0;
+			let backward = index_of(next,current);
+			flow[next][backward]=0;
+			walk.push(next);
+			current = next;
+		}
+		paths.push(remove_loops(&walk));
+	}
+	paths.sort_by_key(|path|path.len());
+	paths
+}
+
+///Non-minimal paths through random intermediate routers, in the manner of Valiant.
+///Each path goes by a random shortest path to the intermediate and another from it to the target, removing any loop that may form.
+///Repeated paths are discarded, so there may be less than `amount` paths for a pair. With `include_minimal` a shortest path is always included.
+#[derive(Debug)]
+pub struct RandomPaths
+{
+	///Number of random intermediates to try for each pair.
+	amount: usize,
+	///Whether to include a shortest path in each set.
+	include_minimal: bool,
+	///`paths[source*num_routers+target]` is the list of paths from `source` to `target`, in non-decreasing length.
+	paths: Vec<Vec<Vec<usize>>>,
+	num_routers: usize,
+}
+
+impl SourceRouting for RandomPaths
+{
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		let n = topology.num_routers();
+		self.num_routers = n;
+		let adjacency = router_adjacency(topology,rng);
+		let amount = self.amount;
+		let include_minimal = self.include_minimal;
+		//A random shortest walk, choosing at each step among the neighbours that get closer.
+		let random_shortest = |from:usize, to:usize, rng:&mut StdRng| {
+			let mut path = vec![from];
+			let mut current = from;
+			while current!=to
+			{
+				let distance = topology.distance(current,to);
+				let closer : Vec<usize> = adjacency[current].iter().cloned().filter(|&neighbour|topology.distance(neighbour,to)+1==distance).collect();
+				current = *closer.choose(rng).expect("no neighbour closer to the target");
+				path.push(current);
+			}
+			path
+		};
+		self.paths = build_path_table(n,|source,target|{
+			let mut paths : Vec<Vec<usize>> = vec![];
+			if include_minimal
+			{
+				paths.push(random_shortest(source,target,rng));
+			}
+			for _ in 0..amount
+			{
+				let middle = rng.gen_range(0..n);
+				let mut walk = random_shortest(source,middle,rng);
+				walk.pop();
+				walk.extend(random_shortest(middle,target,rng));
+				let path = remove_loops(&walk);
+				if !paths.contains(&path)
+				{
+					paths.push(path);
+				}
+			}
+			paths.sort_by_key(|path|path.len());
+			paths
+		});
+	}
+	fn get_paths(&self, source:usize, target:usize) -> &Vec<Vec<usize>>
+	{
+		&self.paths[source*self.num_routers+target]
+	}
+}
+
+impl RandomPaths
+{
+	pub fn new(arg: RoutingBuilderArgument) -> RandomPaths
+	{
+		let mut amount = None;
+		let mut include_minimal = false;
+		match_object_panic!(arg.cv,"RandomPaths",value,
+			"amount" => amount=Some(value.as_usize().expect("bad value for amount")),
+			"include_minimal" => include_minimal=value.as_bool().expect("bad value for include_minimal"),
+		);
+		let amount = amount.expect("There were no amount");
+		if amount==0 && !include_minimal
+		{
+			panic!("RandomPaths would not generate any path");
+		}
+		RandomPaths{
+			amount,
+			include_minimal,
+			paths: vec![],
+			num_routers: 0,
+		}
+	}
+}
+
+///How the `SourceSelectionRouting` chooses the path of each packet.
+#[derive(Debug)]
+pub enum SourcePathSelection
+{
+	///A random path.
+	Random,
+	///The paths of each pair of routers are used in turns.
+	RoundRobin,
+	///All the paths are offered at the source router, labelled by how recently they have been found congested.
+	LeastRecentlyCongested,
+}
+
+pub fn new_source_path_selection(cv: &ConfigurationValue) -> SourcePathSelection
+{
+	if let ConfigurationValue::Object(cv_name, _cv_pairs)=cv
+	{
+		match cv_name.as_ref()
+		{
+			"Random" => SourcePathSelection::Random,
+			"RoundRobin" => SourcePathSelection::RoundRobin,
+			"LeastRecentlyCongested" => SourcePathSelection::LeastRecentlyCongested,
+			_ => panic!("Unknown source path selection {}",cv_name),
+		}
+	}
+	else
+	{
+		panic!("Trying to create a SourcePathSelection from a non-Object");
+	}
+}
+
+///Routing following a path of a `SourceRouting` chosen at the source router according to a `SourcePathSelection`.
+///With `LeastRecentlyCongested` the first hops of all the paths are candidates at the source, labelled with the rank of the path by the last time it was found congested, with never congested paths at label 0.
+///When a path is requested, the paths with lower labels are considered congested at that moment, since the router could not take them.
+///This requires the router to take the candidates in order of label, as with the `LowestLabel` policy after `EnforceFlowControl`.
+#[derive(Debug)]
+pub struct SourceSelectionRouting
+{
+	///The generator of the paths.
+	routing: Box<dyn InstantiableSourceRouting>,
+	selection: SourcePathSelection,
+	num_routers: usize,
+	///The index of the next path to use by each pair of routers, for `RoundRobin`.
+	next_path: RefCell<Vec<usize>>,
+	///The time stamp in which each path of each pair of routers was last found congested, with 0 for never. For `LeastRecentlyCongested`.
+	congested_stamp: RefCell<Vec<Vec<usize>>>,
+	///The counter used as time stamp.
+	current_stamp: RefCell<usize>,
+}
+
+impl SourceSelectionRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> SourceSelectionRouting
+	{
+		let mut routing = None;
+		let mut selection = SourcePathSelection::Random;
+		match_object_panic!(arg.cv,"SourceRouting",value,
+			"paths" => routing=Some(new_source_routing(RoutingBuilderArgument{cv:value,..arg})),
+			"selection" => selection=new_source_path_selection(value),
+		);
+		let routing = routing.expect("There were no paths");
+		SourceSelectionRouting{
+			routing,
+			selection,
+			num_routers: 0,
+			next_path: RefCell::new(vec![]),
+			congested_stamp: RefCell::new(vec![]),
+			current_stamp: RefCell::new(0),
+		}
+	}
+	///The label of each path of a pair, given by the number of paths congested less recently than it.
+	fn congestion_labels(stamps:&[usize]) -> Vec<i32>
+	{
+		stamps.iter().map(|&stamp|i32::try_from(stamps.iter().filter(|&&other|other<stamp).count()).unwrap()).collect()
+	}
+}
+
+impl Routing for SourceSelectionRouting
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		//A list of (path_index,path,label) to follow.
+		let paths = self.routing.get_paths(current_router,target_router);
+		let (options, idempotent) : (Vec<(usize,&Vec<usize>,i32)>,bool) = match routing_info.selected_path
+		{
+			Some(ref path) => (vec![(0,path,0)],true),
+			None =>
+			{
+				let stamps = &self.congested_stamp.borrow()[current_router*self.num_routers+target_router];
+				let labels = Self::congestion_labels(stamps);
+				//The labels change with the requests of other packets.
+				(paths.iter().enumerate().map(|(index,path)|(index,path,labels[index])).collect(),false)
+			},
+		};
+		let mut r=Vec::with_capacity(options.len()*num_virtual_channels);
+		for (path_index,path,label) in options
+		{
+			let next_router = path[routing_info.hops+1];
+			let remain = path.len() - 1 - routing_info.hops;
+			for item in topology.neighbour_router_iter(current_router)
+			{
+				if item.neighbour_router==next_router
+				{
+					r.extend((0..num_virtual_channels).map(|vc|{
+						let mut egress = CandidateEgress::new(item.port_index,vc);
+						egress.estimated_remaining_hops = Some(remain);
+						egress.label = label;
+						if routing_info.selected_path.is_none()
+						{
+							egress.annotation = Some(RoutingAnnotation{values:vec![i32::try_from(path_index).unwrap()],meta:vec![]});
+						}
+						egress
+					}));
+				}
+			}
+		}
+		Ok(RoutingNextCandidates{candidates:r,idempotent})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, current_router:usize, target_router:usize, _target_server:Option<usize>, rng: &mut StdRng)
+	{
+		if current_router==target_router
+		{
+			return;
+		}
+		let path_collection = self.routing.get_paths(current_router,target_router);
+		if path_collection.is_empty()
+		{
+			panic!("No path found from router {} to router {}",current_router,target_router);
+		}
+		let index = match self.selection
+		{
+			SourcePathSelection::Random => rng.gen_range(0..path_collection.len()),
+			SourcePathSelection::RoundRobin =>
+			{
+				let mut next_path = self.next_path.borrow_mut();
+				let pair = current_router*self.num_routers+target_router;
+				let index = next_path[pair] % path_collection.len();
+				next_path[pair] = index+1;
+				index
+			},
+			//The path is decided when requesting the first link.
+			SourcePathSelection::LeastRecentlyCongested => return,
+		};
+		routing_info.borrow_mut().selected_path=Some(path_collection[index].clone());
+	}
+	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, current_router:usize, target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng)
+	{
+		let mut bri = routing_info.borrow_mut();
+		if bri.selected_path.is_some()
+		{
+			return;
+		}
+		if let Some(annotation) = requested.annotation.as_ref()
+		{
+			let path_index = usize::try_from(annotation.values[0]).unwrap();
+			let pair = current_router*self.num_routers+target_router;
+			let mut congested_stamp = self.congested_stamp.borrow_mut();
+			let stamps = &mut congested_stamp[pair];
+			let labels = Self::congestion_labels(stamps);
+			let mut current_stamp = self.current_stamp.borrow_mut();
+			*current_stamp += 1;
+			for (index,stamp) in stamps.iter_mut().enumerate()
+			{
+				if labels[index]<labels[path_index]
+				{
+					*stamp = *current_stamp;
+				}
+			}
+			bri.selected_path = Some(self.routing.get_paths(current_router,target_router)[path_index].clone());
+		}
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.routing.initialize(topology,rng);
+		let n = topology.num_routers();
+		self.num_routers = n;
+		match self.selection
+		{
+			SourcePathSelection::Random => (),
+			SourcePathSelection::RoundRobin => *self.next_path.borrow_mut() = vec![0;n*n],
+			SourcePathSelection::LeastRecentlyCongested =>
+			{
+				*self.congested_stamp.borrow_mut() = (0..n*n).map(|pair|vec![0;self.routing.get_paths(pair/n,pair%n).len()]).collect();
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use ::rand::SeedableRng;
+	use crate::config_parser;
+	use crate::topology::{new_topology,TopologyBuilderArgument};
+	#[test]
+	fn path_sets_in_hamming()
+	{
+		let plugs = Plugs::default();
+		let cv = match config_parser::parse("Hamming{sides:[4,4],servers_per_router:1}").unwrap()
+		{
+			config_parser::Token::Value(value) => value,
+			_ => panic!("Not a value"),
+		};
+		let mut rng = StdRng::seed_from_u64(10);
+		let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+		let adjacency = router_adjacency(topology.as_ref(),&mut rng);
+		//Routers 0 and 5 differ in both coordinates: 2 paths of length 2 and then paths of length 3.
+		let paths = yen_k_shortest_paths(&adjacency,0,5,6);
+		assert_eq!(paths.len(),6);
+		assert_eq!(paths.iter().map(|path|path.len()-1).collect::<Vec<usize>>(),vec![2,2,3,3,3,3]);
+		for path in paths.iter()
+		{
+			assert_eq!(path,&remove_loops(path));
+			assert!(path.windows(2).all(|w|adjacency[w[0]].contains(&w[1])));
+		}
+		//The degree is 6, so there are 6 edge-disjoint paths between any pair.
+		for target in 1..16
+		{
+			let paths = edge_disjoint_paths(&adjacency,0,target,usize::MAX);
+			assert_eq!(paths.len(),6);
+			let mut links : Vec<(usize,usize)> = paths.iter().flat_map(|path|path.windows(2).map(|w|(w[0],w[1]))).collect();
+			let total = links.len();
+			links.sort_unstable();
+			links.dedup();
+			assert_eq!(links.len(),total);
+			assert!(paths.iter().all(|path|path[0]==0 && *path.last().unwrap()==target));
+		}
+	}
+}
@@ -0,0 +1,299 @@
+/*!
+
+Routing by forwarding tables, in the style of the linear forwarding tables (LFT) of subnet managers.
+
+* TableRouting
+
+The tables are stored in text files with a line `router destination port [port ...]` for each entry. Empty lines and lines beginning with `#` are ignored.
+The destination is either a router or a server, according to the `destination` key of the routing.
+
+*/
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufRead,BufReader,Write};
+
+use ::rand::rngs::StdRng;
+
+use crate::{match_object_panic,error,source_location};
+use crate::error::SourceLocation;
+use crate::config_parser::ConfigurationValue;
+use crate::routing::*;
+use crate::topology::{Topology,Location};
+
+///What identifies the destinations in the forwarding tables.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum TableDestination
+{
+	///Each entry is for a destination router. The ejection port is selected by the topology at the target router.
+	Router,
+	///Each entry is for a destination server, the entry at its router giving the ejection port.
+	Server,
+}
+
+pub fn new_table_destination(cv:&ConfigurationValue) -> TableDestination
+{
+	match cv
+	{
+		ConfigurationValue::Object(ref cv_name, ref _cv_pairs) => match cv_name.as_ref()
+		{
+			"Router" => TableDestination::Router,
+			"Server" => TableDestination::Server,
+			_ => panic!("Unknown table destination {}",cv_name),
+		},
+		_ => panic!("Trying to create a TableDestination from a non-Object"),
+	}
+}
+
+///Routes by the ports stored in a forwarding table for each router.
+///The tables are loaded from `filename` or, when a `routing` is given, computed from the candidates that routing gives to a packet generated at each router, which are then written into `filename`.
+///Note that for routings that depend on the state of the packet, such as `Valiant`, the computed tables only reflect its first hop.
+///Any port in the entry may be taken through any virtual channel.
+#[derive(Debug)]
+pub struct TableRouting
+{
+	filename: String,
+	destination: TableDestination,
+	///The routing from which to compute the tables, if any.
+	routing: Option<Box<dyn Routing>>,
+	///The ports for each router and destination. Empty when there is no entry.
+	tables: Vec<Vec<Vec<usize>>>,
+}
+
+impl Routing for TableRouting
+{
+	fn next(&self, _routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let destination = match self.destination
+		{
+			TableDestination::Router => target_router,
+			TableDestination::Server => target_server.expect("target server was not given."),
+		};
+		let ports = &self.tables[current_router][destination];
+		if ports.is_empty()
+		{
+			if current_router==target_router
+			{
+				let target_server = target_server.expect("target server was not given.");
+				for i in 0..topology.ports(current_router)
+				{
+					if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+					{
+						if server==target_server
+						{
+							return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+						}
+					}
+				}
+				unreachable!();
+			}
+			return Err(error!(undetermined).with_message(format!("There is no entry in the table of router {} for destination {}",current_router,destination)));
+		}
+		let candidates = ports.iter().flat_map(|&port|(0..num_virtual_channels).map(move |vc|CandidateEgress::new(port,vc))).collect();
+		Ok(RoutingNextCandidates{candidates,idempotent:true})
+	}
+	fn initialize(&mut self, topology:&dyn Topology, rng: &mut StdRng)
+	{
+		if let Some(routing) = self.routing.as_mut()
+		{
+			routing.initialize(topology,rng);
+			self.tables = compute_tables(routing.as_ref(),topology,self.destination,rng);
+			let mut file=File::create(&self.filename).unwrap_or_else(|error|panic!("could not create the table file {}: {}",self.filename,error));
+			write_tables(&mut file,&self.tables).unwrap_or_else(|error|panic!("could not write the table file {}: {}",self.filename,error));
+		}
+		else
+		{
+			let file=File::open(&self.filename).unwrap_or_else(|error|panic!("could not open the table file {}: {}",self.filename,error));
+			self.tables = read_tables(BufReader::new(file),topology,self.destination);
+		}
+	}
+}
+
+impl TableRouting
+{
+	pub fn new(arg: RoutingBuilderArgument) -> TableRouting
+	{
+		let mut filename=None;
+		let mut destination=TableDestination::Router;
+		let mut routing=None;
+		match_object_panic!(arg.cv,"TableRouting",value,
+			"filename" => filename = Some(value.as_str().expect("bad value for filename").to_string()),
+			"destination" => destination = new_table_destination(value),
+			"routing" => routing = Some(new_routing(RoutingBuilderArgument{cv:value,..arg})),
+		);
+		let filename=filename.expect("There were no filename");
+		TableRouting{
+			filename,
+			destination,
+			routing,
+			tables: vec![],
+		}
+	}
+}
+
+///The number of possible destinations in the tables.
+fn num_destinations(topology:&dyn Topology, destination:TableDestination) -> usize
+{
+	match destination
+	{
+		TableDestination::Router => topology.num_routers(),
+		TableDestination::Server => topology.num_servers(),
+	}
+}
+
+///A server attached to the router, if any.
+fn attached_server(topology:&dyn Topology, router:usize) -> Option<usize>
+{
+	(0..topology.ports(router)).find_map(|port|match topology.neighbour(router,port)
+	{
+		(Location::ServerPort(server),_link_class) => Some(server),
+		_ => None,
+	})
+}
+
+///Computes the forwarding tables of a routing. The entry of each router and destination has the ports of the candidates given to a packet generated at that router.
+///Destination routers without servers and the entries towards the own router when tables are by router are left empty.
+pub fn compute_tables(routing:&dyn Routing, topology:&dyn Topology, destination:TableDestination, rng:&mut StdRng) -> Vec<Vec<Vec<usize>>>
+{
+	let num_routers = topology.num_routers();
+	let num_destinations = num_destinations(topology,destination);
+	let mut tables = vec![vec![vec![];num_destinations];num_routers];
+	for target in 0..num_destinations
+	{
+		let (target_router,target_server) = match destination
+		{
+			TableDestination::Router => match attached_server(topology,target)
+			{
+				Some(server) => (target,server),
+				None => continue,
+			},
+			TableDestination::Server => match topology.server_neighbour(target).0
+			{
+				Location::RouterPort{router_index,..} => (router_index,target),
+				_ => panic!("The server {} is not attached to a router",target),
+			},
+		};
+		for (current_router,table) in tables.iter_mut().enumerate()
+		{
+			if destination==TableDestination::Router && current_router==target_router
+			{
+				continue;
+			}
+			let routing_info = RefCell::new(RoutingInfo{source_server:attached_server(topology,current_router),..RoutingInfo::new()});
+			routing.initialize_routing_info(&routing_info,topology,current_router,target_router,Some(target_server),rng);
+			let candidates = routing.next(&routing_info.borrow(),topology,current_router,target_router,Some(target_server),1,rng)
+				.unwrap_or_else(|error|panic!("The routing failed at router {} towards server {}: {}",current_router,target_server,error));
+			let mut ports : Vec<usize> = candidates.candidates.iter().map(|candidate|candidate.port).collect();
+			ports.sort_unstable();
+			ports.dedup();
+			table[target] = ports;
+		}
+	}
+	tables
+}
+
+///Writes the tables with a line `router destination port [port ...]` for each non-empty entry.
+pub fn write_tables<W:Write>(output:&mut W, tables:&[Vec<Vec<usize>>]) -> std::io::Result<()>
+{
+	writeln!(output,"# router destination port [port ...]")?;
+	for (router,table) in tables.iter().enumerate()
+	{
+		for (destination,ports) in table.iter().enumerate()
+		{
+			if ports.is_empty()
+			{
+				continue;
+			}
+			let ports : Vec<String> = ports.iter().map(|port|port.to_string()).collect();
+			writeln!(output,"{} {} {}",router,destination,ports.join(" "))?;
+		}
+	}
+	Ok(())
+}
+
+///Reads the tables written as by `write_tables`, checking the routers, destinations, and ports against the topology.
+pub fn read_tables<R:BufRead>(input:R, topology:&dyn Topology, destination:TableDestination) -> Vec<Vec<Vec<usize>>>
+{
+	let num_routers = topology.num_routers();
+	let num_destinations = num_destinations(topology,destination);
+	let mut tables = vec![vec![vec![];num_destinations];num_routers];
+	for (line_index,rline) in input.lines().enumerate()
+	{
+		let line=rline.expect("Some problem when reading the table file.");
+		let line=line.trim();
+		if line.is_empty() || line.starts_with('#')
+		{
+			continue;
+		}
+		let numbers : Vec<usize> = line.split_whitespace().map(|word|word.parse::<usize>().unwrap_or_else(|_|panic!("bad value {} in line {} of the table file",word,line_index+1))).collect();
+		if numbers.len()<3
+		{
+			panic!("The line {} of the table file has not a router, a destination, and some port",line_index+1);
+		}
+		let router = numbers[0];
+		let target = numbers[1];
+		if router>=num_routers || target>=num_destinations
+		{
+			panic!("The line {} of the table file is out of the topology",line_index+1);
+		}
+		if let Some(port) = numbers[2..].iter().find(|&&port|port>=topology.ports(router))
+		{
+			panic!("The router {} has not port {}, in line {} of the table file",router,port,line_index+1);
+		}
+		tables[router][target].extend_from_slice(&numbers[2..]);
+	}
+	tables
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::topology::{new_topology,TopologyBuilderArgument};
+	use crate::Plugs;
+	#[test]
+	fn dump_and_load_tables()
+	{
+		let plugs = Plugs::default();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let topology_cv = ConfigurationValue::Object("Hamming".to_string(),vec![
+			("sides".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(3.0),ConfigurationValue::Number(3.0)])),
+			("servers_per_router".to_string(),ConfigurationValue::Number(2.0)),
+		]);
+		let topology = new_topology(TopologyBuilderArgument{cv:&topology_cv,plugs:&plugs,rng:&mut rng});
+		let shortest_cv = ConfigurationValue::Object("Shortest".to_string(),vec![]);
+		let mut shortest = new_routing(RoutingBuilderArgument{cv:&shortest_cv,plugs:&plugs});
+		shortest.initialize(topology.as_ref(),&mut rng);
+		for destination in [TableDestination::Router,TableDestination::Server]
+		{
+			let tables = compute_tables(shortest.as_ref(),topology.as_ref(),destination,&mut rng);
+			let mut text = vec![];
+			write_tables(&mut text,&tables).expect("could not write the tables");
+			let loaded = read_tables(&text[..],topology.as_ref(),destination);
+			assert_eq!(tables,loaded);
+			for router in 0..topology.num_routers()
+			{
+				for target in 0..num_destinations(topology.as_ref(),destination)
+				{
+					let target_router = match destination
+					{
+						TableDestination::Router => target,
+						TableDestination::Server => match topology.server_neighbour(target).0 {
+							Location::RouterPort{router_index,..} => router_index,
+							_ => unreachable!(),
+						},
+					};
+					let distance = topology.distance(router,target_router);
+					//In a 3x3 Hamming there is one minimal port per differing coordinate.
+					match (destination,distance)
+					{
+						(TableDestination::Router,0) => assert!(tables[router][target].is_empty()),
+						(_,0) => assert_eq!(tables[router][target].len(),1),
+						_ => assert_eq!(tables[router][target].len(),distance),
+					}
+				}
+			}
+		}
+	}
+}
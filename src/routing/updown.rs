@@ -4,6 +4,7 @@ Implementation of general Up/Down-like routings.
 
 * UpDown
 * UpDownStar (struct ExplicitUpDown)
+* BalancedUpDown
 
 */
 
@@ -15,6 +16,8 @@ use crate::routing::prelude::*;
 use crate::topology::{Topology,NeighbourRouterIteratorItem,Location};
 use crate::matrix::Matrix;
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash,Hasher};
 use rand::Rng;
 
 ///Use a shortest up/down path from origin to destination.
@@ -663,12 +666,225 @@ impl UpDownDerouting {
     }
 }
 
+///How `BalancedUpDown` selects among the ports that reduce the up/down distance.
+#[derive(Debug)]
+pub enum UpDownPortSelection
+{
+	///Destination-mod-k. The hop `h` with `k_h` candidates takes the candidate `(d/(k_0*...*k_{h-1})) % k_h`, being `d` the destination server.
+	DModK,
+	///A hash of the given fields of the flow and a random salt, mixed with the hop number.
+	Hash{
+		fields: Vec<FlowField>,
+	},
+	///The candidates with least phits at the neighbour, as estimated from the credits.
+	Adaptive,
+}
+
+///Fields of a packet that identify its flow for `UpDownPortSelection::Hash`.
+#[derive(Debug,Clone,Copy)]
+pub enum FlowField
+{
+	SourceServer,
+	DestinationServer,
+	SourceRouter,
+	DestinationRouter,
+}
+
+impl FlowField
+{
+	pub fn new(cv:&ConfigurationValue) -> FlowField
+	{
+		if let ConfigurationValue::Object(cv_name, _cv_pairs)=cv
+		{
+			match cv_name.as_ref()
+			{
+				"SourceServer" => FlowField::SourceServer,
+				"DestinationServer" => FlowField::DestinationServer,
+				"SourceRouter" => FlowField::SourceRouter,
+				"DestinationRouter" => FlowField::DestinationRouter,
+				_ => panic!("Unknown flow field {}",cv_name),
+			}
+		}
+		else
+		{
+			panic!("Trying to create a FlowField from a non-Object");
+		}
+	}
+}
+
+pub fn new_up_down_port_selection(cv:&ConfigurationValue) -> UpDownPortSelection
+{
+	if let ConfigurationValue::Object(cv_name, _cv_pairs)=cv
+	{
+		match cv_name.as_ref()
+		{
+			"DModK" => UpDownPortSelection::DModK,
+			"Hash" =>
+			{
+				let mut fields = vec![FlowField::SourceServer,FlowField::DestinationServer];
+				match_object_panic!(cv,"Hash",value,
+					"fields" => fields=value.as_array().expect("bad value for fields").iter().map(FlowField::new).collect(),
+				);
+				UpDownPortSelection::Hash{fields}
+			},
+			"Adaptive" => UpDownPortSelection::Adaptive,
+			_ => panic!("Unknown up/down port selection {}",cv_name),
+		}
+	}
+	else
+	{
+		panic!("Trying to create an UpDownPortSelection from a non-Object");
+	}
+}
+
+///Up/down routing for fat-trees and other `MultiStage` topologies that selects a single port among those reducing the up/down distance.
+///With the deterministic selections `DModK` and `Hash` all the packets of a flow follow the same path, so they arrive in order.
+#[derive(Debug)]
+pub struct BalancedUpDown
+{
+	selection: UpDownPortSelection,
+	///Random value included in the hashes, set at initialization.
+	salt: u64,
+}
+
+//The selections of the routing info store `[value, hops]`, where the value is the divisor of DModK or the hash of the flow.
+impl Routing for BalancedUpDown
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		if current_router==target_router
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let ports = Self::candidate_ports(topology,current_router,target_router);
+		let ports = if ports.is_empty() { ports } else {
+			let selections = routing_info.selections.as_ref().expect("The routing info has not been initialized");
+			match self.selection
+			{
+				UpDownPortSelection::DModK =>
+				{
+					let destination = target_server.unwrap_or(target_router);
+					vec![ports[(destination/selections[0] as usize)%ports.len()]]
+				},
+				UpDownPortSelection::Hash{..} =>
+				{
+					let mut hasher = DefaultHasher::new();
+					(selections[0],selections[1]).hash(&mut hasher);
+					vec![ports[(hasher.finish()%ports.len() as u64) as usize]]
+				},
+				UpDownPortSelection::Adaptive => ports,
+			}
+		};
+		Ok(RoutingNextCandidates{candidates:ports.into_iter().flat_map(|port|(0..num_virtual_channels).map(move |vc|CandidateEgress::new(port,vc))).collect(),idempotent:true})
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let candidates = self.next(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)?;
+		if let UpDownPortSelection::Adaptive = self.selection
+		{
+			let least = candidates.candidates.iter().map(|candidate|congestion.neighbour_occupation(candidate.port)).min();
+			let candidates = candidates.into_iter().filter(|candidate|Some(congestion.neighbour_occupation(candidate.port))==least).collect();
+			//The least occupied ports change from one cycle to the next.
+			return Ok(RoutingNextCandidates{candidates,idempotent:false});
+		}
+		Ok(candidates)
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		let mut bri = routing_info.borrow_mut();
+		let value = match self.selection
+		{
+			UpDownPortSelection::DModK => 1,
+			UpDownPortSelection::Hash{ref fields} =>
+			{
+				let mut hasher = DefaultHasher::new();
+				self.salt.hash(&mut hasher);
+				for field in fields
+				{
+					let value = match field
+					{
+						FlowField::SourceServer => bri.source_server,
+						FlowField::DestinationServer => target_server,
+						FlowField::SourceRouter => Some(current_router),
+						FlowField::DestinationRouter => Some(target_router),
+					};
+					value.hash(&mut hasher);
+				}
+				(hasher.finish()>>33) as i32
+			},
+			UpDownPortSelection::Adaptive => 0,
+		};
+		bri.selections = Some(vec![value,0]);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		let mut bri = routing_info.borrow_mut();
+		let selections = bri.selections.as_mut().expect("The routing info has not been initialized");
+		selections[1]+=1;
+		if let UpDownPortSelection::DModK = self.selection
+		{
+			if let (Location::RouterPort{router_index:previous_router,..},_link_class) = topology.neighbour(current_router,current_port)
+			{
+				let amount = Self::candidate_ports(topology,previous_router,target_router).len().max(1);
+				selections[0] = (selections[0] as i64 * amount as i64).min(i32::MAX as i64) as i32;
+			}
+		}
+	}
+	fn initialize(&mut self, _topology:&dyn Topology, rng: &mut StdRng)
+	{
+		self.salt = rng.gen();
+	}
+}
+
+impl BalancedUpDown
+{
+	pub fn new(arg: RoutingBuilderArgument) -> BalancedUpDown
+	{
+		let mut selection = None;
+		match_object_panic!(arg.cv,"BalancedUpDown",value,
+			"selection" => selection=Some(new_up_down_port_selection(value)),
+		);
+		let selection = selection.expect("There were no selection");
+		BalancedUpDown{
+			selection,
+			salt: 0,
+		}
+	}
+	///The ports of `current_router` that reduce the up/down distance to `target_router`, in increasing order.
+	fn candidate_ports(topology:&dyn Topology, current_router:usize, target_router:usize) -> Vec<usize>
+	{
+		let (up_distance, down_distance) = topology.up_down_distance(current_router,target_router).unwrap_or_else(||panic!("The topology does not provide an up/down path from {} to {}",current_router,target_router));
+		(0..topology.ports(current_router)).filter(|&port|{
+			if let (Location::RouterPort{router_index,router_port:_},_link_class)=topology.neighbour(current_router,port)
+			{
+				if let Some((new_u, new_d)) = topology.up_down_distance(router_index,target_router)
+				{
+					return (new_u<up_distance && new_d<=down_distance) || (new_u<=up_distance && new_d<down_distance);
+				}
+			}
+			false
+		}).collect()
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use crate::Plugs;
 	use rand::SeedableRng;
 	use crate::topology::cartesian::Hamming;
+	use crate::topology::TopologyBuilderArgument;
 	#[test]
 	fn up_down_star()
 	{
@@ -703,5 +919,56 @@ mod tests {
 			}
 		}
 	}
-}
 
+	#[test]
+	fn balanced_up_down_xgft()
+	{
+		use crate::topology::multistage::MultiStage;
+		let plugs = Plugs::default();
+		let mut rng=StdRng::seed_from_u64(10u64);
+		let xgft_cv = ConfigurationValue::Object("XGFT".to_string(),vec![
+			("height".to_string(),ConfigurationValue::Number(2.0)),
+			("down".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)])),
+			("up".to_string(),ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0),ConfigurationValue::Number(4.0)])),
+			("servers_per_leaf".to_string(),ConfigurationValue::Number(4.0)),
+		]);
+		let topology = MultiStage::new(TopologyBuilderArgument{cv:&xgft_cv,plugs:&plugs,rng:&mut rng});
+		let hash_cv = ConfigurationValue::Object("Hash".to_string(),vec![]);
+		for selection_cv in [ConfigurationValue::Object("DModK".to_string(),vec![]), hash_cv]
+		{
+			let routing_cv = ConfigurationValue::Object("BalancedUpDown".to_string(),vec![("selection".to_string(),selection_cv)]);
+			let mut routing = BalancedUpDown::new(RoutingBuilderArgument{cv:&routing_cv,plugs:&plugs});
+			routing.initialize(&topology,&mut rng);
+			let is_dmodk = matches!(routing.selection,UpDownPortSelection::DModK);
+			// Follow the single candidate from the leaf 0 to each server in the other half, returning the top router of the path.
+			let mut tops = vec![];
+			for target_server in 16..32
+			{
+				let target_router = target_server/4;
+				let routing_info = RefCell::new(RoutingInfo{source_server:Some(0),..RoutingInfo::new()});
+				routing.initialize_routing_info(&routing_info,&topology,0,target_router,Some(target_server),&mut rng);
+				let mut current = 0;
+				let mut path = vec![];
+				while current!=target_router
+				{
+					let candidates = routing.next(&routing_info.borrow(),&topology,current,target_router,Some(target_server),1,&mut rng).expect("routing error");
+					assert_eq!(candidates.len(),1,"the selection must give a single port");
+					let port = candidates.candidates[0].port;
+					let (location,_link_class) = topology.neighbour(current,port);
+					let (next,next_port) = match location { Location::RouterPort{router_index,router_port} => (router_index,router_port), _ => panic!("left the network") };
+					routing.update_routing_info(&routing_info,&topology,next,next_port,target_router,Some(target_server),&mut rng);
+					current = next;
+					path.push(current);
+				}
+				assert_eq!(path.len(),4,"the route must be minimal");
+				tops.push(path[1]);
+			}
+			if is_dmodk
+			{
+				tops.sort_unstable();
+				tops.dedup();
+				assert_eq!(tops.len(),16,"DModK must spread consecutive destinations over all the top routers");
+			}
+		}
+	}
+}
@@ -0,0 +1,362 @@
+/*!
+
+Analytical upper bounds of the throughput, to compare with the `accepted_load` obtained by simulation.
+
+The bound is the maximum concurrent flow of the demands given by a pattern: the largest load each server could inject such that all the demands can be routed at the same time without exceeding the capacity of any link.
+It is computed with the Garg-Könemann multiplicative weights algorithm, which gives both a feasible flow and a dual bound. The computation stops when they are within a factor `1+epsilon`.
+Two models of routing are considered: routing only through minimal paths and routing through any path.
+
+It is enabled by the key `throughput_bound` in the main configuration, which gets added to the result as an object `ThroughputBound` that can be accessed in the outputs with, e.g., `=result.throughput_bound.minimal`.
+
+```ignore
+throughput_bound: ThroughputBound{
+	pattern: Uniform,//the pattern to bound. Usually the same as the one of the traffic.
+	samples: 100,//how many destinations are sampled from each server to build the demands. Defaults to 100.
+	epsilon: 0.1,//the precision of the approximation. Defaults to 0.1.
+}
+```
+
+The result contains
+* `minimal`, `unrestricted`: the upper bounds when routing by minimal or arbitrary paths, limited also by the injection links of the servers.
+* `minimal_feasible`, `unrestricted_feasible`: loads that have been verified to be feasible. The true bound lies between these and the previous ones.
+* `servers`: the bound imposed by the injection and ejection links of the servers alone.
+
+Random patterns are approximated by sampling, which can make some destinations appear more loaded than they are. This is why the ejection is not included in `minimal` and `unrestricted`, which should be combined with `servers` when the pattern has hot spots. Increase `samples` for a better approximation.
+
+Loads are given in phits per cycle per server, as `accepted_load`. The capacity of a link is one phit each `frequency_divisor` cycles of its link class.
+Servers with several attachments only route their traffic through the first one, although all their links count for the bound of `servers`.
+
+*/
+
+use std::collections::BinaryHeap;
+use std::cmp::Reverse;
+
+use ::rand::rngs::StdRng;
+
+use crate::config_parser::ConfigurationValue;
+use crate::match_object_panic;
+use crate::pattern::{Pattern,new_pattern,PatternBuilderArgument};
+use crate::topology::{Topology,Location};
+use crate::Plugs;
+
+///The configuration of the computation of the throughput bound. See the [module documentation](self).
+#[derive(Debug)]
+pub struct ThroughputBound
+{
+	pattern: Box<dyn Pattern>,
+	samples: usize,
+	epsilon: f64,
+}
+
+impl ThroughputBound
+{
+	pub fn new(cv:&ConfigurationValue, plugs:&Plugs) -> ThroughputBound
+	{
+		let mut pattern = None;
+		let mut samples = 100;
+		let mut epsilon = 0.1;
+		match_object_panic!(cv,"ThroughputBound",value,
+			"pattern" => pattern=Some(new_pattern(PatternBuilderArgument{cv:value,plugs})),
+			"samples" => samples=value.as_usize().expect("bad value for samples"),
+			"epsilon" => epsilon=value.as_f64().expect("bad value for epsilon"),
+		);
+		let pattern = pattern.expect("There were no pattern");
+		assert!(samples>0, "ThroughputBound requires some sample.");
+		assert!(epsilon>0.0 && epsilon<0.5, "The epsilon of ThroughputBound must be in (0,0.5).");
+		ThroughputBound{
+			pattern,
+			samples,
+			epsilon,
+		}
+	}
+	///Computes the bounds, being `link_capacities[c]` the capacity in phits per cycle of the links of class `c`.
+	///Returns the `ThroughputBound` object to include in the results.
+	pub fn compute(&mut self, topology:&dyn Topology, link_capacities:&[f64], rng:&mut StdRng) -> ConfigurationValue
+	{
+		let num_servers = topology.num_servers();
+		let n = topology.num_routers();
+		self.pattern.initialize(num_servers,num_servers,topology,rng);
+		let weight = 1.0/self.samples as f64;
+		let mut router_demands = vec![vec![0f64;n];n];
+		let mut server_injection = vec![0f64;num_servers];
+		let mut server_ejection = vec![0f64;num_servers];
+		for origin in 0..num_servers
+		{
+			for _ in 0..self.samples
+			{
+				let destination = self.pattern.get_destination(origin,topology,rng);
+				server_injection[origin] += weight;
+				server_ejection[destination] += weight;
+				let origin_router = server_router(topology,origin);
+				let destination_router = server_router(topology,destination);
+				if origin_router!=destination_router
+				{
+					router_demands[origin_router][destination_router] += weight;
+				}
+			}
+		}
+		//The bounds imposed by the server links.
+		let mut injection_bound = f64::INFINITY;
+		let mut servers_bound = f64::INFINITY;
+		for server in 0..num_servers
+		{
+			let capacity : f64 = topology.server_neighbours(server).iter().map(|&(_,link_class)|link_capacities[link_class]).sum();
+			if server_injection[server]>0.0
+			{
+				injection_bound = injection_bound.min(capacity/server_injection[server]);
+			}
+			if server_ejection[server]>0.0
+			{
+				servers_bound = servers_bound.min(capacity/server_ejection[server]);
+			}
+		}
+		servers_bound = servers_bound.min(injection_bound);
+		let (minimal_feasible,minimal) = maximum_concurrent_flow(topology,&router_demands,link_capacities,self.epsilon,true);
+		let (unrestricted_feasible,unrestricted) = maximum_concurrent_flow(topology,&router_demands,link_capacities,self.epsilon,false);
+		let number = |x:f64| ConfigurationValue::Number(x);
+		ConfigurationValue::Object(String::from("ThroughputBound"),vec![
+			(String::from("minimal"),number(minimal.min(injection_bound))),
+			(String::from("unrestricted"),number(unrestricted.min(injection_bound))),
+			(String::from("minimal_feasible"),number(minimal_feasible.min(injection_bound))),
+			(String::from("unrestricted_feasible"),number(unrestricted_feasible.min(injection_bound))),
+			(String::from("servers"),number(servers_bound)),
+		])
+	}
+}
+
+///The router to which the first attachment of a server is connected.
+fn server_router(topology:&dyn Topology, server:usize) -> usize
+{
+	match topology.server_neighbour(server).0
+	{
+		Location::RouterPort{router_index,router_port:_} => router_index,
+		_ => panic!("The server {} is not attached to a router",server),
+	}
+}
+
+///A directed link between two routers.
+struct Arc
+{
+	to: usize,
+	capacity: f64,
+}
+
+/**
+Computes the maximum concurrent flow for the demands between routers, `demands[origin][destination]` being the amount of demand per unit of load.
+The capacity of each link in each direction is given by its link class.
+When `minimal` is true flow is only allowed through minimal paths in number of hops.
+
+Returns `(feasible,upper)`, with `feasible` a load that has been routed without exceeding any capacity and `upper` a bound from the dual problem, both in the same units as `demands`.
+When there are no demands both values are infinite.
+**/
+pub fn maximum_concurrent_flow(topology:&dyn Topology, demands:&[Vec<f64>], link_capacities:&[f64], epsilon:f64, minimal:bool) -> (f64,f64)
+{
+	let n = topology.num_routers();
+	let mut arcs = vec![];
+	let mut out_arcs = vec![vec![];n];
+	for router_index in 0..n
+	{
+		for port in 0..topology.ports(router_index)
+		{
+			if let (Location::RouterPort{router_index:neighbour,router_port:_},link_class) = topology.neighbour(router_index,port)
+			{
+				if neighbour!=router_index
+				{
+					out_arcs[router_index].push(arcs.len());
+					arcs.push(Arc{to:neighbour,capacity:link_capacities[link_class]});
+				}
+			}
+		}
+	}
+	let sources : Vec<usize> = (0..n).filter(|&s|demands[s].iter().any(|&d|d>0.0)).collect();
+	if sources.is_empty()
+	{
+		return (f64::INFINITY,f64::INFINITY);
+	}
+	let hops = if minimal { Some(topology.compute_distance_matrix(None)) } else { None };
+	let allowed = |arc_from:usize, arc:&Arc, source:usize| match hops
+	{
+		Some(ref hops) => *hops.get(source,arc.to) == hops.get(source,arc_from).saturating_add(1),
+		None => true,
+	};
+	//Shortest path tree from `source` according to `lengths`. Returns the distances, the arc towards each router, and the routers in the order they were settled.
+	let tree = |source:usize, lengths:&[f64]| -> (Vec<f64>,Vec<usize>,Vec<usize>) {
+		let mut distance = vec![f64::INFINITY;n];
+		let mut parent_arc = vec![usize::MAX;n];
+		let mut settled = vec![false;n];
+		let mut order = Vec::with_capacity(n);
+		let mut heap = BinaryHeap::new();
+		distance[source]=0.0;
+		//Non-negative floats compare as their bit representation.
+		heap.push(Reverse((0f64.to_bits(),source)));
+		while let Some(Reverse((_,u))) = heap.pop()
+		{
+			if settled[u] { continue; }
+			settled[u]=true;
+			order.push(u);
+			for &a in out_arcs[u].iter()
+			{
+				let arc = &arcs[a];
+				if !allowed(u,arc,source) { continue; }
+				let alternative = distance[u] + lengths[a];
+				if alternative < distance[arc.to]
+				{
+					distance[arc.to]=alternative;
+					parent_arc[arc.to]=a;
+					heap.push(Reverse((alternative.to_bits(),arc.to)));
+				}
+			}
+		}
+		(distance,parent_arc,order)
+	};
+	//The origin of each arc, to walk the trees upwards.
+	let mut arc_from = vec![0;arcs.len()];
+	for (u,list) in out_arcs.iter().enumerate()
+	{
+		for &a in list.iter() { arc_from[a]=u; }
+	}
+	//Discard the case of disconnected demands.
+	for &s in sources.iter()
+	{
+		let (distance,_,_) = tree(s,&vec![1.0;arcs.len()]);
+		if (0..n).any(|t|demands[s][t]>0.0 && distance[t]==f64::INFINITY)
+		{
+			return (0.0,0.0);
+		}
+	}
+	let m = arcs.len() as f64;
+	let log_inverse_delta = (m/(1.0-epsilon)).ln()/epsilon;
+	assert!(log_inverse_delta < 600.0, "The epsilon of the throughput bound is too small for a network of this size.");
+	let delta = (-log_inverse_delta).exp();
+	let mut lengths : Vec<f64> = arcs.iter().map(|arc|delta/arc.capacity).collect();
+	let mut dual_objective = delta*m;//sum of lengths times capacities
+	let mut arc_flow = vec![0f64;arcs.len()];
+	let mut commodity_flow = vec![vec![0f64;n];n];
+	//Scale the demands so that the optimum is at least 1, by routing them through shortest paths in hops.
+	let mut scale = {
+		let mut load = vec![0f64;arcs.len()];
+		for &s in sources.iter()
+		{
+			let (_,parent_arc,order) = tree(s,&vec![1.0;arcs.len()]);
+			let mut subtree : Vec<f64> = demands[s].clone();
+			for &v in order.iter().rev()
+			{
+				if v==s { continue; }
+				let a = parent_arc[v];
+				load[a] += subtree[v];
+				subtree[arc_from[a]] += subtree[v];
+			}
+		}
+		let congestion = (0..arcs.len()).map(|a|load[a]/arcs[a].capacity).fold(0f64,f64::max);
+		1.0/congestion
+	};
+	//After this many phases the optimum is known to be at least twice the current one, so demands are doubled.
+	let phases_before_doubling = (2.0*log_inverse_delta/(1.0+epsilon).ln()).ceil() as usize;
+	let mut phases = 0;
+	let mut upper = f64::INFINITY;
+	let mut feasible = 0f64;
+	while dual_objective < 1.0
+	{
+		for &s in sources.iter()
+		{
+			let mut remaining : Vec<f64> = demands[s].iter().map(|d|d*scale).collect();
+			while dual_objective < 1.0 && remaining.iter().any(|&d|d>0.0)
+			{
+				let (_,parent_arc,order) = tree(s,&lengths);
+				let mut subtree = remaining.clone();
+				let mut load = vec![];
+				for &v in order.iter().rev()
+				{
+					if v==s || subtree[v]==0.0 { continue; }
+					let a = parent_arc[v];
+					load.push((a,subtree[v]));
+					subtree[arc_from[a]] += subtree[v];
+				}
+				let sigma = load.iter().map(|&(a,l)|l/arcs[a].capacity).fold(1f64,f64::max);
+				for t in 0..n
+				{
+					let routed = remaining[t]/sigma;
+					commodity_flow[s][t] += routed;
+					remaining[t] = if sigma>1.0 { remaining[t]-routed } else { 0.0 };
+				}
+				for &(a,l) in load.iter()
+				{
+					let routed = l/sigma;
+					arc_flow[a] += routed;
+					let increment = lengths[a]*epsilon*routed/arcs[a].capacity;
+					lengths[a] += increment;
+					dual_objective += increment*arcs[a].capacity;
+				}
+			}
+		}
+		//Dual bound: sum of lengths times capacities divided by the cost of routing the demands through shortest paths.
+		let mut alpha = 0.0;
+		for &s in sources.iter()
+		{
+			let (distance,_,_) = tree(s,&lengths);
+			alpha += (0..n).map(|t|demands[s][t]*distance[t]).sum::<f64>();
+		}
+		upper = upper.min(dual_objective/alpha);
+		//Primal bound: the accumulated flow scaled down to respect the capacities.
+		let congestion = (0..arcs.len()).map(|a|arc_flow[a]/arcs[a].capacity).fold(0f64,f64::max);
+		let mut routed_fraction = f64::INFINITY;
+		for &s in sources.iter()
+		{
+			for t in 0..n
+			{
+				if demands[s][t]>0.0
+				{
+					routed_fraction = routed_fraction.min(commodity_flow[s][t]/demands[s][t]);
+				}
+			}
+		}
+		feasible = feasible.max(routed_fraction/congestion);
+		if upper <= (1.0+epsilon)*feasible
+		{
+			//Usually much before the guaranteed termination.
+			break;
+		}
+		phases+=1;
+		if phases % phases_before_doubling == 0
+		{
+			scale*=2.0;
+		}
+	}
+	(feasible,upper.max(feasible))
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use rand::SeedableRng;
+	use crate::topology::{new_topology,TopologyBuilderArgument};
+	use crate::config_parser;
+	fn parse(text:&str) -> ConfigurationValue
+	{
+		match config_parser::parse(text).unwrap()
+		{
+			config_parser::Token::Value(value) => value,
+			_ => panic!("Not a value"),
+		}
+	}
+	#[test]
+	fn ring_shift()
+	{
+		let plugs = Plugs::default();
+		let mut rng = StdRng::seed_from_u64(1);
+		let topology = new_topology(TopologyBuilderArgument{cv:&parse("Torus{sides:[8],servers_per_router:1}"),plugs:&plugs,rng:&mut rng});
+		//Each server sends to the one at distance 4 in a ring of 8. Minimally half goes each way, giving 4 demands through each directed link.
+		let mut bound = ThroughputBound::new(&parse("ThroughputBound{pattern:CartesianTransform{sides:[8],shift:[4]},samples:1,epsilon:0.05}"),&plugs);
+		let result = bound.compute(topology.as_ref(),&[1.0,1.0],&mut rng);
+		let get = |key:&str| match result { ConfigurationValue::Object(_,ref pairs) => pairs.iter().find(|(name,_)|name==key).unwrap().1.as_f64().unwrap(), _ => panic!() };
+		assert!( (get("servers")-1.0).abs()<1e-9 );
+		for key in ["minimal","unrestricted"]
+		{
+			let upper = get(key);
+			let feasible = get(&format!("{}_feasible",key));
+			assert!(feasible<=0.5+1e-9 && upper>=0.5-1e-9, "{} feasible={} upper={}",key,feasible,upper);
+			assert!(feasible>=0.5*0.85 && upper<=0.5*1.15, "{} feasible={} upper={}",key,feasible,upper);
+		}
+	}
+}
@@ -0,0 +1,506 @@
+/*!
+
+Structural analysis of topologies, intended to characterize a network without running any simulation.
+
+The main entry point is [`analyze_topology`], which builds a [`TopologyAnalysis`] with
+* the distribution of distances between routers and the average distance,
+* an estimation of the bisection width by the Kernighan-Lin partitioning heuristic,
+* the amount of edge-disjoint paths between pairs of routers, computed as unit-capacity maximum flows,
+* the spectral gap of the adjacency matrix of the router graph,
+* the amount of links of each link class.
+
+This is used by the `analyze_topology` [action](crate::experiments::Action::AnalyzeTopology).
+
+*/
+
+use ::rand::{Rng,SeedableRng,rngs::StdRng};
+
+use super::{Topology,Location,TopologyBuilderArgument,new_topology};
+use crate::config_parser::ConfigurationValue;
+use crate::Plugs;
+
+///Parameters controlling the cost of the analysis.
+#[derive(Debug,Clone)]
+pub struct TopologyAnalysisOptions
+{
+	///Number of random restarts of the Kernighan-Lin heuristic. The best bisection found is reported.
+	pub bisection_restarts: usize,
+	///Maximum number of pairs of routers in which to compute the edge-disjoint paths.
+	///If there are more pairs than this then a random sample of this size is taken.
+	pub edge_disjoint_samples: usize,
+	///Maximum number of iterations of the power method for each eigenvalue.
+	pub spectral_iterations: usize,
+}
+
+impl Default for TopologyAnalysisOptions
+{
+	fn default() -> TopologyAnalysisOptions
+	{
+		TopologyAnalysisOptions{
+			bisection_restarts: 8,
+			edge_disjoint_samples: 1000,
+			spectral_iterations: 2000,
+		}
+	}
+}
+
+///The result of [`analyze_topology`].
+#[derive(Debug,Clone)]
+pub struct TopologyAnalysis
+{
+	pub num_routers: usize,
+	pub num_servers: usize,
+	///Number of links between two routers.
+	pub num_router_links: usize,
+	pub diameter: usize,
+	///`distance_distribution[d]` is the number of ordered pairs of different routers at distance `d`.
+	pub distance_distribution: Vec<usize>,
+	///Number of ordered pairs of routers without any path between them.
+	pub unreachable_pairs: usize,
+	///Average distance between different routers, considering only the reachable pairs.
+	pub average_distance: f64,
+	///Smallest number of links crossing a balanced partition of the routers that has been found.
+	pub bisection_links: usize,
+	///The routers in the first part of the best bisection found.
+	pub bisection_part: Vec<usize>,
+	///Number of pairs of routers in which the edge-disjoint paths have been computed.
+	pub edge_disjoint_pairs: usize,
+	pub edge_disjoint_minimum: usize,
+	pub edge_disjoint_average: f64,
+	pub edge_disjoint_maximum: usize,
+	///Largest eigenvalue of the adjacency matrix of the routers.
+	pub largest_eigenvalue: f64,
+	///Second largest eigenvalue of the adjacency matrix of the routers.
+	pub second_eigenvalue: f64,
+	///`link_class_counts[c]` is the number of links of class `c`, including the links to servers.
+	pub link_class_counts: Vec<usize>,
+}
+
+impl TopologyAnalysis
+{
+	///The difference between the two largest eigenvalues.
+	pub fn spectral_gap(&self) -> f64
+	{
+		self.largest_eigenvalue - self.second_eigenvalue
+	}
+	///The bisection width divided by the links required to give full bandwidth to half the servers.
+	pub fn normalized_bisection(&self) -> f64
+	{
+		if self.num_servers < 2 { return 0f64; }
+		self.bisection_links as f64 / (self.num_servers/2) as f64
+	}
+	///Builds an object `TopologyAnalysis{...}` with all the metrics.
+	pub fn to_configuration_value(&self) -> ConfigurationValue
+	{
+		let number = |x:f64| ConfigurationValue::Number(x);
+		let array = |v:&[usize]| ConfigurationValue::Array(v.iter().map(|&x|ConfigurationValue::Number(x as f64)).collect());
+		ConfigurationValue::Object(String::from("TopologyAnalysis"),vec![
+			(String::from("num_routers"),number(self.num_routers as f64)),
+			(String::from("num_servers"),number(self.num_servers as f64)),
+			(String::from("num_router_links"),number(self.num_router_links as f64)),
+			(String::from("diameter"),number(self.diameter as f64)),
+			(String::from("distance_distribution"),array(&self.distance_distribution)),
+			(String::from("unreachable_pairs"),number(self.unreachable_pairs as f64)),
+			(String::from("average_distance"),number(self.average_distance)),
+			(String::from("bisection_links"),number(self.bisection_links as f64)),
+			(String::from("normalized_bisection"),number(self.normalized_bisection())),
+			(String::from("bisection_part"),array(&self.bisection_part)),
+			(String::from("edge_disjoint_paths"),ConfigurationValue::Object(String::from("EdgeDisjointPaths"),vec![
+				(String::from("pairs"),number(self.edge_disjoint_pairs as f64)),
+				(String::from("minimum"),number(self.edge_disjoint_minimum as f64)),
+				(String::from("average"),number(self.edge_disjoint_average)),
+				(String::from("maximum"),number(self.edge_disjoint_maximum as f64)),
+			])),
+			(String::from("largest_eigenvalue"),number(self.largest_eigenvalue)),
+			(String::from("second_eigenvalue"),number(self.second_eigenvalue)),
+			(String::from("spectral_gap"),number(self.spectral_gap())),
+			(String::from("link_class_counts"),array(&self.link_class_counts)),
+		])
+	}
+	///The header of the CSV generated by [`Self::csv_row`]. The distance distribution and the link class counts are given a column per entry.
+	pub fn csv_header(max_distance:usize, num_link_classes:usize) -> String
+	{
+		let mut header : Vec<String> = ["num_routers","num_servers","num_router_links","diameter","average_distance","unreachable_pairs","bisection_links","normalized_bisection","edge_disjoint_minimum","edge_disjoint_average","edge_disjoint_maximum","largest_eigenvalue","second_eigenvalue","spectral_gap"].iter().map(|s|s.to_string()).collect();
+		header.extend( (0..=max_distance).map(|d|format!("distance_{}",d)) );
+		header.extend( (0..num_link_classes).map(|c|format!("link_class_{}",c)) );
+		header.join(",")
+	}
+	///A line of CSV with the metrics, with as many entries as in [`Self::csv_header`] with the same arguments.
+	pub fn csv_row(&self, max_distance:usize, num_link_classes:usize) -> String
+	{
+		let mut row = vec![
+			self.num_routers.to_string(),
+			self.num_servers.to_string(),
+			self.num_router_links.to_string(),
+			self.diameter.to_string(),
+			self.average_distance.to_string(),
+			self.unreachable_pairs.to_string(),
+			self.bisection_links.to_string(),
+			self.normalized_bisection().to_string(),
+			self.edge_disjoint_minimum.to_string(),
+			self.edge_disjoint_average.to_string(),
+			self.edge_disjoint_maximum.to_string(),
+			self.largest_eigenvalue.to_string(),
+			self.second_eigenvalue.to_string(),
+			self.spectral_gap().to_string(),
+		];
+		row.extend( (0..=max_distance).map(|d|self.distance_distribution.get(d).unwrap_or(&0).to_string()) );
+		row.extend( (0..num_link_classes).map(|c|self.link_class_counts.get(c).unwrap_or(&0).to_string()) );
+		row.join(",")
+	}
+}
+
+///Computes all the metrics in [`TopologyAnalysis`] for the given topology.
+///The random number generator is employed by the bisection heuristic and to sample the pairs for the edge-disjoint paths.
+pub fn analyze_topology(topology:&dyn Topology, options:&TopologyAnalysisOptions, rng:&mut StdRng) -> TopologyAnalysis
+{
+	let n = topology.num_routers();
+	let adjacency = router_adjacency(topology);
+	//Distances
+	let distance_matrix = topology.compute_distance_matrix(None);
+	let mut distance_distribution = vec![];
+	let mut unreachable_pairs = 0;
+	let mut distance_sum = 0;
+	for origin in 0..n
+	{
+		for destination in 0..n
+		{
+			if origin==destination { continue; }
+			let d = *distance_matrix.get(origin,destination);
+			if d==usize::MAX
+			{
+				unreachable_pairs+=1;
+				continue;
+			}
+			if d>=distance_distribution.len()
+			{
+				distance_distribution.resize(d+1,0);
+			}
+			distance_distribution[d]+=1;
+			distance_sum+=d;
+		}
+	}
+	let reachable_pairs = n*n.saturating_sub(1) - unreachable_pairs;
+	let average_distance = if reachable_pairs>0 { distance_sum as f64 / reachable_pairs as f64 } else { 0f64 };
+	let diameter = distance_distribution.len().saturating_sub(1);
+	//Link classes
+	let mut link_class_counts = vec![];
+	let mut count_class = |link_class:usize| {
+		if link_class>=link_class_counts.len()
+		{
+			link_class_counts.resize(link_class+1,0);
+		}
+		link_class_counts[link_class]+=1;
+	};
+	let mut num_router_links = 0;
+	for router_index in 0..n
+	{
+		for port in 0..topology.ports(router_index)
+		{
+			if let (Location::RouterPort{router_index:neighbour_router,router_port:neighbour_port},link_class) = topology.neighbour(router_index,port)
+			{
+				//Count each link only from its smallest endpoint.
+				if (router_index,port) < (neighbour_router,neighbour_port)
+				{
+					count_class(link_class);
+					num_router_links+=1;
+				}
+			}
+		}
+	}
+	for server_index in 0..topology.num_servers()
+	{
+		for (_location,link_class) in topology.server_neighbours(server_index)
+		{
+			count_class(link_class);
+		}
+	}
+	//Bisection
+	let (bisection_links,bisection_part) = kernighan_lin_bisection(&adjacency,options.bisection_restarts,rng);
+	//Edge-disjoint paths
+	let pairs : Vec<(usize,usize)> = if n*n.saturating_sub(1) <= options.edge_disjoint_samples
+	{
+		(0..n).flat_map(|origin|(0..n).filter(move |&destination|destination!=origin).map(move |destination|(origin,destination))).collect()
+	}
+	else
+	{
+		(0..options.edge_disjoint_samples).map(|_|{
+			let origin = rng.gen_range(0..n);
+			let destination = (origin + rng.gen_range(1..n)) % n;
+			(origin,destination)
+		}).collect()
+	};
+	let paths : Vec<usize> = pairs.iter().map(|&(origin,destination)|edge_disjoint_paths(&adjacency,origin,destination)).collect();
+	let edge_disjoint_minimum = paths.iter().copied().min().unwrap_or(0);
+	let edge_disjoint_maximum = paths.iter().copied().max().unwrap_or(0);
+	let edge_disjoint_average = if paths.is_empty() { 0f64 } else { paths.iter().sum::<usize>() as f64 / paths.len() as f64 };
+	//Spectrum
+	let (largest_eigenvalue,second_eigenvalue) = two_largest_eigenvalues(&adjacency,options.spectral_iterations);
+	TopologyAnalysis{
+		num_routers: n,
+		num_servers: topology.num_servers(),
+		num_router_links,
+		diameter,
+		distance_distribution,
+		unreachable_pairs,
+		average_distance,
+		bisection_links,
+		bisection_part,
+		edge_disjoint_pairs: pairs.len(),
+		edge_disjoint_minimum,
+		edge_disjoint_average,
+		edge_disjoint_maximum,
+		largest_eigenvalue,
+		second_eigenvalue,
+		link_class_counts,
+	}
+}
+
+///Analyzes the topology of a simulation configuration, built as in `Simulation::new` from its `topology` and `random_seed` fields.
+///The rest of the fields are ignored.
+pub fn analyze_configuration_topology(configuration:&ConfigurationValue, plugs:&Plugs, options:&TopologyAnalysisOptions) -> TopologyAnalysis
+{
+	let mut seed = None;
+	let mut topology_cfg = None;
+	if let ConfigurationValue::Object(_cv_name,cv_pairs) = configuration
+	{
+		for (name,value) in cv_pairs
+		{
+			match name.as_ref()
+			{
+				"random_seed" => seed=Some(value.as_usize().expect("bad value for random_seed")),
+				"topology" => topology_cfg=Some(value),
+				_ => (),
+			}
+		}
+	}
+	else
+	{
+		panic!("Trying to analyze the topology of a non-Object");
+	}
+	let seed=seed.expect("There were no random_seed");
+	let topology_cfg=topology_cfg.expect("There were no topology");
+	let mut rng=StdRng::seed_from_u64(seed as u64);
+	let topology=new_topology(TopologyBuilderArgument{cv:topology_cfg,plugs,rng:&mut rng});
+	analyze_topology(topology.as_ref(),options,&mut rng)
+}
+
+///Lists of neighbour routers, with repetitions for parallel links. Links to the same router are ignored.
+fn router_adjacency(topology:&dyn Topology) -> Vec<Vec<usize>>
+{
+	(0..topology.num_routers()).map(|router_index|
+		topology.neighbour_router_iter(router_index).map(|item|item.neighbour_router).filter(|&neighbour|neighbour!=router_index).collect()
+	).collect()
+}
+
+///Searches a balanced partition of the routers with few links crossing it.
+///Each restart begins with a random partition that is improved by passes of the Kernighan-Lin heuristic until no pass improves it.
+///Returns the number of crossing links and the routers of the first part.
+fn kernighan_lin_bisection(adjacency:&[Vec<usize>], restarts:usize, rng:&mut StdRng) -> (usize,Vec<usize>)
+{
+	let n = adjacency.len();
+	if n<2
+	{
+		return (0,(0..n).collect());
+	}
+	let cut_size = |side:&[bool]| -> usize {
+		(0..n).map(|u|adjacency[u].iter().filter(|&&v|side[u]!=side[v]).count()).sum::<usize>()/2
+	};
+	let mut best : Option<(usize,Vec<bool>)> = None;
+	for _restart in 0..restarts.max(1)
+	{
+		let mut order : Vec<usize> = (0..n).collect();
+		for i in (1..n).rev()
+		{
+			order.swap(i,rng.gen_range(0..=i));
+		}
+		let mut side = vec![false;n];
+		for &u in order.iter().take(n/2)
+		{
+			side[u]=true;
+		}
+		loop
+		{
+			//`gain[u]` is the reduction of the cut obtained by moving `u` alone to the other side.
+			let mut gain : Vec<i64> = (0..n).map(|u|adjacency[u].iter().map(|&v|if side[u]!=side[v] {1} else {-1}).sum()).collect();
+			let mut locked = vec![false;n];
+			let mut swaps = vec![];
+			let mut accumulated = 0i64;
+			let mut best_accumulated = 0i64;
+			let mut best_prefix = 0;
+			for _step in 0..n/2
+			{
+				let a = (0..n).filter(|&u|side[u] && !locked[u]).max_by_key(|&u|gain[u]);
+				let b = (0..n).filter(|&u|!side[u] && !locked[u]).max_by_key(|&u|{
+					let shared = a.map(|a|adjacency[a].iter().filter(|&&v|v==u).count() as i64).unwrap_or(0);
+					gain[u] - 2*shared
+				});
+				let (a,b) = match (a,b) { (Some(a),Some(b)) => (a,b), _ => break };
+				let shared = adjacency[a].iter().filter(|&&v|v==b).count() as i64;
+				accumulated += gain[a] + gain[b] - 2*shared;
+				locked[a]=true;
+				locked[b]=true;
+				//Update the gains as if `a` and `b` were swapped.
+				for (moved,to_side) in [(a,false),(b,true)]
+				{
+					for &v in adjacency[moved].iter()
+					{
+						//The link moved-v was internal for v if v is now in the same side as moved.
+						if side[v]==to_side { gain[v]-=2 } else { gain[v]+=2 }
+					}
+				}
+				side[a]=false;
+				side[b]=true;
+				swaps.push((a,b));
+				if accumulated>best_accumulated
+				{
+					best_accumulated=accumulated;
+					best_prefix=swaps.len();
+				}
+			}
+			//Undo the swaps after the best prefix.
+			for &(a,b) in swaps.iter().skip(best_prefix)
+			{
+				side[a]=true;
+				side[b]=false;
+			}
+			if best_accumulated<=0
+			{
+				break;
+			}
+		}
+		let cut = cut_size(&side);
+		if best.as_ref().map(|(best_cut,_)|cut<*best_cut).unwrap_or(true)
+		{
+			best=Some((cut,side));
+		}
+	}
+	let (cut,side) = best.unwrap();
+	(cut,(0..n).filter(|&u|side[u]).collect())
+}
+
+///The maximum number of edge-disjoint paths from `origin` to `destination`, computed as a maximum flow with unit capacities by augmenting paths.
+fn edge_disjoint_paths(adjacency:&[Vec<usize>], origin:usize, destination:usize) -> usize
+{
+	let n = adjacency.len();
+	//Residual capacities for each directed arc. Each link gives a unit of capacity in both directions.
+	let mut capacity : Vec<std::collections::BTreeMap<usize,i64>> = vec![std::collections::BTreeMap::new();n];
+	for u in 0..n
+	{
+		for &v in adjacency[u].iter()
+		{
+			*capacity[u].entry(v).or_insert(0)+=1;
+		}
+	}
+	let mut flow = 0;
+	loop
+	{
+		let mut parent = vec![usize::MAX;n];
+		parent[origin]=origin;
+		let mut queue = std::collections::VecDeque::new();
+		queue.push_back(origin);
+		while let Some(u) = queue.pop_front()
+		{
+			if u==destination { break; }
+			for (&v,&c) in capacity[u].iter()
+			{
+				if c>0 && parent[v]==usize::MAX
+				{
+					parent[v]=u;
+					queue.push_back(v);
+				}
+			}
+		}
+		if parent[destination]==usize::MAX
+		{
+			return flow;
+		}
+		let mut v = destination;
+		while v!=origin
+		{
+			let u = parent[v];
+			*capacity[u].get_mut(&v).unwrap()-=1;
+			*capacity[v].entry(u).or_insert(0)+=1;
+			v=u;
+		}
+		flow+=1;
+	}
+}
+
+///The two largest eigenvalues of the adjacency matrix, computed by the power method with deflation.
+///The matrix is shifted by the maximum degree to make it positive semidefinite, so the power method converges to the largest eigenvalues and not to the ones with largest absolute value.
+fn two_largest_eigenvalues(adjacency:&[Vec<usize>], iterations:usize) -> (f64,f64)
+{
+	let n = adjacency.len();
+	if n==0 { return (0f64,0f64); }
+	let shift = adjacency.iter().map(|l|l.len()).max().unwrap_or(0) as f64;
+	let multiply = |x:&[f64]| -> Vec<f64> {
+		(0..n).map(|u|shift*x[u] + adjacency[u].iter().map(|&v|x[v]).sum::<f64>()).collect()
+	};
+	let normalize = |x:&mut Vec<f64>| {
+		let norm = x.iter().map(|e|e*e).sum::<f64>().sqrt();
+		if norm>0f64 { for e in x.iter_mut() { *e/=norm; } }
+	};
+	let dot = |x:&[f64],y:&[f64]| x.iter().zip(y.iter()).map(|(a,b)|a*b).sum::<f64>();
+	let power = |deflate:Option<&[f64]>, initial:Vec<f64>| -> (f64,Vec<f64>) {
+		let mut x = initial;
+		let project = |x:&mut Vec<f64>| if let Some(w) = deflate {
+			let p = dot(x,w);
+			for (e,we) in x.iter_mut().zip(w.iter()) { *e-=p*we; }
+		};
+		project(&mut x);
+		normalize(&mut x);
+		let mut lambda = 0f64;
+		for _ in 0..iterations
+		{
+			let mut y = multiply(&x);
+			project(&mut y);
+			let new_lambda = dot(&x,&y);
+			normalize(&mut y);
+			let converged = (new_lambda-lambda).abs() < 1e-12*new_lambda.abs().max(1f64);
+			x=y;
+			lambda=new_lambda;
+			if converged { break; }
+		}
+		(lambda-shift,x)
+	};
+	let (largest,vector) = power(None,vec![1f64;n]);
+	if n==1 { return (largest,largest); }
+	//A deterministic start that is unlikely to be orthogonal to the second eigenvector.
+	let initial = (0..n).map(|u|((u*7919+1)%101) as f64 - 50f64).collect();
+	let (second,_) = power(Some(&vector),initial);
+	(largest,second)
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::config_parser;
+	#[test]
+	fn hamming_metrics()
+	{
+		let plugs = Plugs::default();
+		let cv = match config_parser::parse("Hamming{sides:[4,4],servers_per_router:2}").unwrap()
+		{
+			config_parser::Token::Value(value) => value,
+			_ => panic!("Not a value"),
+		};
+		let mut rng = StdRng::seed_from_u64(10);
+		let topology = new_topology(TopologyBuilderArgument{cv:&cv,plugs:&plugs,rng:&mut rng});
+		let analysis = analyze_topology(topology.as_ref(),&TopologyAnalysisOptions::default(),&mut rng);
+		assert_eq!(analysis.diameter,2);
+		assert_eq!(analysis.distance_distribution,vec![0,16*6,16*9]);
+		assert!( (analysis.average_distance-1.6).abs()<1e-9 );
+		assert_eq!(analysis.num_router_links,48);
+		assert_eq!(analysis.link_class_counts,vec![24,24,32]);
+		//Splitting by one coordinate crosses 2*2 links in each of the 4 lines of that dimension.
+		assert_eq!(analysis.bisection_links,16);
+		assert_eq!(analysis.edge_disjoint_minimum,6);
+		assert_eq!(analysis.edge_disjoint_maximum,6);
+		//The spectrum of K4xK4 is 6,2,-2.
+		assert!( (analysis.largest_eigenvalue-6f64).abs()<1e-6 );
+		assert!( (analysis.second_eigenvalue-2f64).abs()<1e-6 );
+		assert!( (analysis.spectral_gap()-4f64).abs()<1e-6 );
+	}
+}
@@ -1,9 +1,10 @@
 
 use std::cell::RefCell;
+use std::rc::Rc;
 use ::rand::{Rng,rngs::StdRng};
 use quantifiable_derive::Quantifiable;//the derive macro
 use crate::config_parser::ConfigurationValue;
-use crate::topology::{Topology,Location,NeighbourRouterIteratorItem,TopologyBuilderArgument,new_topology};
+use crate::topology::{Topology,Location,NeighbourRouterIteratorItem,TopologyBuilderArgument,new_topology,AlgebraicTopology};
 //use crate::routing::{RoutingInfo,Routing,CandidateEgress,RoutingBuilderArgument,RoutingNextCandidates};
 use crate::routing::prelude::*;
 use crate::matrix::Matrix;
@@ -663,6 +664,7 @@ impl Topology for AsCartesianTopology
 	fn num_servers(&self) -> usize { self.topology.num_servers() }
 	fn neighbour(&self, router_index:usize, port:usize) -> (Location,usize) { self.topology.neighbour(router_index,port) }
 	fn server_neighbour(&self, server_index:usize) -> (Location,usize) { self.topology.server_neighbour(server_index) }
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)> { self.topology.server_neighbours(server_index) }
 	fn diameter(&self) -> usize { self.topology.diameter() }
 	fn distance(&self,origin:usize,destination:usize) -> usize { self.topology.distance(origin,destination) }
 	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize { self.topology.distance(origin,destination) }
@@ -687,6 +689,8 @@ impl Topology for AsCartesianTopology
 	{ self.topology.up_down_distance(origin,destination) }
 	fn dragonfly_size(&self) -> Option<crate::topology::dragonfly::ArrangementSize>
 	{ self.topology.dragonfly_size() }
+	fn algebraic_topology(&self) -> Option<&dyn AlgebraicTopology>
+	{ self.topology.algebraic_topology() }
 	fn bfs(&self, origin:usize, class_weight:Option<&[usize]>) -> Vec<usize>
 	{ self.topology.bfs(origin,class_weight) }
 	fn compute_distance_matrix(&self, class_weight:Option<&[usize]>) -> Matrix<usize>
@@ -823,12 +827,12 @@ impl Routing for DOR
 						//}
 						let amount=(if routing_record[i]<0
 						{
-							up_current[i]-up_next[i]
+							up_current[i]+side-up_next[i]
 						}
 						else
 						{
-							up_next[i]-up_current[i]
-						}+side)%side;
+							up_next[i]+side-up_current[i]
+						})%side;
 						if amount<=limit
 						{
 							if amount>best_amount
@@ -875,43 +879,7 @@ impl Routing for DOR
 	{
 		//let dimension=current_port/2;
 		//let delta=if current_port%2==0 { -1i32 } else { 1i32 };
-		let cartesian_data=topology.cartesian_data().expect("DOR requires a Cartesian topology");
-		if let (Location::RouterPort{router_index: previous_router, router_port:_},dimension)=topology.neighbour(current_router,current_port)
-		{
-			let up_current=cartesian_data.unpack(current_router);
-			let up_previous=cartesian_data.unpack(previous_router);
-			let side=cartesian_data.sides[dimension] as i32;
-			match routing_info.borrow_mut().routing_record
-			{
-				Some(ref mut rr) =>
-				{
-					let delta:i32=if rr[dimension]<0
-					{
-						(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
-					}
-					else
-					{
-						-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
-					};
-					rr[dimension]+=delta;
-					// --- DEBUG vvv
-					//let (target_location,_link_class)=topology.server_neighbour(target_server);
-					//let target_router=match target_location
-					//{
-					//	Location::RouterPort{router_index,router_port:_} =>router_index,
-					//	_ => panic!("The server is not attached to a router"),
-					//};
-					//let up_target=cartesian_data.unpack(target_router);
-					//println!("new routing record. current_router={}({:?}, current_port={} previous_router={}({:?}), delta={}, rr={:?}, target_server={} target_router={}({:?})",current_router,up_current,current_port,previous_router,up_previous,delta,rr,target_server,target_router,up_target);
-					// --- DEBUG ^^^
-				},
-				None => panic!("trying to update without routing_record"),
-			};
-		}
-		else
-		{
-			panic!("!!");
-		}
+		advance_routing_record(routing_info,topology,current_router,current_port);
 	}
 	fn initialize(&mut self, _topology:&dyn Topology, _rng: &mut StdRng)
 	{
@@ -975,6 +943,38 @@ impl DOR
 	}
 }
 
+///Reduces the `routing_record` of a packet in a Cartesian topology by the hop just made, arriving to `current_router` by `current_port`.
+fn advance_routing_record(routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize)
+{
+	let cartesian_data=topology.cartesian_data().expect("The routing record requires a Cartesian topology");
+	if let (Location::RouterPort{router_index: previous_router, router_port:_},dimension)=topology.neighbour(current_router,current_port)
+	{
+		let up_current=cartesian_data.unpack(current_router);
+		let up_previous=cartesian_data.unpack(previous_router);
+		let side=cartesian_data.sides[dimension] as i32;
+		match routing_info.borrow_mut().routing_record
+		{
+			Some(ref mut rr) =>
+			{
+				let delta:i32=if rr[dimension]<0
+				{
+					(up_previous[dimension] as i32 - up_current[dimension] as i32 + side)%side
+				}
+				else
+				{
+					-((up_current[dimension] as i32 - up_previous[dimension] as i32 + side)%side)
+				};
+				rr[dimension]+=delta;
+			},
+			None => panic!("trying to update without routing_record"),
+		};
+	}
+	else
+	{
+		panic!("!!");
+	}
+}
+
 /// Valiant DOR. Proposed by Valiant for Multidimensional grids. Generally you should randomize n-1 dimensions, thereby employing shortest routes when the topology is just a path.
 /// `routing_info.selections=Some([k,r])` indicates that the `next` call should go toward `r` at dimension `randomized[k]`. `r` having been selected randomly previously.
 /// `routing_info.selections=None` indicates to behave as DOR.
@@ -2314,6 +2314,226 @@ impl DimWAR
 }
 
 
+/**
+Minimal fully adaptive routing for `Torus` and `Mesh` following Duato's methodology, with DOR over dateline virtual channels as escape.
+The adaptive candidates are all the ports reducing the routing record, using any of the `adaptive_virtual_channels`.
+The escape candidate is the DOR port in the given `order`. For each dimension a pair of virtual channels is given in `dateline_virtual_channels`: the first one is used while the remaining path in that dimension crosses the wrap-around link and the second one otherwise.
+Since this only depends on the current position, packets can move freely between the adaptive and the escape channels.
+The escape candidates have their label increased by `escape_extra_label`, 1 by default, to let the virtual channel policies prefer the adaptive ones.
+Stores the `routing_record` as DOR.
+With `enable_statistics` it counts the hops made in each dimension and the link utilization of each dimension in packets per cycle. Each hop is classified as adaptive or escape by the virtual channel in which the packet arrives.
+
+```ignore
+DatelineAdaptive{
+	order: [0,1],//optional, by default in increasing dimension
+	dateline_virtual_channels: [[0,1],[0,1]],//one pair for each dimension, or a single pair for all of them
+	adaptive_virtual_channels: [2,3],
+	escape_extra_label: 1,//optional
+	enable_statistics: true,
+	legend_name: "minimal adaptive with dateline DOR escape",
+}
+```
+**/
+#[derive(Debug)]
+pub struct DatelineAdaptive
+{
+	order: Option<Vec<usize>>,
+	dateline_virtual_channels: Vec<[usize;2]>,
+	adaptive_virtual_channels: Vec<usize>,
+	escape_extra_label: i32,
+	enabled_statistics: bool,
+	///The number of directed links in each dimension, computed at initialization.
+	dimension_links: Vec<usize>,
+	///The hops made in each dimension, through adaptive and escape channels.
+	dimension_hops: RefCell<Vec<[usize;2]>>,
+	///The cycle in which the statistics began.
+	statistics_begin_cycle: Time,
+}
+
+impl Routing for DatelineAdaptive
+{
+	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, _target_router: usize, target_server:Option<usize>, num_virtual_channels:usize, _rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		let routing_record=routing_info.routing_record.as_ref().expect("DatelineAdaptive requires a routing record");
+		if routing_record.iter().all(|&x|x==0)
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			panic!("The server {} is not attached to this router ({}) but the routing record is {:?}",target_server,current_router,routing_record);
+		}
+		let cartesian_data=topology.cartesian_data().expect("DatelineAdaptive requires a Cartesian topology");
+		let up_current=cartesian_data.unpack(current_router);
+		let mut r=vec![];
+		for (dimension,&remaining) in routing_record.iter().enumerate()
+		{
+			if remaining==0
+			{
+				continue;
+			}
+			let ports = self.dimension_ports(topology,current_router,&up_current,dimension,remaining);
+			for &port in ports.iter()
+			{
+				r.extend(self.adaptive_virtual_channels.iter().map(|&vc|CandidateEgress::new(port,vc)));
+			}
+		}
+		let escape_dimension = match self.order
+		{
+			Some(ref order) => *order.iter().find(|&&dimension|routing_record[dimension]!=0).expect("the order does not include all the dimensions"),
+			None => routing_record.iter().position(|&x|x!=0).unwrap(),
+		};
+		let remaining = routing_record[escape_dimension];
+		let side = cartesian_data.sides[escape_dimension] as i32;
+		let destination_coordinate = up_current[escape_dimension] as i32 + remaining;
+		let crosses_dateline = destination_coordinate<0 || destination_coordinate>=side;
+		let vc = self.dateline_virtual_channels[escape_dimension][if crosses_dateline {0} else {1}];
+		for port in self.dimension_ports(topology,current_router,&up_current,escape_dimension,remaining)
+		{
+			r.push(CandidateEgress{port,virtual_channel:vc,label:self.escape_extra_label,..Default::default()});
+		}
+		Ok(RoutingNextCandidates{candidates:r,idempotent:true})
+	}
+	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, _target_server:Option<usize>, rng: &mut StdRng)
+	{
+		let cartesian_data=topology.cartesian_data().expect("DatelineAdaptive requires a Cartesian topology");
+		let up_current=cartesian_data.unpack(current_router);
+		let up_target=cartesian_data.unpack(target_router);
+		let routing_record=topology.coordinated_routing_record(&up_current,&up_target,Some(rng));
+		routing_info.borrow_mut().routing_record=Some(routing_record);
+	}
+	fn update_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, current_port:usize, _target_router:usize, _target_server:Option<usize>, _rng: &mut StdRng)
+	{
+		if self.enabled_statistics
+		{
+			let dimension = topology.neighbour(current_router,current_port).1;
+			let arrival_virtual_channel = routing_info.borrow().arrival_virtual_channel.expect("the packet arrived without virtual channel");
+			let kind = if self.adaptive_virtual_channels.contains(&arrival_virtual_channel) {0} else {1};
+			self.dimension_hops.borrow_mut()[dimension][kind]+=1;
+		}
+		advance_routing_record(routing_info,topology,current_router,current_port);
+	}
+	fn initialize(&mut self, topology:&dyn Topology, _rng: &mut StdRng)
+	{
+		let cartesian_data=topology.cartesian_data().expect("DatelineAdaptive requires a Cartesian topology");
+		let dimensions = cartesian_data.sides.len();
+		if self.dateline_virtual_channels.len()==1
+		{
+			self.dateline_virtual_channels = vec![self.dateline_virtual_channels[0];dimensions];
+		}
+		if self.dateline_virtual_channels.len()!=dimensions
+		{
+			panic!("DatelineAdaptive has {} pairs of dateline virtual channels for {} dimensions",self.dateline_virtual_channels.len(),dimensions);
+		}
+		if let Some(vc) = self.dateline_virtual_channels.iter().flatten().find(|vc|self.adaptive_virtual_channels.contains(vc))
+		{
+			panic!("The virtual channel {} cannot be both adaptive and dateline in DatelineAdaptive",vc);
+		}
+		self.dimension_links = vec![0;dimensions];
+		for router in 0..topology.num_routers()
+		{
+			for port in 0..topology.ports(router)
+			{
+				if let (Location::RouterPort{..},link_class)=topology.neighbour(router,port)
+				{
+					self.dimension_links[link_class]+=1;
+				}
+			}
+		}
+		self.dimension_hops = RefCell::new(vec![[0,0];dimensions]);
+	}
+	fn statistics(&self, cycle:Time) -> Option<ConfigurationValue>
+	{
+		if !self.enabled_statistics
+		{
+			return None;
+		}
+		let cycles = (cycle - self.statistics_begin_cycle) as f64;
+		let dimension_hops = self.dimension_hops.borrow();
+		let adaptive_hops : usize = dimension_hops.iter().map(|hops|hops[0]).sum();
+		let escape_hops : usize = dimension_hops.iter().map(|hops|hops[1]).sum();
+		let content = vec![
+			(String::from("adaptive_hops"),ConfigurationValue::Number(adaptive_hops as f64)),
+			(String::from("escape_hops"),ConfigurationValue::Number(escape_hops as f64)),
+			(String::from("dimension_hops"),ConfigurationValue::Array(dimension_hops.iter().map(|hops|ConfigurationValue::Number((hops[0]+hops[1]) as f64)).collect())),
+			(String::from("dimension_escape_hops"),ConfigurationValue::Array(dimension_hops.iter().map(|hops|ConfigurationValue::Number(hops[1] as f64)).collect())),
+			(String::from("dimension_link_utilization"),ConfigurationValue::Array(dimension_hops.iter().zip(self.dimension_links.iter()).map(|(hops,&links)|
+				ConfigurationValue::Number((hops[0]+hops[1]) as f64/links as f64/cycles)
+			).collect())),
+		];
+		Some(ConfigurationValue::Object(String::from("DatelineAdaptiveStatistics"),content))
+	}
+	fn reset_statistics(&mut self, next_cycle:Time)
+	{
+		self.statistics_begin_cycle = next_cycle;
+		for hops in self.dimension_hops.borrow_mut().iter_mut()
+		{
+			*hops = [0,0];
+		}
+	}
+}
+
+impl DatelineAdaptive
+{
+	pub fn new(arg:RoutingBuilderArgument) -> DatelineAdaptive
+	{
+		let mut order=None;
+		let mut dateline_virtual_channels=None;
+		let mut adaptive_virtual_channels=None;
+		let mut escape_extra_label=1;
+		let mut enabled_statistics=false;
+		match_object_panic!(arg.cv,"DatelineAdaptive",value,
+			"order" => order = Some(value.as_array().expect("bad value for order").iter()
+				.map(|v|v.as_usize().expect("bad value in order")).collect()),
+			"dateline_virtual_channels" => dateline_virtual_channels = Some(value.as_array().expect("bad value for dateline_virtual_channels").iter()
+				.map(|pair|{
+					let pair = pair.as_array().expect("bad value in dateline_virtual_channels");
+					if pair.len()!=2
+					{
+						panic!("each entry of dateline_virtual_channels must be a pair of virtual channels");
+					}
+					[pair[0].as_usize().expect("bad value in dateline_virtual_channels"),pair[1].as_usize().expect("bad value in dateline_virtual_channels")]
+				}).collect()),
+			"adaptive_virtual_channels" => adaptive_virtual_channels = Some(value.as_array().expect("bad value for adaptive_virtual_channels").iter()
+				.map(|v|v.as_usize().expect("bad value in adaptive_virtual_channels")).collect()),
+			"escape_extra_label" => escape_extra_label = value.as_i32().expect("bad value for escape_extra_label"),
+			"enable_statistics" => enabled_statistics = value.as_bool().expect("bad value for enable_statistics"),
+		);
+		let dateline_virtual_channels=dateline_virtual_channels.expect("There were no dateline_virtual_channels");
+		let adaptive_virtual_channels=adaptive_virtual_channels.expect("There were no adaptive_virtual_channels");
+		DatelineAdaptive{
+			order,
+			dateline_virtual_channels,
+			adaptive_virtual_channels,
+			escape_extra_label,
+			enabled_statistics,
+			dimension_links: vec![],
+			dimension_hops: RefCell::new(vec![]),
+			statistics_begin_cycle: 0,
+		}
+	}
+	///The ports at `current_router` advancing one step in `dimension` in the direction of the sign of `remaining`.
+	fn dimension_ports(&self, topology:&dyn Topology, current_router:usize, up_current:&[usize], dimension:usize, remaining:i32) -> Vec<usize>
+	{
+		let cartesian_data=topology.cartesian_data().expect("DatelineAdaptive requires a Cartesian topology");
+		let side=cartesian_data.sides[dimension];
+		let next_coordinate = if remaining>0 { (up_current[dimension]+1)%side } else { (up_current[dimension]+side-1)%side };
+		(0..topology.ports(current_router)).filter(|&port|{
+			if let (Location::RouterPort{router_index:next_router,..},link_class)=topology.neighbour(current_router,port)
+			{
+				link_class==dimension && cartesian_data.unpack(next_router)[dimension]==next_coordinate
+			} else { false }
+		}).collect()
+	}
+}
+
 /**
 This is an adapted Valiant version for the Hamming topology, suitable for source adaptive routings, as UGAL.
 It removes switches from source and target groups as intermediate switches.
@@ -2350,93 +2570,15 @@ impl Routing for Valiant4Hamming
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
-		/*let (target_location,_link_class)=topology.server_neighbour(target_server);
-		let target_router=match target_location
-		{
-			Location::RouterPort{router_index,router_port:_} =>router_index,
-			_ => panic!("The server is not attached to a router"),
-		};*/
-		let distance=topology.distance(current_router,target_router);
-		if distance==0 //careful here
-		{
-			for i in 0..topology.ports(current_router)
-			{
-				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
-				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
-				{
-					if server==target_server.expect("There sould be a server here")
-					{
-						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
-						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
-						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true})
-					}
-				}
-			}
-			unreachable!();
-		}
-		let meta=routing_info.meta.as_ref().unwrap();
-		match routing_info.selections
-		{
-			None =>
-				{
-					//self.second.next(&meta[1].borrow(),topology,current_router,target_server,num_virtual_channels,rng)
-					let base=self.second.next(&meta[1].borrow(),topology,current_router,target_router, target_server,num_virtual_channels,rng)?;
-					let idempotent = base.idempotent;
-					let r=base.into_iter().filter_map(|egress|
-						{
-							if !self.first_reserved_virtual_channels.contains(&egress.virtual_channel)
-							{
-								Some(egress)
-							}else{
-								None
-							}
-						}).collect();
-
-					Ok(RoutingNextCandidates{candidates:r,idempotent})
-				}
-			Some(ref s) =>
-				{
-					let middle=s[0] as usize;
-					let middle_server=
-						{
-							let mut x=None;
-							for i in 0..topology.ports(middle)
-							{
-								if let (Location::ServerPort(server),_link_class)=topology.neighbour(middle,i)
-								{
-									x=Some(server);
-									break;
-								}
-							}
-							x.unwrap()
-						};
-
-					let second_distance=topology.distance(middle,target_router);//Only exact if the base routing is shortest.
-					//self.first.next(&meta[0].borrow(),topology,current_router,middle_server,num_virtual_channels,rng).into_iter().filter(|egress|!self.second_reserved_virtual_channels.contains(&egress.virtual_channel)).collect()
-					let base = self.first.next(&meta[0].borrow(),topology,current_router, middle, Some(middle_server),  num_virtual_channels,rng)?;
-					let idempotent = base.idempotent;
-					let r=base.into_iter().filter_map(|mut egress|{
-						//egress.hops = Some(routing_info.hops);
-						if self.second_reserved_virtual_channels.contains(&egress.virtual_channel) { //may not be the best way....
-							None
-							/*if let Some(ref mut eh)=egress.estimated_remaining_hops
-                            {
-                                *eh += second_distance;
-                            }
-                            Some(egress)*/
-
-						} else {
-							if let Some(ref mut eh)=egress.estimated_remaining_hops
-							{
-								*eh += second_distance;
-							}
-							Some(egress)
-						}
-					}).collect();
-					Ok(RoutingNextCandidates{candidates:r,idempotent})
-				}
-		}
-
+		self.next_with_base(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,target_router,target_server|
+			routing.next(&info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)
+		)
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_base(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,target_router,target_server|
+			routing.next_with_congestion(info,topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)
+		)
 	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, _target_server:Option<usize>, rng: &mut StdRng)
 	{
@@ -2589,6 +2731,18 @@ impl Routing for Valiant4Hamming
 		self.second.initialize(topology,rng);
 		self.pattern.initialize(topology.num_servers(), topology.num_servers(), topology, rng);
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		let first = self.first.set_network_state(state);
+		let second = self.second.set_network_state(state);
+		first || second
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		let mut events = self.first.initial_events();
+		events.extend(self.second.initial_events());
+		events
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		let mut bri=routing_info.borrow_mut();
@@ -2613,6 +2767,99 @@ impl Routing for Valiant4Hamming
 
 impl Valiant4Hamming
 {
+	///The candidates of `next`, obtaining those of the routing of the current stage with `base_next(routing,routing_info,target_router,target_server)`.
+	#[allow(clippy::too_many_arguments)]
+	fn next_with_base<F>(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, mut base_next:F) -> Result<RoutingNextCandidates,Error>
+		where F: FnMut(&dyn Routing,&RefCell<RoutingInfo>,usize,Option<usize>) -> Result<RoutingNextCandidates,Error>
+	{
+		/*let (target_location,_link_class)=topology.server_neighbour(target_server);
+		let target_router=match target_location
+		{
+			Location::RouterPort{router_index,router_port:_} =>router_index,
+			_ => panic!("The server is not attached to a router"),
+		};*/
+		let distance=topology.distance(current_router,target_router);
+		if distance==0 //careful here
+		{
+			for i in 0..topology.ports(current_router)
+			{
+				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server.expect("There sould be a server here")
+					{
+						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
+						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true})
+					}
+				}
+			}
+			unreachable!();
+		}
+		let meta=routing_info.meta.as_ref().unwrap();
+		match routing_info.selections
+		{
+			None =>
+				{
+					//self.second.next(&meta[1].borrow(),topology,current_router,target_server,num_virtual_channels,rng)
+					let base=base_next(self.second.as_ref(),&meta[1],target_router,target_server)?;
+					let idempotent = base.idempotent;
+					let r=base.into_iter().filter_map(|egress|
+						{
+							if !self.first_reserved_virtual_channels.contains(&egress.virtual_channel)
+							{
+								Some(egress)
+							}else{
+								None
+							}
+						}).collect();
+
+					Ok(RoutingNextCandidates{candidates:r,idempotent})
+				}
+			Some(ref s) =>
+				{
+					let middle=s[0] as usize;
+					let middle_server=
+						{
+							let mut x=None;
+							for i in 0..topology.ports(middle)
+							{
+								if let (Location::ServerPort(server),_link_class)=topology.neighbour(middle,i)
+								{
+									x=Some(server);
+									break;
+								}
+							}
+							x.unwrap()
+						};
+
+					let second_distance=topology.distance(middle,target_router);//Only exact if the base routing is shortest.
+					//self.first.next(&meta[0].borrow(),topology,current_router,middle_server,num_virtual_channels,rng).into_iter().filter(|egress|!self.second_reserved_virtual_channels.contains(&egress.virtual_channel)).collect()
+					let base = base_next(self.first.as_ref(),&meta[0],middle,Some(middle_server))?;
+					let idempotent = base.idempotent;
+					let r=base.into_iter().filter_map(|mut egress|{
+						//egress.hops = Some(routing_info.hops);
+						if self.second_reserved_virtual_channels.contains(&egress.virtual_channel) { //may not be the best way....
+							None
+							/*if let Some(ref mut eh)=egress.estimated_remaining_hops
+                            {
+                                *eh += second_distance;
+                            }
+                            Some(egress)*/
+
+						} else {
+							if let Some(ref mut eh)=egress.estimated_remaining_hops
+							{
+								*eh += second_distance;
+							}
+							Some(egress)
+						}
+					}).collect();
+					Ok(RoutingNextCandidates{candidates:r,idempotent})
+				}
+		}
+
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> Valiant4Hamming
 	{
 		//let mut order=None;
@@ -521,6 +521,7 @@ pub fn new_arrangement(arg:ArrangementBuilderArgument) -> Box<dyn Arrangement>
 
 use crate::routing::prelude::*;
 use crate::routing::{RoutingAnnotation};
+use std::rc::Rc;
 
 /**
 With the switches colored in {0,1} with a global arrangement such that global links connect only switches of the same color, the global link is labelled by that color.
@@ -706,110 +707,15 @@ impl Routing for Valiant4Dragonfly
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
-		/*let (target_location,_link_class)=topology.server_neighbour(target_server);
-		let target_router=match target_location
-		{
-			Location::RouterPort{router_index,router_port:_} =>router_index,
-			_ => panic!("The server is not attached to a router"),
-		};*/
-		let distance=topology.distance(current_router,target_router);
-		if distance==0 //careful here
-		{
-			for i in 0..topology.ports(current_router)
-			{
-				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
-				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
-				{
-					if server==target_server.expect("There sould be a server here")
-					{
-						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
-						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
-						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true})
-					}
-				}
-			}
-			unreachable!();
-		}
-		let meta=routing_info.meta.as_ref().unwrap();
-		match routing_info.selections
-		{
-			None =>
-				{
-					//self.second.next(&meta[1].borrow(),topology,current_router,target_server,num_virtual_channels,rng)
-					let base=self.second.next(&meta[1].borrow(),topology,current_router,target_router, target_server,num_virtual_channels,rng)?;
-					let idempotent = base.idempotent;
-
-
-					let r=base.into_iter().filter_map(|egress|
-						{
-							if !self.first_reserved_virtual_channels.contains(&egress.virtual_channel)
-							{
-								Some(egress)
-
-							}else{
-								None
-							}
-						}).collect();
-
-					Ok(RoutingNextCandidates{candidates:r,idempotent})
-				}
-			Some(ref s) =>
-				{
-					let middle=s[0] as usize;
-					let middle_server=
-						{
-							let mut x=None;
-							for i in 0..topology.ports(middle)
-							{
-								if let (Location::ServerPort(server),_link_class)=topology.neighbour(middle,i)
-								{
-									x=Some(server);
-									break;
-								}
-							}
-							x.unwrap()
-						};
-
-					let second_distance=topology.distance(middle,target_router);//Only exact if the base routing is shortest.
-					//self.first.next(&meta[0].borrow(),topology,current_router,middle_server,num_virtual_channels,rng).into_iter().filter(|egress|!self.second_reserved_virtual_channels.contains(&egress.virtual_channel)).collect()
-					let base = self.first.next(&meta[0].borrow(),topology,current_router, middle, Some(middle_server),  num_virtual_channels,rng)?;
-					let idempotent = base.idempotent;
-					let r=base.into_iter().filter_map(|mut egress|{
-						//egress.hops = Some(routing_info.hops);
-						if self.second_reserved_virtual_channels.contains(&egress.virtual_channel) { //may not be the best way....
-							None
-							/*if let Some(ref mut eh)=egress.estimated_remaining_hops
-                            {
-                                *eh += second_distance;
-                            }
-                            Some(egress)*/
-
-						} else {
-							if let Some(ref mut eh)=egress.estimated_remaining_hops
-							{
-								*eh += second_distance;
-							}
-							Some(egress)
-						}
-					}).collect();
-					Ok(RoutingNextCandidates{candidates:r,idempotent})
-				}
-		}
-		// let num_ports=topology.ports(current_router);
-		// let mut r=Vec::with_capacity(num_ports*num_virtual_channels);
-		// for i in 0..num_ports
-		// {
-		// 	//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
-		// 	if let (Location::RouterPort{router_index,router_port:_},_link_class)=topology.neighbour(current_router,i)
-		// 	{
-		// 		if distance-1==topology.distance(router_index,target_router)
-		// 		{
-		// 			r.extend((0..num_virtual_channels).map(|vc|(i,vc)));
-		// 		}
-		// 	}
-		// }
-		// //println!("From router {} to router {} distance={} cand={}",current_router,target_router,distance,r.len());
-		// r
+		self.next_with_base(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,target_router,target_server|
+			routing.next(&info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)
+		)
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_base(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,target_router,target_server|
+			routing.next_with_congestion(info,topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)
+		)
 	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, _target_server:Option<usize>, rng: &mut StdRng)
 	{
@@ -943,6 +849,18 @@ impl Routing for Valiant4Dragonfly
 			pattern.initialize(size,size,topology,rng);
 		}
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		let first = self.first.set_network_state(state);
+		let second = self.second.set_network_state(state);
+		first || second
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		let mut events = self.first.initial_events();
+		events.extend(self.second.initial_events());
+		events
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng:&mut StdRng)
 	{
 		let mut bri=routing_info.borrow_mut();
@@ -967,6 +885,116 @@ impl Routing for Valiant4Dragonfly
 
 impl Valiant4Dragonfly
 {
+	///The candidates of `next`, obtaining those of the routing of the current stage with `base_next(routing,routing_info,target_router,target_server)`.
+	#[allow(clippy::too_many_arguments)]
+	fn next_with_base<F>(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, mut base_next:F) -> Result<RoutingNextCandidates,Error>
+		where F: FnMut(&dyn Routing,&RefCell<RoutingInfo>,usize,Option<usize>) -> Result<RoutingNextCandidates,Error>
+	{
+		/*let (target_location,_link_class)=topology.server_neighbour(target_server);
+		let target_router=match target_location
+		{
+			Location::RouterPort{router_index,router_port:_} =>router_index,
+			_ => panic!("The server is not attached to a router"),
+		};*/
+		let distance=topology.distance(current_router,target_router);
+		if distance==0 //careful here
+		{
+			for i in 0..topology.ports(current_router)
+			{
+				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server.expect("There sould be a server here")
+					{
+						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
+						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true})
+					}
+				}
+			}
+			unreachable!();
+		}
+		let meta=routing_info.meta.as_ref().unwrap();
+		match routing_info.selections
+		{
+			None =>
+				{
+					//self.second.next(&meta[1].borrow(),topology,current_router,target_server,num_virtual_channels,rng)
+					let base=base_next(self.second.as_ref(),&meta[1],target_router,target_server)?;
+					let idempotent = base.idempotent;
+
+
+					let r=base.into_iter().filter_map(|egress|
+						{
+							if !self.first_reserved_virtual_channels.contains(&egress.virtual_channel)
+							{
+								Some(egress)
+
+							}else{
+								None
+							}
+						}).collect();
+
+					Ok(RoutingNextCandidates{candidates:r,idempotent})
+				}
+			Some(ref s) =>
+				{
+					let middle=s[0] as usize;
+					let middle_server=
+						{
+							let mut x=None;
+							for i in 0..topology.ports(middle)
+							{
+								if let (Location::ServerPort(server),_link_class)=topology.neighbour(middle,i)
+								{
+									x=Some(server);
+									break;
+								}
+							}
+							x.unwrap()
+						};
+
+					let second_distance=topology.distance(middle,target_router);//Only exact if the base routing is shortest.
+					//self.first.next(&meta[0].borrow(),topology,current_router,middle_server,num_virtual_channels,rng).into_iter().filter(|egress|!self.second_reserved_virtual_channels.contains(&egress.virtual_channel)).collect()
+					let base = base_next(self.first.as_ref(),&meta[0],middle,Some(middle_server))?;
+					let idempotent = base.idempotent;
+					let r=base.into_iter().filter_map(|mut egress|{
+						//egress.hops = Some(routing_info.hops);
+						if self.second_reserved_virtual_channels.contains(&egress.virtual_channel) { //may not be the best way....
+							None
+							/*if let Some(ref mut eh)=egress.estimated_remaining_hops
+                            {
+                                *eh += second_distance;
+                            }
+                            Some(egress)*/
+
+						} else {
+							if let Some(ref mut eh)=egress.estimated_remaining_hops
+							{
+								*eh += second_distance;
+							}
+							Some(egress)
+						}
+					}).collect();
+					Ok(RoutingNextCandidates{candidates:r,idempotent})
+				}
+		}
+		// let num_ports=topology.ports(current_router);
+		// let mut r=Vec::with_capacity(num_ports*num_virtual_channels);
+		// for i in 0..num_ports
+		// {
+		// 	//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
+		// 	if let (Location::RouterPort{router_index,router_port:_},_link_class)=topology.neighbour(current_router,i)
+		// 	{
+		// 		if distance-1==topology.distance(router_index,target_router)
+		// 		{
+		// 			r.extend((0..num_virtual_channels).map(|vc|(i,vc)));
+		// 		}
+		// 	}
+		// }
+		// //println!("From router {} to router {} distance={} cand={}",current_router,target_router,distance,r.len());
+		// r
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> Valiant4Dragonfly
 	{
 		//let mut order=None;
@@ -1036,96 +1064,15 @@ impl Routing for PAR
 {
 	fn next(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
 	{
-		/*let (target_location,_link_class)=topology.server_neighbour(target_server);
-		let target_router=match target_location
-		{
-			Location::RouterPort{router_index,router_port:_} =>router_index,
-			_ => panic!("The server is not attached to a router"),
-		};*/
-		let distance=topology.distance(current_router,target_router);
-		if distance==0
-		{
-			let target_server = target_server.expect("target server was not given.");
-			for i in 0..topology.ports(current_router)
-			{
-				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
-				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
-				{
-					if server==target_server
-					{
-						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
-						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
-						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
-					}
-				}
-			}
-			unreachable!();
-		}
-		let binding = routing_info.auxiliar.borrow();
-		let aux = binding.as_ref().unwrap().downcast_ref::<Vec<usize>>().unwrap();
-		let vc_local = aux[0] + aux[1] * 2;
-		let vc_global = aux[1];
-		let meta=routing_info.meta.as_ref().unwrap();
-		let r: Vec<CandidateEgress> = match routing_info.selections
-		{
-			None =>
-			{
-				unreachable!();
-			}
-			Some(ref s) =>
-			{
-				//let both = if let &SumRoutingPolicy::TryBoth=&self.policy { routing_info.hops==0 } else { false };
-				//if both
-				if s.len() >= 2 //Both Valiant or MIN
-				{
-
-					let el0 = 0;
-					//let r0=self.first_routing.next(&meta[0].borrow(),topology,current_router,target_server,avc0.len(),rng).into_iter().map( |candidate| CandidateEgress{virtual_channel:avc0[candidate.virtual_channel],label:candidate.label+el0,annotation:Some(RoutingAnnotation{values:vec![0],meta:vec![candidate.annotation]}),..candidate} );
-					let r0 = self.first.next(&meta[0].borrow(), topology, current_router, target_router, target_server, 1, rng)?.into_iter().map(|candidate| {
-
-						let (_next_location,link_class)=topology.neighbour(current_router,candidate.port);
-						let vc = if link_class == 0 {vc_local} else { vc_global };
-
-						CandidateEgress { virtual_channel:vc, label: candidate.label + el0, annotation: Some(RoutingAnnotation { values: vec![0], meta: vec![candidate.annotation] }), ..candidate }
-
-					});
-					//let avc1=&self.second_allowed_virtual_channels;
-					//let avc1 = &self.allowed_virtual_channels[1];
-					//let el1=self.second_extra_label;
-					let el1 = self.valiant_extra_label;
-					//let r1=self.second_routing.next(&meta[1].borrow(),topology,current_router,target_server,avc1.len(),rng).into_iter().map( |candidate| CandidateEgress{virtual_channel:avc1[candidate.virtual_channel],label:candidate.label+el1,annotation:Some(RoutingAnnotation{values:vec![1],meta:vec![candidate.annotation]}),..candidate} );
-					let r1 = self.second.next(&meta[1].borrow(), topology, current_router, target_router, target_server, 1, rng)?.into_iter().map(|candidate| {
-						let (_next_location,link_class)=topology.neighbour(current_router,candidate.port);
-						let vc = if link_class == 0 {vc_local  }else { vc_global};
-
-						CandidateEgress { virtual_channel: vc, label: candidate.label + el1, annotation: Some(RoutingAnnotation { values: vec![1], meta: vec![candidate.annotation] }), ..candidate }
-
-					});
-
-
-					r0.chain(r1).collect()
-
-				} else if s.len() == 1{
-
-					let (routing,extra_label) =
-						if s[0] == 0 { (&self.first, 0) } else { (&self.second,self.valiant_extra_label) };
-					let r = routing.next(&meta[1].borrow(), topology, current_router, target_router, target_server, 1, rng)?;
-					r.into_iter().map(|candidate|{
-							let (_next_location,link_class)=topology.neighbour(current_router,candidate.port);
-							let vc = if link_class == 0 { vc_local }else { vc_global };
-							CandidateEgress { virtual_channel: vc, label: candidate.label + extra_label,annotation: Some(RoutingAnnotation { values: vec![0], meta: vec![candidate.annotation] }), ..candidate }
-
-					}).collect()
-				}else{
-					//print the routing info
-					println!("Routing info: {:?}",routing_info);
-					panic!()
-				}
-			}
-		};
-
-		//FIXME: we can recover idempotence in some cases.
-		Ok(RoutingNextCandidates{candidates:r,idempotent:false})
+		self.next_with_base(routing_info,topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,num_virtual_channels|
+			routing.next(&info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,rng)
+		)
+	}
+	fn next_with_congestion(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, congestion:&CongestionInfo, rng: &mut StdRng) -> Result<RoutingNextCandidates,Error>
+	{
+		self.next_with_base(&routing_info.borrow(),topology,current_router,target_router,target_server,num_virtual_channels,|routing,info,num_virtual_channels|
+			routing.next_with_congestion(info,topology,current_router,target_router,target_server,num_virtual_channels,congestion,rng)
+		)
 	}
 	fn initialize_routing_info(&self, routing_info:&RefCell<RoutingInfo>, topology:&dyn Topology, current_router:usize, target_router:usize, _target_server:Option<usize>, rng: &mut StdRng)
 	{
@@ -1207,6 +1154,18 @@ impl Routing for PAR
 		self.first.initialize(topology,rng);
 		self.second.initialize(topology,rng);
 	}
+	fn set_network_state(&mut self, state:&Rc<NetworkState>) -> bool
+	{
+		let first = self.first.set_network_state(state);
+		let second = self.second.set_network_state(state);
+		first || second
+	}
+	fn initial_events(&self) -> Vec<EventGeneration>
+	{
+		let mut events = self.first.initial_events();
+		events.extend(self.second.initial_events());
+		events
+	}
 	fn performed_request(&self, requested:&CandidateEgress, routing_info:&RefCell<RoutingInfo>, _topology:&dyn Topology, _current_router:usize, _target_router:usize, _target_server:Option<usize>, _num_virtual_channels:usize, _rng:&mut StdRng)
 	{
 		let mut bri=routing_info.borrow_mut();
@@ -1224,6 +1183,102 @@ impl Routing for PAR
 
 impl PAR
 {
+	///The candidates of `next`, obtaining those of each routing with `base_next(routing,routing_info,num_virtual_channels)`.
+	#[allow(clippy::too_many_arguments)]
+	fn next_with_base<F>(&self, routing_info:&RoutingInfo, topology:&dyn Topology, current_router:usize, target_router:usize, target_server:Option<usize>, num_virtual_channels:usize, mut base_next:F) -> Result<RoutingNextCandidates,Error>
+		where F: FnMut(&dyn Routing,&RefCell<RoutingInfo>,usize) -> Result<RoutingNextCandidates,Error>
+	{
+		/*let (target_location,_link_class)=topology.server_neighbour(target_server);
+		let target_router=match target_location
+		{
+			Location::RouterPort{router_index,router_port:_} =>router_index,
+			_ => panic!("The server is not attached to a router"),
+		};*/
+		let distance=topology.distance(current_router,target_router);
+		if distance==0
+		{
+			let target_server = target_server.expect("target server was not given.");
+			for i in 0..topology.ports(current_router)
+			{
+				//println!("{} -> {:?}",i,topology.neighbour(current_router,i));
+				if let (Location::ServerPort(server),_link_class)=topology.neighbour(current_router,i)
+				{
+					if server==target_server
+					{
+						//return (0..num_virtual_channels).map(|vc|(i,vc)).collect();
+						//return (0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect();
+						return Ok(RoutingNextCandidates{candidates:(0..num_virtual_channels).map(|vc|CandidateEgress::new(i,vc)).collect(),idempotent:true});
+					}
+				}
+			}
+			unreachable!();
+		}
+		let binding = routing_info.auxiliar.borrow();
+		let aux = binding.as_ref().unwrap().downcast_ref::<Vec<usize>>().unwrap();
+		let vc_local = aux[0] + aux[1] * 2;
+		let vc_global = aux[1];
+		let meta=routing_info.meta.as_ref().unwrap();
+		let r: Vec<CandidateEgress> = match routing_info.selections
+		{
+			None =>
+			{
+				unreachable!();
+			}
+			Some(ref s) =>
+			{
+				//let both = if let &SumRoutingPolicy::TryBoth=&self.policy { routing_info.hops==0 } else { false };
+				//if both
+				if s.len() >= 2 //Both Valiant or MIN
+				{
+
+					let el0 = 0;
+					//let r0=self.first_routing.next(&meta[0].borrow(),topology,current_router,target_server,avc0.len(),rng).into_iter().map( |candidate| CandidateEgress{virtual_channel:avc0[candidate.virtual_channel],label:candidate.label+el0,annotation:Some(RoutingAnnotation{values:vec![0],meta:vec![candidate.annotation]}),..candidate} );
+					let r0 = base_next(self.first.as_ref(), &meta[0], 1)?.into_iter().map(|candidate| {
+
+						let (_next_location,link_class)=topology.neighbour(current_router,candidate.port);
+						let vc = if link_class == 0 {vc_local} else { vc_global };
+
+						CandidateEgress { virtual_channel:vc, label: candidate.label + el0, annotation: Some(RoutingAnnotation { values: vec![0], meta: vec![candidate.annotation] }), ..candidate }
+
+					});
+					//let avc1=&self.second_allowed_virtual_channels;
+					//let avc1 = &self.allowed_virtual_channels[1];
+					//let el1=self.second_extra_label;
+					let el1 = self.valiant_extra_label;
+					//let r1=self.second_routing.next(&meta[1].borrow(),topology,current_router,target_server,avc1.len(),rng).into_iter().map( |candidate| CandidateEgress{virtual_channel:avc1[candidate.virtual_channel],label:candidate.label+el1,annotation:Some(RoutingAnnotation{values:vec![1],meta:vec![candidate.annotation]}),..candidate} );
+					let r1 = base_next(self.second.as_ref(), &meta[1], 1)?.into_iter().map(|candidate| {
+						let (_next_location,link_class)=topology.neighbour(current_router,candidate.port);
+						let vc = if link_class == 0 {vc_local  }else { vc_global};
+
+						CandidateEgress { virtual_channel: vc, label: candidate.label + el1, annotation: Some(RoutingAnnotation { values: vec![1], meta: vec![candidate.annotation] }), ..candidate }
+
+					});
+
+
+					r0.chain(r1).collect()
+
+				} else if s.len() == 1{
+
+					let (routing,extra_label) =
+						if s[0] == 0 { (&self.first, 0) } else { (&self.second,self.valiant_extra_label) };
+					let r = base_next(routing.as_ref(), &meta[1], 1)?;
+					r.into_iter().map(|candidate|{
+							let (_next_location,link_class)=topology.neighbour(current_router,candidate.port);
+							let vc = if link_class == 0 { vc_local }else { vc_global };
+							CandidateEgress { virtual_channel: vc, label: candidate.label + extra_label,annotation: Some(RoutingAnnotation { values: vec![0], meta: vec![candidate.annotation] }), ..candidate }
+
+					}).collect()
+				}else{
+					//print the routing info
+					println!("Routing info: {:?}",routing_info);
+					panic!()
+				}
+			}
+		};
+
+		//FIXME: we can recover idempotence in some cases.
+		Ok(RoutingNextCandidates{candidates:r,idempotent:false})
+	}
 	pub fn new(arg: RoutingBuilderArgument) -> PAR
 	{
 		// //let mut order=None;
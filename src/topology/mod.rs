@@ -15,6 +15,7 @@ pub mod projective;
 pub mod slimfly;
 pub mod multistage;
 pub mod megafly;
+pub mod analysis;
 
 use std::fs::File;
 use ::rand::{rngs::StdRng};
@@ -67,6 +68,19 @@ pub struct NeighbourRouterIteratorItem
 	pub neighbour_port: usize,
 }
 
+///Topologies with an algebraic description, as the `SlimFly`, `Projective`, and `LeviProjective`.
+///It allows routings to compute minimal routes in low-diameter networks without building distance matrices.
+pub trait AlgebraicTopology
+{
+	///The port of `origin` that connects to the router `destination`, if they are neighbours.
+	fn port_towards(&self, origin:usize, destination:usize) -> Option<usize>;
+	///The routers that are neighbours of both `origin` and `destination`. These are the middle routers of the paths of length 2.
+	fn common_neighbours(&self, origin:usize, destination:usize) -> Vec<usize>;
+	///The routers to consider as intermediates of the Valiant routes from `origin` to `destination`, as in `UGAL`.
+	///By default `None`, meaning that any router may be the intermediate.
+	fn valiant_intermediates(&self, _origin:usize, _destination:usize) -> Option<Vec<usize>> { None }
+}
+
 ///A topology describes how routers and servers are connected.
 ///The router `index` has `ports(index)` neighbours. The first `degree(index)` must be other routers.
 pub trait Topology : Quantifiable + std::fmt::Debug
@@ -81,6 +95,12 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 	///The neighbour of a server: Location+link class index
 	//FIXME: What to do with BCube and similar?
 	fn server_neighbour(&self, server_index:usize) -> (Location,usize);
+	///All the attachments of a server: Location+link class index.
+	///Multi-homed servers (e.g., dual-rail NICs) have several; the first one must be the one returned by `server_neighbour`.
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)>
+	{
+		vec![self.server_neighbour(server_index)]
+	}
 	//diameter is only used in private projects...
 	///the greatest distance from server to server
 	fn diameter(&self) -> usize;
@@ -151,6 +171,8 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>;
 	/// Information for Dragonfly-like networks.
 	fn dragonfly_size(&self) -> Option<dragonfly::ArrangementSize> { None }
+	/// Algebraic description of the topology, for routings to find minimal paths without distance matrices.
+	fn algebraic_topology(&self) -> Option<&dyn AlgebraicTopology> { None }
 
 	///Breadth First Search to compute distances from a router to all others.
 	///It may use weights, but it there are multiple paths with different distances it may give a non-minimal distance, since it is not Dijkstra.
@@ -585,21 +607,12 @@ pub trait Topology : Quantifiable + std::fmt::Debug
 					},
 					Location::ServerPort(server_index) =>
 					{
-						let (rev_location, rev_link_class) = self.server_neighbour(server_index);
-						match rev_location
+						let attachments = self.server_neighbours(server_index);
+						let (_rev_location, rev_link_class) = attachments.iter().find(|(rev_location,_)|match rev_location
 						{
-							Location::RouterPort{
-								router_index: rev_router,
-								router_port: rev_port,
-							} =>
-							{
-								if router_index!=rev_router || port_index!=rev_port
-								{
-									panic!("Non-matching port ({},{}) to server {} non-returns to ({},{}).",router_index,port_index,server_index,rev_router,rev_port);
-								}
-							},
+							&Location::RouterPort{router_index: rev_router, router_port: rev_port} => router_index==rev_router && port_index==rev_port,
 							_ => panic!("It does not even return to a router"),
-						};
+						}).cloned().unwrap_or_else(||panic!("Non-matching port ({},{}) to server {} non-returns to any of {:?}.",router_index,port_index,server_index,attachments));
 						if link_class!=rev_link_class
 						{
 							panic!("port {} at router {} has non-matching link class {} vs {}",port_index,router_index,link_class,rev_link_class);
@@ -862,6 +875,20 @@ RemappedServers{
 }
 ```
 
+### MultiHomed
+
+[MultiHomed](operations::MultiHomed) attaches each server to several routers, as with multi-rail NICs. The base attachment is kept as the first rail and
+for each offset in `rail_offsets` another attachment is made to the router `(base_router+offset)%num_routers`, using additional server ports at the end of the router.
+Packets may be ejected through any of the rails of their destination. The rail used to inject is selected with the `server_rail_selection` key of the simulation.
+
+Example configuration:
+```ignore
+MultiHomed{
+	topology: Hamming{sides:[4,4],servers_per_router:2},
+	rail_offsets: [1],
+}
+```
+
 ## AsCartesianTopology
 [AsCartesianTopology] provides a topology with a given representation as a block with Cartesian coordinates.
 
@@ -895,6 +922,7 @@ pub fn new_topology(arg:TopologyBuilderArgument) -> Box<dyn Topology>
 			"MultiStage" | "XGFT" | "OFT" | "RFC" => Box::new(MultiStage::new(arg)),
 			"Megafly" => Box::new(megafly::Megafly::new(arg)),
 			"RemappedServers" => Box::new(operations::RemappedServersTopology::new(arg)),
+			"MultiHomed" => Box::new(operations::MultiHomed::new(arg)),
 			"AsCartesianTopology" => Box::new(AsCartesianTopology::new(arg)),
 			_ => panic!("Unknown topology {}",cv_name),
 		}
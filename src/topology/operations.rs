@@ -1,7 +1,7 @@
 
 use super::prelude::*;
 use crate::pattern::prelude::*;
-use super::NeighbourRouterIteratorItem;
+use super::{NeighbourRouterIteratorItem,AlgebraicTopology};
 use crate::matrix::Matrix;
 use crate::match_object_panic;
 use crate::config_parser::ConfigurationValue;
@@ -53,6 +53,11 @@ impl Topology for RemappedServersTopology
 		}
 		(loc,link_class)
 	}
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)>
+	{
+		let base_server = self.into_base_map[server_index];
+		self.topology.server_neighbours(base_server)
+	}
 	fn diameter(&self) -> usize { self.topology.diameter() }
 	fn distance(&self,origin:usize,destination:usize) -> usize { self.topology.distance(origin,destination) }
 	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize { self.topology.amount_shortest_paths(origin,destination) }
@@ -78,6 +83,10 @@ impl Topology for RemappedServersTopology
 	{
 		self.topology.up_down_distance(origin,destination)
 	}
+	fn algebraic_topology(&self) -> Option<&dyn AlgebraicTopology>
+	{
+		self.topology.algebraic_topology()
+	}
 	// Noone really overrides this...
 	fn bfs(&self, origin:usize, class_weight:Option<&[usize]>) -> Vec<usize>
 	{
@@ -171,3 +180,133 @@ impl RemappedServersTopology
 }
 
 
+/**
+Attaches each server of a base topology to several routers, as with dual-rail NICs. The first rail is the attachment in the base topology.
+For each value `offset` in `rail_offsets` the server gets an additional attachment to the router `(base_router+offset)%num_routers`.
+These additional server ports are appended after the ports of the base topology, using the same link class as the base attachment.
+Router to router links are exactly those of the base topology.
+
+Example configuration:
+```ignore
+MultiHomed{
+	topology: Hamming{sides:[4,4],servers_per_router:2},
+	rail_offsets: [1],//dual-rail, the second NIC attached to the next router.
+}
+```
+**/
+#[derive(Debug,Quantifiable)]
+pub struct MultiHomed
+{
+	/// The base topology.
+	topology: Box<dyn Topology>,
+	/// `extra_attachments[server]` is the list of `(router,port)` of the additional rails of the server.
+	extra_attachments: Vec<Vec<(usize,usize)>>,
+	/// `extra_ports[router]` is the list of servers attached at the ports after the base ones.
+	extra_ports: Vec<Vec<usize>>,
+}
+
+impl Topology for MultiHomed
+{
+	fn num_routers(&self) -> usize { self.topology.num_routers() }
+	fn num_servers(&self) -> usize { self.topology.num_servers() }
+	fn neighbour(&self, router_index:usize, port:usize) -> (Location,usize)
+	{
+		let base_ports = self.topology.ports(router_index);
+		if port < base_ports
+		{
+			self.topology.neighbour(router_index,port)
+		}
+		else
+		{
+			let server = self.extra_ports[router_index][port-base_ports];
+			let (_location,link_class) = self.topology.server_neighbour(server);
+			(Location::ServerPort(server),link_class)
+		}
+	}
+	fn server_neighbour(&self, server_index:usize) -> (Location,usize) { self.topology.server_neighbour(server_index) }
+	fn server_neighbours(&self, server_index:usize) -> Vec<(Location,usize)>
+	{
+		let base = self.topology.server_neighbour(server_index);
+		let link_class = base.1;
+		let mut r = vec![base];
+		r.extend( self.extra_attachments[server_index].iter().map(|&(router_index,router_port)|(Location::RouterPort{router_index,router_port},link_class)) );
+		r
+	}
+	fn diameter(&self) -> usize { self.topology.diameter() }
+	fn distance(&self,origin:usize,destination:usize) -> usize { self.topology.distance(origin,destination) }
+	fn amount_shortest_paths(&self,origin:usize,destination:usize) -> usize { self.topology.amount_shortest_paths(origin,destination) }
+	fn average_amount_shortest_paths(&self) -> f32 { self.topology.average_amount_shortest_paths() }
+	fn maximum_degree(&self) -> usize { self.topology.maximum_degree() }
+	fn minimum_degree(&self) -> usize { self.topology.minimum_degree() }
+	fn degree(&self, router_index: usize) -> usize { self.topology.degree(router_index) }
+	fn ports(&self, router_index: usize) -> usize { self.topology.ports(router_index) + self.extra_ports[router_index].len() }
+	fn neighbour_router_iter<'a>(&'a self, router_index:usize) -> Box<dyn Iterator<Item=NeighbourRouterIteratorItem> + 'a>
+	{
+		self.topology.neighbour_router_iter(router_index)
+	}
+	fn cartesian_data(&self) -> Option<&CartesianData> { self.topology.cartesian_data() }
+	fn coordinated_routing_record(&self, coordinates_a:&[usize], coordinates_b:&[usize], rng:Option<&mut StdRng>)->Vec<i32>
+	{
+		self.topology.coordinated_routing_record(coordinates_a,coordinates_b,rng)
+	}
+	fn is_direction_change(&self, router_index:usize, input_port: usize, output_port: usize) -> bool
+	{
+		self.topology.is_direction_change(router_index,input_port,output_port)
+	}
+	fn up_down_distance(&self,origin:usize,destination:usize) -> Option<(usize,usize)>
+	{
+		self.topology.up_down_distance(origin,destination)
+	}
+	fn algebraic_topology(&self) -> Option<&dyn AlgebraicTopology>
+	{
+		self.topology.algebraic_topology()
+	}
+}
+
+impl MultiHomed
+{
+	pub fn new(arg:TopologyBuilderArgument) -> MultiHomed
+	{
+		let mut topology = None;
+		let mut rail_offsets = None;
+		match_object_panic!(arg.cv, "MultiHomed", value,
+			"topology" => topology = Some(new_topology(TopologyBuilderArgument{cv:value,rng:&mut *arg.rng,..arg})),
+			"rail_offsets" => rail_offsets = Some(value.as_array().expect("bad value for rail_offsets").iter()
+				.map(|v|v.as_usize().expect("bad value in rail_offsets")).collect::<Vec<usize>>()),
+		);
+		let topology = topology.expect("There were no topology in configuration of MultiHomed.");
+		let rail_offsets = rail_offsets.expect("There were no rail_offsets in configuration of MultiHomed.");
+		let num_routers = topology.num_routers();
+		let num_servers = topology.num_servers();
+		let mut extra_attachments = vec![Vec::with_capacity(rail_offsets.len());num_servers];
+		let mut extra_ports = vec![vec![];num_routers];
+		for (index,&offset) in rail_offsets.iter().enumerate()
+		{
+			if offset % num_routers == 0
+			{
+				panic!("MultiHomed rail offset {} attaches servers twice to the same router.",offset);
+			}
+			if let Some(&other) = rail_offsets[..index].iter().find(|&&other|other % num_routers == offset % num_routers)
+			{
+				panic!("MultiHomed rail offsets {} and {} attach servers twice to the same router.",other,offset);
+			}
+			for (server,attachments) in extra_attachments.iter_mut().enumerate()
+			{
+				let base_router = match topology.server_neighbour(server).0
+				{
+					Location::RouterPort{router_index,..} => router_index,
+					_ => panic!("The server {} is not attached to a router.",server),
+				};
+				let router_index = (base_router+offset) % num_routers;
+				let router_port = topology.ports(router_index) + extra_ports[router_index].len();
+				extra_ports[router_index].push(server);
+				attachments.push((router_index,router_port));
+			}
+		}
+		MultiHomed{
+			topology,
+			extra_attachments,
+			extra_ports,
+		}
+	}
+}
@@ -5,7 +5,7 @@ use quantifiable_derive::Quantifiable;//the derive macro
 use crate::{
 	error,source_location,
 	quantify::Quantifiable,
-	topology::{Topology,Location,CartesianData,TopologyBuilderArgument},
+	topology::{Topology,Location,CartesianData,TopologyBuilderArgument,AlgebraicTopology},
 	config_parser::ConfigurationValue,
 	error::{Error,SourceLocation},
 };
@@ -431,6 +431,34 @@ impl Topology for Projective
 	{
 		None
 	}
+	fn algebraic_topology(&self) -> Option<&dyn AlgebraicTopology>
+	{
+		Some(self)
+	}
+}
+
+impl AlgebraicTopology for Projective
+{
+	fn port_towards(&self, origin:usize, destination:usize) -> Option<usize>
+	{
+		if origin==destination
+		{
+			return None;
+		}
+		let neighs = self.plane.incident_points(origin).unwrap_or_else(|_|panic!("invalid router_index={}",origin));
+		neighs.iter().position(|&(neighbour_router,_)|neighbour_router==destination)
+	}
+	fn common_neighbours(&self, origin:usize, destination:usize) -> Vec<usize>
+	{
+		//The point of the line joining both points, when seen as lines.
+		let neighs = self.plane.incident_points(origin).unwrap_or_else(|_|panic!("invalid router_index={}",origin));
+		neighs.iter().filter_map(|&(middle,_)|{
+			if middle!=origin && middle!=destination && self.plane.geometry.is_incident(middle,destination).expect("destination should be in range")
+			{
+				Some(middle)
+			} else { None }
+		}).collect()
+	}
 }
 
 impl Projective
@@ -649,6 +677,58 @@ impl Topology for LeviProjective
 	{
 		None
 	}
+	fn algebraic_topology(&self) -> Option<&dyn AlgebraicTopology>
+	{
+		Some(self)
+	}
+}
+
+impl AlgebraicTopology for LeviProjective
+{
+	fn port_towards(&self, origin:usize, destination:usize) -> Option<usize>
+	{
+		let np = self.plane.geometry.amount_points();
+		match (origin<np, destination<np)
+		{
+			(true,false) =>
+			{
+				let neighs = self.plane.incident_lines(origin).unwrap_or_else(|_|panic!("invalid router_index={}",origin));
+				neighs.iter().position(|&(line,_)|line+np==destination)
+			},
+			(false,true) =>
+			{
+				let neighs = self.plane.incident_points(origin-np).unwrap_or_else(|_|panic!("invalid router_index={}",origin));
+				neighs.iter().position(|&(point,_)|point==destination)
+			},
+			//The graph is bipartite.
+			_ => None,
+		}
+	}
+	fn common_neighbours(&self, origin:usize, destination:usize) -> Vec<usize>
+	{
+		let np = self.plane.geometry.amount_points();
+		if origin==destination
+		{
+			return vec![];
+		}
+		match (origin<np, destination<np)
+		{
+			(true,true) =>
+			{
+				//The line joining both points.
+				let neighs = self.plane.incident_lines(origin).unwrap_or_else(|_|panic!("invalid router_index={}",origin));
+				neighs.iter().filter(|&&(line,_)|self.plane.geometry.is_incident(line,destination).expect("destination should be in range")).map(|&(line,_)|line+np).collect()
+			},
+			(false,false) =>
+			{
+				//The point in which both lines meet.
+				let neighs = self.plane.incident_points(origin-np).unwrap_or_else(|_|panic!("invalid router_index={}",origin));
+				neighs.iter().filter(|&&(point,_)|self.plane.geometry.is_incident(destination-np,point).expect("destination should be in range")).map(|&(point,_)|point).collect()
+			},
+			//The graph is bipartite.
+			_ => vec![],
+		}
+	}
 }
 
 impl LeviProjective
@@ -4,7 +4,7 @@ use std::fmt::Debug;
 use std::convert::TryInto;
 use quantifiable_derive::Quantifiable;//the derive macro
 use super::{
-	Topology,TopologyBuilderArgument,CartesianData,Location,
+	Topology,TopologyBuilderArgument,CartesianData,Location,AlgebraicTopology,
 };
 use crate::{
 	config_parser::ConfigurationValue,
@@ -334,10 +334,120 @@ impl Topology for SlimFly
 	{
 		None
 	}
+	fn algebraic_topology(&self) -> Option<&dyn AlgebraicTopology>
+	{
+		Some(self)
+	}
+}
+
+impl AlgebraicTopology for SlimFly
+{
+	fn port_towards(&self, origin:usize, destination:usize) -> Option<usize>
+	{
+		let n = self.field.size();
+		let origin_coords = SlimFlyCoordinates::unpack(origin,n);
+		let destination_coords = SlimFlyCoordinates::unpack(destination,n);
+		if origin_coords.block==destination_coords.block
+		{
+			if origin_coords.global!=destination_coords.global
+			{
+				return None;
+			}
+			//local link. class 0.
+			let local_diff = self.field.sub(destination_coords.local,origin_coords.local);
+			self.paley_sets[origin_coords.block].iter().position(|&x|x==local_diff)
+		}
+		else if self.global_neighbour_local(&origin_coords,destination_coords.global)==destination_coords.local
+		{
+			//global link. class 1.
+			Some(self.paley_sets[0].len() + destination_coords.global)
+		}
+		else
+		{
+			None
+		}
+	}
+	fn common_neighbours(&self, origin:usize, destination:usize) -> Vec<usize>
+	{
+		let n = self.field.size();
+		let origin_coords = SlimFlyCoordinates::unpack(origin,n);
+		let destination_coords = SlimFlyCoordinates::unpack(destination,n);
+		let mut middles = vec![];
+		if origin_coords.block==destination_coords.block
+		{
+			if origin_coords.global==destination_coords.global
+			{
+				//Two local links inside the same group.
+				for &offset in self.paley_sets[origin_coords.block].iter()
+				{
+					let local = self.field.add(origin_coords.local,offset);
+					if local!=destination_coords.local && self.paley_sets[origin_coords.block].contains(&self.field.sub(destination_coords.local,local))
+					{
+						middles.push(SlimFlyCoordinates{local,..origin_coords}.pack(n));
+					}
+				}
+			}
+			else
+			{
+				//Two global links through a group of the other block. Each group has at most one router adjacent to both.
+				for global in 0..n
+				{
+					let local = self.global_neighbour_local(&origin_coords,global);
+					if local==self.global_neighbour_local(&destination_coords,global)
+					{
+						middles.push(SlimFlyCoordinates{local,global,block:1-origin_coords.block}.pack(n));
+					}
+				}
+			}
+		}
+		else
+		{
+			//A global link followed by a local link, or a local link followed by a global link.
+			let local = self.global_neighbour_local(&origin_coords,destination_coords.global);
+			if local!=destination_coords.local && self.paley_sets[destination_coords.block].contains(&self.field.sub(destination_coords.local,local))
+			{
+				middles.push(SlimFlyCoordinates{local,..destination_coords}.pack(n));
+			}
+			let local = self.global_neighbour_local(&destination_coords,origin_coords.global);
+			if local!=origin_coords.local && self.paley_sets[origin_coords.block].contains(&self.field.sub(local,origin_coords.local))
+			{
+				middles.push(SlimFlyCoordinates{local,..origin_coords}.pack(n));
+			}
+		}
+		middles
+	}
+	fn valiant_intermediates(&self, origin:usize, destination:usize) -> Option<Vec<usize>>
+	{
+		//The routers outside the groups of `origin` and `destination`, so that the Valiant route does not repeat their local links.
+		let n = self.field.size();
+		let origin_coords = SlimFlyCoordinates::unpack(origin,n);
+		let destination_coords = SlimFlyCoordinates::unpack(destination,n);
+		let same_group = |coords:&SlimFlyCoordinates,other:&SlimFlyCoordinates| coords.block==other.block && coords.global==other.global;
+		Some( (0..2*n*n).filter(|&router|{
+			let coords = SlimFlyCoordinates::unpack(router,n);
+			!same_group(&coords,&origin_coords) && !same_group(&coords,&destination_coords)
+		}).collect() )
+	}
 }
 
 impl SlimFly
 {
+	///The local coordinate of the neighbour of the router at `coords` through its global link towards the group `global` of the other block.
+	fn global_neighbour_local(&self, coords:&SlimFlyCoordinates, global:usize) -> usize
+	{
+		//y2=y1 - x1*x2
+		//y1=y2 + x1*x2
+		let global_product = self.field.mul(coords.global,global);
+		if coords.block==0
+		{
+			self.field.sub(coords.local,global_product)
+		}
+		else
+		{
+			self.field.add(coords.local,global_product)
+		}
+	}
+
 	pub fn new(arg:TopologyBuilderArgument) -> SlimFly
 	{
 		let mut prime=None;
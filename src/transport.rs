@@ -0,0 +1,234 @@
+/*!
+
+Reliable end-to-end transport between servers.
+
+With the `server_transport` key of the simulation each server keeps a copy of the packets it has sent until the destination acknowledges them, retransmitting those lost by the network.
+It implies `server_packet_sequencing`, the sequence number of each packet identifying it inside its flow.
+
+* The destination answers each data packet with an acknowledgement packet of `acknowledgement_size` phits, sent through the network with the same routing as any other packet. Duplicated packets are acknowledged again, as the previous acknowledgement may have been lost, but are otherwise discarded.
+* When a packet arrives while some earlier packet of its flow is missing, the destination sends a negative acknowledgement of the missing packet, at most once for each missing packet. It can be disabled with `negative_acknowledgements:false`.
+* The origin retransmits a packet when it receives a negative acknowledgement of it or when `timeout` cycles have passed since its last transmission without being acknowledged.
+
+```ignore
+server_transport: Transport{
+	timeout: 1000,//cycles to wait for the acknowledgement before retransmitting.
+	acknowledgement_size: 1,//optional, the size in phits of the acknowledgements. Defaults to 1.
+	negative_acknowledgements: true,//optional, defaults to true.
+}
+```
+
+With a `server_congestion_control` the controller follows the acknowledgements of the transport instead of its own, see the [congestion](crate::congestion) module.
+A data packet counts in the window of its flow from its first transmission until it is acknowledged or considered lost, by the expiration of its timer or by a negative acknowledgement. The acknowledgement echoes whether the packet was marked and the round trip is measured since its transmission.
+Retransmissions are not delayed by the controller and do not count in the window, and neither the acknowledgements of duplicates nor those of packets already considered lost reach the controller.
+
+Control packets and retransmissions count in the `injected_load` and `accepted_load`, while the `transport_statistics` report the `goodput` counting only the first arrival of each data phit, together with the number of `retransmitted_packets`, `timeouts`, `negative_acknowledgements` and `duplicate_packets`.
+The delay of a message includes any retransmission of its packets.
+
+*/
+
+use std::rc::Rc;
+use std::collections::BTreeMap;
+
+use quantifiable_derive::Quantifiable;//the derive macro
+
+use crate::config_parser::ConfigurationValue;
+use crate::event::Time;
+use crate::match_object_panic;
+use crate::packet::{Message,Packet};
+
+///The kind of a packet generated by the transport layer. Data packets do not have any.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Quantifiable)]
+pub enum TransportControl
+{
+	///Acknowledges the data packet with the given sequence number, together with whether it was marked by some router.
+	Acknowledgement(usize,bool),
+	///Requests the retransmission of the data packet with the given sequence number.
+	NegativeAcknowledgement(usize),
+}
+
+///A data packet sent and not yet acknowledged.
+#[derive(Debug,Quantifiable)]
+pub struct PendingPacket
+{
+	///The message of the packet.
+	pub message: Rc<Message>,
+	///The position of the packet inside its message.
+	pub index: usize,
+	///Number of phits.
+	pub size: usize,
+	///The cycle of its last transmission.
+	last_sent: Time,
+	///Whether a retransmission is waiting in the server to be sent.
+	queued: bool,
+	///Whether the packet still counts in the window of the congestion controller. It stops counting once considered lost.
+	in_window: bool,
+}
+
+///The transport state of a server, both as origin and as destination.
+#[derive(Debug,Quantifiable)]
+pub struct Transport
+{
+	///Cycles to wait for an acknowledgement before retransmitting.
+	timeout: Time,
+	///The size in phits of the control packets.
+	pub acknowledgement_size: usize,
+	///Whether to request retransmissions when detecting a missing packet.
+	negative_acknowledgements: bool,
+	///The packets sent and not acknowledged, indexed by `(destination,sequence)`.
+	pending: BTreeMap<(usize,usize),PendingPacket>,
+	///The expiration cycles of the retransmission timers.
+	timers: BTreeMap<Time,Vec<(usize,usize)>>,
+	///For each origin the last sequence for which a negative acknowledgement has been sent.
+	requested: BTreeMap<usize,usize>,
+	///For each destination the number of packets sent at least once. Packets of a flow are first sent in order, so lower sequences are retransmissions.
+	sent: BTreeMap<usize,usize>,
+}
+
+impl Transport
+{
+	pub fn new(cv:&ConfigurationValue) -> Transport
+	{
+		let mut timeout = None;
+		let mut acknowledgement_size = 1;
+		let mut negative_acknowledgements = true;
+		match_object_panic!(cv,"Transport",value,
+			"timeout" => timeout = Some(value.as_time().expect("bad value for timeout")),
+			"acknowledgement_size" => acknowledgement_size = value.as_usize().expect("bad value for acknowledgement_size"),
+			"negative_acknowledgements" => negative_acknowledgements = value.as_bool().expect("bad value for negative_acknowledgements"),
+		);
+		let timeout = timeout.expect("There were no timeout");
+		if timeout==0
+		{
+			panic!("The timeout of the Transport must be positive");
+		}
+		if acknowledgement_size==0
+		{
+			panic!("The acknowledgement_size of the Transport must be positive");
+		}
+		Transport{
+			timeout,
+			acknowledgement_size,
+			negative_acknowledgements,
+			pending: BTreeMap::new(),
+			timers: BTreeMap::new(),
+			requested: BTreeMap::new(),
+			sent: BTreeMap::new(),
+		}
+	}
+	///Called when a data packet begins to be sent, either for the first time or as a retransmission.
+	///Returns whether it is its first transmission.
+	pub fn track_sent(&mut self, packet:&Packet, cycle:Time) -> bool
+	{
+		let key = (packet.message.destination,packet.sequence);
+		let first = !self.is_retransmission(packet);
+		if first
+		{
+			self.sent.insert(packet.message.destination,packet.sequence+1);
+			self.pending.insert(key,PendingPacket{
+				message: packet.message.clone(),
+				index: packet.index,
+				size: packet.size,
+				last_sent: cycle,
+				queued: false,
+				in_window: true,
+			});
+		}
+		//A retransmission queued before the acknowledgement arrived has nothing left to track.
+		if let Some(pending) = self.pending.get_mut(&key)
+		{
+			pending.last_sent = cycle;
+			pending.queued = false;
+			self.timers.entry(cycle+self.timeout).or_default().push(key);
+		}
+		first
+	}
+	///Whether a data packet has been already sent, so that sending it again is a retransmission.
+	pub fn is_retransmission(&self, packet:&Packet) -> bool
+	{
+		packet.sequence < self.sent.get(&packet.message.destination).copied().unwrap_or(0)
+	}
+	///Called when the acknowledgement from `destination` of the packet `sequence` arrives.
+	///Returns the size and the cycle of transmission of the packet if it was pending and still counted in the congestion window.
+	pub fn acknowledge(&mut self, destination:usize, sequence:usize) -> Option<(usize,Time)>
+	{
+		//Further timers of the packet will find it missing.
+		let pending = self.pending.remove(&(destination,sequence))?;
+		if pending.in_window { Some((pending.size,pending.last_sent)) } else { None }
+	}
+	///Called when a pending packet is considered lost, to remove it from the congestion window.
+	///Returns its size if it was still counted in the window.
+	pub fn release(&mut self, destination:usize, sequence:usize) -> Option<usize>
+	{
+		let pending = self.pending.get_mut(&(destination,sequence))?;
+		if pending.in_window
+		{
+			pending.in_window = false;
+			Some(pending.size)
+		}
+		else
+		{
+			None
+		}
+	}
+	///Called when a negative acknowledgement arrives. Returns whether to retransmit the packet, which happens if it is still pending and not already waiting to be retransmitted.
+	pub fn negative_acknowledge(&mut self, destination:usize, sequence:usize) -> bool
+	{
+		match self.pending.get_mut(&(destination,sequence))
+		{
+			Some(pending) if !pending.queued =>
+			{
+				pending.queued = true;
+				true
+			},
+			_ => false,
+		}
+	}
+	///Extract the packets whose timer has expired at `cycle`, as `(destination,sequence)`. They are marked as waiting to be retransmitted.
+	pub fn expired(&mut self, cycle:Time) -> Vec<(usize,usize)>
+	{
+		let mut expired = vec![];
+		while let Some(&time) = self.timers.keys().next()
+		{
+			if time > cycle
+			{
+				break;
+			}
+			for key in self.timers.remove(&time).unwrap()
+			{
+				if let Some(pending) = self.pending.get_mut(&key)
+				{
+					//Only the timer of the last transmission counts.
+					if !pending.queued && pending.last_sent + self.timeout <= cycle
+					{
+						pending.queued = true;
+						expired.push(key);
+					}
+				}
+			}
+		}
+		expired
+	}
+	///Get a packet pending of acknowledgement.
+	pub fn pending(&self, destination:usize, sequence:usize) -> Option<&PendingPacket>
+	{
+		self.pending.get(&(destination,sequence))
+	}
+	///Called when the destination receives a data packet out of order, with `expected` the earliest missing sequence of the flow from `origin`.
+	///Returns whether to send a negative acknowledgement of `expected`.
+	pub fn request_missing(&mut self, origin:usize, expected:usize) -> bool
+	{
+		if !self.negative_acknowledgements
+		{
+			return false;
+		}
+		if self.requested.get(&origin) == Some(&expected)
+		{
+			false
+		}
+		else
+		{
+			self.requested.insert(origin,expected);
+			true
+		}
+	}
+}
@@ -0,0 +1,143 @@
+/*!
+    Tests for the algebraic routings of the SlimFly and projective topologies
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use caminos_lib::topology::{new_topology, TopologyBuilderArgument};
+use common::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// A topology of the given kind and prime with a single server per router.
+fn create_algebraic_topology(name: &str, prime: usize) -> ConfigurationValue
+{
+    ConfigurationValue::Object(name.to_string(), vec![
+        ("prime".to_string(), ConfigurationValue::Number(prime as f64)),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(1.0)),
+    ])
+}
+
+/// Run a burst in the given topology with 4 virtual channels.
+/// Returns the number of cycles, the average hops of the packets and the routing statistics if any.
+fn run_burst(topology: ConfigurationValue, pattern: ConfigurationValue, servers: usize, routing: ConfigurationValue) -> (f64, f64, Option<ConfigurationValue>)
+{
+    let message_size = 16;
+    let burst_traffic_builder = BurstTrafficBuilder{
+        pattern,
+        servers,
+        messages_per_server: 20,
+        message_size,
+    };
+    let vcp_args = VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    };
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 4,
+        vcp: create_vcp(vcp_args),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    };
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 20000,
+        topology,
+        traffic: create_burst_traffic(burst_traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut cycle = None;
+    let mut hops = None;
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "average_packet_hops" => hops = Some(value.as_f64().expect("average_packet_hops data")),
+        "routing_statistics" => statistics = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), hops.expect("There were no average_packet_hops"), statistics)
+}
+
+fn create_ugal_routing(global: bool) -> ConfigurationValue
+{
+    let ugal = ConfigurationValue::Object("UGAL".to_string(), vec![
+        ("global".to_string(), if global { ConfigurationValue::True } else { ConfigurationValue::False }),
+        ("intermediates".to_string(), ConfigurationValue::Number(2.0)),
+        ("enable_statistics".to_string(), ConfigurationValue::True),
+    ]);
+    // The hop `k` uses the virtual channel `k`, and the delivery into the server any of them.
+    let mut channels: Vec<ConfigurationValue> = (0..4).map(|vc|ConfigurationValue::Array(vec![ConfigurationValue::Number(vc as f64)])).collect();
+    channels.push(ConfigurationValue::Array((0..4).map(|vc|ConfigurationValue::Number(vc as f64)).collect()));
+    ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+        ("routing".to_string(), ugal),
+        ("channels".to_string(), ConfigurationValue::Array(channels)),
+    ])
+}
+
+fn get_decisions(statistics: &ConfigurationValue) -> (f64, f64)
+{
+    let mut minimal = None;
+    let mut nonminimal = None;
+    match_object_panic!( statistics, "UGALStatistics", value,
+        "minimal_decisions" => minimal = Some(value.as_f64().expect("minimal_decisions data")),
+        "nonminimal_decisions" => nonminimal = Some(value.as_f64().expect("nonminimal_decisions data")),
+    );
+    (minimal.expect("There were no minimal_decisions"), nonminimal.expect("There were no nonminimal_decisions"))
+}
+
+/// Every server sends to the server of the next router, so the packets must make exactly the average distance between consecutive routers.
+#[test]
+fn algebraic_minimal_path_lengths()
+{
+    let plugs = Plugs::default();
+    for (name, prime) in [("SlimFly", 5), ("Projective", 3), ("LeviProjective", 3)]
+    {
+        let cv = create_algebraic_topology(name, prime);
+        let topology = new_topology(TopologyBuilderArgument{cv: &cv, plugs: &plugs, rng: &mut StdRng::seed_from_u64(0)});
+        let n = topology.num_routers();
+        let expected = (0..n).map(|router|topology.distance(router, (router+1)%n)).sum::<usize>() as f64 / n as f64;
+        let shift_pattern_builder = ShiftPatternBuilder{
+            sides: vec![ConfigurationValue::Number(n as f64)],
+            shift: vec![ConfigurationValue::Number(1.0)],
+        };
+        let routing = ConfigurationValue::Object("AlgebraicMinimal".to_string(), vec![]);
+        let (cycles, hops, _statistics) = run_burst(cv, create_shift_pattern(shift_pattern_builder), n, routing);
+        assert!(cycles < 20000.0, "the burst in {} did not complete", name);
+        assert!((hops-expected).abs() < 1e-9, "{} packets made {} hops on average instead of the minimal {}", name, hops, expected);
+    }
+}
+
+/// Under uniform traffic in the SlimFly both UGAL-L and UGAL-G must deliver the whole burst, taking both minimal and Valiant routes through the algebraic intermediates.
+#[test]
+fn algebraic_ugal_delivers_under_load()
+{
+    for global in [false, true]
+    {
+        let topology = create_algebraic_topology("SlimFly", 5);
+        let pattern = ConfigurationValue::Object("Uniform".to_string(), vec![]);
+        let (cycles, hops, statistics) = run_burst(topology, pattern, 50, create_ugal_routing(global));
+        let (decisions_min, decisions_nonmin) = get_decisions(&statistics.expect("There were no routing_statistics"));
+        assert!(cycles < 20000.0, "the burst with UGAL global={} did not complete", global);
+        assert_eq!(decisions_min + decisions_nonmin, 50.0*20.0, "total decisions with UGAL global={}", global);
+        assert!(decisions_min > 0.0 && decisions_nonmin > 0.0, "UGAL global={} made {} minimal and {} nonminimal decisions", global, decisions_min, decisions_nonmin);
+        assert!(hops <= 4.0, "UGAL global={} made {} hops on average", global, hops);
+    }
+}
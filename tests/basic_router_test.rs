@@ -65,6 +65,7 @@ fn basic_switch_local_traffic()
         output_buffer_size: 32,
         neglect_busy_outport: ConfigurationValue::False,
         output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
     };
 
 
@@ -168,6 +169,7 @@ fn basic_two_servers_two_routers()
         output_buffer_size: 32,
         neglect_busy_outport: ConfigurationValue::False,
         output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
     };
 
 
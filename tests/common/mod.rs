@@ -63,11 +63,13 @@ pub struct BasicRouterBuilder {
     pub output_buffer_size: usize,
     pub neglect_busy_outport: ConfigurationValue,
     pub output_prioritize_lowest_label: ConfigurationValue,
+    /// Additional keys of the router, such as the `transmission_mechanism`.
+    pub extra_pairs: Vec<(String, ConfigurationValue)>,
 }
 /// Creates a Configuration Value with the parameters for the Basic router
 pub fn create_basic_router( arg: BasicRouterBuilder)-> ConfigurationValue
 {
-    ConfigurationValue::Object("Basic".to_string(), vec![
+    let mut pairs = vec![
         ("virtual_channels".to_string(),ConfigurationValue::Number(arg.virtual_channels as f64)),
         ("virtual_channel_policies".to_string(), arg.vcp),
         ("buffer_size".to_string(), ConfigurationValue::Number(arg.buffer_size as f64)),
@@ -78,8 +80,40 @@ pub fn create_basic_router( arg: BasicRouterBuilder)-> ConfigurationValue
         ("output_buffer_size".to_string(), ConfigurationValue::Number(arg.output_buffer_size as f64)),
         ("neglect_busy_output".to_string(), arg.neglect_busy_outport),
         ("output_prioritize_lowest_label".to_string(), arg.output_prioritize_lowest_label)
-    ])
+    ];
+    pairs.extend(arg.extra_pairs);
+    ConfigurationValue::Object("Basic".to_string(), pairs)
+}
 
+/// Encapsulates the parameters needed to create an OutputQueued router
+pub struct OutputQueuedRouterBuilder {
+    pub virtual_channels: usize,
+    pub vcp: ConfigurationValue,
+    pub buffer_size: usize,
+    pub flit_size: usize,
+    pub output_buffer_size: Option<usize>,
+    pub speedup: Option<usize>,
+    pub output_scheduler: ConfigurationValue,
+}
+/// Creates a Configuration Value with the parameters for the OutputQueued router
+pub fn create_output_queued_router(arg: OutputQueuedRouterBuilder) -> ConfigurationValue
+{
+    let mut pairs = vec![
+        ("virtual_channels".to_string(), ConfigurationValue::Number(arg.virtual_channels as f64)),
+        ("virtual_channel_policies".to_string(), arg.vcp),
+        ("buffer_size".to_string(), ConfigurationValue::Number(arg.buffer_size as f64)),
+        ("flit_size".to_string(), ConfigurationValue::Number(arg.flit_size as f64)),
+        ("output_scheduler".to_string(), arg.output_scheduler),
+    ];
+    if let Some(output_buffer_size) = arg.output_buffer_size
+    {
+        pairs.push(("output_buffer_size".to_string(), ConfigurationValue::Number(output_buffer_size as f64)));
+    }
+    if let Some(speedup) = arg.speedup
+    {
+        pairs.push(("speedup".to_string(), ConfigurationValue::Number(speedup as f64)));
+    }
+    ConfigurationValue::Object("OutputQueued".to_string(), pairs)
 }
 
 /// Encapsulates the parameters needed to create a HyperX topology
@@ -232,3 +266,67 @@ pub fn create_simulation(arg: SimulationBuilder) -> ConfigurationValue
     ])
 
 }
+
+/// Adds the `extra` pairs to the Object `cv`, replacing the values of the keys it already has.
+pub fn add_pairs(cv: ConfigurationValue, extra: Vec<(String, ConfigurationValue)>) -> ConfigurationValue
+{
+    match cv
+    {
+        ConfigurationValue::Object(name, mut pairs) =>
+        {
+            for (key, value) in extra
+            {
+                match pairs.iter_mut().find(|(other, _)| *other == key)
+                {
+                    Some(pair) => pair.1 = value,
+                    None => pairs.push((key, value)),
+                }
+            }
+            ConfigurationValue::Object(name, pairs)
+        },
+        _ => panic!("Trying to add pairs to a non-Object"),
+    }
+}
+
+/// Creates a Configuration Value for a Burst in which each of the 64 servers of a 4x4 Hamming graph sends `messages_per_server` messages of 16 phits to uniformly random destinations.
+pub fn create_uniform_burst_traffic(messages_per_server: usize) -> ConfigurationValue
+{
+    create_burst_traffic(BurstTrafficBuilder{
+        pattern: ConfigurationValue::Object("Uniform".to_string(), vec![]),
+        servers: 64,
+        messages_per_server,
+        message_size: 16,
+    })
+}
+
+/// Runs a uniform burst of 20 messages per server in a 4x4 Hamming graph with 4 servers per router, returning the results of the simulation.
+/// The `extra_simulation_keys` are added to the configuration, replacing the default of keys such as `traffic` or `measured`, and the `extra_link_keys` are added to every link class.
+pub fn run_uniform_burst(router: ConfigurationValue, routing: ConfigurationValue, extra_simulation_keys: Vec<(String, ConfigurationValue)>, extra_link_keys: Vec<(String, ConfigurationValue)>) -> ConfigurationValue
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)],
+        servers_per_router: 4,
+    };
+    let link_classes = match create_link_classes()
+    {
+        ConfigurationValue::Array(classes) => ConfigurationValue::Array(classes.into_iter().map(|class| add_pairs(class, extra_link_keys.clone())).collect()),
+        _ => unreachable!(),
+    };
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 20000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic: create_uniform_burst_traffic(20),
+        router,
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing,
+        link_classes,
+    };
+    let simulation_cv = add_pairs(create_simulation(simulation_builder), extra_simulation_keys);
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    simulation.get_simulation_results()
+}
@@ -0,0 +1,135 @@
+/*!
+    Tests for the ECN marking at the routers and the congestion controllers at the servers
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a burst with the given pattern in a 4x4 Hamming graph with DOR, marking at the given threshold of the output buffers, and the given congestion control.
+/// When `lossy` the routers drop the packets not fitting in the next buffer.
+/// Returns the number of cycles, the congestion statistics and the temporal statistics.
+fn run_burst(pattern: ConfigurationValue, ecn_threshold: usize, congestion_control: ConfigurationValue, lossy: bool) -> (f64, ConfigurationValue, ConfigurationValue)
+{
+    let mut router_keys = vec![("ecn_threshold".to_string(), ConfigurationValue::Number(ecn_threshold as f64))];
+    if lossy
+    {
+        router_keys.push(("transmission_mechanism".to_string(), ConfigurationValue::Literal("Lossy".to_string())));
+    }
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: router_keys,
+    });
+    let traffic = create_burst_traffic(BurstTrafficBuilder{
+        pattern,
+        servers: 64,
+        messages_per_server: 50,
+        message_size: 16,
+    });
+    let results = run_uniform_burst(router, create_dor_routing(vec![0,1]), vec![
+        ("traffic".to_string(), traffic),
+        ("measured".to_string(), ConfigurationValue::Number(10000.0)),
+        ("server_congestion_control".to_string(), congestion_control),
+        ("statistics_temporal_step".to_string(), ConfigurationValue::Number(500.0)),
+    ], vec![]);
+    let mut cycle = None;
+    let mut congestion = None;
+    let mut temporal = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "congestion_statistics" => congestion = Some(value.clone()),
+        "temporal_statistics" => temporal = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), congestion.expect("There were no congestion_statistics"), temporal.expect("There were no temporal_statistics"))
+}
+
+/// Returns the marked packets, the acknowledgements, the losses and the average flow rate.
+fn get_congestion(statistics: &ConfigurationValue) -> (f64, f64, f64, f64)
+{
+    let mut marked_packets = None;
+    let mut congestion_acks = None;
+    let mut congestion_losses = None;
+    let mut average_flow_rate = None;
+    match_object_panic!( statistics, "CongestionStatistics", value,
+        "marked_packets" => marked_packets = Some(value.as_f64().expect("bad marked_packets")),
+        "congestion_acks" => congestion_acks = Some(value.as_f64().expect("bad congestion_acks")),
+        "congestion_losses" => congestion_losses = Some(value.as_f64().expect("bad congestion_losses")),
+        "average_flow_rate" => average_flow_rate = Some(value.as_f64().expect("bad average_flow_rate")),
+        _ => (),
+    );
+    (marked_packets.unwrap(), congestion_acks.unwrap(), congestion_losses.unwrap(), average_flow_rate.unwrap())
+}
+
+/// Returns the number of flows with some rate recorded in each period.
+fn get_temporal_flows(temporal: &ConfigurationValue) -> Vec<usize>
+{
+    let mut flows = None;
+    match_object_panic!( temporal, "TemporalStatistics", value,
+        "flow_rates" => flows = Some(value.as_array().expect("bad flow_rates").iter().map(|step|step.as_array().expect("bad flow_rates step").len()).collect()),
+        _ => (),
+    );
+    flows.expect("There were no flow_rates")
+}
+
+/// 64 servers sending 50 messages of one packet each. The acknowledgements still returning when the burst ends are not counted.
+const TOTAL_PACKETS: f64 = 3200.0;
+
+#[test]
+fn dcqcn_reduces_rate()
+{
+    let (cycle, congestion, temporal) = run_burst(ConfigurationValue::Object("Uniform".to_string(), vec![]), 8, ConfigurationValue::Object("DCQCN".to_string(), vec![]), false);
+    let (marked_packets, congestion_acks, _congestion_losses, average_flow_rate) = get_congestion(&congestion);
+    assert!(cycle < 10000.0, "The burst must complete, it took {} cycles", cycle);
+    assert!(marked_packets > 0.0, "Some packets should be marked");
+    assert!(congestion_acks > 0.0 && congestion_acks <= TOTAL_PACKETS, "Bad number of acknowledgements {}", congestion_acks);
+    assert!(average_flow_rate < 1.0, "The marks should reduce the rates, got {}", average_flow_rate);
+    let flows = get_temporal_flows(&temporal);
+    assert!(flows.iter().any(|&n|n>0), "The temporal statistics should record the rate of the flows");
+}
+
+#[test]
+fn window_controls_complete()
+{
+    let dctcp = ConfigurationValue::Object("DCTCP".to_string(), vec![]);
+    let swift = ConfigurationValue::Object("Swift".to_string(), vec![
+        ("target_delay".to_string(), ConfigurationValue::Number(50.0)),
+    ]);
+    for control in [dctcp, swift]
+    {
+        let (cycle, congestion, _temporal) = run_burst(ConfigurationValue::Object("Uniform".to_string(), vec![]), 8, control, false);
+        let (_marked_packets, congestion_acks, _congestion_losses, average_flow_rate) = get_congestion(&congestion);
+        assert!(cycle < 10000.0, "The burst must complete, it took {} cycles", cycle);
+        assert!(congestion_acks > 0.0 && congestion_acks <= TOTAL_PACKETS, "Bad number of acknowledgements {}", congestion_acks);
+        assert!(average_flow_rate > 0.0 && average_flow_rate <= 1.0, "Bad average flow rate {}", average_flow_rate);
+    }
+}
+
+/// With lossy routers the dropped packets are never acknowledged, but their space in the window must be released so that every packet gets sent.
+/// Each server sends all its packets to the same destination, so that the window of its flow gets full.
+#[test]
+fn window_released_on_loss()
+{
+    let dctcp = ConfigurationValue::Object("DCTCP".to_string(), vec![
+        ("loss_timeout".to_string(), ConfigurationValue::Number(500.0)),
+    ]);
+    let (_cycle, congestion, _temporal) = run_burst(ConfigurationValue::Object("FixedRandom".to_string(), vec![]), 8, dctcp, true);
+    let (_marked_packets, congestion_acks, congestion_losses, _average_flow_rate) = get_congestion(&congestion);
+    assert!(congestion_losses > 0.0, "Some packets should be lost");
+    assert_eq!(congestion_acks + congestion_losses, TOTAL_PACKETS, "Every packet should be either acknowledged or lost");
+}
@@ -0,0 +1,65 @@
+/*!
+    Tests for the credit return delay and credit coalescing of the link classes
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Link classes for a 2D Hamming graph, with the given credit delay and credit period in the router-router links.
+fn create_credit_link_classes(credit_delay: f64, credit_period: f64) -> ConfigurationValue
+{
+    let router_link = ConfigurationValue::Object("LinkClass".to_string(), vec![
+        ("delay".to_string(), ConfigurationValue::Number(1.0)),
+        ("credit_delay".to_string(), ConfigurationValue::Number(credit_delay)),
+        ("credit_period".to_string(), ConfigurationValue::Number(credit_period)),
+    ]);
+    let server_link = ConfigurationValue::Object("LinkClass".to_string(), vec![("delay".to_string(), ConfigurationValue::Number(1.0))]);
+    ConfigurationValue::Array(vec![router_link.clone(), router_link, server_link])
+}
+
+/// Run a uniform burst in a 4x4 Hamming graph with DOR and buffers of 32 phits. Returns the number of cycles.
+fn run_credit_burst(credit_delay: f64, credit_period: f64) -> f64
+{
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 32,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    });
+    let results = run_uniform_burst(router, create_dor_routing(vec![0,1]), vec![
+        ("traffic".to_string(), create_uniform_burst_traffic(50)),
+        ("link_classes".to_string(), create_credit_link_classes(credit_delay, credit_period)),
+    ], vec![]);
+    let mut cycle = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        _ => (),
+    );
+    cycle.expect("There were no cycle")
+}
+
+/// A longer credit round-trip or coalescing the credits slows the burst when the buffers are small, but it still completes.
+#[test]
+fn credit_round_trip()
+{
+    let immediate = run_credit_burst(1.0, 1.0);
+    let delayed = run_credit_burst(20.0, 1.0);
+    let coalesced = run_credit_burst(1.0, 32.0);
+    assert!(delayed < 20000.0 && coalesced < 20000.0, "the burst did not complete");
+    assert!(delayed > immediate, "a credit delay of 20 cycles took {} cycles against {}", delayed, immediate);
+    assert!(coalesced > immediate, "coalescing credits each 32 cycles took {} cycles against {}", coalesced, immediate);
+}
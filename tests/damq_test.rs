@@ -0,0 +1,62 @@
+/*!
+    Tests for the DAMQ transmission mechanism, with a shared buffer pool per port
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a uniform burst in a 4x4 Hamming graph with the given transmission mechanism and occupancy-aware virtual channel policies.
+/// Returns the number of cycles.
+fn run_damq_burst(transmission_mechanism: Option<ConfigurationValue>) -> f64
+{
+    let vcp_args = VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("LowestSinghWeight".to_string(), vec![
+                ("extra_congestion".to_string(), ConfigurationValue::Number(0.0)),
+                ("extra_distance".to_string(), ConfigurationValue::Number(0.0)),
+                ("aggregate_buffers".to_string(), ConfigurationValue::True),
+                ("use_internal_space".to_string(), ConfigurationValue::True),
+            ]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    };
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp: create_vcp(vcp_args),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: transmission_mechanism.into_iter().map(|mechanism|("transmission_mechanism".to_string(), mechanism)).collect(),
+    });
+    let results = run_uniform_burst(router, create_shortest_routing(), vec![
+        ("traffic".to_string(), create_uniform_burst_traffic(50)),
+    ], vec![]);
+    let mut cycle = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        _ => (),
+    );
+    cycle.expect("There were no cycle")
+}
+
+/// The burst completes with both the default and a configured reserved space, without overflowing the shared pool.
+#[test]
+fn damq_burst()
+{
+    let cycles = run_damq_burst(None);
+    assert!(cycles < 20000.0, "the burst did not complete with private buffers");
+    let cycles = run_damq_burst(Some(ConfigurationValue::Literal("DAMQ".to_string())));
+    assert!(cycles < 20000.0, "the burst did not complete with the default DAMQ");
+    let cycles = run_damq_burst(Some(ConfigurationValue::Object("DAMQ".to_string(), vec![
+        ("reserved_size".to_string(), ConfigurationValue::Number(32.0)),
+    ])));
+    assert!(cycles < 20000.0, "the burst did not complete with a DAMQ reserving 32 phits");
+}
@@ -0,0 +1,92 @@
+/*!
+    Tests for the minimal adaptive torus routing with dateline escape channels
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a uniform burst in a 5x5 torus with minimal adaptive routing over the virtual channel 2 and dateline DOR escape over the virtual channels 0 and 1.
+/// Returns the number of cycles and the routing statistics.
+fn run_dateline_burst() -> (f64, ConfigurationValue)
+{
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 3,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("LowestLabel".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 32,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    });
+    let pair = ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0), ConfigurationValue::Number(1.0)]);
+    let routing = ConfigurationValue::Object("DatelineAdaptive".to_string(), vec![
+        ("dateline_virtual_channels".to_string(), ConfigurationValue::Array(vec![pair])),
+        ("adaptive_virtual_channels".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(2.0)])),
+        ("enable_statistics".to_string(), ConfigurationValue::True),
+    ]);
+    let topology = ConfigurationValue::Object("Torus".to_string(), vec![
+        ("sides".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(5.0), ConfigurationValue::Number(5.0)])),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(2.0)),
+    ]);
+    let traffic = create_burst_traffic(BurstTrafficBuilder{
+        pattern: ConfigurationValue::Object("Uniform".to_string(), vec![]),
+        servers: 50,
+        messages_per_server: 50,
+        message_size: 16,
+    });
+    let results = run_uniform_burst(router, routing, vec![
+        ("topology".to_string(), topology),
+        ("traffic".to_string(), traffic),
+    ], vec![]);
+    let mut cycle = None;
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "routing_statistics" => statistics = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), statistics.expect("There were no routing_statistics"))
+}
+
+/// The burst must complete using both kinds of channels and every dimension.
+#[test]
+fn dateline_adaptive()
+{
+    let (cycles, statistics) = run_dateline_burst();
+    let mut adaptive = None;
+    let mut escape = None;
+    let mut dimension_hops = None;
+    let mut utilization = None;
+    match_object_panic!( &statistics, "DatelineAdaptiveStatistics", value,
+        "adaptive_hops" => adaptive = Some(value.as_f64().expect("adaptive_hops data")),
+        "escape_hops" => escape = Some(value.as_f64().expect("escape_hops data")),
+        "dimension_hops" => dimension_hops = Some(value.as_array().expect("dimension_hops data").iter().map(|x|x.as_f64().expect("hops")).collect::<Vec<f64>>()),
+        "dimension_escape_hops" => (),
+        "dimension_link_utilization" => utilization = Some(value.as_array().expect("dimension_link_utilization data").iter().map(|x|x.as_f64().expect("utilization")).collect::<Vec<f64>>()),
+    );
+    let adaptive = adaptive.expect("There were no adaptive_hops");
+    let escape = escape.expect("There were no escape_hops");
+    let dimension_hops = dimension_hops.expect("There were no dimension_hops");
+    let utilization = utilization.expect("There were no dimension_link_utilization");
+    assert!(cycles < 20000.0, "the burst did not complete");
+    assert!(adaptive > 0.0, "no adaptive hops");
+    assert!(escape > 0.0, "no escape hops");
+    assert_eq!(dimension_hops.len(), 2);
+    assert_eq!(dimension_hops.iter().sum::<f64>(), adaptive + escape);
+    for u in utilization
+    {
+        assert!(u > 0.0 && u <= 1.0, "bad link utilization {}", u);
+    }
+}
@@ -0,0 +1,153 @@
+/*!
+    Tests for the progressive adaptive routings of dragonfly-like networks: OLM, PiggyBack and ContentionCounters.
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a burst in which every server sends to the servers of the next group, with the hop `k` using the virtual channel `k`.
+/// `sides` are the number of servers per group and the number of groups.
+/// Returns the number of cycles and the routing statistics, if any.
+fn run_adversarial_burst(topology: ConfigurationValue, sides: [f64;2], routing: ConfigurationValue) -> (f64, Option<ConfigurationValue>)
+{
+    let servers = (sides[0]*sides[1]) as usize;
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: sides.iter().map(|&side|ConfigurationValue::Number(side)).collect(),
+        shift: vec![ConfigurationValue::Number(0.0), ConfigurationValue::Number(1.0)],
+    };
+    let message_size = 16;
+    let burst_traffic_builder = BurstTrafficBuilder{
+        pattern: create_shift_pattern(shift_pattern_builder),
+        servers,
+        messages_per_server: 20,
+        message_size,
+    };
+    let vcp_args = VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    };
+    let virtual_channels = 8;
+    let router_args = BasicRouterBuilder{
+        virtual_channels,
+        vcp: create_vcp(vcp_args),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    };
+    let channels = (0..16).map(|hop:usize| ConfigurationValue::Array(vec![ConfigurationValue::Number(hop.min(virtual_channels-1) as f64)])).collect();
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 20000,
+        topology,
+        traffic: create_burst_traffic(burst_traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+            ("routing".to_string(), routing),
+            ("channels".to_string(), ConfigurationValue::Array(channels)),
+        ]),
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut cycle = None;
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "routing_statistics" => statistics = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), statistics)
+}
+
+fn create_adaptive_routings() -> Vec<ConfigurationValue>
+{
+    vec![
+        ConfigurationValue::Object("OLM".to_string(), vec![
+            ("enable_statistics".to_string(), ConfigurationValue::True),
+        ]),
+        ConfigurationValue::Object("PiggyBack".to_string(), vec![
+            ("broadcast_period".to_string(), ConfigurationValue::Number(10.0)),
+            ("enable_statistics".to_string(), ConfigurationValue::True),
+        ]),
+        ConfigurationValue::Object("ContentionCounters".to_string(), vec![
+            ("threshold".to_string(), ConfigurationValue::Number(2.0)),
+            ("enable_statistics".to_string(), ConfigurationValue::True),
+        ]),
+    ]
+}
+
+/// Returns the number of packets and of global and local misroutes.
+fn get_misroutes(statistics: &ConfigurationValue) -> (f64, f64, f64)
+{
+    let mut packets = None;
+    let mut global_misroutes = None;
+    let mut local_misroutes = None;
+    match_object_panic!( statistics, "MisroutingStatistics", value,
+        "packets" => packets = Some(value.as_f64().expect("packets data")),
+        "global_misroutes" => global_misroutes = Some(value.as_f64().expect("global_misroutes data")),
+        "local_misroutes" => local_misroutes = Some(value.as_f64().expect("local_misroutes data")),
+    );
+    (packets.expect("There were no packets"), global_misroutes.expect("There were no global_misroutes"), local_misroutes.expect("There were no local_misroutes"))
+}
+
+/// Under a shift of groups the three routings must misroute through other groups, and OLM and PiggyBack must finish earlier than the minimal routing.
+#[test]
+fn dragonfly_adversarial_groups()
+{
+    let topology = || ConfigurationValue::Object("Dragonfly".to_string(), vec![
+        ("global_ports_per_router".to_string(), ConfigurationValue::Number(2.0)),
+        ("servers_per_router".to_string(), ConfigurationValue::Number(2.0)),
+    ]);
+    let sides = [8.0, 9.0];
+    let (minimal_cycles, _) = run_adversarial_burst(topology(), sides, create_shortest_routing());
+    for (index,routing) in create_adaptive_routings().into_iter().enumerate()
+    {
+        let (cycles, statistics) = run_adversarial_burst(topology(), sides, routing);
+        let (packets, global_misroutes, local_misroutes) = get_misroutes(&statistics.expect("There were no routing_statistics"));
+        assert_eq!(packets, 72.0*20.0, "packets of routing {}", index);
+        assert!(global_misroutes > 0.0, "routing {} never misrouted through a global link", index);
+        if index == 1
+        {
+            // The local hops of PiggyBack towards the intermediate group are also misroutes.
+            assert!(local_misroutes > 0.0, "PiggyBack never misrouted through a local link");
+        }
+        if index < 2
+        {
+            assert!(cycles < minimal_cycles, "routing {} took {} cycles against {} of the minimal routes", index, cycles, minimal_cycles);
+        }
+    }
+}
+
+/// The routings also detect the groups of a Megafly, in which only the spines have global links.
+#[test]
+fn megafly_adversarial_groups()
+{
+    let topology = || ConfigurationValue::Object("Megafly".to_string(), vec![
+        ("global_ports_per_spine".to_string(), ConfigurationValue::Number(2.0)),
+        ("servers_per_leaf".to_string(), ConfigurationValue::Number(2.0)),
+        ("group_size".to_string(), ConfigurationValue::Number(2.0)),
+        ("number_of_groups".to_string(), ConfigurationValue::Number(5.0)),
+    ]);
+    for routing in create_adaptive_routings()
+    {
+        let (_cycles, statistics) = run_adversarial_burst(topology(), [4.0, 5.0], routing);
+        let (packets, global_misroutes, _local_misroutes) = get_misroutes(&statistics.expect("There were no routing_statistics"));
+        assert_eq!(packets, 20.0*20.0);
+        assert!(global_misroutes > 0.0);
+    }
+}
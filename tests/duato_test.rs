@@ -0,0 +1,88 @@
+/*!
+    Tests for the Duato escape channels routing
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a uniform burst in a 4x4 Hamming graph with fully adaptive minimal routing over the virtual channel 1 and DOR escape over the virtual channel 0.
+/// Returns the number of cycles and the routing statistics.
+fn run_duato_burst(return_to_adaptive: bool) -> (f64, ConfigurationValue)
+{
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("LowestLabel".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 32,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    });
+    let routing = ConfigurationValue::Object("Duato".to_string(), vec![
+        ("adaptive_routing".to_string(), create_shortest_routing()),
+        ("escape_routing".to_string(), create_dor_routing(vec![0,1])),
+        ("adaptive_virtual_channels".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)])),
+        ("escape_virtual_channels".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)])),
+        ("return_to_adaptive".to_string(), if return_to_adaptive { ConfigurationValue::True } else { ConfigurationValue::False }),
+        ("enable_statistics".to_string(), ConfigurationValue::True),
+    ]);
+    let results = run_uniform_burst(router, routing, vec![
+        ("traffic".to_string(), create_uniform_burst_traffic(50)),
+    ], vec![]);
+    let mut cycle = None;
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "routing_statistics" => statistics = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), statistics.expect("There were no routing_statistics"))
+}
+
+/// Returns the adaptive hops, escape hops, and returns to the adaptive channels.
+fn get_hops(statistics: &ConfigurationValue) -> (f64, f64, f64)
+{
+    let mut adaptive = None;
+    let mut escape = None;
+    let mut returns = None;
+    match_object_panic!( statistics, "DuatoStatistics", value,
+        "adaptive_hops" => adaptive = Some(value.as_f64().expect("adaptive_hops data")),
+        "escape_hops" => escape = Some(value.as_f64().expect("escape_hops data")),
+        "returns_to_adaptive" => returns = Some(value.as_f64().expect("returns_to_adaptive data")),
+    );
+    (adaptive.expect("There were no adaptive_hops"), escape.expect("There were no escape_hops"), returns.expect("There were no returns_to_adaptive"))
+}
+
+/// The burst must complete using both kinds of channels, and only return to the adaptive channels when allowed.
+#[test]
+fn duato_escape()
+{
+    for return_to_adaptive in [true, false]
+    {
+        let (cycles, statistics) = run_duato_burst(return_to_adaptive);
+        let (adaptive, escape, returns) = get_hops(&statistics);
+        assert!(cycles < 20000.0, "the burst did not complete with return_to_adaptive={}", return_to_adaptive);
+        assert!(adaptive > 0.0, "no adaptive hops with return_to_adaptive={}", return_to_adaptive);
+        assert!(escape > 0.0, "no escape hops with return_to_adaptive={}", return_to_adaptive);
+        if return_to_adaptive
+        {
+            assert!(returns > 0.0, "packets never returned to the adaptive channels");
+        }
+        else
+        {
+            assert_eq!(returns, 0.0, "packets returned to the adaptive channels");
+        }
+    }
+}
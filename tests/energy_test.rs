@@ -0,0 +1,99 @@
+/*!
+    Tests for the energy accounting of routers and links
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// 64 servers sending 20 messages of 16 phits.
+const TOTAL_PHITS: f64 = 20480.0;
+
+/// Run a uniform burst in a 4x4 Hamming graph with DOR, optionally with energy costs in the Basic router and the links.
+/// Returns the number of cycles and the energy statistics, if any.
+fn run_energy_burst(with_energy: bool) -> (f64, Option<ConfigurationValue>)
+{
+    let router_energy = ConfigurationValue::Object("RouterEnergy".to_string(), vec![
+        ("buffer_write".to_string(), ConfigurationValue::Number(1.0)),
+        ("buffer_read".to_string(), ConfigurationValue::Number(1.0)),
+        ("crossbar_traversal".to_string(), ConfigurationValue::Number(2.0)),
+        ("static_power".to_string(), ConfigurationValue::Number(1.0)),
+    ]);
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: if with_energy { vec![("energy".to_string(), router_energy)] } else { vec![] },
+    });
+    let link_keys = if with_energy { vec![("energy_per_phit".to_string(), ConfigurationValue::Number(3.0))] } else { vec![] };
+    let results = run_uniform_burst(router, create_dor_routing(vec![0,1]), vec![
+        ("phit_bits".to_string(), ConfigurationValue::Number(64.0)),
+    ], link_keys);
+    let mut cycle = None;
+    let mut energy = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "energy_statistics" => energy = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), energy)
+}
+
+fn get_energy_value(statistics: &ConfigurationValue, key: &str) -> f64
+{
+    match statistics
+    {
+        ConfigurationValue::Object(name, pairs) if name == "EnergyStatistics" =>
+        {
+            let (_, value) = pairs.iter().find(|(pair_key, _)| pair_key == key).unwrap_or_else(||panic!("There were no {}", key));
+            value.as_f64().expect("bad energy value")
+        },
+        _ => panic!("bad energy_statistics"),
+    }
+}
+
+#[test]
+fn no_energy_without_costs()
+{
+    let (_cycles, energy) = run_energy_burst(false);
+    assert!(energy.is_none(), "There should be no energy_statistics without costs");
+}
+
+/// In the completed burst every phit is written and read twice at each router, once in the input buffer and once in the output buffer, and traverses one link more than routers.
+#[test]
+fn energy_accounts_events()
+{
+    let (cycles, energy) = run_energy_burst(true);
+    let energy = energy.expect("There were no energy_statistics");
+    assert!(cycles < 20000.0, "The burst must complete, it took {} cycles", cycles);
+    let writes = get_energy_value(&energy, "buffer_writes");
+    let reads = get_energy_value(&energy, "buffer_reads");
+    let crossbar = get_energy_value(&energy, "crossbar_traversals");
+    let links = get_energy_value(&energy, "link_traversals");
+    assert_eq!(writes, reads, "Every written phit must have been read");
+    assert_eq!(writes, 2.0*crossbar, "Each router visit writes two buffers");
+    assert_eq!(links, crossbar + TOTAL_PHITS, "Each phit traverses one link more than routers");
+    assert!(get_energy_value(&energy, "allocations") >= crossbar/16.0, "Each packet needs an allocation at each router");
+    let router_dynamic = get_energy_value(&energy, "router_dynamic_energy");
+    assert_eq!(router_dynamic, writes + reads + 2.0*crossbar);
+    assert_eq!(get_energy_value(&energy, "link_dynamic_energy"), 3.0*links);
+    assert_eq!(get_energy_value(&energy, "router_static_energy"), 16.0*cycles);
+    assert_eq!(get_energy_value(&energy, "link_static_energy"), 0.0);
+    let total = get_energy_value(&energy, "total_energy");
+    assert_eq!(total, get_energy_value(&energy, "dynamic_energy") + get_energy_value(&energy, "static_energy"));
+    let per_bit = get_energy_value(&energy, "energy_per_delivered_bit");
+    assert!((per_bit - total/(TOTAL_PHITS*64.0)).abs() < 1e-9, "Bad energy per bit {}", per_bit);
+}
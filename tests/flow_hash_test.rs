@@ -0,0 +1,89 @@
+/*!
+    Tests for the FlowHash routing wrapper
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a uniform burst of multi-packet messages in a 4x4 Hamming graph with a single virtual channel, so that packets can only overtake others through different paths.
+/// Returns the `FlowHashStatistics`.
+fn run_flow_hash_burst(flowlet_gap: Option<f64>) -> ConfigurationValue
+{
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    });
+    let mut routing_args = vec![
+        ("routing".to_string(), create_shortest_routing()),
+        ("enable_statistics".to_string(), ConfigurationValue::True),
+    ];
+    if let Some(gap) = flowlet_gap
+    {
+        routing_args.push(("flowlet_gap".to_string(), ConfigurationValue::Number(gap)));
+    }
+    let topology = create_hamming_topology(HammingBuilder{
+        sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)],
+        servers_per_router: 2,
+    });
+    let traffic = create_burst_traffic(BurstTrafficBuilder{
+        pattern: ConfigurationValue::Object("Uniform".to_string(), vec![]),
+        servers: 32,
+        messages_per_server: 40,
+        message_size: 32,
+    });
+    let results = run_uniform_burst(router, ConfigurationValue::Object("FlowHash".to_string(), routing_args), vec![
+        ("topology".to_string(), topology),
+        ("traffic".to_string(), traffic),
+    ], vec![]);
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "routing_statistics" => statistics = Some(value.clone()),
+        _ => (),
+    );
+    statistics.expect("There were no routing_statistics")
+}
+
+/// Returns the number of packets, flowlets, and out of order packets.
+fn get_reordering(statistics: &ConfigurationValue) -> (f64, f64, f64)
+{
+    let mut packets = None;
+    let mut flowlets = None;
+    let mut out_of_order = None;
+    match_object_panic!( statistics, "FlowHashStatistics", value,
+        "packets" => packets = Some(value.as_f64().expect("packets data")),
+        "flowlets" => flowlets = Some(value.as_f64().expect("flowlets data")),
+        "out_of_order_packets" => out_of_order = Some(value.as_f64().expect("out_of_order_packets data")),
+        "average_reorder_distance" => (),
+        "maximum_reorder_distance" => (),
+    );
+    (packets.expect("There were no packets"), flowlets.expect("There were no flowlets"), out_of_order.expect("There were no out_of_order_packets"))
+}
+
+/// Pinning whole flows keeps them in order, while splitting them into flowlets at every idle cycle lets them reorder.
+#[test]
+fn flow_hash_ordering()
+{
+    let (packets, flows, out_of_order) = get_reordering(&run_flow_hash_burst(None));
+    assert_eq!(packets, 32.0*40.0*2.0, "packets");
+    assert_eq!(out_of_order, 0.0, "pinned flows arrived out of order");
+    let (packets, flowlets, out_of_order) = get_reordering(&run_flow_hash_burst(Some(0.0)));
+    assert_eq!(packets, 32.0*40.0*2.0, "packets");
+    assert!(flowlets > flows, "there were {} flowlets for {} flows", flowlets, flows);
+    assert!(out_of_order > 0.0, "flowlets never arrived out of order");
+}
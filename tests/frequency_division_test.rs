@@ -64,6 +64,7 @@ fn basic_frequency_division_two_routers()
         output_buffer_size: 32,
         neglect_busy_outport: ConfigurationValue::False,
         output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
     };
 
 
@@ -0,0 +1,117 @@
+/*!
+    Tests for the power management of the links: sleep states and rate levels
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+fn basic_router() -> ConfigurationValue
+{
+    create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    })
+}
+
+/// Run a uniform burst in a 4x4 Hamming graph with DOR, with all the links having a static power of 1 and the given power management, if any.
+/// Returns the number of cycles, the link power statistics and the energy statistics.
+fn run_power_burst(power_management: Option<ConfigurationValue>) -> (f64, Option<ConfigurationValue>, ConfigurationValue)
+{
+    let mut link_keys = vec![("static_power".to_string(), ConfigurationValue::Number(1.0))];
+    if let Some(power_management) = power_management
+    {
+        link_keys.push(("power_management".to_string(), power_management));
+    }
+    let results = run_uniform_burst(basic_router(), create_dor_routing(vec![0,1]), vec![], link_keys);
+    let mut cycle = None;
+    let mut link_power = None;
+    let mut energy = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "link_power_statistics" => link_power = Some(value.clone()),
+        "energy_statistics" => energy = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), link_power, energy.expect("There were no energy_statistics"))
+}
+
+fn get_link_static_energy(energy: &ConfigurationValue) -> f64
+{
+    let mut link_static_energy = None;
+    match_object_panic!( energy, "EnergyStatistics", value,
+        "link_static_energy" => link_static_energy = Some(value.as_f64().expect("bad link_static_energy")),
+        _ => (),
+    );
+    link_static_energy.expect("There were no link_static_energy")
+}
+
+/// Checks that the time of each class is split among its states and returns the total wake-ups and rate changes.
+fn check_fractions(statistics: &ConfigurationValue) -> (f64, f64)
+{
+    let mut wake_ups = 0.0;
+    let mut rate_changes = 0.0;
+    for class in statistics.as_array().expect("bad link_power_statistics")
+    {
+        let mut total = 0.0;
+        match_object_panic!( class, "LinkPowerStatistics", value,
+            "sleep_fraction" => total += value.as_f64().expect("bad sleep_fraction"),
+            "waking_fraction" => total += value.as_f64().expect("bad waking_fraction"),
+            "rate_fractions" => total += value.as_array().expect("bad rate_fractions").iter().map(|fraction| fraction.as_f64().expect("bad rate fraction")).sum::<f64>(),
+            "wake_ups" => wake_ups += value.as_f64().expect("bad wake_ups"),
+            "rate_changes" => rate_changes += value.as_f64().expect("bad rate_changes"),
+            _ => (),
+        );
+        assert!((total - 1.0).abs() < 1e-9, "The fractions of time should add to 1, but they add to {}", total);
+    }
+    (wake_ups, rate_changes)
+}
+
+#[test]
+fn idle_links_sleep()
+{
+    let (always_on_cycles, always_on_statistics, always_on_energy) = run_power_burst(None);
+    assert!(always_on_statistics.is_none(), "There should be no link_power_statistics without power management");
+    let power_management = ConfigurationValue::Object("LinkPowerManagement".to_string(), vec![
+        ("idle_timeout".to_string(), ConfigurationValue::Number(20.0)),
+        ("wake_up_latency".to_string(), ConfigurationValue::Number(10.0)),
+    ]);
+    let (cycles, statistics, energy) = run_power_burst(Some(power_management));
+    let (wake_ups, rate_changes) = check_fractions(&statistics.expect("There were no link_power_statistics"));
+    assert!(cycles < 20000.0, "The burst must complete, it took {} cycles", cycles);
+    assert!(wake_ups > 0.0, "Some links should wake up");
+    assert_eq!(rate_changes, 0.0, "There is a single rate level");
+    // Without power management the static energy is proportional to the cycles.
+    let always_on_power = get_link_static_energy(&always_on_energy)/always_on_cycles;
+    let power = get_link_static_energy(&energy)/cycles;
+    assert!(power < always_on_power, "Sleeping should reduce the static power, {} against {} always on", power, always_on_power);
+}
+
+#[test]
+fn links_adapt_their_rate()
+{
+    let power_management = ConfigurationValue::Object("LinkPowerManagement".to_string(), vec![
+        ("rate_divisors".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(2.0), ConfigurationValue::Number(1.0)])),
+        ("rate_window".to_string(), ConfigurationValue::Number(50.0)),
+    ]);
+    let (cycles, statistics, _energy) = run_power_burst(Some(power_management));
+    let (wake_ups, rate_changes) = check_fractions(&statistics.expect("There were no link_power_statistics"));
+    assert!(cycles < 20000.0, "The burst must complete, it took {} cycles", cycles);
+    assert_eq!(wake_ups, 0.0, "Links without idle_timeout never sleep");
+    assert!(rate_changes > 0.0, "Some links should change their rate");
+}
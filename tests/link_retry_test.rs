@@ -0,0 +1,110 @@
+/*!
+    Tests for the phit errors in the links and their link-level replay
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// The virtual channel policies used by both routers.
+fn create_policies() -> ConfigurationValue
+{
+    create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    })
+}
+
+fn basic_router() -> ConfigurationValue
+{
+    create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_policies(),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    })
+}
+
+fn input_output_router() -> ConfigurationValue
+{
+    create_input_output_router(InputOutputRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_policies(),
+        crossbar_delay: 1,
+        crossbar_frequency_divisor: 1,
+        allocator: ConfigurationValue::Object("Random".to_string(), vec![("seed".to_string(), ConfigurationValue::Number(1f64))]),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+    })
+}
+
+/// Run a uniform burst in a 4x4 Hamming graph with DOR and the given router, with all the links having the given phit error rate.
+/// Returns the number of cycles and the link retry statistics, if any.
+fn run_retry_burst(phit_error_rate: f64, router: ConfigurationValue) -> (f64, Option<ConfigurationValue>)
+{
+    let results = run_uniform_burst(router, create_dor_routing(vec![0,1]), vec![], vec![
+        ("phit_error_rate".to_string(), ConfigurationValue::Number(phit_error_rate)),
+    ]);
+    let mut cycle = None;
+    let mut link_retries = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "link_retry_statistics" => link_retries = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), link_retries)
+}
+
+/// Returns the total retries and the largest bandwidth lost among the link classes.
+fn get_retries(statistics: &ConfigurationValue) -> (f64, f64)
+{
+    let mut retries = 0.0;
+    let mut bandwidth_lost = 0.0f64;
+    for class in statistics.as_array().expect("bad link_retry_statistics")
+    {
+        match_object_panic!( class, "LinkRetryStatistics", value,
+            "retries" => retries += value.as_f64().expect("bad retries"),
+            "bandwidth_lost" => bandwidth_lost = bandwidth_lost.max(value.as_f64().expect("bad bandwidth_lost")),
+            _ => (),
+        );
+    }
+    (retries, bandwidth_lost)
+}
+
+#[test]
+fn link_errors_are_replayed()
+{
+    let (clean_cycles, clean_statistics) = run_retry_burst(0.0, basic_router());
+    assert!(clean_statistics.is_none(), "There should be no link_retry_statistics without errors");
+    let (cycles, statistics) = run_retry_burst(0.2, basic_router());
+    let (retries, bandwidth_lost) = get_retries(&statistics.expect("There were no link_retry_statistics"));
+    assert!(cycles < 20000.0, "The burst must complete, it took {} cycles", cycles);
+    assert!(retries > 0.0, "Some phits should be replayed");
+    assert!(bandwidth_lost > 0.0 && bandwidth_lost < 1.0, "Bad bandwidth lost {}", bandwidth_lost);
+    assert!(cycles > clean_cycles, "The replays should delay the burst, {} cycles against {} without errors", cycles, clean_cycles);
+}
+
+#[test]
+fn input_output_replays_link_errors()
+{
+    let (cycles, statistics) = run_retry_burst(0.2, input_output_router());
+    let (retries, bandwidth_lost) = get_retries(&statistics.expect("There were no link_retry_statistics"));
+    assert!(cycles < 20000.0, "The burst must complete, it took {} cycles", cycles);
+    assert!(retries > 0.0, "Some phits should be replayed");
+    assert!(bandwidth_lost > 0.0 && bandwidth_lost < 1.0, "Bad bandwidth lost {}", bandwidth_lost);
+}
@@ -0,0 +1,84 @@
+/*!
+    Tests for the links carrying several phits per cycle and the crossbar speedup of the routers
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// The virtual channel policies used by both routers.
+fn create_policies() -> ConfigurationValue
+{
+    create_vcp(VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    })
+}
+
+fn basic_router() -> ConfigurationValue
+{
+    create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_policies(),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    })
+}
+
+fn input_output_router() -> ConfigurationValue
+{
+    create_input_output_router(InputOutputRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_policies(),
+        crossbar_delay: 1,
+        crossbar_frequency_divisor: 1,
+        allocator: ConfigurationValue::Object("Random".to_string(), vec![("seed".to_string(), ConfigurationValue::Number(1f64))]),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+    })
+}
+
+/// Run a uniform burst in a 4x4 Hamming graph with DOR and the given router, with all the links having the given width and the crossbar the given speedup.
+/// Returns the number of cycles.
+fn run_width_burst(width: usize, crossbar_speedup: usize, router: ConfigurationValue) -> f64
+{
+    let router = add_pairs(router, vec![("crossbar_speedup".to_string(), ConfigurationValue::Number(crossbar_speedup as f64))]);
+    let results = run_uniform_burst(router, create_dor_routing(vec![0,1]), vec![], vec![
+        ("width".to_string(), ConfigurationValue::Number(width as f64)),
+    ]);
+    let mut cycle = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        _ => (),
+    );
+    cycle.expect("There were no cycle")
+}
+
+/// Each server injects 320 phits, which takes at least 320 cycles through a link of width 1 and 160 through a link of width 2.
+#[test]
+fn wide_links_speed_up_burst()
+{
+    for router in [basic_router(), input_output_router()]
+    {
+        let narrow = run_width_burst(1, 1, router.clone());
+        let wide = run_width_burst(2, 2, router);
+        assert!((320.0..20000.0).contains(&narrow), "The narrow burst took {} cycles", narrow);
+        assert!(wide < 20000.0, "The wide burst must complete, it took {} cycles", wide);
+        assert!(wide*1.5 < narrow, "Wide links should be much faster, {} cycles against {} with narrow links", wide, narrow);
+    }
+}
@@ -0,0 +1,85 @@
+/*!
+    Tests for the lossy and PFC transmission mechanisms
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a uniform burst in a 4x4 Hamming graph with DOR and the given transmission mechanism between routers.
+/// Returns the number of cycles and the flow control statistics of the routers.
+fn run_lossy_burst(transmission_mechanism: ConfigurationValue) -> (f64, ConfigurationValue)
+{
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![("transmission_mechanism".to_string(), transmission_mechanism)],
+    });
+    let results = run_uniform_burst(router, create_dor_routing(vec![0,1]), vec![
+        ("traffic".to_string(), create_uniform_burst_traffic(50)),
+        ("measured".to_string(), ConfigurationValue::Number(5000.0)),
+    ], vec![]);
+    let mut cycle = None;
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "router_aggregated_statistics" => match_object_panic!( value, "Basic", router_value,
+            "flow_control_statistics" => statistics = Some(router_value.clone()),
+            _ => (),
+        ),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), statistics.expect("There were no flow_control_statistics"))
+}
+
+/// Returns the dropped packets and the total paused cycles.
+fn get_flow_control(statistics: &ConfigurationValue) -> (f64, f64)
+{
+    let mut dropped = None;
+    let mut paused = None;
+    match_object_panic!( statistics, "FlowControlStatistics", value,
+        "dropped_packets" => dropped = Some(value.as_f64().expect("dropped_packets data")),
+        "dropped_phits" => (),
+        "paused_cycles_per_vc" => paused = Some(value.as_array().expect("paused_cycles_per_vc data").iter().map(|x|x.as_f64().expect("paused cycles")).sum::<f64>()),
+    );
+    (dropped.expect("There were no dropped_packets"), paused.expect("There were no paused_cycles_per_vc"))
+}
+
+/// Without flow control the routers drop packets, so the burst cannot complete.
+#[test]
+fn lossy_drops()
+{
+    let (cycles, statistics) = run_lossy_burst(ConfigurationValue::Literal("Lossy".to_string()));
+    let (dropped, paused) = get_flow_control(&statistics);
+    assert!(dropped > 0.0, "no packets were dropped");
+    assert_eq!(paused, 0.0, "lossy links never pause");
+    assert_eq!(cycles, 5000.0, "the burst completed despite the drops");
+}
+
+/// PFC with enough headroom pauses the links and does not drop any packet.
+#[test]
+fn pfc_pauses()
+{
+    let (cycles, statistics) = run_lossy_burst(ConfigurationValue::Object("PFC".to_string(), vec![
+        ("xoff_threshold".to_string(), ConfigurationValue::Number(32.0)),
+        ("xon_threshold".to_string(), ConfigurationValue::Number(16.0)),
+    ]));
+    let (dropped, paused) = get_flow_control(&statistics);
+    assert_eq!(dropped, 0.0, "PFC dropped packets");
+    assert!(paused > 0.0, "PFC never paused");
+    assert!(cycles < 5000.0, "the burst did not complete");
+}
@@ -0,0 +1,102 @@
+/*!
+    Tests for servers attached to several routers
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+
+/// Test dual-rail servers. There are two routers and two servers, each server attached to both routers. Each server sends one message of 16 phits to the other.
+/// With `RoundRobin` rail selection the servers inject through their second rail, which is attached to the same router as the first rail of the destination.
+/// Then packets must be ejected without any hop between routers.
+#[test]
+fn basic_dual_rail_two_routers()
+{
+    // Hamming with a second rail to the next router
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(2.0)],
+        servers_per_router: 1,
+    };
+    let topology = ConfigurationValue::Object("MultiHomed".to_string(), vec![
+        ("topology".to_string(), create_hamming_topology(hamming_builder)),
+        ("rail_offsets".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)])),
+    ]);
+
+    //Pattern
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: vec![ConfigurationValue::Number(1.0), ConfigurationValue::Number(2.0)],
+        shift: vec![ConfigurationValue::Number(0.0), ConfigurationValue::Number(1.0)],
+    };
+    let pattern = create_shift_pattern(shift_pattern_builder);
+
+    // Burst traffic
+    let messages_per_server = 1;
+    let message_size = 16;
+    let burst_traffic_builder = BurstTrafficBuilder{
+        pattern,
+        servers: 2,
+        messages_per_server,
+        message_size,
+    };
+
+    //Virtual Channel Policies
+    let vcp_args = VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("LowestLabel".to_string(), vec![]),
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    };
+    let vcp = create_vcp(vcp_args);
+
+    //Router Basic
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp,
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size, //vct
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    };
+
+    let cycles = messages_per_server * message_size + 2; //+2 is because of the switch-Nic and Nic-switch links which take one cycle each
+
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: cycles,
+        topology,
+        traffic: create_burst_traffic(burst_traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: create_shortest_routing(),
+        link_classes: create_link_classes(),
+    };
+
+    let plugs = Plugs::default();
+    let simulation_cv = add_pairs(create_simulation(simulation_builder), vec![
+        ("server_rail_selection".to_string(), ConfigurationValue::Object("RoundRobin".to_string(), vec![])),
+    ]);
+
+    let mut simulation = Simulation::new(&simulation_cv, &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+
+    let estimated_injected_load =  (message_size * messages_per_server) as f64 / (cycles as f64);
+
+    match_object_panic!( &results, "Result", value,
+        "cycle" => assert_eq!(value.as_f64().expect("Cycle data"), cycles as f64, "Cycle"),
+        "injected_load" => assert_eq!(value.as_f64().expect("Injected load data"), estimated_injected_load, "Injected load"),
+        "accepted_load" => assert_eq!(value.as_f64().expect("Accepted load load data"), estimated_injected_load, "Accepted load"),
+        "average_packet_hops" => assert_eq!(value.as_f64().expect("Packet hops data"), 0.0, "Total hops"),
+        _ => (),
+    );
+}
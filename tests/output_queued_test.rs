@@ -0,0 +1,89 @@
+/*!
+    Tests for the OutputQueued router, both as an ideal output-queued switch and as a CIOQ switch with speedup
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// 64 servers sending 20 messages of 16 phits.
+const TOTAL_PHITS: f64 = 20480.0;
+
+fn output_queued_router(speedup: Option<usize>, output_scheduler: &str) -> ConfigurationValue
+{
+    create_output_queued_router(OutputQueuedRouterBuilder{
+        virtual_channels: 2,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 64,
+        flit_size: 16,
+        output_buffer_size: Some(32),
+        speedup,
+        output_scheduler: ConfigurationValue::Object(output_scheduler.to_string(), vec![]),
+    })
+}
+
+/// Run a uniform burst with DOR and the given router.
+/// Returns the number of cycles, the accepted load and the router statistics.
+fn run_output_queued_burst(router: ConfigurationValue) -> (f64, f64, ConfigurationValue)
+{
+    let results = run_uniform_burst(router, create_dor_routing(vec![0,1]), vec![], vec![]);
+    let mut cycle = None;
+    let mut accepted_load = None;
+    let mut router_statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "accepted_load" => accepted_load = Some(value.as_f64().expect("Accepted load data")),
+        "router_aggregated_statistics" => router_statistics = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), accepted_load.expect("There were no accepted_load"), router_statistics.expect("There were no router_aggregated_statistics"))
+}
+
+/// Returns the average phits moved through the crossbar and the average blocked input heads.
+fn get_crossbar_statistics(statistics: &ConfigurationValue) -> (f64, f64)
+{
+    let mut crossbar_phits = None;
+    let mut blocked_input_heads = None;
+    match_object_panic!( statistics, "OutputQueued", value,
+        "average_crossbar_phits" => crossbar_phits = Some(value.as_f64().expect("bad average_crossbar_phits")),
+        "average_blocked_input_heads" => blocked_input_heads = Some(value.as_f64().expect("bad average_blocked_input_heads")),
+        _ => (),
+    );
+    (crossbar_phits.expect("There were no average_crossbar_phits"), blocked_input_heads.expect("There were no average_blocked_input_heads"))
+}
+
+/// All the phits of the burst are delivered with every scheduler, both with and without speedup.
+#[test]
+fn output_queued_completes_burst()
+{
+    for output_scheduler in ["FIFO", "RoundRobin", "StrictPriority"]
+    {
+        for speedup in [None, Some(1)]
+        {
+            let (cycles, accepted_load, statistics) = run_output_queued_burst(output_queued_router(speedup, output_scheduler));
+            assert!((320.0..20000.0).contains(&cycles), "The burst with {} and speedup {:?} took {} cycles", output_scheduler, speedup, cycles);
+            let delivered = accepted_load*64.0*cycles;
+            assert!((delivered - TOTAL_PHITS).abs() < 1e-6, "Delivered {} phits instead of {} with {} and speedup {:?}", delivered, TOTAL_PHITS, output_scheduler, speedup);
+            let (crossbar_phits, _blocked_input_heads) = get_crossbar_statistics(&statistics);
+            assert!(crossbar_phits > 0.0, "Some phits should traverse the crossbar");
+        }
+    }
+}
+
+/// The ideal output-queued switch suffers less head-of-line blocking than the CIOQ switch without speedup.
+#[test]
+fn speedup_reduces_head_of_line_blocking()
+{
+    let (ideal_cycles, _, ideal_statistics) = run_output_queued_burst(output_queued_router(None, "RoundRobin"));
+    let (cioq_cycles, _, cioq_statistics) = run_output_queued_burst(output_queued_router(Some(1), "RoundRobin"));
+    let (_, ideal_blocked) = get_crossbar_statistics(&ideal_statistics);
+    let (_, cioq_blocked) = get_crossbar_statistics(&cioq_statistics);
+    assert!(ideal_blocked < cioq_blocked, "The ideal switch should block less, {} blocked heads against {} without speedup", ideal_blocked, cioq_blocked);
+    assert!(ideal_cycles <= cioq_cycles, "The ideal switch should not be slower, {} cycles against {} without speedup", ideal_cycles, cioq_cycles);
+}
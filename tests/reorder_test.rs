@@ -0,0 +1,123 @@
+/*!
+    Tests for the packet sequencing and reorder buffer of the servers
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a uniform burst of 4-packet messages in a complete graph of 8 routers with 4 servers each.
+/// Returns the number of cycles, the average message delay, and the reorder statistics.
+fn run_reorder_burst(routing: ConfigurationValue, virtual_channels: usize, reorder_buffer: bool) -> (f64, f64, ConfigurationValue)
+{
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    });
+    let topology = create_hamming_topology(HammingBuilder{
+        sides: vec![ConfigurationValue::Number(8.0)],
+        servers_per_router: 4,
+    });
+    let traffic = create_burst_traffic(BurstTrafficBuilder{
+        pattern: ConfigurationValue::Object("Uniform".to_string(), vec![]),
+        servers: 32,
+        messages_per_server: 20,
+        message_size: 64,
+    });
+    let key = if reorder_buffer { "server_reorder_buffer" } else { "server_packet_sequencing" };
+    let results = run_uniform_burst(router, routing, vec![
+        ("topology".to_string(), topology),
+        ("traffic".to_string(), traffic),
+        (key.to_string(), ConfigurationValue::True),
+    ], vec![]);
+    let mut cycle = None;
+    let mut delay = None;
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "average_message_delay" => delay = Some(value.as_f64().expect("average_message_delay data")),
+        "reorder_statistics" => statistics = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), delay.expect("There were no average_message_delay"), statistics.expect("There were no reorder_statistics"))
+}
+
+/// Returns the number of sequenced packets, the out of order packets, the maximum occupancy of the reorder buffer, and the effective message delay.
+fn get_reordering(statistics: &ConfigurationValue) -> (f64, f64, f64, f64)
+{
+    let mut packets = None;
+    let mut out_of_order = None;
+    let mut maximum_occupancy = None;
+    let mut effective_delay = None;
+    match_object_panic!( statistics, "ReorderStatistics", value,
+        "out_of_order_packets" => out_of_order = Some(value.as_f64().expect("out_of_order_packets data")),
+        "average_reorder_distance" => (),
+        "reorder_distance_count" => packets = Some(value.as_array().expect("reorder_distance_count data").iter().map(|x|x.as_f64().expect("count")).sum::<f64>()),
+        "reorder_buffer_occupancy_count" => (),
+        "maximum_reorder_buffer_occupancy" => maximum_occupancy = Some(value.as_f64().expect("maximum_reorder_buffer_occupancy data")),
+        "average_effective_message_delay" => effective_delay = Some(value.as_f64().expect("average_effective_message_delay data")),
+    );
+    (packets.expect("There were no reorder_distance_count"), out_of_order.expect("There were no out_of_order_packets"),
+        maximum_occupancy.expect("There were no maximum_reorder_buffer_occupancy"), effective_delay.expect("There were no average_effective_message_delay"))
+}
+
+/// Valiant routing using a virtual channel for each hop.
+fn create_valiant_routing() -> ConfigurationValue
+{
+    let valiant = ConfigurationValue::Object("Valiant".to_string(), vec![
+        ("first".to_string(), create_shortest_routing()),
+        ("second".to_string(), create_shortest_routing()),
+    ]);
+    ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+        ("routing".to_string(), valiant),
+        ("channels".to_string(), ConfigurationValue::Array(vec![
+            ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)]),
+            ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)]),
+            ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0), ConfigurationValue::Number(1.0)]),
+        ])),
+    ])
+}
+
+/// Single path routing with a single virtual channel keeps the packets in order, while Valiant makes them wait in the reorder buffer.
+#[test]
+fn reorder_statistics()
+{
+    let (_cycles, delay, statistics) = run_reorder_burst(create_shortest_routing(), 1, false);
+    let (packets, out_of_order, maximum_occupancy, effective_delay) = get_reordering(&statistics);
+    assert_eq!(packets, 32.0*20.0*4.0, "sequenced packets");
+    assert_eq!(out_of_order, 0.0, "out of order packets with minimal routing");
+    assert_eq!(maximum_occupancy, 0.0, "reorder buffer occupancy with minimal routing");
+    assert!((effective_delay - delay).abs() < 1e-9, "effective delay {} differs from message delay {} without reordering", effective_delay, delay);
+    let (_cycles, delay, statistics) = run_reorder_burst(create_valiant_routing(), 2, false);
+    let (packets, out_of_order, maximum_occupancy, effective_delay) = get_reordering(&statistics);
+    assert_eq!(packets, 32.0*20.0*4.0, "sequenced packets");
+    assert!(out_of_order > 0.0, "Valiant never delivered out of order");
+    assert!(maximum_occupancy > 0.0, "Valiant never used the reorder buffer");
+    assert!(effective_delay > delay, "effective delay {} is not greater than message delay {}", effective_delay, delay);
+}
+
+/// When the traffic waits for the in-order delivery the burst still completes.
+#[test]
+fn reorder_buffer_delivery()
+{
+    let (cycles, _delay, statistics) = run_reorder_burst(create_valiant_routing(), 2, true);
+    let (packets, out_of_order, _maximum_occupancy, _effective_delay) = get_reordering(&statistics);
+    assert!(cycles < 20000.0, "the burst did not complete");
+    assert_eq!(packets, 32.0*20.0*4.0, "sequenced packets");
+    assert!(out_of_order > 0.0, "Valiant never delivered out of order");
+}
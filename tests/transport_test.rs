@@ -0,0 +1,114 @@
+/*!
+    Tests for the reliable transport between servers
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a uniform burst in a 4x4 Hamming graph with DOR, the given transmission mechanism between routers, a transport with the given timeout and optionally a congestion control.
+/// Returns the number of cycles, the accepted load, the transport statistics and the congestion statistics if any.
+fn run_transport_burst(transmission_mechanism: &str, timeout: f64, congestion_control: Option<ConfigurationValue>) -> (f64, f64, ConfigurationValue, Option<ConfigurationValue>)
+{
+    let router = create_basic_router(BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_vcp(VirtualChannelPoliciesBuilder{
+            policies: vec![
+                ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+                ConfigurationValue::Object("Random".to_string(), vec![])
+            ]
+        }),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: 16,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![("transmission_mechanism".to_string(), ConfigurationValue::Literal(transmission_mechanism.to_string()))],
+    });
+    let mut simulation_keys = vec![
+        ("server_transport".to_string(), ConfigurationValue::Object("Transport".to_string(), vec![
+            ("timeout".to_string(), ConfigurationValue::Number(timeout)),
+        ])),
+    ];
+    if let Some(congestion_control) = congestion_control
+    {
+        simulation_keys.push(("server_congestion_control".to_string(), congestion_control));
+    }
+    let results = run_uniform_burst(router, create_dor_routing(vec![0,1]), simulation_keys, vec![]);
+    let mut cycle = None;
+    let mut accepted_load = None;
+    let mut transport = None;
+    let mut congestion = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "accepted_load" => accepted_load = Some(value.as_f64().expect("accepted_load data")),
+        "transport_statistics" => transport = Some(value.clone()),
+        "congestion_statistics" => congestion = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), accepted_load.expect("There were no accepted_load"), transport.expect("There were no transport_statistics"), congestion)
+}
+
+/// Returns the goodput, the retransmitted packets and the duplicate packets.
+fn get_transport(statistics: &ConfigurationValue) -> (f64, f64, f64)
+{
+    let mut goodput = None;
+    let mut retransmitted = None;
+    let mut duplicates = None;
+    match_object_panic!( statistics, "TransportStatistics", value,
+        "goodput" => goodput = Some(value.as_f64().expect("bad goodput")),
+        "retransmitted_packets" => retransmitted = Some(value.as_f64().expect("bad retransmitted_packets")),
+        "duplicate_packets" => duplicates = Some(value.as_f64().expect("bad duplicate_packets")),
+        _ => (),
+    );
+    (goodput.unwrap(), retransmitted.unwrap(), duplicates.unwrap())
+}
+
+/// Without losses nothing is retransmitted, but the acknowledgements make the goodput lower than the accepted load.
+#[test]
+fn transport_without_losses()
+{
+    let (cycles, accepted_load, statistics, _congestion) = run_transport_burst("SimpleVirtualChannels", 5000.0, None);
+    let (goodput, retransmitted, duplicates) = get_transport(&statistics);
+    assert!(cycles < 20000.0, "the burst did not complete");
+    assert_eq!(retransmitted, 0.0, "packets were retransmitted without losses");
+    assert_eq!(duplicates, 0.0, "packets were duplicated without losses");
+    assert!(goodput > 0.0 && goodput < accepted_load, "bad goodput {} with accepted load {}", goodput, accepted_load);
+}
+
+/// The lossy routers drop packets, which the transport retransmits until the burst completes.
+#[test]
+fn transport_recovers_losses()
+{
+    let (cycles, accepted_load, statistics, _congestion) = run_transport_burst("Lossy", 500.0, None);
+    let (goodput, retransmitted, _duplicates) = get_transport(&statistics);
+    assert!(cycles < 20000.0, "the burst did not complete");
+    assert!(retransmitted > 0.0, "no packet was retransmitted");
+    assert!(goodput < accepted_load, "bad goodput {} with accepted load {}", goodput, accepted_load);
+}
+
+/// The congestion control follows the transport: each packet enters the window once and leaves it once, either acknowledged or lost, whatever its retransmissions and duplicates.
+#[test]
+fn transport_drives_congestion_control()
+{
+    let dctcp = ConfigurationValue::Object("DCTCP".to_string(), vec![]);
+    let (cycles, _accepted_load, statistics, congestion) = run_transport_burst("Lossy", 500.0, Some(dctcp));
+    let (_goodput, retransmitted, _duplicates) = get_transport(&statistics);
+    let mut congestion_acks = None;
+    let mut congestion_losses = None;
+    match_object_panic!( &congestion.expect("There were no congestion_statistics"), "CongestionStatistics", value,
+        "congestion_acks" => congestion_acks = Some(value.as_f64().expect("bad congestion_acks")),
+        "congestion_losses" => congestion_losses = Some(value.as_f64().expect("bad congestion_losses")),
+        _ => (),
+    );
+    let (congestion_acks, congestion_losses) = (congestion_acks.unwrap(), congestion_losses.unwrap());
+    assert!(cycles < 20000.0, "the burst did not complete");
+    assert!(retransmitted > 0.0, "no packet was retransmitted");
+    assert!(congestion_losses > 0.0 && congestion_losses <= retransmitted, "bad number of losses {} with {} retransmissions", congestion_losses, retransmitted);
+    // 64 servers sending 20 messages of one packet each.
+    assert_eq!(congestion_acks + congestion_losses, 1280.0, "every packet should be either acknowledged or lost once");
+}
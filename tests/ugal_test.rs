@@ -0,0 +1,137 @@
+/*!
+    Tests for the generic UGAL routing
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a burst in a complete graph of 8 routers with 4 servers each, in which every server sends to the next router.
+/// Returns the number of cycles and the routing statistics.
+fn run_adversarial_burst(routing: ConfigurationValue) -> (f64, ConfigurationValue)
+{
+    let hamming_builder = HammingBuilder{
+        sides: vec![ConfigurationValue::Number(8.0)],
+        servers_per_router: 4,
+    };
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(8.0)],
+        shift: vec![ConfigurationValue::Number(0.0), ConfigurationValue::Number(1.0)],
+    };
+    let message_size = 16;
+    let burst_traffic_builder = BurstTrafficBuilder{
+        pattern: create_shift_pattern(shift_pattern_builder),
+        servers: 32,
+        messages_per_server: 20,
+        message_size,
+    };
+    let vcp_args = VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    };
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 2,
+        vcp: create_vcp(vcp_args),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    };
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 10000,
+        topology: create_hamming_topology(hamming_builder),
+        traffic: create_burst_traffic(burst_traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        // Minimal hops use the VC 0 and Valiant second hops the VC 1.
+        routing: ConfigurationValue::Object("ChannelsPerHop".to_string(), vec![
+            ("routing".to_string(), routing),
+            ("channels".to_string(), ConfigurationValue::Array(vec![
+                ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)]),
+                ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)]),
+                ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0), ConfigurationValue::Number(1.0)]),
+            ])),
+        ]),
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut cycle = None;
+    let mut statistics = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        "routing_statistics" => statistics = Some(value.clone()),
+        _ => (),
+    );
+    (cycle.expect("There were no cycle"), statistics.expect("There were no routing_statistics"))
+}
+
+fn create_ugal_routing(global: bool, threshold: f64) -> ConfigurationValue
+{
+    ConfigurationValue::Object("UGAL".to_string(), vec![
+        ("global".to_string(), if global { ConfigurationValue::True } else { ConfigurationValue::False }),
+        ("intermediates".to_string(), ConfigurationValue::Number(2.0)),
+        ("threshold".to_string(), ConfigurationValue::Number(threshold)),
+        ("enable_statistics".to_string(), ConfigurationValue::True),
+    ])
+}
+
+fn get_decisions(statistics: &ConfigurationValue) -> (f64, f64)
+{
+    let mut minimal = None;
+    let mut nonminimal = None;
+    match_object_panic!( statistics, "UGALStatistics", value,
+        "minimal_decisions" => minimal = Some(value.as_f64().expect("minimal_decisions data")),
+        "nonminimal_decisions" => nonminimal = Some(value.as_f64().expect("nonminimal_decisions data")),
+    );
+    (minimal.expect("There were no minimal_decisions"), nonminimal.expect("There were no nonminimal_decisions"))
+}
+
+/// Under an adversarial shift both UGAL-L and UGAL-G must divert some packets through intermediates and finish earlier than when forced to be minimal with a huge threshold.
+#[test]
+fn ugal_adversarial_shift()
+{
+    let (minimal_cycles, minimal_statistics) = run_adversarial_burst(create_ugal_routing(true, 1e9));
+    let (decisions_min, decisions_nonmin) = get_decisions(&minimal_statistics);
+    assert_eq!(decisions_min, 32.0*20.0, "minimal decisions with huge threshold");
+    assert_eq!(decisions_nonmin, 0.0, "nonminimal decisions with huge threshold");
+    for global in [false, true]
+    {
+        let (cycles, statistics) = run_adversarial_burst(create_ugal_routing(global, 0.0));
+        let (decisions_min, decisions_nonmin) = get_decisions(&statistics);
+        assert_eq!(decisions_min + decisions_nonmin, 32.0*20.0, "total decisions");
+        assert!(decisions_nonmin > 0.0, "UGAL global={} never took a Valiant route", global);
+        assert!(cycles < minimal_cycles, "UGAL global={} took {} cycles against {} of the minimal routes", global, cycles, minimal_cycles);
+    }
+}
+
+/// UGAL-L wrapped in a `ChannelMap` must still receive the congestion view and take its decisions.
+#[test]
+fn ugal_local_inside_channel_map()
+{
+    let identity = ConfigurationValue::Array(vec![
+        ConfigurationValue::Array(vec![ConfigurationValue::Number(0.0)]),
+        ConfigurationValue::Array(vec![ConfigurationValue::Number(1.0)]),
+    ]);
+    let routing = ConfigurationValue::Object("ChannelMap".to_string(), vec![
+        ("routing".to_string(), create_ugal_routing(false, 0.0)),
+        ("map".to_string(), identity),
+    ]);
+    let (_cycles, statistics) = run_adversarial_burst(routing);
+    let (decisions_min, decisions_nonmin) = get_decisions(&statistics);
+    assert_eq!(decisions_min + decisions_nonmin, 32.0*20.0, "total decisions");
+    assert!(decisions_nonmin > 0.0, "UGAL-L inside ChannelMap never took a Valiant route");
+}
@@ -0,0 +1,84 @@
+/*!
+    Tests for the BalancedUpDown routing in fat trees
+*/
+
+mod common;
+use caminos_lib::*;
+use caminos_lib::config_parser::ConfigurationValue;
+use common::*;
+
+/// Run a burst in a two-level XGFT with 16 leaves of 4 servers, in which every server sends to the server in the other half of the tree.
+/// Returns the number of cycles.
+fn run_half_shift_burst(selection: ConfigurationValue) -> f64
+{
+    let topology = ConfigurationValue::Object("XGFT".to_string(), vec![
+        ("height".to_string(), ConfigurationValue::Number(2.0)),
+        ("down".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)])),
+        ("up".to_string(), ConfigurationValue::Array(vec![ConfigurationValue::Number(4.0), ConfigurationValue::Number(4.0)])),
+        ("servers_per_leaf".to_string(), ConfigurationValue::Number(4.0)),
+    ]);
+    let shift_pattern_builder = ShiftPatternBuilder{
+        sides: vec![ConfigurationValue::Number(64.0)],
+        shift: vec![ConfigurationValue::Number(32.0)],
+    };
+    let message_size = 16;
+    let burst_traffic_builder = BurstTrafficBuilder{
+        pattern: create_shift_pattern(shift_pattern_builder),
+        servers: 64,
+        messages_per_server: 20,
+        message_size,
+    };
+    let vcp_args = VirtualChannelPoliciesBuilder{
+        policies: vec![
+            ConfigurationValue::Object("EnforceFlowControl".to_string(), vec![]),
+            ConfigurationValue::Object("Random".to_string(), vec![])
+        ]
+    };
+    let router_args = BasicRouterBuilder{
+        virtual_channels: 1,
+        vcp: create_vcp(vcp_args),
+        buffer_size: 64,
+        bubble: ConfigurationValue::False,
+        flit_size: message_size,
+        allow_request_busy_port: ConfigurationValue::True,
+        intransit_priority: ConfigurationValue::False,
+        output_buffer_size: 32,
+        neglect_busy_outport: ConfigurationValue::False,
+        output_prioritize_lowest_label: ConfigurationValue::False,
+        extra_pairs: vec![],
+    };
+    let simulation_builder = SimulationBuilder{
+        random_seed: 1,
+        warmup: 0,
+        measured: 20000,
+        topology,
+        traffic: create_burst_traffic(burst_traffic_builder),
+        router: create_basic_router(router_args),
+        maximum_packet_size: 16,
+        general_frequency_divisor: 1,
+        routing: ConfigurationValue::Object("BalancedUpDown".to_string(), vec![
+            ("selection".to_string(), selection),
+        ]),
+        link_classes: create_link_classes(),
+    };
+    let plugs = Plugs::default();
+    let mut simulation = Simulation::new(&create_simulation(simulation_builder), &plugs);
+    simulation.run();
+    let results = simulation.get_simulation_results();
+    let mut cycle = None;
+    match_object_panic!( &results, "Result", value,
+        "cycle" => cycle = Some(value.as_f64().expect("Cycle data")),
+        _ => (),
+    );
+    cycle.expect("There were no cycle")
+}
+
+/// The Adaptive selection must deliver the whole burst choosing among the upward ports by their occupation, at least as fast as the oblivious Hash selection.
+#[test]
+fn balanced_up_down_adaptive()
+{
+    let hash_cycles = run_half_shift_burst(ConfigurationValue::Object("Hash".to_string(), vec![]));
+    let adaptive_cycles = run_half_shift_burst(ConfigurationValue::Object("Adaptive".to_string(), vec![]));
+    assert!(adaptive_cycles < 20000.0, "the burst was not delivered");
+    assert!(adaptive_cycles <= hash_cycles, "Adaptive took {} cycles against {} of Hash", adaptive_cycles, hash_cycles);
+}